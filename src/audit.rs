@@ -0,0 +1,169 @@
+// A SymlinkOps that writes a JSON record of each change it makes (feature `audit`). There’s no
+// serde here: the records are small and flat, and escaping strings is all they need.
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use {SymlinkOps, SystemSymlinks};
+
+/// A [`SymlinkOps`] that logs each operation as a line of JSON (feature `audit`).
+///
+/// Every creation and removal made through it is passed on to the wrapped operations (the real
+/// filesystem, by default) and then written to the log as one JSON object per line, like this:
+///
+/// ```text
+/// {"op":"create","kind":"dir","target":"releases/42","link":"current","result":"ok"}
+/// {"op":"remove","kind":"file","link":"old","result":"error","error":"Permission denied"}
+/// ```
+///
+/// `op` is `create` or `remove`; `kind` is `file`, `dir` or `auto`, after the method called;
+/// removals have no `target`. Paths are written as given, with anything that isn’t valid Unicode
+/// replaced by U+FFFD, so a log is something to read, not something to replay byte for byte.
+/// Reading a link and asking whether something is one change nothing, and aren’t logged.
+///
+/// The log is written after the operation, so it only records what was actually attempted.
+/// A failure to write it is not the operation’s failure, and is ignored: the link has been made
+/// (or not) either way, and I’d rather not report an error for something that happened. If the
+/// log matters that much, give it a writer that can’t lose records, or check it afterwards.
+///
+/// Without the feature, none of this exists and other operations don’t pay for it.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use std::path::Path;
+/// use symlink_rs::{SymlinkOps, SystemSymlinks};
+///
+/// # let dir = std::env::temp_dir().join("symlink-crate-audit-doctest");
+/// # let _ = std::fs::remove_dir_all(&dir);
+/// # std::fs::create_dir(&dir)?;
+/// # let link = dir.join("link");
+/// let ops = SystemSymlinks.with_audit_log(Vec::new());
+/// ops.symlink_file(Path::new("target"), &link)?;
+/// let log = String::from_utf8(ops.into_writer()).unwrap();
+/// assert!(log.starts_with(r#"{"op":"create","kind":"file","target":"target","#));
+/// # std::fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct AuditLog<W: Write, O: SymlinkOps = SystemSymlinks> {
+    ops: O,
+    writer: Mutex<W>,
+}
+
+impl<W: Write, O: SymlinkOps> AuditLog<W, O> {
+    /// Log the operations made through `ops` to `writer`.
+    pub fn new(ops: O, writer: W) -> AuditLog<W, O> {
+        AuditLog { ops, writer: Mutex::new(writer) }
+    }
+
+    /// The operations being logged.
+    pub fn ops(&self) -> &O {
+        &self.ops
+    }
+
+    /// Stop logging, and get the writer back.
+    pub fn into_writer(self) -> W {
+        self.writer.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn log<T>(&self, op: &str, kind: &str, target: Option<&Path>, link: &Path,
+              result: &io::Result<T>) {
+        let mut record = String::new();
+        record.push_str("{\"op\":");
+        push_json_str(&mut record, op);
+        record.push_str(",\"kind\":");
+        push_json_str(&mut record, kind);
+        if let Some(target) = target {
+            record.push_str(",\"target\":");
+            push_json_str(&mut record, &target.to_string_lossy());
+        }
+        record.push_str(",\"link\":");
+        push_json_str(&mut record, &link.to_string_lossy());
+        match *result {
+            Ok(_) => record.push_str(",\"result\":\"ok\""),
+            Err(ref e) => {
+                record.push_str(",\"result\":\"error\",\"error\":");
+                push_json_str(&mut record, &e.to_string());
+            }
+        }
+        record.push_str("}\n");
+        // One write per record, under the lock, so records from different threads don’t
+        // interleave; see the type’s docs for why a failure here is ignored.
+        let mut writer = self.writer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let _ = writer.write_all(record.as_bytes()).and_then(|()| writer.flush());
+    }
+
+    fn create(&self, kind: &str, src: &Path, dst: &Path, result: io::Result<()>)
+              -> io::Result<()> {
+        self.log("create", kind, Some(src), dst, &result);
+        result
+    }
+
+    fn remove(&self, kind: &str, path: &Path, result: io::Result<()>) -> io::Result<()> {
+        self.log("remove", kind, None, path, &result);
+        result
+    }
+}
+
+impl SystemSymlinks {
+    /// The real filesystem, with each operation logged to `writer`; see [`AuditLog`].
+    pub fn with_audit_log<W: Write>(self, writer: W) -> AuditLog<W> {
+        AuditLog::new(self, writer)
+    }
+}
+
+impl<W: Write, O: SymlinkOps> SymlinkOps for AuditLog<W, O> {
+    fn symlink_file(&self, src: &Path, dst: &Path) -> io::Result<()> {
+        self.create("file", src, dst, self.ops.symlink_file(src, dst))
+    }
+
+    fn symlink_dir(&self, src: &Path, dst: &Path) -> io::Result<()> {
+        self.create("dir", src, dst, self.ops.symlink_dir(src, dst))
+    }
+
+    fn symlink_auto(&self, src: &Path, dst: &Path) -> io::Result<()> {
+        self.create("auto", src, dst, self.ops.symlink_auto(src, dst))
+    }
+
+    fn remove_symlink_file(&self, path: &Path) -> io::Result<()> {
+        self.remove("file", path, self.ops.remove_symlink_file(path))
+    }
+
+    fn remove_symlink_dir(&self, path: &Path) -> io::Result<()> {
+        self.remove("dir", path, self.ops.remove_symlink_dir(path))
+    }
+
+    fn remove_symlink_auto(&self, path: &Path) -> io::Result<()> {
+        self.remove("auto", path, self.ops.remove_symlink_auto(path))
+    }
+
+    fn read_symlink(&self, path: &Path) -> io::Result<PathBuf> {
+        self.ops.read_symlink(path)
+    }
+
+    fn is_symlink(&self, path: &Path) -> bool {
+        self.ops.is_symlink(path)
+    }
+}
+
+// A JSON string literal: quotes, backslashes and control characters escaped, the rest as is.
+fn push_json_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
@@ -0,0 +1,1152 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+#[cfg(windows)]
+use std::sync::mpsc;
+#[cfg(windows)]
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use error;
+#[cfg(windows)]
+use internal;
+use paths;
+use policy::PolicyOutcome;
+use resolve;
+#[cfg(not(unix))]
+use remove_symlink_auto;
+use stats;
+use {check_not_empty, check_not_self_link, check_trailing_names, create_checked, create_symlink,
+     SymlinkKind, TargetComparator};
+
+/// A builder for creating symlinks with non-default options.
+///
+/// The free functions [`symlink_file`](crate::symlink_file), [`symlink_dir`](crate::symlink_dir)
+/// and [`symlink_auto`](crate::symlink_auto) are equivalent to calling the corresponding methods
+/// on `SymlinkBuilder::new()`; reach for the builder only when you need one of its options.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// symlink_rs::SymlinkBuilder::new()
+///     .nofollow_parents(true)
+///     .symlink_file("/srv/data/blob", "/srv/extract/root/link")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SymlinkBuilder {
+    nofollow_parents: bool,
+    reject_self_reference: bool,
+    prefer_kind: Option<SymlinkKind>,
+    classify: ClassifyHeuristic,
+    create_parents: bool,
+    max_parent_creation: Option<usize>,
+    parent_dir_mode: Option<u32>,
+    parent_dir_mode_exact: bool,
+    replace_existing: bool,
+    preserve_attrs: bool,
+    preserve_xattrs: bool,
+    target_style: TargetStyle,
+    target_separators: TargetSeparators,
+    classify_timeout: Option<Duration>,
+    lexically_normalize: bool,
+    case_sensitive_check: bool,
+    follow_target: bool,
+    verify_after_create: bool,
+    target_comparator: TargetComparator,
+    skip_if_newer: Option<SystemTime>,
+    skip_if_same_content: bool,
+    collapse_target: bool,
+    verify_reparse_point: bool,
+    require_target_exists: bool,
+    durable: bool,
+    preserve_trailing_dots: bool,
+    expand_tilde: bool,
+}
+
+/// How [`SymlinkBuilder::symlink_auto`] decides between a file and a directory symlink.
+///
+/// This only matters on Windows; elsewhere there’s just the one kind of symlink.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ClassifyHeuristic {
+    /// Stat the target, and fail if that fails. This is what [`symlink_auto`](crate::symlink_auto)
+    /// does, and the default.
+    #[default]
+    StatOnly,
+    /// Stat the target, and if that fails—most likely because it doesn’t exist yet—guess from the
+    /// shape of the path.
+    StatThenHeuristic,
+    /// Don’t stat the target at all; just guess from the shape of the path.
+    HeuristicOnly,
+}
+
+/// How [`SymlinkBuilder`] writes the target into the link.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TargetStyle {
+    /// Store the target exactly as given. This is the default, and what the free functions do.
+    #[default]
+    AsGiven,
+    /// Store the target as an absolute path. A relative target is resolved against the link’s
+    /// parent directory first, as the OS would resolve it, so the link points where it would
+    /// have anyway.
+    Absolute,
+    /// Store the target relative to the link’s parent directory, so that the link keeps working
+    /// if the tree containing both is moved.
+    Relative,
+}
+
+/// Which separators [`SymlinkBuilder`] writes in the target (Windows only).
+///
+/// Windows accepts both `\` and `/` in paths, and so in symlink targets—mostly. The target is
+/// stored in the link as written, and tools that read it raw see whichever was used. But the
+/// OS only translates `/` when it’s handed a path to open, not when it follows a relative target
+/// out of a reparse point, so a relative target with forward slashes may not resolve, depending
+/// on the version of Windows and what’s doing the following. `ForwardSlash` is for interop with
+/// tools that insist on it; otherwise `\` is the safe choice.
+///
+/// Verbatim (`\\?\`) targets are never rewritten, since `/` isn’t a separator in them. On other
+/// platforms `\` is an ordinary character, so the option does nothing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TargetSeparators {
+    /// Leave the separators as they are in the target. This is the default, and what the free
+    /// functions do.
+    #[default]
+    AsGiven,
+    /// Write every separator as `\`, the platform’s own.
+    Native,
+    /// Write every separator as `/`.
+    ForwardSlash,
+}
+
+impl SymlinkBuilder {
+    /// Create a builder with all options at their defaults.
+    pub fn new() -> SymlinkBuilder {
+        SymlinkBuilder::default()
+    }
+
+    /// Refuse to create the link if any component of its parent path is a symlink.
+    ///
+    /// Ordinarily the OS resolves symlinks in the link path’s parents, so a link to be created at
+    /// `root/a/b/link` could land somewhere else entirely if `root/a` is a symlink. With this
+    /// option, the parent directory is opened one component at a time with `O_NOFOLLOW`, and the
+    /// link is then created relative to that directory with `symlinkat`, so no parent component
+    /// is ever silently followed—not even if it’s swapped for a symlink halfway through. If one
+    /// is a symlink, an [`InvalidInput`](io::ErrorKind::InvalidInput) error is returned. This is
+    /// what you want when extracting untrusted archives that mustn’t write outside a given root.
+    ///
+    /// This requires the directory file descriptor machinery, and so is Unix-only (and at
+    /// present, only Linux, Android, macOS, iOS and FreeBSD). Elsewhere, creating a link with
+    /// this option set returns an [`Unsupported`](io::ErrorKind::Unsupported) error.
+    pub fn nofollow_parents(&mut self, nofollow_parents: bool) -> &mut SymlinkBuilder {
+        self.nofollow_parents = nofollow_parents;
+        self
+    }
+
+    /// Refuse to create a link that points at itself or at one of its own ancestors.
+    ///
+    /// A directory symlink pointing into itself (`a/b/link` → `..`, say) sends naive tree walkers
+    /// into an infinite loop. With this option, the target is resolved (relative to the link’s
+    /// parent directory, as the OS will resolve it) and if it is the link location or one of its
+    /// ancestors, an [`InvalidInput`](io::ErrorKind::InvalidInput) error is returned instead of
+    /// creating the link.
+    ///
+    /// The check is primarily lexical, so it works for targets that don’t exist yet; but when both
+    /// the target and the link’s parent directory exist, they are also canonicalized and compared,
+    /// which catches cases where symlinks along the way would fool lexical analysis. It is still
+    /// not proof against a link that reaches itself through *other* links created later.
+    pub fn reject_self_reference(&mut self, reject_self_reference: bool) -> &mut SymlinkBuilder {
+        self.reject_self_reference = reject_self_reference;
+        self
+    }
+
+    /// Have [`symlink_auto`](Self::symlink_auto) create this kind of link without looking first.
+    ///
+    /// On Windows, `symlink_auto` ordinarily stats the target to decide between a file and a
+    /// directory symlink. If nearly all of your links are of one kind, that’s a syscall per link
+    /// spent learning what you already knew. With a preferred kind, the link is created as that
+    /// kind straight away, and the target isn’t looked at at all; if creating it fails, that’s
+    /// the error, with no second try as the other kind.
+    ///
+    /// This is a performance hint, and you should still only give it when you know it holds:
+    /// Windows doesn’t check the kind of a new symlink against its target, so if the hint is wrong
+    /// you get a link of the wrong kind (which, like any such link, fails to open) rather than an
+    /// error followed by the right kind. It doesn’t affect [`symlink_file`](Self::symlink_file) or
+    /// [`symlink_dir`](Self::symlink_dir), and on other platforms, where there’s only one kind of
+    /// symlink, it makes no difference at all.
+    pub fn prefer_kind(&mut self, kind: SymlinkKind) -> &mut SymlinkBuilder {
+        self.prefer_kind = Some(kind);
+        self
+    }
+
+    /// Choose how [`symlink_auto`](Self::symlink_auto) decides what kind of link to create.
+    ///
+    /// The default, [`StatOnly`](ClassifyHeuristic::StatOnly), needs the target to exist. The
+    /// other strategies let you create links to targets that will only exist later, by guessing
+    /// from the path: a target ending in a separator (`assets/`), or in `.` or `..`, or that is a
+    /// root or drive, gets a directory symlink, and anything else a file symlink.
+    ///
+    /// The guess is best-effort and will be wrong for directories named like files—`bin`, say—
+    /// which is why it has to be asked for. If you know the kind, don’t guess: use
+    /// [`symlink_file`](Self::symlink_file) or [`symlink_dir`](Self::symlink_dir). On platforms
+    /// other than Windows, where the kind makes no difference, this option does nothing.
+    pub fn classify(&mut self, classify: ClassifyHeuristic) -> &mut SymlinkBuilder {
+        self.classify = classify;
+        self
+    }
+
+    /// Have [`symlink_auto`](Self::symlink_auto) classify a target that is itself a symlink by
+    /// what it leads to, rather than by the kind of link it is.
+    ///
+    /// By default a file symlink to a directory counts as a file, since that’s how Windows will
+    /// follow the new link through it; with this option, the chain is followed and the target is
+    /// a directory. That suits trees where links may later be replaced by what they point to, but
+    /// a target that doesn’t resolve is then a [`NotFound`](io::ErrorKind::NotFound) error. On
+    /// other platforms this does nothing.
+    pub fn follow_target(&mut self, follow_target: bool) -> &mut SymlinkBuilder {
+        self.follow_target = follow_target;
+        self
+    }
+
+    /// Give up on [`symlink_auto`](Self::symlink_auto)’s look at the target after `timeout`.
+    ///
+    /// On Windows, deciding between a file and a directory symlink means statting the target,
+    /// and on a slow or unresponsive network share that can take indefinitely long. With a
+    /// timeout, the stat is done on a worker thread, and if it hasn’t finished in time a
+    /// [`TimedOut`](io::ErrorKind::TimedOut) error is returned (the thread is left to finish,
+    /// or not, by itself). Under [`StatThenHeuristic`](ClassifyHeuristic::StatThenHeuristic), a
+    /// timeout is a failed stat like any other, and the kind is guessed from the path instead.
+    ///
+    /// Only the look at the target is timed, not the creation of the link. On other platforms
+    /// the target isn’t looked at, so this does nothing.
+    pub fn classify_timeout(&mut self, timeout: Duration) -> &mut SymlinkBuilder {
+        self.classify_timeout = Some(timeout);
+        self
+    }
+
+    /// Create any missing parent directories of the link, as [`fs::create_dir_all`] would.
+    ///
+    /// Without this, a missing parent is a [`NotFound`](io::ErrorKind::NotFound) error from the
+    /// OS. See also [`max_parent_creation`](Self::max_parent_creation), which caps how many are
+    /// created.
+    ///
+    /// On Unix, each directory is created relative to a file descriptor for the one before it,
+    /// and opened without following symlinks before going on, so someone who can write to the
+    /// tree can’t swap a freshly created directory for a symlink and have the rest created
+    /// somewhere else: that’s an [`InvalidInput`](io::ErrorKind::InvalidInput) error instead.
+    /// Two gaps remain. The link itself is still created by path, so the last directory could
+    /// yet be swapped before it’s made; combine this with
+    /// [`nofollow_parents`](Self::nofollow_parents) to close that one. And on other platforms the
+    /// directories are created by path, as [`fs::create_dir_all`] would, with no such check.
+    pub fn create_parents(&mut self, create_parents: bool) -> &mut SymlinkBuilder {
+        self.create_parents = create_parents;
+        self
+    }
+
+    /// Limit how many missing ancestor directories [`create_parents`](Self::create_parents) will
+    /// create for one link.
+    ///
+    /// A typo in a generated path—`out/v1.2/bin` for `out/v1.2-bin`, say—would otherwise quietly
+    /// grow a directory tree. With a limit, if more than `max` directories are missing, nothing
+    /// is created and a [`NotFound`](io::ErrorKind::NotFound) error saying how many were needed
+    /// is returned. `max_parent_creation(0)` means the parent must already exist, as without
+    /// `create_parents`. There is no limit by default.
+    pub fn max_parent_creation(&mut self, max: usize) -> &mut SymlinkBuilder {
+        self.max_parent_creation = Some(max);
+        self
+    }
+
+    /// Create the directories [`create_parents`](Self::create_parents) makes with this mode
+    /// (Unix), rather than `0o777`.
+    ///
+    /// As with `mkdir`, the umask is taken away from it: `0o770` with the usual umask of `0o022`
+    /// gives `0o750`. That’s usually what’s wanted, as it leaves the user the last word; for
+    /// exactly `mode`, whatever the umask, see
+    /// [`parent_dir_mode_exact`](Self::parent_dir_mode_exact). Only directories created are
+    /// affected, not those already there. On Windows, where directories have ACLs rather than
+    /// modes, and elsewhere, this does nothing.
+    pub fn parent_dir_mode(&mut self, mode: u32) -> &mut SymlinkBuilder {
+        self.parent_dir_mode = Some(mode);
+        self
+    }
+
+    /// Give the directories [`create_parents`](Self::create_parents) makes exactly the mode set
+    /// by [`parent_dir_mode`](Self::parent_dir_mode) (or `0o777`), umask or no umask (Unix).
+    ///
+    /// Each is created as usual and then has its mode set, so a multi-user link farm can have
+    /// group-writable directories even when run by someone with a strict umask. The mode is set
+    /// through the descriptor the directory was opened with, not by path, and only on a
+    /// directory this created; one that something else created in the meantime keeps the mode it
+    /// was given. On Windows and elsewhere, this does nothing.
+    pub fn parent_dir_mode_exact(&mut self, exact: bool) -> &mut SymlinkBuilder {
+        self.parent_dir_mode_exact = exact;
+        self
+    }
+
+    /// Replace a symlink that is already at the link path, rather than failing.
+    ///
+    /// Only symlinks are replaced; anything else there is still an
+    /// [`AlreadyExists`](io::ErrorKind::AlreadyExists) error. On Unix the replacement is atomic:
+    /// the new link is created under a temporary name beside the old one and renamed over it, so
+    /// there’s never a moment with no link. (The temporary link is created like any other, failing
+    /// if something is already there, so two processes replacing links in the same directory can’t
+    /// clobber each other’s; a name that’s taken means trying another, up to 16 times, and then an
+    /// [`AlreadyExists`](io::ErrorKind::AlreadyExists) error saying so.) Elsewhere the old link is
+    /// removed and then the new one created, so there is a moment with no link; if the new one
+    /// can’t be created, the old one is restored.
+    /// [`ATOMIC_REPLACE_SUPPORTED`](crate::ATOMIC_REPLACE_SUPPORTED) says which. This can’t be
+    /// combined with [`nofollow_parents`](Self::nofollow_parents), as the rename would follow
+    /// parent symlinks; trying to returns an [`InvalidInput`](io::ErrorKind::InvalidInput) error
+    /// before anything is looked at, whether or not there’s a link to replace.
+    pub fn replace_existing(&mut self, replace_existing: bool) -> &mut SymlinkBuilder {
+        self.replace_existing = replace_existing;
+        self
+    }
+
+    /// When replacing a symlink, give the new one the old one’s owner and times.
+    ///
+    /// With [`replace_existing`](Self::replace_existing), this reads the old link’s own
+    /// attributes (with `lstat`) and applies them to the replacement before it’s renamed into
+    /// place—the owner with `lchown`, and the access and modification times with `utimensat`
+    /// (on Linux, Android, macOS, iOS and FreeBSD)—so that a repoint is invisible to anything
+    /// that looks at them. A symlink’s mode is meaningless on most platforms, so it isn’t copied.
+    /// Changing the owner to someone else takes privilege, and if it fails, nothing is replaced.
+    ///
+    /// On Windows, and elsewhere, this does nothing. For extended attributes, see
+    /// [`preserve_xattrs`](Self::preserve_xattrs).
+    pub fn preserve_attrs(&mut self, preserve_attrs: bool) -> &mut SymlinkBuilder {
+        self.preserve_attrs = preserve_attrs;
+        self
+    }
+
+    /// When replacing a symlink, give the new one the old one’s extended attributes (Linux only).
+    ///
+    /// With [`replace_existing`](Self::replace_existing), this reads the old link’s own extended
+    /// attributes (with `llistxattr` and `lgetxattr`) and sets them on the replacement (with
+    /// `lsetxattr`) before it’s renamed into place. That’s chiefly for SELinux, whose
+    /// `security.selinux` label a fresh link would otherwise get from policy rather than from
+    /// the link it replaces, and a system tool working under an enforcing policy may depend on
+    /// it. Setting some attributes takes privilege, and if setting any fails, nothing is
+    /// replaced; a filesystem without extended attributes has none to copy.
+    ///
+    /// Linux is the only platform this does anything on; elsewhere it’s a no-op.
+    pub fn preserve_xattrs(&mut self, preserve_xattrs: bool) -> &mut SymlinkBuilder {
+        self.preserve_xattrs = preserve_xattrs;
+        self
+    }
+
+    /// Choose whether the target is stored as given, as an absolute path, or relative to the link.
+    ///
+    /// Whether a link survives its tree being moved or copied elsewhere depends on this, and
+    /// setting it here gives one place to decide for a whole batch of links, whatever form the
+    /// targets arrive in. [`Absolute`](TargetStyle::Absolute) and
+    /// [`Relative`](TargetStyle::Relative) both work out where the target really is first—relative
+    /// to the link’s parent—so the link points to the same place in every style; only the
+    /// spelling changes.
+    ///
+    /// The conversion is lexical, against the working directory, and doesn’t touch the
+    /// filesystem, so targets that don’t exist yet are fine. That means `..` is taken at face
+    /// value: if a symlink in the link’s parent path leads somewhere else, a relative target
+    /// computed this way won’t resolve as intended. On Windows, a relative target can’t cross
+    /// drives; asking for one that would is an [`InvalidInput`](io::ErrorKind::InvalidInput)
+    /// error.
+    pub fn target_style(&mut self, target_style: TargetStyle) -> &mut SymlinkBuilder {
+        self.target_style = target_style;
+        self
+    }
+
+    /// Choose which separators are written in the target, on Windows.
+    ///
+    /// This is applied after [`target_style`](Self::target_style), so it covers the separators
+    /// that computing an absolute or relative target introduces too. See [`TargetSeparators`]
+    /// for the caveats of forward slashes. Elsewhere this does nothing.
+    pub fn target_separators(&mut self, separators: TargetSeparators) -> &mut SymlinkBuilder {
+        self.target_separators = separators;
+        self
+    }
+
+    /// Tidy up the link path—not the target—before creating the link: drop `.` components, and
+    /// collapse each `..` with the component before it.
+    ///
+    /// This is purely lexical, and so it changes where the link is created whenever a component
+    /// before a `..` is a symlink: the OS would resolve `a/../b` by following `a` and then going
+    /// up from wherever that leads, where this makes it plain `b`. That’s the point, if you want
+    /// link paths to mean what they look like, but it’s why this is off by default. It also means
+    /// a relative target is taken relative to the tidied path’s parent.
+    ///
+    /// A link path that tidies away to nothing (`a/..`) is an
+    /// [`InvalidInput`](io::ErrorKind::InvalidInput) error.
+    pub fn lexically_normalize(&mut self, lexically_normalize: bool) -> &mut SymlinkBuilder {
+        self.lexically_normalize = lexically_normalize;
+        self
+    }
+
+    /// Refuse to create a link whose name differs only in case from something already beside it.
+    ///
+    /// On the default filesystems of macOS and Windows, `Foo` and `foo` are the same name, so a
+    /// set of links written on Linux with both can fail, or replace one another, when used there.
+    /// With this option, the link’s parent directory is scanned first, and if it has an entry
+    /// whose name matches the link’s ignoring case but isn’t exactly the same, an
+    /// [`AlreadyExists`](io::ErrorKind::AlreadyExists) error naming it is returned—on every
+    /// platform, so that the problem shows up wherever the links are made, not just where it
+    /// bites. (An entry with exactly the link’s name is handled as usual.)
+    ///
+    /// Names are compared after Unicode lowercasing, which is close to but not exactly what any
+    /// particular filesystem does; names that aren’t valid Unicode are compared exactly. This
+    /// costs a directory listing per link.
+    pub fn case_sensitive_check(&mut self, case_sensitive_check: bool) -> &mut SymlinkBuilder {
+        self.case_sensitive_check = case_sensitive_check;
+        self
+    }
+
+    /// Read the link back after creating it and check that it holds the target it was given.
+    ///
+    /// The stored target is compared with the one written—after
+    /// [`target_style`](Self::target_style) and [`target_separators`](Self::target_separators)
+    /// have had their say—using [`targets_equivalent`](crate::targets_equivalent). A mismatch,
+    /// whether from a filesystem that mangles targets or from something swapping in a link of its
+    /// own, is an [`InvalidData`](io::ErrorKind::InvalidData) error naming both. The link is left
+    /// where it is, since it may not be ours to remove.
+    ///
+    /// This checks what the link says, not that what it says exists: a dangling link passes.
+    /// It’s also only a check at one moment, so it can’t stop the link being changed afterwards.
+    pub fn verify_after_create(&mut self, verify_after_create: bool) -> &mut SymlinkBuilder {
+        self.verify_after_create = verify_after_create;
+        self
+    }
+
+    /// Compare targets with `comparator` for [`verify_after_create`](Self::verify_after_create),
+    /// rather than with [`targets_equivalent`](crate::targets_equivalent).
+    ///
+    /// That is, with a case sensitivity other than the platform’s, for a filesystem that differs
+    /// from it: on a case-insensitive one, a filesystem that stores the target differently cased
+    /// passes, and on a case-sensitive one it doesn’t.
+    pub fn target_comparator(&mut self, comparator: TargetComparator) -> &mut SymlinkBuilder {
+        self.target_comparator = comparator;
+        self
+    }
+
+    /// Succeed without doing anything if there’s already a symlink at the link path modified
+    /// after `reference_time`.
+    ///
+    /// This is for incremental builds with large sets of links, most of them up to date from
+    /// last time: pass the time the targets were last generated, and a link made since then is
+    /// taken on trust—not read, not compared, not touched—and the call returns `Ok(())` as if it
+    /// had been created. (To tell the two apart, and count what was skipped, there are the
+    /// `_outcome` methods, such as [`symlink_file_outcome`](Self::symlink_file_outcome), which
+    /// say [`Skipped`](PolicyOutcome::Skipped).) It’s the link’s own modification time that
+    /// counts, as `lstat` gives it, not its target’s, so this says nothing about whether the
+    /// target has changed since. A link no newer than `reference_time`, or anything that isn’t a
+    /// symlink, is dealt with as usual.
+    pub fn skip_if_newer(&mut self, reference_time: SystemTime) -> &mut SymlinkBuilder {
+        self.skip_if_newer = Some(reference_time);
+        self
+    }
+
+    /// Flush the link’s directory to disk once the link is in place (Unix only).
+    ///
+    /// Creating a link, or renaming a new one over the old with
+    /// [`replace_existing`](Self::replace_existing), changes a directory entry, and the OS is
+    /// free to keep that change in memory for a while: after a power failure the link may be
+    /// missing, or still point at the old target. With this option, the link’s parent directory
+    /// is opened and `fsync`ed after the link is created or replaced, so that when the call
+    /// returns the change has reached the disk (as far as the disk itself can be trusted).
+    ///
+    /// That costs an open and an `fsync` per link, and an `fsync` can take milliseconds or more
+    /// on a busy disk, since it waits for the hardware; for a batch of links in one directory it’s
+    /// much cheaper to leave this off and sync the directory once at the end. A link left alone
+    /// (by [`skip_if_newer`](Self::skip_if_newer), say) isn’t synced. If the sync fails, the
+    /// error is returned, though the link is in place.
+    ///
+    /// Windows offers no way to flush a directory’s entries through the standard library, and
+    /// NTFS journals them anyway, so there (and on other platforms) this does nothing.
+    pub fn durable(&mut self, durable: bool) -> &mut SymlinkBuilder {
+        self.durable = durable;
+        self
+    }
+
+    /// On Windows, create links whose path or target has a name ending in a dot or space, keeping
+    /// the name as written, rather than refusing to.
+    ///
+    /// Windows drops such a dot or space from a name unless the path is verbatim, so by default
+    /// creating such a link is an error (see the
+    /// [crate docs](crate#names-ending-in-a-dot-or-space)).
+    /// With this option, a link path with such a name is made verbatim—absolute, normalised, and
+    /// prefixed with `\\?\`—before the link is created, and so is a target with one, which means
+    /// such a target is stored absolute, whatever [`target_style`](Self::target_style) says: a
+    /// relative target would be resolved by Windows from the link’s directory without the
+    /// verbatim prefix, and lose the dot all over again. Paths without such names are left as
+    /// they are.
+    ///
+    /// Many Windows programs, Explorer included, can’t open or delete a file called `foo.` that
+    /// this makes possible; think twice before handing them one. Elsewhere names are taken as
+    /// given anyway, and this does nothing.
+    pub fn preserve_trailing_dots(&mut self, preserve_trailing_dots: bool)
+                                  -> &mut SymlinkBuilder {
+        self.preserve_trailing_dots = preserve_trailing_dots;
+        self
+    }
+
+    /// Expand a leading `~` in the target and the link path to the home directory.
+    ///
+    /// As a shell would for `~` and `~/…`: a path whose first component is `~` has it replaced
+    /// with the home directory, taken from `$HOME` on Unix and WASI and `%USERPROFILE%` on
+    /// Windows, and a `~` further along is left alone. Where the variable isn’t set (or is
+    /// empty), a path that needs it is a [`NotFound`](io::ErrorKind::NotFound) error. `~user`,
+    /// for another user’s home directory, isn’t expanded: that needs the password database,
+    /// which this crate doesn’t read, so a path whose first component starts with `~` and goes
+    /// on is an [`InvalidInput`](io::ErrorKind::InvalidInput) error, rather than a link to or at
+    /// a directory of that name, which is unlikely to be what was meant. (`./~user` gets one.)
+    ///
+    /// This is for dotfiles-style manifests written by hand, and happens first, before any
+    /// other option has its say; an expanded target is absolute, so
+    /// [`target_style`](Self::target_style) is the way to have it stored relative.
+    pub fn expand_tilde(&mut self, expand_tilde: bool) -> &mut SymlinkBuilder {
+        self.expand_tilde = expand_tilde;
+        self
+    }
+
+    /// Refuse to create a dangling link.
+    ///
+    /// Dangling links are legitimate—a link may well be made before the thing it points to—so
+    /// by default nothing checks. With this option, the target is looked up first, following any
+    /// symlinks on the way as the new link would, and if there’s nothing there a
+    /// [`NotFound`](io::ErrorKind::NotFound) error saying so is returned and nothing is created.
+    /// A relative target is looked for relative to the link’s parent directory, where the link
+    /// will look for it, not the working directory. Any other failure to look it up is returned
+    /// as it is.
+    ///
+    /// It’s only a check at one moment: the target can still disappear afterwards.
+    pub fn require_target_exists(&mut self, require_target_exists: bool)
+                                 -> &mut SymlinkBuilder {
+        self.require_target_exists = require_target_exists;
+        self
+    }
+
+    /// Check, after creating the link, that it’s still a symlink reparse point (Windows only).
+    ///
+    /// Security software on some Windows machines quarantines or rewrites a symlink the moment
+    /// it’s created, after `CreateSymbolicLinkW` has reported success. With this option the new
+    /// link is opened again and its reparse data read, as `read_symlink_names` does, to confirm
+    /// that it still carries `FILE_ATTRIBUTE_REPARSE_POINT` with the tag `IO_REPARSE_TAG_SYMLINK`.
+    /// If it’s gone, isn’t a reparse point any more, or has another tag, that’s an
+    /// [`InvalidData`](io::ErrorKind::InvalidData) error saying which, so the interference is
+    /// found out at once rather than by whatever next follows the link. It’s checked before
+    /// [`verify_after_create`](Self::verify_after_create) reads the target back, and like that
+    /// it’s only a check at one moment. Elsewhere this does nothing.
+    pub fn verify_reparse_point(&mut self, verify_reparse_point: bool) -> &mut SymlinkBuilder {
+        self.verify_reparse_point = verify_reparse_point;
+        self
+    }
+
+    /// When [replacing](Self::replace_existing) a symlink, leave it alone if its target has the
+    /// same contents as the new one.
+    ///
+    /// This is for links into content-addressed stores, where two different blob paths may hold
+    /// identical bytes and repointing from one to the other would change nothing but the link’s
+    /// timestamp. Both targets are read and compared byte for byte (after comparing their sizes,
+    /// which settles most differences for free), so it costs as much as reading the file twice;
+    /// only regular files are compared, and one that can’t be read counts as different. A link
+    /// left alone because of this is a success, as if it had been replaced, and
+    /// [`Skipped`](PolicyOutcome::Skipped) to the `_outcome` methods.
+    ///
+    /// It only ever applies to an existing symlink that would otherwise be replaced, so it does
+    /// nothing without `replace_existing`.
+    pub fn skip_if_same_content(&mut self, skip_if_same_content: bool) -> &mut SymlinkBuilder {
+        self.skip_if_same_content = skip_if_same_content;
+        self
+    }
+
+    /// If the target is itself a symlink, link to the end of its chain instead.
+    ///
+    /// Layouts generated over and over—each release’s `current` pointing at the last one’s, say—
+    /// can grow chains of links with no bound, each adding a hop to every lookup until the OS’s
+    /// limit is reached. With this option, the target is followed (as by
+    /// [`resolve_symlink`](crate::resolve_symlink), which stops at loops) to the first path that
+    /// isn’t a symlink, and that is what the new link stores. Only the last component is followed
+    /// at each step, so symlinked parent directories stay as they are. A target that isn’t a
+    /// symlink is left exactly as given; one whose chain dangles is linked to where it dangles.
+    ///
+    /// The collapsed target is written in the same form as the one given, relative to the link’s
+    /// parent if that was relative (or absolute, if it can’t be, as across drives on Windows)
+    /// and absolute if it was absolute; [`target_style`](Self::target_style) then restyles it
+    /// like any other target.
+    pub fn collapse_target(&mut self, collapse_target: bool) -> &mut SymlinkBuilder {
+        self.collapse_target = collapse_target;
+        self
+    }
+
+    /// Create a symlink to a file, with the configured options.
+    ///
+    /// See [`symlink_file`](crate::symlink_file) for details.
+    pub fn symlink_file<P: AsRef<Path>, Q: AsRef<Path>>(&self, src: P, dst: Q) -> io::Result<()> {
+        self.create(Some(SymlinkKind::File), src.as_ref(), dst.as_ref())
+    }
+
+    /// Create a symlink to a directory, with the configured options.
+    ///
+    /// See [`symlink_dir`](crate::symlink_dir) for details.
+    pub fn symlink_dir<P: AsRef<Path>, Q: AsRef<Path>>(&self, src: P, dst: Q) -> io::Result<()> {
+        self.create(Some(SymlinkKind::Dir), src.as_ref(), dst.as_ref())
+    }
+
+    /// Create a symlink (non-preferred way), with the configured options.
+    ///
+    /// See [`symlink_auto`](crate::symlink_auto) for details.
+    pub fn symlink_auto<P: AsRef<Path>, Q: AsRef<Path>>(&self, src: P, dst: Q) -> io::Result<()> {
+        self.create(None, src.as_ref(), dst.as_ref())
+    }
+
+    /// Create a symlink to a file, as [`symlink_file`](Self::symlink_file) does, and say
+    /// what was done.
+    ///
+    /// The outcome is [`Created`](PolicyOutcome::Created) for a new link,
+    /// [`Replaced`](PolicyOutcome::Replaced) for one that took the place of an old link with
+    /// [`replace_existing`](Self::replace_existing), and [`Skipped`](PolicyOutcome::Skipped) for
+    /// an old link left alone by [`skip_if_newer`](Self::skip_if_newer) or
+    /// [`skip_if_same_content`](Self::skip_if_same_content): enough for an incremental build to
+    /// count what it made and what it took on trust.
+    pub fn symlink_file_outcome<P: AsRef<Path>, Q: AsRef<Path>>(&self, src: P, dst: Q)
+                                                                -> io::Result<PolicyOutcome> {
+        self.create_outcome(Some(SymlinkKind::File), src.as_ref(), dst.as_ref())
+    }
+
+    /// Create a symlink to a directory, as [`symlink_dir`](Self::symlink_dir) does, and say
+    /// what was done.
+    ///
+    /// See [`symlink_file_outcome`](Self::symlink_file_outcome) for details.
+    pub fn symlink_dir_outcome<P: AsRef<Path>, Q: AsRef<Path>>(&self, src: P, dst: Q)
+                                                               -> io::Result<PolicyOutcome> {
+        self.create_outcome(Some(SymlinkKind::Dir), src.as_ref(), dst.as_ref())
+    }
+
+    /// Create a symlink (non-preferred way), as [`symlink_auto`](Self::symlink_auto) does, and
+    /// say what was done.
+    ///
+    /// See [`symlink_file_outcome`](Self::symlink_file_outcome) for details.
+    pub fn symlink_auto_outcome<P: AsRef<Path>, Q: AsRef<Path>>(&self, src: P, dst: Q)
+                                                                -> io::Result<PolicyOutcome> {
+        self.create_outcome(None, src.as_ref(), dst.as_ref())
+    }
+
+    // A kind of None means auto.
+    pub(crate) fn create(&self, kind: Option<SymlinkKind>, src: &Path, dst: &Path)
+                         -> io::Result<()> {
+        self.create_outcome(kind, src, dst).map(|_| ())
+    }
+
+    // The target and link path as create_outcome first rewrites them: with a leading `~`
+    // expanded, and the link path normalised, as the options say; None for a path left as it is.
+    // SymlinkContext checks what this gives, not what it was given, and hands it on, which is
+    // fine since rewriting them again changes nothing.
+    pub(crate) fn rewritten_paths(&self, src: &Path, dst: &Path)
+                                  -> io::Result<(Option<PathBuf>, Option<PathBuf>)> {
+        let (expanded_src, expanded_dst) = if self.expand_tilde {
+            (paths::expand_tilde(src)?, paths::expand_tilde(dst)?)
+        } else {
+            (None, None)
+        };
+        let dst = if self.lexically_normalize {
+            Some(paths::normalize_lexically(expanded_dst.as_deref().unwrap_or(dst)))
+        } else {
+            expanded_dst
+        };
+        Ok((expanded_src, dst))
+    }
+
+    // Where the link will really be made, given the link path after rewritten_paths: made
+    // verbatim if preserve_trailing_dots says so, or None for the link path as it is.
+    pub(crate) fn link_path_made(&self, dst: &Path) -> io::Result<Option<PathBuf>> {
+        if self.preserve_trailing_dots {
+            preserve_trailing_dot(dst)
+        } else {
+            Ok(None)
+        }
+    }
+
+    // Options that can’t go together, refused before anything is looked at or changed.
+    pub(crate) fn check_options(&self) -> io::Result<()> {
+        if self.replace_existing && self.nofollow_parents {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "replace_existing can’t be combined with nofollow_parents"));
+        }
+        Ok(())
+    }
+
+    fn create_outcome(&self, kind: Option<SymlinkKind>, src: &Path, dst: &Path)
+                      -> io::Result<PolicyOutcome> {
+        self.check_options()?;
+        let (rewritten_src, rewritten_dst) = self.rewritten_paths(src, dst)?;
+        let src = rewritten_src.as_deref().unwrap_or(src);
+        let dst = rewritten_dst.as_deref().unwrap_or(dst);
+        check_not_empty(src, dst)?;
+        if let Some(reference_time) = self.skip_if_newer {
+            if is_newer_symlink(dst, reference_time) {
+                return Ok(PolicyOutcome::Skipped);
+            }
+        }
+        let collapsed;
+        let src = if self.collapse_target {
+            collapsed = collapse_target(src, dst)?;
+            &collapsed
+        } else {
+            src
+        };
+        let styled;
+        let src = match self.target_style {
+            TargetStyle::AsGiven => src,
+            style => {
+                styled = style_target(style, src, dst)?;
+                &styled
+            }
+        };
+        let separated;
+        let src = match self.target_separators {
+            TargetSeparators::AsGiven => src,
+            separators => {
+                separated = paths::with_separators(src,
+                                                   separators == TargetSeparators::ForwardSlash);
+                &separated
+            }
+        };
+        let (preserved_src, preserved_dst) = if self.preserve_trailing_dots {
+            preserve_trailing_dots(src, dst)?
+        } else {
+            (None, None)
+        };
+        let src = preserved_src.as_deref().unwrap_or(src);
+        let dst = preserved_dst.as_deref().unwrap_or(dst);
+        // Checked here against the real link path, as well as where the link is made, since a
+        // replacement is made under a temporary name.
+        check_not_self_link(src, dst).and_then(|()| check_trailing_names(src, dst))?;
+        if self.reject_self_reference {
+            check_self_reference(src, dst)?;
+        }
+        if self.require_target_exists {
+            check_target_exists(src, dst)?;
+        }
+        if self.create_parents {
+            create_parents(dst, self.max_parent_creation, self.parent_dir_mode.unwrap_or(0o777),
+                           self.parent_dir_mode_exact)?;
+        }
+        if self.case_sensitive_check {
+            check_case_collision(dst)?;
+        }
+        let outcome = self.place(kind, src, dst)?;
+        if outcome != PolicyOutcome::Skipped {
+            if self.durable {
+                sync_parent(dst)?;
+            }
+            if self.verify_reparse_point {
+                verify_reparse_point(dst)?;
+            }
+            if self.verify_after_create {
+                verify_target(&self.target_comparator, src, dst)?;
+            }
+        }
+        Ok(outcome)
+    }
+
+    // Create the link, or replace the one there if so configured, or leave the old link alone.
+    fn place(&self, kind: Option<SymlinkKind>, src: &Path, dst: &Path)
+             -> io::Result<PolicyOutcome> {
+        if self.replace_existing {
+            if let Ok(old) = stats::symlink_metadata(dst) {
+                if old.file_type().is_symlink() {
+                    if self.skip_if_same_content
+                       && same_content(dst, &paths::resolve_target(dst, src)) {
+                        return Ok(PolicyOutcome::Skipped);
+                    }
+                    let attrs = if self.preserve_attrs { Some(&old) } else { None };
+                    replace_symlink(dst, attrs, self.preserve_xattrs,
+                                    |path| self.create_new(kind, src, path))?;
+                    return Ok(PolicyOutcome::Replaced);
+                }
+            }
+        }
+        self.create_new(kind, src, dst).map(|()| PolicyOutcome::Created)
+    }
+
+    // Create the link, which isn’t expected to exist yet.
+    fn create_new(&self, kind: Option<SymlinkKind>, src: &Path, dst: &Path) -> io::Result<()> {
+        if self.nofollow_parents {
+            return create_checked(kind, src, dst, symlink_nofollow_parents);
+        }
+        let auto_kind = || classify(self, src, dst);
+        match (kind, self.prefer_kind) {
+            (None, Some(preferred)) => create_symlink(Some(preferred), src, dst),
+            (None, None) => create_symlink(auto_kind()?, src, dst),
+            _ => create_symlink(kind, src, dst),
+        }
+    }
+}
+
+// Whether dst is a symlink modified after reference_time. Anything unreadable counts as not.
+fn is_newer_symlink(dst: &Path, reference_time: SystemTime) -> bool {
+    match stats::symlink_metadata(dst) {
+        Ok(ref metadata) if metadata.file_type().is_symlink() => {
+            metadata.modified().map(|modified| modified > reference_time).unwrap_or(false)
+        }
+        _ => false,
+    }
+}
+
+// Whether a and b, followed through any symlinks, are regular files with the same contents.
+fn same_content(a: &Path, b: &Path) -> bool {
+    fn compare(a: &Path, b: &Path) -> io::Result<bool> {
+        let (mut a, mut b) = (fs::File::open(a)?, fs::File::open(b)?);
+        let (a_meta, b_meta) = (a.metadata()?, b.metadata()?);
+        if !a_meta.is_file() || !b_meta.is_file() || a_meta.len() != b_meta.len() {
+            return Ok(false);
+        }
+        let (mut a_buf, mut b_buf) = (vec![0; 64 * 1024], vec![0; 64 * 1024]);
+        loop {
+            let n = a.read(&mut a_buf)?;
+            if n == 0 {
+                // Equal lengths, so b should be done too, unless it’s growing under us.
+                return Ok(b.read(&mut b_buf[..1])? == 0);
+            }
+            b.read_exact(&mut b_buf[..n])?;
+            if a_buf[..n] != b_buf[..n] {
+                return Ok(false);
+            }
+        }
+    }
+    compare(a, b).unwrap_or(false)
+}
+
+// Read the new link back and check it holds the target it was created with.
+fn verify_target(comparator: &TargetComparator, src: &Path, dst: &Path) -> io::Result<()> {
+    let stored = stats::read_link(dst)?;
+    if comparator.equivalent(&stored, src) {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::InvalidData,
+                           format!("symlink {} was created pointing at {} but reads back as {}",
+                                   dst.display(), src.display(), stored.display())))
+    }
+}
+
+fn check_target_exists(src: &Path, dst: &Path) -> io::Result<()> {
+    match stats::metadata(paths::resolve_target(dst, src)) {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            Err(error::with_context(io::ErrorKind::NotFound,
+                                    format!("symlink {} would dangle: its target {} doesn’t \
+                                             exist", dst.display(), src.display()),
+                                    e))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+// Create the missing ancestors of the link, outermost first, having counted them all first so
+// that going over the limit creates nothing.
+fn create_parents(dst: &Path, max: Option<usize>, mode: u32, exact: bool) -> io::Result<()> {
+    let mut missing = vec![];
+    let mut ancestors = dst.ancestors().skip(1);
+    while let Some(ancestor) = ancestors.next().filter(|a| !a.as_os_str().is_empty()) {
+        match stats::symlink_metadata(ancestor) {
+            Ok(_) => break,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => missing.push(ancestor),
+            Err(e) => return Err(e),
+        }
+    }
+    if let Some(max) = max {
+        if missing.len() > max {
+            return Err(io::Error::new(io::ErrorKind::NotFound,
+                                      format!("creating {} would need {} missing parent \
+                                               directories, more than the limit of {}",
+                                              dst.display(), missing.len(), max)));
+        }
+    }
+    missing.reverse();
+    create_dirs(&missing, mode, exact)
+}
+
+#[cfg(unix)]
+fn create_dirs(dirs: &[&Path], mode: u32, exact: bool) -> io::Result<()> {
+    ::unix::create_dirs_nofollow(dirs, ::unix::DirMode { mode, exact })
+}
+
+#[cfg(not(unix))]
+fn create_dirs(dirs: &[&Path], _mode: u32, _exact: bool) -> io::Result<()> {
+    for dir in dirs {
+        match fs::create_dir(dir) {
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists && dir.is_dir() => {}
+            result => result?,
+        }
+    }
+    Ok(())
+}
+
+// The target rewritten as absolute or relative, pointing where src would from dst.
+fn style_target(style: TargetStyle, src: &Path, dst: &Path) -> io::Result<PathBuf> {
+    let target = paths::absolute(&paths::resolve_target(dst, src))?;
+    if style == TargetStyle::Absolute {
+        return Ok(target);
+    }
+    let link = paths::absolute(dst)?;
+    let parent = link.parent().unwrap_or(&link);
+    paths::relative_to(&target, parent).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput,
+                       format!("symlink target {} can’t be made relative to {}",
+                               src.display(), parent.display()))
+    })
+}
+
+// The end of the chain of links from src as seen from dst, written the way src was.
+fn collapse_target(src: &Path, dst: &Path) -> io::Result<PathBuf> {
+    let start = paths::resolve_target(dst, src);
+    let walk = resolve::walk_chain(&start, resolve::MAX_RESOLVED_COMPONENTS, |_| ())?;
+    if walk.hops == 0 {
+        return Ok(src.to_owned());
+    }
+    let end = paths::absolute(&walk.end)?;
+    if src.is_relative() {
+        if let Ok(relative) = style_target(TargetStyle::Relative, &end, dst) {
+            return Ok(relative);
+        }
+    }
+    Ok(end)
+}
+
+// Look for an entry beside dst whose name is dst’s but for case.
+fn check_case_collision(dst: &Path) -> io::Result<()> {
+    let name = match dst.file_name().and_then(|name| name.to_str()) {
+        Some(name) => name,
+        None => return Ok(()),
+    };
+    let folded = name.to_lowercase();
+    let parent = match dst.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let entries = match fs::read_dir(parent) {
+        Ok(entries) => entries,
+        // Creating the link will fail with a better error.
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let entry = entry?.file_name();
+        if let Some(other) = entry.to_str() {
+            if other != name && other.to_lowercase() == folded {
+                let message = format!("{} differs only in case from {}, which exists already",
+                                      dst.display(), parent.join(other).display());
+                return Err(error::marked(error::Cause::Conflict,
+                                         io::Error::new(io::ErrorKind::AlreadyExists, message)));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_self_reference(src: &Path, dst: &Path) -> io::Result<()> {
+    let link = paths::absolute(dst)?;
+    let target = paths::resolve_target(dst, src);
+    let mut self_referential = link.starts_with(paths::absolute(&target)?);
+    if !self_referential {
+        if let (Ok(target), Some(Ok(parent)), Some(name)) =
+            (fs::canonicalize(&target), link.parent().map(fs::canonicalize), link.file_name())
+        {
+            self_referential = parent.join(name).starts_with(target);
+        }
+    }
+    if self_referential {
+        Err(io::Error::new(io::ErrorKind::InvalidInput,
+                           format!("symlink target {} is the link itself or one of its ancestors",
+                                   src.display())))
+    } else {
+        Ok(())
+    }
+}
+
+// How many temporary names replace_symlink tries before giving up.
+#[cfg(unix)]
+const TEMP_NAME_ATTEMPTS: usize = 16;
+
+// Replace the symlink at dst with whatever create makes, atomically: beside it and then over it.
+// Creating a symlink fails if anything is already there, so a temporary name someone else is
+// using is never clobbered; it just means trying another.
+#[cfg(unix)]
+pub(crate) fn replace_symlink<F>(dst: &Path, attrs: Option<&fs::Metadata>, xattrs: bool,
+                                 mut create: F) -> io::Result<()>
+    where F: FnMut(&Path) -> io::Result<()>
+{
+    let xattrs = if xattrs { ::unix::read_link_xattrs(dst)? } else { vec![] };
+    let mut attempts = 0;
+    let temp = loop {
+        let temp = paths::temp_sibling(dst)?;
+        attempts += 1;
+        match create(&temp) {
+            Ok(()) => break temp,
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists
+                          && attempts < TEMP_NAME_ATTEMPTS => {}
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                return Err(error::with_context(io::ErrorKind::AlreadyExists,
+                                               format!("no unused temporary name beside {} in \
+                                                        {} tries", dst.display(), attempts),
+                                               e));
+            }
+            Err(e) => return Err(e),
+        }
+    };
+    let result = attrs.map_or(Ok(()), |attrs| ::unix::copy_link_attrs(attrs, &temp))
+        .and_then(|()| ::unix::write_link_xattrs(&temp, &xattrs))
+        .and_then(|()| fs::rename(&temp, dst));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+// Without rename-over, replacing has to be a removal and then a creation, with the old link put
+// back as it was if the creation fails. Attributes aren’t copied.
+#[cfg(not(unix))]
+fn replace_symlink<F>(dst: &Path, _attrs: Option<&fs::Metadata>, _xattrs: bool, create: F)
+                      -> io::Result<()>
+    where F: FnOnce(&Path) -> io::Result<()>
+{
+    let old_target = stats::read_link(dst)?;
+    #[cfg(windows)]
+    let old_kind = internal::symlink_kind(dst)?;
+    #[cfg(not(windows))]
+    let old_kind = None;
+    remove_symlink_auto(dst)?;
+    create(dst).map_err(|e| {
+        let _ = create_symlink(old_kind, &old_target, dst);
+        e
+    })
+}
+
+// The kind symlink_auto should create, or None to leave it to internal::symlink_auto.
+#[cfg(windows)]
+fn classify(builder: &SymlinkBuilder, src: &Path, dst: &Path)
+            -> io::Result<Option<SymlinkKind>> {
+    let guess = || if paths::looks_like_dir(src) { SymlinkKind::Dir } else { SymlinkKind::File };
+    let stat = || match builder.classify_timeout {
+        Some(timeout) => target_kind_within(src, dst, builder.follow_target, timeout),
+        None => internal::target_kind(src, dst, builder.follow_target),
+    };
+    match builder.classify {
+        ClassifyHeuristic::StatOnly if builder.classify_timeout.is_none()
+                                       && !builder.follow_target => Ok(None),
+        ClassifyHeuristic::StatOnly => stat().map(Some),
+        ClassifyHeuristic::StatThenHeuristic => Ok(Some(stat().unwrap_or_else(|_| guess()))),
+        ClassifyHeuristic::HeuristicOnly => Ok(Some(guess())),
+    }
+}
+
+// The kind symlink_auto would make a link at dst to src, worked out ahead of time so that it can
+// be cached; None where it wouldn’t look at the target to decide.
+#[cfg(windows)]
+pub(crate) fn auto_kind(builder: &SymlinkBuilder, src: &Path, dst: &Path)
+                        -> io::Result<Option<SymlinkKind>> {
+    if builder.prefer_kind.is_some() || builder.nofollow_parents
+       || builder.classify == ClassifyHeuristic::HeuristicOnly {
+        return Ok(None);
+    }
+    match classify(builder, src, dst)? {
+        Some(kind) => Ok(Some(kind)),
+        None => internal::target_kind(src, dst, builder.follow_target).map(Some),
+    }
+}
+
+#[cfg(not(windows))]
+fn classify(_builder: &SymlinkBuilder, _src: &Path, _dst: &Path)
+            -> io::Result<Option<SymlinkKind>> {
+    Ok(None)
+}
+
+#[cfg(unix)]
+fn sync_parent(dst: &Path) -> io::Result<()> {
+    let parent = match dst.parent() {
+        Some(parent) if parent != Path::new("") => parent,
+        _ => Path::new("."),
+    };
+    fs::File::open(parent)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_parent(_dst: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+// The target and link path made verbatim where they have names Windows would otherwise drop
+// the last dot or space from, or None where they’re fine as they are.
+#[cfg(windows)]
+fn preserve_trailing_dots(src: &Path, dst: &Path)
+                          -> io::Result<(Option<PathBuf>, Option<PathBuf>)> {
+    let src = match paths::trailing_dot_or_space(src) {
+        Some(_) => Some(paths::verbatim(&paths::resolve_target(dst, src))?),
+        None => None,
+    };
+    Ok((src, preserve_trailing_dot(dst)?))
+}
+
+#[cfg(not(windows))]
+fn preserve_trailing_dots(_src: &Path, _dst: &Path)
+                          -> io::Result<(Option<PathBuf>, Option<PathBuf>)> {
+    Ok((None, None))
+}
+
+// The link path made verbatim, if it has a name Windows would drop the last dot or space from.
+#[cfg(windows)]
+fn preserve_trailing_dot(dst: &Path) -> io::Result<Option<PathBuf>> {
+    match paths::trailing_dot_or_space(dst) {
+        Some(_) => paths::verbatim(dst).map(Some),
+        None => Ok(None),
+    }
+}
+
+#[cfg(not(windows))]
+fn preserve_trailing_dot(_dst: &Path) -> io::Result<Option<PathBuf>> {
+    Ok(None)
+}
+
+#[cfg(windows)]
+fn verify_reparse_point(dst: &Path) -> io::Result<()> {
+    internal::verify_reparse_point(dst)
+}
+
+#[cfg(not(windows))]
+fn verify_reparse_point(_dst: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+// internal::target_kind on a worker thread, abandoned if it takes longer than timeout.
+#[cfg(windows)]
+fn target_kind_within(src: &Path, dst: &Path, follow: bool, timeout: Duration)
+                      -> io::Result<SymlinkKind> {
+    let (sender, receiver) = mpsc::channel();
+    let (path, link) = (src.to_owned(), dst.to_owned());
+    // The probe thread’s own count dies with it, so count the look here.
+    stats::stat();
+    thread::Builder::new().name("symlink_auto probe".into()).spawn(move || {
+        // Nobody’s listening any more if this took too long, and that’s fine.
+        let _ = sender.send(internal::target_kind(&path, &link, follow));
+    })?;
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            Err(io::Error::new(io::ErrorKind::TimedOut,
+                               format!("timed out after {:?} reading the metadata of symlink \
+                                        target {}", timeout, src.display())))
+        }
+        Err(mpsc::RecvTimeoutError::Disconnected) => {
+            Err(io::Error::other(format!("reading the metadata of symlink target {} panicked",
+                                         src.display())))
+        }
+    }
+}
+
+// On Unix there’s no distinction between the kinds, so all three requests come down to this.
+#[cfg(unix)]
+fn symlink_nofollow_parents(src: &Path, dst: &Path) -> io::Result<()> {
+    ::unix::symlink_nofollow_parents(src, dst)
+}
+
+#[cfg(not(unix))]
+fn symlink_nofollow_parents(_src: &Path, _dst: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "nofollow_parents is only supported on Unix"))
+}
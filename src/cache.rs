@@ -0,0 +1,124 @@
+// A bounded cache of symlink_auto’s classifications, least recently used out first, for
+// SymlinkContext.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use SymlinkKind;
+
+/// How a [`SymlinkContext`](crate::SymlinkContext)’s classification cache has fared.
+///
+/// Only [`symlink_auto`](crate::SymlinkContext::symlink_auto) on Windows classifies targets, so
+/// elsewhere the cache stays empty, with no hits or misses.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CacheStats {
+    /// Targets whose kind was found in the cache, sparing a look at the filesystem.
+    pub hits: u64,
+    /// Targets that had to be looked at.
+    pub misses: u64,
+    /// Targets in the cache now.
+    pub len: usize,
+    /// The most targets the cache will hold.
+    pub capacity: usize,
+}
+
+pub(crate) const DEFAULT_CAPACITY: usize = 1024;
+
+#[derive(Debug)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub(crate) struct ClassificationCache {
+    // Each target’s kind, and when it was last used; and the targets by when they were last used.
+    entries: HashMap<PathBuf, (SymlinkKind, u64)>,
+    by_use: BTreeMap<u64, PathBuf>,
+    clock: u64,
+    capacity: usize,
+    hits: u64,
+    misses: u64,
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl ClassificationCache {
+    pub fn new(capacity: usize) -> ClassificationCache {
+        ClassificationCache {
+            entries: HashMap::new(),
+            by_use: BTreeMap::new(),
+            clock: 0,
+            capacity,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    // The kind of target, if it’s cached, counting the hit or miss.
+    pub fn get(&mut self, target: &Path) -> Option<SymlinkKind> {
+        self.clock += 1;
+        match self.entries.get_mut(target) {
+            Some(&mut (kind, ref mut used)) => {
+                self.by_use.remove(used);
+                *used = self.clock;
+                self.by_use.insert(self.clock, target.to_owned());
+                self.hits += 1;
+                Some(kind)
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn insert(&mut self, target: PathBuf, kind: SymlinkKind) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.clock += 1;
+        if let Some((_, used)) = self.entries.insert(target.clone(), (kind, self.clock)) {
+            self.by_use.remove(&used);
+        }
+        self.by_use.insert(self.clock, target);
+        while self.entries.len() > self.capacity {
+            let oldest = *self.by_use.keys().next().expect("cache entries without uses");
+            let target = self.by_use.remove(&oldest).expect("cache use just found");
+            self.entries.remove(&target);
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            len: self.entries.len(),
+            capacity: self.capacity,
+        }
+    }
+}
+
+impl Default for ClassificationCache {
+    fn default() -> ClassificationCache {
+        ClassificationCache::new(DEFAULT_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut cache = ClassificationCache::new(2);
+        let (a, b, c) = (PathBuf::from("a"), PathBuf::from("b"), PathBuf::from("c"));
+        cache.insert(a.clone(), SymlinkKind::File);
+        cache.insert(b.clone(), SymlinkKind::Dir);
+        assert_eq!(cache.get(&a), Some(SymlinkKind::File));
+        // b is now the least recently used, so it goes.
+        cache.insert(c.clone(), SymlinkKind::File);
+        assert_eq!(cache.get(&b), None);
+        assert_eq!(cache.get(&c), Some(SymlinkKind::File));
+        assert_eq!(cache.get(&a), Some(SymlinkKind::File));
+        assert_eq!(cache.stats(), CacheStats { hits: 3, misses: 1, len: 2, capacity: 2 });
+
+        let mut none = ClassificationCache::new(0);
+        none.insert(a.clone(), SymlinkKind::File);
+        assert_eq!(none.get(&a), None);
+    }
+}
@@ -0,0 +1,95 @@
+// Shell commands equivalent to creating a link, for logs and bug reports.
+
+use std::path::Path;
+
+use SymlinkKind;
+
+/// The shell command that would create the same link: `ln -s` on Unix, `mklink` on Windows.
+///
+/// Nothing is run; this is a formatter, for logging what a program is doing in a form that can be
+/// pasted into a terminal, and for bug reports. On Windows the command is for `cmd.exe`, with
+/// `mklink` for a file symlink and `mklink /D` for a directory symlink, and the link first, as
+/// `mklink` wants it; elsewhere it’s for a POSIX shell, `ln -s -- target link`, with the `--` so
+/// that a path starting with `-` isn’t taken for an option. (`ln -s` makes the same link whichever
+/// kind is asked for, as there’s only the one kind.)
+///
+/// Paths are quoted only if they need it: for a POSIX shell, in single quotes, with any single
+/// quote in them written `'\''`; for `cmd.exe`, in double quotes, which Windows paths can’t
+/// contain. `cmd.exe` expands `%VARIABLE%` even inside quotes, so a path containing `%` may not
+/// come out as intended there. Paths that aren’t valid Unicode are shown with U+FFFD in place of
+/// what isn’t, so such a command is only approximately equivalent.
+///
+/// ```
+/// use std::path::Path;
+/// use symlink_rs::{equivalent_command, SymlinkKind};
+///
+/// let command = equivalent_command(Path::new("../my files"), Path::new("docs"), SymlinkKind::Dir);
+/// if cfg!(windows) {
+///     assert_eq!(command, r#"mklink /D docs "../my files""#);
+/// } else {
+///     assert_eq!(command, "ln -s -- '../my files' docs");
+/// }
+/// ```
+pub fn equivalent_command(target: &Path, link: &Path, kind: SymlinkKind) -> String {
+    if cfg!(windows) {
+        cmd_command(target, link, kind)
+    } else {
+        posix_command(target, link)
+    }
+}
+
+fn posix_command(target: &Path, link: &Path) -> String {
+    format!("ln -s -- {} {}", posix_quote(target), posix_quote(link))
+}
+
+fn cmd_command(target: &Path, link: &Path, kind: SymlinkKind) -> String {
+    let flag = match kind {
+        SymlinkKind::File => "",
+        SymlinkKind::Dir => "/D ",
+    };
+    format!("mklink {}{} {}", flag, cmd_quote(link), cmd_quote(target))
+}
+
+fn posix_quote(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let plain = |c: char| c.is_ascii_alphanumeric() || "/._-+,:@%=".contains(c);
+    if !path.is_empty() && path.chars().all(plain) {
+        path.into_owned()
+    } else {
+        format!("'{}'", path.replace('\'', r"'\''"))
+    }
+}
+
+fn cmd_quote(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let special = |c: char| c.is_whitespace() || "&|<>^()%!,;=\"".contains(c);
+    if !path.is_empty() && !path.chars().any(special) {
+        path.into_owned()
+    } else {
+        format!("\"{}\"", path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn posix() {
+        let command = |target: &str, link: &str| posix_command(Path::new(target), Path::new(link));
+        assert_eq!(command("../a/b.txt", "link"), "ln -s -- ../a/b.txt link");
+        assert_eq!(command("it's here", "-n"), r"ln -s -- 'it'\''s here' -n");
+        assert_eq!(command("$HOME", ""), "ln -s -- '$HOME' ''");
+    }
+
+    #[test]
+    fn cmd() {
+        let command = |target: &str, link: &str, kind| {
+            cmd_command(Path::new(target), Path::new(link), kind)
+        };
+        assert_eq!(command(r"..\a\b.txt", "link", SymlinkKind::File), r"mklink link ..\a\b.txt");
+        assert_eq!(command(r"C:\Program Files", "pf", SymlinkKind::Dir),
+                   r#"mklink /D pf "C:\Program Files""#);
+        assert_eq!(command("a&b", "c", SymlinkKind::File), r#"mklink c "a&b""#);
+    }
+}
@@ -0,0 +1,640 @@
+// Settings for a run of operations, configured once: the builder’s options for creating links,
+// and a conflict policy for what to do when something is in the way.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+#[cfg(windows)]
+use builder::auto_kind;
+use cache::{CacheStats, ClassificationCache};
+use dirlock::DirLock;
+use error;
+use events::{EventOp, EventOutcome, SymlinkEvent};
+use inspect::device_id;
+use paths;
+use policy::{apply_policy, predict_policy};
+use resolve::{resolve_parents, resolve_symlink_limited, symlink_chain_limited, walk_chain,
+              MAX_RESOLVED_COMPONENTS};
+use {remove_symlink_auto, remove_symlink_dir, remove_symlink_file, stats, ConflictPolicy,
+     LinkKind, PolicyOutcome, SymlinkBuilder, SymlinkKind, SymlinkOps};
+
+/// A set of options and a [`ConflictPolicy`], applied to every link created through it.
+///
+/// Where a [`SymlinkBuilder`] configures how a link is made, a context adds what to do about
+/// anything already at the link path, so that a program making many links can settle both once
+/// and pass the context around. `SymlinkContext::new()` behaves exactly as the free functions do:
+/// default options, and [`ConflictPolicy::Fail`].
+///
+/// The policy takes precedence over the builder’s
+/// [`replace_existing`](SymlinkBuilder::replace_existing): only
+/// [`ReplaceSymlinkOnly`](ConflictPolicy::ReplaceSymlinkOnly) and
+/// [`Overwrite`](ConflictPolicy::Overwrite) replace anything.
+///
+/// A context is also a [`SymlinkOps`], so code written against that can be handed one, and it
+/// can be wrapped in an `AuditLog` (feature `audit`) to log what it does. Through `SymlinkOps` the
+/// [`PolicyOutcome`] is lost; call the methods directly to get it.
+///
+/// To confine everything done through it beneath one directory, as when extracting an untrusted
+/// archive, a context can be [jailed](Self::jail); to keep it from straying onto another
+/// filesystem, a network drive mounted somewhere in the tree say, it can be
+/// [restricted to a device](Self::restrict_to_device).
+///
+/// For bookkeeping layered on top—registering each link in an index, say—a context can also
+/// run a hook after each link it creates ([`on_created`](Self::on_created)) or removes
+/// ([`on_removed`](Self::on_removed)).
+///
+/// On Windows, [`symlink_auto`](Self::symlink_auto) has to look at each target to decide between
+/// a file and a directory symlink, and a context remembers what it found, so that linking to the
+/// same target again doesn’t look again. The cache holds the 1024 targets most recently used,
+/// by default, dropping the least recently used beyond that, so a long-running program creating
+/// links to a shifting set of targets doesn’t accumulate them forever; see
+/// [`classification_cache_capacity`](Self::classification_cache_capacity) and
+/// [`cache_stats`](Self::cache_stats).
+///
+/// Where several threads make and remove links in the same directories, a context can make them
+/// [take turns](Self::serialize_per_dir) there.
+///
+/// To preview a run without changing anything, as for a `--dry-run` option, set
+/// [`dry_run`](Self::dry_run): every call then only predicts what it would have done.
+///
+/// For a record of everything done through it, to look through afterwards, have it
+/// [record events](Self::record_events).
+///
+/// Chains of symlinks are followed—by [`jail_targets`](Self::jail_targets), and by
+/// [`resolve_symlink`](Self::resolve_symlink) and [`symlink_chain`](Self::symlink_chain)—with
+/// a bound on how long the paths along them may get, [`MAX_RESOLVED_COMPONENTS`] unless
+/// [`max_resolved_components`](Self::max_resolved_components) says otherwise.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// use symlink_rs::{ConflictPolicy, SymlinkBuilder, SymlinkContext, TargetStyle};
+///
+/// let mut options = SymlinkBuilder::new();
+/// options.create_parents(true).target_style(TargetStyle::Relative);
+/// let mut ctx = SymlinkContext::new();
+/// ctx.options(options).conflict_policy(ConflictPolicy::Backup { suffix: ".bak".into() });
+/// for name in &["bashrc", "vimrc", "gitconfig"] {
+///     ctx.symlink_file(format!("dotfiles/{}", name), format!(".{}", name))?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct SymlinkContext {
+    options: SymlinkBuilder,
+    policy: ConflictPolicy,
+    on_created: Option<Arc<CreatedHook>>,
+    on_removed: Option<Arc<RemovedHook>>,
+    jail: Option<PathBuf>,
+    jail_targets: bool,
+    device: Option<PathBuf>,
+    cache: Arc<Mutex<ClassificationCache>>,
+    dry_run: bool,
+    serialize_per_dir: bool,
+    lock_files: bool,
+    max_resolved_components: Option<usize>,
+    events: Option<Arc<Mutex<Vec<SymlinkEvent>>>>,
+}
+
+type CreatedHook = dyn Fn(&Path, &Path, LinkKind) -> io::Result<()> + Send + Sync;
+type RemovedHook = dyn Fn(&Path, LinkKind) -> io::Result<()> + Send + Sync;
+
+impl fmt::Debug for SymlinkContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SymlinkContext")
+            .field("options", &self.options)
+            .field("policy", &self.policy)
+            .field("on_created", &self.on_created.as_ref().map(|_| ".."))
+            .field("on_removed", &self.on_removed.as_ref().map(|_| ".."))
+            .field("jail", &self.jail)
+            .field("jail_targets", &self.jail_targets)
+            .field("device", &self.device)
+            .field("cache", &self.cache_stats())
+            .field("dry_run", &self.dry_run)
+            .field("serialize_per_dir", &self.serialize_per_dir)
+            .field("lock_files", &self.lock_files)
+            .field("max_resolved_components", &self.max_components())
+            .field("events", &self.events.as_ref().map(|_| self.lock_events().len()))
+            .finish()
+    }
+}
+
+impl SymlinkContext {
+    /// A context with the default options and [`ConflictPolicy::Fail`].
+    pub fn new() -> SymlinkContext {
+        SymlinkContext::default()
+    }
+
+    /// Create links with these options.
+    ///
+    /// The classification cache is emptied, since the options may classify targets differently.
+    pub fn options(&mut self, options: SymlinkBuilder) -> &mut SymlinkContext {
+        self.options = options;
+        self.cache = Arc::new(Mutex::new(ClassificationCache::new(self.cache_stats().capacity)));
+        self
+    }
+
+    /// Remember the kinds of at most `capacity` targets; 0 turns the cache off.
+    ///
+    /// Each cached target costs a path and a little more, so the default of 1024 is some tens
+    /// of kilobytes at most. The cache is emptied.
+    ///
+    /// A cached kind is trusted for as long as it’s in the cache: if a target is replaced by
+    /// something of the other kind, links to it will be of the old kind until it drops out. A
+    /// program that replaces its targets like that should turn the cache off, or set the options
+    /// again (which empties it) after doing so.
+    ///
+    /// Clones of a context share its cache, and so its statistics, until this or
+    /// [`options`](Self::options) gives one a cache of its own.
+    pub fn classification_cache_capacity(&mut self, capacity: usize) -> &mut SymlinkContext {
+        self.cache = Arc::new(Mutex::new(ClassificationCache::new(capacity)));
+        self
+    }
+
+    /// How the classification cache is doing: how often a target’s kind was found in it, and
+    /// how full it is.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).stats()
+    }
+
+    /// Deal with anything already at a link path as `policy` says.
+    pub fn conflict_policy(&mut self, policy: ConflictPolicy) -> &mut SymlinkContext {
+        self.policy = policy;
+        self
+    }
+
+    /// Refuse to create or remove any link outside `root`.
+    ///
+    /// Each link path is checked before anything is done with it—as the
+    /// [`options`](Self::options) have it, with a `~` expanded and so on—and one that isn’t
+    /// beneath `root` is an [`InvalidInput`](io::ErrorKind::InvalidInput) error. The check is
+    /// made twice: lexically, on the path made absolute and with `.` and `..` tidied away, so
+    /// that `root/a/../../etc` is caught; and physically, on the nearest of its parent
+    /// directories that exists, with symlinks resolved as the OS resolves them, so that
+    /// `root/a/passwd` is caught when `a` is a symlink to `/etc`, and so is `root/a/../passwd`,
+    /// which lexically is `root/passwd` but is really `/passwd`. `root` itself must exist. See also
+    /// [`jail_targets`](Self::jail_targets).
+    ///
+    /// This is a check before the fact, not a sandbox: something else changing the tree between
+    /// the check and the operation can still get a link made elsewhere. Where that matters, also
+    /// use [`SymlinkBuilder::nofollow_parents`], or work beneath a directory handle with
+    /// [`symlink_file_in`](crate::symlink_file_in) and friends.
+    pub fn jail<P: Into<PathBuf>>(&mut self, root: P) -> &mut SymlinkContext {
+        self.jail = Some(root.into());
+        self
+    }
+
+    /// With a [`jail`](Self::jail), also refuse to create a link whose target leads outside it.
+    ///
+    /// The target is resolved against the link’s directory, as the OS would, and then the chain
+    /// of symlinks from there is followed (as [`symlink_chain`](crate::symlink_chain) would),
+    /// each path along it checked as the link path is, so a target like `a/../../x`, with `a` a
+    /// symlink, is judged by where the OS would take it. A chain that loops is refused with the
+    /// platform’s “too many levels of symbolic links” error. A target that doesn’t exist is fine,
+    /// so long as where it would be is inside. Without a jail this does nothing.
+    pub fn jail_targets(&mut self, jail_targets: bool) -> &mut SymlinkContext {
+        self.jail_targets = jail_targets;
+        self
+    }
+
+    /// Refuse to create or remove any link that isn’t on the same device as `path`.
+    ///
+    /// This limits the blast radius of a tool that removes or overwrites things: a link path
+    /// in a directory on another filesystem—a network drive mounted in the tree, a USB stick,
+    /// another volume on Windows—is a [`CrossesDevices`](io::ErrorKind::CrossesDevices) error,
+    /// and nothing is done there. The device of a link path is that of the nearest of its
+    /// parent directories that exists, with symlinks resolved, so a parent that’s a symlink onto
+    /// another device is caught too, as is `dir/link/../name` when `link` leads onto one; the
+    /// device of `path` is that of what it leads to. On Unix,
+    /// devices are told apart by `st_dev`; on Windows, by the volume serial number. Elsewhere
+    /// there’s nothing to go on, and every operation is an
+    /// [`Unsupported`](io::ErrorKind::Unsupported) error.
+    ///
+    /// `path` must exist; it’s looked at afresh for each operation, as the jail is. Like the
+    /// [`jail`](Self::jail), this is a check before the fact, and it’s made in a
+    /// [dry run](Self::dry_run) too.
+    pub fn restrict_to_device<P: Into<PathBuf>>(&mut self, path: P) -> &mut SymlinkContext {
+        self.device = Some(path.into());
+        self
+    }
+
+    /// Change nothing: predict what each creation and removal would do, and pretend to have
+    /// done it.
+    ///
+    /// This is for a `--dry-run` option without an `if` at every call site. The methods look at
+    /// what’s at each link path and return what they would have returned—the
+    /// [`PolicyOutcome`] the policy would lead to, say, or the error it would give for
+    /// something in the way—but create, replace, move aside and remove nothing. The
+    /// [jail](Self::jail) is still checked, since that’s only looking. The hooks aren’t run, as
+    /// there’s nothing for them to keep track of.
+    ///
+    /// The outcomes are best-effort predictions, not promises. Only what’s at the link path is
+    /// looked at: whether the OS would actually allow the link to be made (permissions, a
+    /// missing parent directory, or on Windows the privilege to make symlinks at all) isn’t
+    /// tried, and something else changing the tree in the meantime can change the answer. Nor
+    /// does one prediction see what the last would have done, so a run that creates a link and
+    /// then replaces it predicts it created both times.
+    ///
+    /// To get a record of what would have been done, wrap the context in an `AuditLog` (feature
+    /// `audit`): each prediction is logged as if it had happened.
+    pub fn dry_run(&mut self, dry_run: bool) -> &mut SymlinkContext {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Make creations and removals in the same directory wait for each other.
+    ///
+    /// Each operation looks before it acts—whether something is in the way, what a link points
+    /// at now—and another thread acting on the same directory in between can make what it found
+    /// out of date, so that two threads replacing the same link, say, trip over each other’s
+    /// temporary names or backups. With this option, each operation takes a lock on the link’s
+    /// directory first, and holds it until it’s done (though not while a hook runs), so that
+    /// within a directory they happen one after another; operations in different directories
+    /// still run side by side. The directory is known by its canonical path, so two routes to
+    /// the same one still wait on each other.
+    ///
+    /// The lock is advisory, and by default only in-process: it’s shared by every context in
+    /// the process with this option set, but other processes, and code here that works on the
+    /// directory some other way, don’t know about it. For other processes to take part too, set
+    /// [`lock_files`](Self::lock_files) as well.
+    pub fn serialize_per_dir(&mut self, serialize_per_dir: bool) -> &mut SymlinkContext {
+        self.serialize_per_dir = serialize_per_dir;
+        self
+    }
+
+    /// With [`serialize_per_dir`](Self::serialize_per_dir), also lock a lock file in each
+    /// directory, so that processes doing the same wait for each other too.
+    ///
+    /// The file is [`LOCK_FILE_NAME`](crate::LOCK_FILE_NAME), created in the link’s directory
+    /// if it isn’t there, and locked exclusively with [`File::lock`](std::fs::File::lock)
+    /// (`flock` on Unix, `LockFileEx` on Windows) for the length of each operation. It’s left
+    /// in place afterwards, since removing a lock file someone else may be about to lock defeats
+    /// the point; a tool that lists or cleans its directories will want to ignore it. This is as
+    /// advisory as the in-process lock: only processes that lock the same file wait for it. A
+    /// directory that doesn’t exist yet, because [`create_parents`](SymlinkBuilder::create_parents)
+    /// is about to create it, gets only the in-process lock. Without `serialize_per_dir` this
+    /// does nothing.
+    pub fn lock_files(&mut self, lock_files: bool) -> &mut SymlinkContext {
+        self.lock_files = lock_files;
+        self
+    }
+
+    /// Give up on a chain of symlinks once a path along it has more than `max` components.
+    ///
+    /// Following a chain joins each relative target to the directory of the link it came from,
+    /// and a hostile chain—links with targets like `../../../../x`, leading to more of the
+    /// same—can make the path longer at every hop. Beyond `max` components, following the chain
+    /// stops with an [`InvalidFilename`](io::ErrorKind::InvalidFilename) error. The default,
+    /// [`MAX_RESOLVED_COMPONENTS`], is the bound [`resolve_symlink`](crate::resolve_symlink)
+    /// uses; one lower suits a program that knows its trees are shallow and is following links
+    /// someone else made. It applies to [`jail_targets`](Self::jail_targets) and to this
+    /// context’s [`resolve_symlink`](Self::resolve_symlink) and
+    /// [`symlink_chain`](Self::symlink_chain).
+    pub fn max_resolved_components(&mut self, max: usize) -> &mut SymlinkContext {
+        self.max_resolved_components = Some(max);
+        self
+    }
+
+    /// Keep a record of each creation and removal made through this context, for
+    /// [`events`](Self::events) to return.
+    ///
+    /// Each is recorded once it’s done, successful or not, with what was asked for and what came
+    /// of it—the [`PolicyOutcome`], as an [`EventOutcome`](crate::EventOutcome), or the error—so
+    /// that a test of a tool built on this crate can assert on what the tool did, or a report
+    /// can be made of it, without a log to parse (for which see `AuditLog`, feature `audit`). In
+    /// a [dry run](Self::dry_run), the outcomes are the predictions.
+    ///
+    /// The record grows for as long as it’s kept, so this is off by default. Turning it on starts
+    /// a new, empty record, and off drops it. Clones of a context share its record, until this is
+    /// called on one of them.
+    pub fn record_events(&mut self, record_events: bool) -> &mut SymlinkContext {
+        self.events = if record_events { Some(Arc::default()) } else { None };
+        self
+    }
+
+    /// What’s been done so far, oldest first, if [`record_events`](Self::record_events) is on;
+    /// otherwise nothing.
+    pub fn events(&self) -> Vec<SymlinkEvent> {
+        match self.events {
+            Some(_) => self.lock_events().clone(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Call `hook` after each link this context creates, with the target, the link and the kind
+    /// of link asked for.
+    ///
+    /// It runs synchronously, once the link is in place (whether created afresh, replacing
+    /// another, or after a backup), and not when the policy skips it or creation fails. An error
+    /// from the hook is returned from the method that created the link, which is otherwise left
+    /// as it is: the link exists, and it’s up to the caller what to do about the bookkeeping that
+    /// failed. A context has one hook of each sort; setting another replaces it.
+    pub fn on_created<F>(&mut self, hook: F) -> &mut SymlinkContext
+        where F: Fn(&Path, &Path, LinkKind) -> io::Result<()> + Send + Sync + 'static
+    {
+        self.on_created = Some(Arc::new(hook));
+        self
+    }
+
+    /// Call `hook` after each link this context removes, with the link and the kind of removal
+    /// asked for.
+    ///
+    /// As with [`on_created`](Self::on_created), it runs synchronously, only after success, and
+    /// an error from it is returned with the link already gone.
+    pub fn on_removed<F>(&mut self, hook: F) -> &mut SymlinkContext
+        where F: Fn(&Path, LinkKind) -> io::Result<()> + Send + Sync + 'static
+    {
+        self.on_removed = Some(Arc::new(hook));
+        self
+    }
+
+    /// Create a symlink of the given kind, with the configured options and policy.
+    ///
+    /// See [`symlink`](crate::symlink) for details.
+    pub fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(&self, kind: LinkKind, src: P, dst: Q)
+                                                   -> io::Result<PolicyOutcome> {
+        self.create(kind, src.as_ref(), dst.as_ref())
+    }
+
+    /// Create a symlink to a file, with the configured options and policy.
+    ///
+    /// See [`symlink_file`](crate::symlink_file) for details.
+    pub fn symlink_file<P: AsRef<Path>, Q: AsRef<Path>>(&self, src: P, dst: Q)
+                                                        -> io::Result<PolicyOutcome> {
+        self.create(LinkKind::File, src.as_ref(), dst.as_ref())
+    }
+
+    /// Create a symlink to a directory, with the configured options and policy.
+    ///
+    /// See [`symlink_dir`](crate::symlink_dir) for details.
+    pub fn symlink_dir<P: AsRef<Path>, Q: AsRef<Path>>(&self, src: P, dst: Q)
+                                                       -> io::Result<PolicyOutcome> {
+        self.create(LinkKind::Dir, src.as_ref(), dst.as_ref())
+    }
+
+    /// Create a symlink (non-preferred way), with the configured options and policy.
+    ///
+    /// See [`symlink_auto`](crate::symlink_auto) for details.
+    pub fn symlink_auto<P: AsRef<Path>, Q: AsRef<Path>>(&self, src: P, dst: Q)
+                                                        -> io::Result<PolicyOutcome> {
+        self.create(LinkKind::Auto, src.as_ref(), dst.as_ref())
+    }
+
+    /// Remove a file symlink, as [`remove_symlink_file`](crate::remove_symlink_file) does.
+    pub fn remove_symlink_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.remove(LinkKind::File, path.as_ref(), |path| remove_symlink_file(path))
+    }
+
+    /// Remove a directory symlink, as [`remove_symlink_dir`](crate::remove_symlink_dir) does.
+    pub fn remove_symlink_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.remove(LinkKind::Dir, path.as_ref(), |path| remove_symlink_dir(path))
+    }
+
+    /// Remove a symlink of either kind, as [`remove_symlink_auto`](crate::remove_symlink_auto)
+    /// does.
+    pub fn remove_symlink_auto<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.remove(LinkKind::Auto, path.as_ref(), |path| remove_symlink_auto(path))
+    }
+
+    /// Follow a chain of symlinks to its end, as [`resolve_symlink`](crate::resolve_symlink)
+    /// does, within this context’s [bound](Self::max_resolved_components) on path length.
+    pub fn resolve_symlink<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        resolve_symlink_limited(path.as_ref(), self.max_components())
+    }
+
+    /// List every path in a chain of symlinks, as [`symlink_chain`](crate::symlink_chain) does,
+    /// within this context’s [bound](Self::max_resolved_components) on path length.
+    pub fn symlink_chain<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<PathBuf>> {
+        symlink_chain_limited(path.as_ref(), self.max_components())
+    }
+
+    fn max_components(&self) -> usize {
+        self.max_resolved_components.unwrap_or(MAX_RESOLVED_COMPONENTS)
+    }
+
+    fn create(&self, kind: LinkKind, src: &Path, dst: &Path) -> io::Result<PolicyOutcome> {
+        let result = self.create_unrecorded(kind, src, dst);
+        if self.events.is_some() {
+            let event = SymlinkEvent::new(EventOp::Create, kind, dst, Some(src), &result,
+                                          |outcome| outcome.clone().into());
+            self.lock_events().push(event);
+        }
+        result
+    }
+
+    fn create_unrecorded(&self, kind: LinkKind, given_src: &Path, given_dst: &Path)
+                         -> io::Result<PolicyOutcome> {
+        // The checks are of the paths the options make of these, and those are what’s used.
+        let (rewritten_src, rewritten_dst) = self.options.rewritten_paths(given_src, given_dst)?;
+        let src = rewritten_src.as_deref().unwrap_or(given_src);
+        let dst = rewritten_dst.as_deref().unwrap_or(given_dst);
+        let made = self.options.link_path_made(dst)?;
+        let checked = made.as_deref().unwrap_or(dst);
+        if let Some(ref root) = self.jail {
+            let jail = Jail::new(root)?;
+            jail.check(checked, || format!("link {}", dst.display()))?;
+            if self.jail_targets {
+                let mut chain = Vec::new();
+                walk_chain(&paths::resolve_target(checked, src), self.max_components(),
+                           |path| chain.push(path.to_owned()))?;
+                for path in &chain {
+                    jail.check(path, || {
+                        format!("target {} of link {} (by way of {})", src.display(),
+                                dst.display(), path.display())
+                    })?;
+                }
+            }
+        }
+        if let Some(ref expected) = self.device {
+            check_device(expected, checked)?;
+        }
+        if self.dry_run {
+            return predict_policy(dst, &self.policy);
+        }
+        let outcome = {
+            let _lock = self.lock_dir(dst)?;
+            let symlink_kind = match kind {
+                LinkKind::Auto => self.classify_cached(src, dst),
+                kind => kind.symlink_kind(),
+            };
+            apply_policy(&self.options, symlink_kind, src, dst, &self.policy)?
+        };
+        if let Some(ref hook) = self.on_created {
+            if outcome != PolicyOutcome::Skipped {
+                hook(given_src, given_dst, kind)?;
+            }
+        }
+        Ok(outcome)
+    }
+
+    // The kind of link to src at dst, from the cache or found and cached, or None to leave it to
+    // the policy to decide. A failure to find it is left to the policy too, which will report it
+    // if it needs the answer.
+    #[cfg(windows)]
+    fn classify_cached(&self, src: &Path, dst: &Path) -> Option<SymlinkKind> {
+        let target = paths::absolute(&paths::resolve_target(dst, src)).ok()?;
+        if let Some(kind) = self.lock_cache().get(&target) {
+            return Some(kind);
+        }
+        // Not holding the lock while looking, which may be slow.
+        let kind = auto_kind(&self.options, src, dst).ok()??;
+        self.lock_cache().insert(target, kind);
+        Some(kind)
+    }
+
+    #[cfg(not(windows))]
+    fn classify_cached(&self, _src: &Path, _dst: &Path) -> Option<SymlinkKind> {
+        None
+    }
+
+    #[cfg(windows)]
+    fn lock_cache(&self) -> ::std::sync::MutexGuard<'_, ClassificationCache> {
+        self.cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn remove<F>(&self, kind: LinkKind, path: &Path, remove: F) -> io::Result<()>
+        where F: FnOnce(&Path) -> io::Result<()>
+    {
+        let result = self.remove_unrecorded(kind, path, remove);
+        if self.events.is_some() {
+            let event = SymlinkEvent::new(EventOp::Remove, kind, path, None, &result,
+                                          |()| EventOutcome::Removed);
+            self.lock_events().push(event);
+        }
+        result
+    }
+
+    fn remove_unrecorded<F>(&self, kind: LinkKind, path: &Path, remove: F) -> io::Result<()>
+        where F: FnOnce(&Path) -> io::Result<()>
+    {
+        if let Some(ref root) = self.jail {
+            Jail::new(root)?.check(path, || format!("link {}", path.display()))?;
+        }
+        if let Some(ref expected) = self.device {
+            check_device(expected, path)?;
+        }
+        if self.dry_run {
+            return if stats::symlink_metadata(path)?.file_type().is_symlink() {
+                Ok(())
+            } else {
+                Err(error::not_a_symlink(path, ""))
+            };
+        }
+        {
+            let _lock = self.lock_dir(path)?;
+            remove(path)?;
+        }
+        match self.on_removed {
+            Some(ref hook) => hook(path, kind),
+            None => Ok(()),
+        }
+    }
+
+    // The record of events; only to be called when there is one.
+    fn lock_events(&self) -> ::std::sync::MutexGuard<'_, Vec<SymlinkEvent>> {
+        let events = self.events.as_ref().expect("no events are being recorded");
+        events.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // The lock on path’s directory, if operations are being serialised.
+    fn lock_dir(&self, path: &Path) -> io::Result<Option<DirLock>> {
+        if self.serialize_per_dir {
+            DirLock::acquire(path, self.lock_files).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+// A jail root, as given (made absolute) and with symlinks resolved.
+struct Jail<'a> {
+    given: &'a Path,
+    absolute: PathBuf,
+    canonical: PathBuf,
+}
+
+impl<'a> Jail<'a> {
+    fn new(root: &'a Path) -> io::Result<Jail<'a>> {
+        let canonical = root.canonicalize().map_err(|e| {
+            io::Error::new(e.kind(), format!("cannot resolve the jail {}: {}", root.display(), e))
+        })?;
+        Ok(Jail { given: root, absolute: paths::absolute(root)?, canonical })
+    }
+
+    // Whether path is beneath the jail (the jail itself not counting), both lexically and
+    // physically: through the nearest of its parent directories that exists, once the symlinks
+    // among them are followed, `..` after them included. what describes path for the error.
+    fn check<F: FnOnce() -> String>(&self, path: &Path, what: F) -> io::Result<()> {
+        // A link path made verbatim for preserve_trailing_dots is compared without the prefix.
+        let absolute = paths::absolute(path)?;
+        let stripped = paths::strip_verbatim(&absolute);
+        let absolute = Path::new(&stripped);
+        let physical = resolve_parents(path)?;
+        let inside = absolute != self.absolute && absolute.starts_with(&self.absolute)
+            && physical.parent().and_then(|p| p.ancestors().find_map(|a| a.canonicalize().ok()))
+                .is_none_or(|existing| existing.starts_with(&self.canonical));
+        if inside {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::InvalidInput,
+                               format!("{} is outside the jail {}", what(), self.given.display())))
+        }
+    }
+}
+
+// Whether link is on the same device as expected, going by the nearest of its parent directories
+// that exists, with symlinks among them followed as the OS would follow them.
+fn check_device(expected: &Path, link: &Path) -> io::Result<()> {
+    let expected_device = expected.canonicalize().and_then(|path| device_id(&path)).map_err(|e| {
+        io::Error::new(e.kind(), format!("cannot find the device of {}: {}", expected.display(), e))
+    })?;
+    let physical = resolve_parents(link)?;
+    let parent = physical.parent().and_then(|p| p.ancestors().find_map(|a| a.canonicalize().ok()));
+    let device = match parent {
+        Some(parent) => device_id(&parent)?,
+        None => return Ok(()),
+    };
+    if device == expected_device {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::CrossesDevices,
+                           format!("link {} is on a different device from {}", link.display(),
+                                   expected.display())))
+    }
+}
+
+impl SymlinkOps for SymlinkContext {
+    fn symlink_file(&self, src: &Path, dst: &Path) -> io::Result<()> {
+        SymlinkContext::symlink_file(self, src, dst).map(|_| ())
+    }
+
+    fn symlink_dir(&self, src: &Path, dst: &Path) -> io::Result<()> {
+        SymlinkContext::symlink_dir(self, src, dst).map(|_| ())
+    }
+
+    fn symlink_auto(&self, src: &Path, dst: &Path) -> io::Result<()> {
+        SymlinkContext::symlink_auto(self, src, dst).map(|_| ())
+    }
+
+    fn remove_symlink_file(&self, path: &Path) -> io::Result<()> {
+        SymlinkContext::remove_symlink_file(self, path)
+    }
+
+    fn remove_symlink_dir(&self, path: &Path) -> io::Result<()> {
+        SymlinkContext::remove_symlink_dir(self, path)
+    }
+
+    fn remove_symlink_auto(&self, path: &Path) -> io::Result<()> {
+        SymlinkContext::remove_symlink_auto(self, path)
+    }
+
+    fn read_symlink(&self, path: &Path) -> io::Result<PathBuf> {
+        stats::read_link(path)
+    }
+
+    fn is_symlink(&self, path: &Path) -> bool {
+        stats::symlink_metadata(path).map(|m| m.file_type().is_symlink()).unwrap_or(false)
+    }
+}
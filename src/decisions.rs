@@ -0,0 +1,167 @@
+// Recording symlink_auto’s file-or-directory decisions on one machine and replaying them on
+// another, where the targets might not be there to look at.
+
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+#[cfg(windows)]
+use internal;
+#[cfg(not(windows))]
+use {paths, stats};
+use {create_symlink, SymlinkKind};
+
+/// A record of which kind of symlink each target got, for reproducing a layout elsewhere.
+///
+/// Create links through [`symlink_auto`](Self::symlink_auto) and each decision is recorded,
+/// keyed by the target as given. Save the log with [`write_to`](Self::write_to), load it
+/// somewhere else with [`read_from`](Self::read_from), and create the same links through it
+/// again: a target that’s in the log gets the recorded kind without being looked at, so the run
+/// is deterministic and works even if the targets aren’t there yet. That’s mostly of use in
+/// generating a layout on Unix, where the kind makes no difference, for replaying on Windows,
+/// where it does.
+///
+/// The saved form is plain text, one decision per line: `file` or `dir`, a tab, and the target.
+/// (There’s no serde support, so that the crate keeps no dependencies; the format is simple
+/// enough to produce or consume by other means.)
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// use std::fs::File;
+/// use std::io::BufReader;
+/// use symlink_rs::DecisionLog;
+///
+/// // On the first machine:
+/// let mut log = DecisionLog::new();
+/// log.symlink_auto("../shared/assets", "app/assets")?;
+/// log.write_to(File::create("decisions.txt")?)?;
+///
+/// // On the second:
+/// let mut log = DecisionLog::read_from(BufReader::new(File::open("decisions.txt")?))?;
+/// log.symlink_auto("../shared/assets", "app/assets")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DecisionLog {
+    kinds: BTreeMap<PathBuf, SymlinkKind>,
+}
+
+impl DecisionLog {
+    /// Create an empty log.
+    pub fn new() -> DecisionLog {
+        DecisionLog::default()
+    }
+
+    /// The kind recorded for `target`, if any.
+    pub fn get<P: AsRef<Path>>(&self, target: P) -> Option<SymlinkKind> {
+        self.kinds.get(target.as_ref()).cloned()
+    }
+
+    /// Record a kind for `target`, returning the one it replaces, if any.
+    pub fn insert<P: Into<PathBuf>>(&mut self, target: P, kind: SymlinkKind)
+                                    -> Option<SymlinkKind> {
+        self.kinds.insert(target.into(), kind)
+    }
+
+    /// All the decisions, ordered by target.
+    pub fn iter(&self) -> impl Iterator<Item = (&Path, SymlinkKind)> {
+        self.kinds.iter().map(|(target, &kind)| (&**target, kind))
+    }
+
+    /// Create a symlink of the recorded kind for `src`, or decide and record it if there’s none.
+    ///
+    /// To decide, the target is looked at as [`symlink_auto`](crate::symlink_auto) would on
+    /// Windows. Elsewhere, where symlinks have no kind of their own, the target is resolved
+    /// against the link’s parent directory and what it leads to is what counts. Either way,
+    /// deciding needs the target to exist. The kind is recorded before the link is created, so it
+    /// is kept even if creating the link fails.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if there’s no recorded kind and the target can’t be looked at,
+    /// or if the symlink can’t be created.
+    pub fn symlink_auto<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, src: P, dst: Q)
+                                                        -> io::Result<SymlinkKind> {
+        let (src, dst) = (src.as_ref(), dst.as_ref());
+        let kind = match self.get(src) {
+            Some(kind) => kind,
+            None => {
+                let kind = decide(src, dst)?;
+                self.insert(src, kind);
+                kind
+            }
+        };
+        create_symlink(Some(kind), src, dst)?;
+        Ok(kind)
+    }
+
+    /// Save the log, in the format described on [`DecisionLog`].
+    ///
+    /// # Errors
+    ///
+    /// An [`InvalidData`](io::ErrorKind::InvalidData) error will be returned if a target isn’t
+    /// valid Unicode or contains a line break, and otherwise errors are from writing.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for (target, kind) in self.iter() {
+            let target = match target.to_str() {
+                Some(target) if !target.contains(['\n', '\r']) => target,
+                _ => {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                              format!("target {:?} can’t be saved in a decision \
+                                                       log", target)));
+                }
+            };
+            let kind = match kind {
+                SymlinkKind::File => "file",
+                SymlinkKind::Dir => "dir",
+            };
+            writeln!(writer, "{}\t{}", kind, target)?;
+        }
+        writer.flush()
+    }
+
+    /// Load a log saved with [`write_to`](Self::write_to).
+    ///
+    /// Blank lines are ignored.
+    ///
+    /// # Errors
+    ///
+    /// An [`InvalidData`](io::ErrorKind::InvalidData) error, giving the line number, will be
+    /// returned for a line that isn’t a decision, and otherwise errors are from reading.
+    pub fn read_from<R: BufRead>(reader: R) -> io::Result<DecisionLog> {
+        let mut log = DecisionLog::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim_end_matches('\r');
+            if line.is_empty() {
+                continue;
+            }
+            let (kind, target) = match line.split_once('\t') {
+                Some(("file", target)) if !target.is_empty() => (SymlinkKind::File, target),
+                Some(("dir", target)) if !target.is_empty() => (SymlinkKind::Dir, target),
+                _ => {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                              format!("line {} of the decision log isn’t a \
+                                                       decision: {:?}", i + 1, line)));
+                }
+            };
+            log.insert(target, kind);
+        }
+        Ok(log)
+    }
+}
+
+#[cfg(windows)]
+fn decide(src: &Path, dst: &Path) -> io::Result<SymlinkKind> {
+    internal::target_kind(src, dst, false)
+}
+
+#[cfg(not(windows))]
+fn decide(src: &Path, dst: &Path) -> io::Result<SymlinkKind> {
+    if stats::metadata(paths::resolve_target(dst, src))?.is_dir() {
+        Ok(SymlinkKind::Dir)
+    } else {
+        Ok(SymlinkKind::File)
+    }
+}
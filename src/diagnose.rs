@@ -0,0 +1,261 @@
+// A self-test for bug reports: what works here, found out by trying each thing in a scratch
+// directory and clearing up after. The links are made with the internal module, not through
+// create_symlink, so that a diagnosis doesn’t count as links created, as probe.rs does.
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use {filesystem_supports_symlinks, internal, paths, ATOMIC_REPLACE_SUPPORTED};
+
+/// How one of [`diagnose`]’s probes went.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Probe {
+    /// It worked, and what was made was read back as it should be.
+    Works,
+    /// It failed, with this error (or, where the error was that something read back wrong, an
+    /// [`Other`](io::ErrorKind::Other) saying what).
+    Failed {
+        /// The error’s kind.
+        kind: io::ErrorKind,
+        /// The error’s message.
+        message: String,
+    },
+    /// There’s no such thing on this platform, so it wasn’t tried.
+    NotApplicable,
+}
+
+impl Probe {
+    /// Whether it worked.
+    pub fn works(&self) -> bool {
+        *self == Probe::Works
+    }
+
+    fn from_result(result: io::Result<()>) -> Probe {
+        match result {
+            Ok(()) => Probe::Works,
+            Err(e) => Probe::Failed { kind: e.kind(), message: e.to_string() },
+        }
+    }
+}
+
+impl fmt::Display for Probe {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Probe::Works => f.write_str("works"),
+            Probe::Failed { kind, ref message } => write!(f, "fails: {:?}: {}", kind, message),
+            Probe::NotApplicable => f.write_str("n/a"),
+        }
+    }
+}
+
+/// What [`diagnose`] found.
+///
+/// Its `Display` is a line per field, for pasting into a bug report.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiagnosticReport {
+    /// The operating system, as [`env::consts::OS`] names it.
+    pub os: &'static str,
+    /// The directory the probes were made in, made absolute.
+    pub test_dir: PathBuf,
+    /// What [`filesystem_supports_symlinks`] says of it, or `None` if it couldn’t say.
+    pub filesystem_supports_symlinks: Option<bool>,
+    /// Whether file and directory symlinks are different things here: true on Windows only.
+    pub kinds_distinct: bool,
+    /// On Windows, whether the process holds `SeCreateSymbolicLinkPrivilege`; `None` elsewhere.
+    pub privilege_held: Option<bool>,
+    /// On Windows, whether Developer Mode is on, which lets symlinks be created without the
+    /// privilege; `None` elsewhere.
+    pub developer_mode: Option<bool>,
+    /// A relative symlink to a file, created, read back, followed and removed.
+    pub file_symlinks: Probe,
+    /// A relative symlink to a directory, likewise.
+    pub dir_symlinks: Probe,
+    /// A symlink to nothing, created, read back and removed.
+    pub dangling_symlinks: Probe,
+    /// A symlink replaced with [`replace_existing`](SymlinkBuilder::replace_existing).
+    pub replace_existing: Probe,
+    /// Whether that replacement is atomic here: [`ATOMIC_REPLACE_SUPPORTED`].
+    pub atomic_replace: bool,
+    /// A symlink whose absolute path is longer than Windows’s traditional `MAX_PATH` of 260.
+    pub long_paths: Probe,
+    /// A junction, on Windows; [`NotApplicable`](Probe::NotApplicable) elsewhere.
+    pub junctions: Probe,
+    /// Removing the scratch directory the probes were made in, afterwards.
+    pub cleanup: Probe,
+}
+
+impl fmt::Display for DiagnosticReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let maybe = |answer: Option<bool>| answer.map_or("n/a".to_owned(), |yes| yes.to_string());
+        writeln!(f, "os: {}", self.os)?;
+        writeln!(f, "test dir: {}", self.test_dir.display())?;
+        writeln!(f, "filesystem supports symlinks: {}", maybe(self.filesystem_supports_symlinks))?;
+        writeln!(f, "file and dir symlinks distinct: {}", self.kinds_distinct)?;
+        writeln!(f, "privilege held: {}", maybe(self.privilege_held))?;
+        writeln!(f, "developer mode: {}", maybe(self.developer_mode))?;
+        writeln!(f, "file symlinks: {}", self.file_symlinks)?;
+        writeln!(f, "dir symlinks: {}", self.dir_symlinks)?;
+        writeln!(f, "dangling symlinks: {}", self.dangling_symlinks)?;
+        writeln!(f, "replace existing: {}", self.replace_existing)?;
+        writeln!(f, "atomic replace: {}", self.atomic_replace)?;
+        writeln!(f, "long paths: {}", self.long_paths)?;
+        writeln!(f, "junctions: {}", self.junctions)?;
+        writeln!(f, "cleanup: {}", self.cleanup)
+    }
+}
+
+/// Find out what this crate can do here, for a bug report.
+///
+/// A scratch directory with a name no one else will be using is made in `test_dir`, and in it
+/// each probe creates something—a file symlink, a directory symlink, a dangling one, a
+/// replacement, one with a long path, a junction on Windows—reads it back to check it came out
+/// as asked, and removes it again; then the scratch directory is removed. Nothing outside it is
+/// touched. The probes make their links the way the crate’s functions do, with the same calls
+/// into the OS, but not through those functions, so that, as with
+/// [`symlinks_supported_in`](crate::symlinks_supported_in), probing doesn’t count in the
+/// `metrics` and `stats` features’ counters. The rest of the report is asked of the system: the
+/// filesystem type, and on Windows the privilege and Developer Mode.
+///
+/// A probe that fails is reported, not returned as an error, so one failure doesn’t hide the
+/// rest; the report’s `Display` is meant for pasting into an issue as it is.
+///
+/// ```no_run
+/// println!("{}", symlink_rs::diagnose(std::env::temp_dir())?);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// An error will be returned if `test_dir` can’t be made absolute or the scratch directory
+/// can’t be created in it, as then nothing can be tried. A scratch directory that can’t be
+/// removed afterwards is [`cleanup`](DiagnosticReport::cleanup)’s failure, not an error.
+pub fn diagnose<P: AsRef<Path>>(test_dir: P) -> io::Result<DiagnosticReport> {
+    let test_dir = paths::absolute(test_dir.as_ref())?;
+    let scratch = paths::temp_sibling(&test_dir.join("symlink-diagnose"))?;
+    fs::create_dir(&scratch)?;
+    let (privilege_held, developer_mode) = privilege();
+    let mut report = DiagnosticReport {
+        os: env::consts::OS,
+        filesystem_supports_symlinks: filesystem_supports_symlinks(&scratch).ok(),
+        kinds_distinct: cfg!(windows),
+        privilege_held,
+        developer_mode,
+        file_symlinks: Probe::from_result(probe_file(&scratch)),
+        dir_symlinks: Probe::from_result(probe_dir(&scratch)),
+        dangling_symlinks: Probe::from_result(probe_dangling(&scratch)),
+        replace_existing: Probe::from_result(probe_replace(&scratch)),
+        atomic_replace: ATOMIC_REPLACE_SUPPORTED,
+        long_paths: Probe::from_result(probe_long_path(&scratch)),
+        junctions: probe_junction(&scratch),
+        cleanup: Probe::Works,
+        test_dir,
+    };
+    report.cleanup = Probe::from_result(fs::remove_dir_all(&scratch));
+    Ok(report)
+}
+
+fn probe_file(scratch: &Path) -> io::Result<()> {
+    fs::write(scratch.join("file"), "diagnose")?;
+    let link = scratch.join("file-link");
+    internal::symlink_file(Path::new("file"), &link)?;
+    let result = check_target(&link, "file").and_then(|()| {
+        if fs::read_to_string(&link)? == "diagnose" {
+            Ok(())
+        } else {
+            Err(io::Error::other("reading through the file symlink gave something else"))
+        }
+    });
+    result.and(fs::remove_file(&link))
+}
+
+fn probe_dir(scratch: &Path) -> io::Result<()> {
+    fs::create_dir(scratch.join("dir"))?;
+    fs::write(scratch.join("dir").join("inside"), "")?;
+    let link = scratch.join("dir-link");
+    internal::symlink_dir(Path::new("dir"), &link)?;
+    let result = check_target(&link, "dir").and_then(|()| {
+        if link.join("inside").exists() {
+            Ok(())
+        } else {
+            Err(io::Error::other("the directory symlink doesn’t lead into the directory"))
+        }
+    });
+    result.and(internal::remove_symlink_dir(&link))
+}
+
+fn probe_dangling(scratch: &Path) -> io::Result<()> {
+    let link = scratch.join("dangling-link");
+    internal::symlink_file(Path::new("nothing-here"), &link)?;
+    check_target(&link, "nothing-here").and(fs::remove_file(&link))
+}
+
+fn probe_replace(scratch: &Path) -> io::Result<()> {
+    let link = scratch.join("replaced-link");
+    internal::symlink_file(Path::new("old"), &link)?;
+    let result = replace(&link, |path| internal::symlink_file(Path::new("new"), path))
+        .and_then(|()| check_target(&link, "new"));
+    result.and(fs::remove_file(&link))
+}
+
+// As SymlinkBuilder::replace_existing replaces a link, save for the counting.
+#[cfg(unix)]
+fn replace<F: FnMut(&Path) -> io::Result<()>>(link: &Path, create: F) -> io::Result<()> {
+    ::builder::replace_symlink(link, None, false, create)
+}
+
+#[cfg(not(unix))]
+fn replace<F: FnMut(&Path) -> io::Result<()>>(link: &Path, mut create: F) -> io::Result<()> {
+    internal::remove_symlink_auto(link)?;
+    create(link)
+}
+
+// Long enough to pass 260 characters whatever the scratch directory, with each name short of the
+// usual 255-byte limit.
+fn probe_long_path(scratch: &Path) -> io::Result<()> {
+    let dir = scratch.join("d".repeat(150));
+    fs::create_dir(&dir)?;
+    let link = dir.join("l".repeat(150));
+    internal::symlink_file(Path::new("long-path-target"), &link)?;
+    check_target(&link, "long-path-target").and(fs::remove_file(&link))
+}
+
+#[cfg(windows)]
+fn probe_junction(scratch: &Path) -> Probe {
+    let link = scratch.join("junction");
+    Probe::from_result(internal::symlink_junction(&scratch.join("dir"), &link).and_then(|()| {
+        let result = if link.join("inside").exists() {
+            Ok(())
+        } else {
+            Err(io::Error::other("the junction doesn’t lead into the directory"))
+        };
+        result.and(internal::remove_symlink_dir(&link))
+    }))
+}
+
+#[cfg(not(windows))]
+fn probe_junction(_scratch: &Path) -> Probe {
+    Probe::NotApplicable
+}
+
+fn check_target(link: &Path, expected: &str) -> io::Result<()> {
+    let target = fs::read_link(link)?;
+    if target == Path::new(expected) {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("the symlink to {} reads back as {}", expected,
+                                     target.display())))
+    }
+}
+
+#[cfg(windows)]
+fn privilege() -> (Option<bool>, Option<bool>) {
+    (Some(internal::holds_symlink_privilege()), Some(internal::developer_mode()))
+}
+
+#[cfg(not(windows))]
+fn privilege() -> (Option<bool>, Option<bool>) {
+    (None, None)
+}
@@ -0,0 +1,73 @@
+// Taking turns within a directory, for SymlinkContext::serialize_per_dir: an in-process lock on
+// each directory, and optionally a lock file in it for other processes to respect too.
+
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+use paths;
+
+/// The name of the lock file [`SymlinkContext::lock_files`](crate::SymlinkContext::lock_files)
+/// uses in each directory it works in.
+pub const LOCK_FILE_NAME: &str = ".symlink-rs.lock";
+
+// The directories locked by some thread of this process, by canonical path; and a signal for
+// threads waiting on one of them.
+static LOCKED: Mutex<Option<HashSet<PathBuf>>> = Mutex::new(None);
+static RELEASED: Condvar = Condvar::new();
+
+// A directory this process has to itself, until it’s dropped.
+pub(crate) struct DirLock {
+    dir: PathBuf,
+    // Holding the lock file’s lock, which goes with it.
+    file: Option<File>,
+}
+
+impl DirLock {
+    // Wait for the directory that dst is in, and then for its lock file too if lock_file. The
+    // directory is named by its canonical path, where it exists, so that two routes to the same
+    // directory wait on each other; where it doesn’t exist yet (create_parents is about to make
+    // it), by its absolute path, and without a lock file, as there’s nowhere to put one.
+    pub fn acquire(dst: &Path, lock_file: bool) -> io::Result<DirLock> {
+        let parent = match dst.parent() {
+            Some(parent) if parent != Path::new("") => parent,
+            _ => Path::new("."),
+        };
+        let (dir, exists) = match parent.canonicalize() {
+            Ok(dir) => (dir, true),
+            Err(_) => (paths::absolute(parent)?, false),
+        };
+        let mut locked = lock();
+        while locked.get_or_insert_with(HashSet::new).contains(&dir) {
+            locked = RELEASED.wait(locked).unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        locked.get_or_insert_with(HashSet::new).insert(dir.clone());
+        drop(locked);
+        // From here on, dropping the DirLock gives the directory back, error or not.
+        let mut dir_lock = DirLock { dir, file: None };
+        if lock_file && exists {
+            let file = OpenOptions::new().create(true).truncate(false).write(true)
+                .open(dir_lock.dir.join(LOCK_FILE_NAME))?;
+            file.lock()?;
+            dir_lock.file = Some(file);
+        }
+        Ok(dir_lock)
+    }
+}
+
+impl Drop for DirLock {
+    fn drop(&mut self) {
+        // The lock file first, so that a thread woken here doesn’t find it still locked.
+        drop(self.file.take());
+        if let Some(ref mut locked) = *lock() {
+            locked.remove(&self.dir);
+        }
+        RELEASED.notify_all();
+    }
+}
+
+fn lock() -> MutexGuard<'static, Option<HashSet<PathBuf>>> {
+    LOCKED.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,310 @@
+// Idempotent creation: making sure a symlink is there, rather than insisting on creating it.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use error;
+#[cfg(windows)]
+use internal;
+use paths;
+use stats;
+use {create_symlink, repoint_symlink, SymlinkKind};
+
+/// What one of the `ensure_*` functions did to get the link into the state asked for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CreateOutcome {
+    /// There was nothing at the path, so the link was created.
+    Created,
+    /// The link was already there, pointing at the requested target; nothing was changed.
+    AlreadyCorrect,
+    /// There was a symlink at the path pointing somewhere else; it was replaced.
+    Repointed,
+}
+
+/// What one of the `ensure_*_detailed` functions found, and what it did about it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DetailedOutcome {
+    /// What was at the link path beforehand: nothing, or a symlink and where it pointed.
+    /// (Anything other than a symlink is an error from the ensure functions, so this is only ever
+    /// [`ExistingEntry::Symlink`].)
+    pub before: Option<ExistingEntry>,
+    /// What was done.
+    pub action: CreateOutcome,
+}
+
+pub(crate) fn ensure_symlink(kind: Option<SymlinkKind>, src: &Path, dst: &Path)
+                             -> io::Result<CreateOutcome> {
+    ensure_symlink_detailed(kind, src, dst).map(|outcome| outcome.action)
+}
+
+pub(crate) fn ensure_symlink_detailed(kind: Option<SymlinkKind>, src: &Path, dst: &Path)
+                                      -> io::Result<DetailedOutcome> {
+    let outcome = inspect(kind, src, dst)?;
+    match outcome.action {
+        CreateOutcome::Created => create_symlink(kind, src, dst)?,
+        CreateOutcome::AlreadyCorrect => {}
+        CreateOutcome::Repointed => repoint_symlink(dst, src, kind)?,
+    }
+    Ok(outcome)
+}
+
+// What ensure_symlink would do, without doing it.
+pub(crate) fn plan_symlink(kind: Option<SymlinkKind>, src: &Path, dst: &Path)
+                           -> io::Result<CreateOutcome> {
+    inspect(kind, src, dst).map(|outcome| outcome.action)
+}
+
+// What’s at dst, and what ensure_symlink would do about it.
+fn inspect(kind: Option<SymlinkKind>, src: &Path, dst: &Path) -> io::Result<DetailedOutcome> {
+    match stats::symlink_metadata(dst) {
+        Ok(ref metadata) if metadata.file_type().is_symlink() => {}
+        Ok(_) => return Err(not_a_symlink(dst)),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+            return Ok(DetailedOutcome { before: None, action: CreateOutcome::Created });
+        }
+        Err(e) => return Err(e),
+    }
+    let target = stats::read_link(dst)?;
+    let action = if paths::targets_equivalent(&target, src) && kind_matches(kind, dst)? {
+        CreateOutcome::AlreadyCorrect
+    } else {
+        CreateOutcome::Repointed
+    };
+    Ok(DetailedOutcome { before: Some(ExistingEntry::Symlink { target }), action })
+}
+
+// On Windows a file symlink and a directory symlink to the same target aren’t interchangeable, so
+// the kind has to match too. Elsewhere there’s only one kind.
+#[cfg(windows)]
+pub(crate) fn kind_matches(kind: Option<SymlinkKind>, dst: &Path) -> io::Result<bool> {
+    match kind {
+        Some(kind) => Ok(internal::symlink_kind(dst)? == Some(kind)),
+        None => Ok(true),
+    }
+}
+
+#[cfg(not(windows))]
+pub(crate) fn kind_matches(_kind: Option<SymlinkKind>, _dst: &Path) -> io::Result<bool> {
+    Ok(true)
+}
+
+fn not_a_symlink(dst: &Path) -> io::Error {
+    error::marked(error::Cause::Conflict,
+                  io::Error::new(io::ErrorKind::AlreadyExists,
+                                 format!("{} exists and is not a symlink", dst.display())))
+}
+
+/// Make sure there is a symlink to a file at `dst` pointing to `src`.
+///
+/// If there’s nothing at `dst`, the link is created. If there’s a symlink there already pointing
+/// to `src`—compared with [`targets_equivalent`](crate::targets_equivalent), not by resolving
+/// either—it’s left alone. If there’s a symlink pointing anywhere else, it’s replaced. (On
+/// Windows a directory symlink to `src` counts as pointing anywhere else.) The returned
+/// [`CreateOutcome`] says which of these happened.
+///
+/// Replacing is done as [`change_symlink_kind`](crate::change_symlink_kind) does it: on Unix the
+/// new link is renamed over the old, so there’s never a moment with no link, and elsewhere the
+/// old link is removed and the new one created, and put back if the new one can’t be. Either way,
+/// a new target that would be refused—an empty one, say, or the link itself—is refused before
+/// the old link is touched.
+///
+/// # Errors
+///
+/// An error will be returned if `dst` exists but is not a symlink
+/// ([`AlreadyExists`](io::ErrorKind::AlreadyExists)), if it cannot be inspected, or if the
+/// symlink cannot be removed or created.
+pub fn ensure_symlink_file<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q)
+                                                          -> io::Result<CreateOutcome> {
+    ensure_symlink(Some(SymlinkKind::File), src.as_ref(), dst.as_ref())
+}
+
+/// Make sure there is a symlink to a directory at `dst` pointing to `src`.
+///
+/// See [`ensure_symlink_file`] for details.
+///
+/// # Errors
+///
+/// As for [`ensure_symlink_file`].
+pub fn ensure_symlink_dir<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q)
+                                                         -> io::Result<CreateOutcome> {
+    ensure_symlink(Some(SymlinkKind::Dir), src.as_ref(), dst.as_ref())
+}
+
+/// Make sure there is a symlink at `dst` pointing to `src`, creating it with
+/// [`symlink_auto`](crate::symlink_auto) if need be.
+///
+/// See [`ensure_symlink_file`] for details. An existing link to `src` is accepted whatever its
+/// kind.
+///
+/// # Errors
+///
+/// As for [`ensure_symlink_file`], and [`symlink_auto`](crate::symlink_auto).
+pub fn ensure_symlink_auto<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q)
+                                                          -> io::Result<CreateOutcome> {
+    ensure_symlink(None, src.as_ref(), dst.as_ref())
+}
+
+/// Make sure there is a symlink to a file at `dst` pointing to `src`, and say what was there
+/// before as well as what was done.
+///
+/// This is [`ensure_symlink_file`] for tools that show what changed: with the
+/// [`DetailedOutcome`] you have both sides of “was: symlink → old, now: symlink → new” from the
+/// one call, at no extra cost, since the old target had to be read anyway to compare it.
+///
+/// # Errors
+///
+/// As for [`ensure_symlink_file`].
+pub fn ensure_symlink_file_detailed<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q)
+                                                                   -> io::Result<DetailedOutcome> {
+    ensure_symlink_detailed(Some(SymlinkKind::File), src.as_ref(), dst.as_ref())
+}
+
+/// Make sure there is a symlink to a directory at `dst` pointing to `src`, and say what was
+/// there before as well as what was done.
+///
+/// See [`ensure_symlink_file_detailed`] for details.
+///
+/// # Errors
+///
+/// As for [`ensure_symlink_file`].
+pub fn ensure_symlink_dir_detailed<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q)
+                                                                  -> io::Result<DetailedOutcome> {
+    ensure_symlink_detailed(Some(SymlinkKind::Dir), src.as_ref(), dst.as_ref())
+}
+
+/// What [`try_symlink_file`] or [`try_symlink_dir`] found.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TrySymlinkResult {
+    /// There was nothing at the path, so the link was created.
+    Created,
+    /// There was already a symlink at the path pointing at an equivalent target, per
+    /// [`targets_equivalent`](crate::targets_equivalent). Nothing was changed.
+    ExistsEquivalent,
+    /// Something else was at the path. Nothing was changed.
+    ExistsConflict(ExistingEntry),
+}
+
+/// What was in the way of a [`try_symlink_file`] or [`try_symlink_dir`], or what an
+/// `ensure_*_detailed` function found.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ExistingEntry {
+    /// A symlink. From the try functions, it’s one pointing somewhere else—or, on Windows, a
+    /// symlink of the other kind pointing at the same place.
+    Symlink {
+        /// Its target, as stored.
+        target: PathBuf,
+    },
+    /// A regular file.
+    File,
+    /// A directory.
+    Dir,
+    /// Anything else: a socket, a device, a FIFO and so on.
+    Other,
+}
+
+fn try_symlink(kind: SymlinkKind, src: &Path, dst: &Path) -> io::Result<TrySymlinkResult> {
+    let error = match create_symlink(Some(kind), src, dst) {
+        Ok(()) => return Ok(TrySymlinkResult::Created),
+        Err(e) => if e.kind() == io::ErrorKind::AlreadyExists { e } else { return Err(e) },
+    };
+    // If whatever it was has gone again by now, there’s nothing to describe, and the original
+    // error is the truest answer.
+    let metadata = match stats::symlink_metadata(dst) {
+        Ok(metadata) => metadata,
+        Err(_) => return Err(error),
+    };
+    let file_type = metadata.file_type();
+    let entry = if file_type.is_symlink() {
+        let target = stats::read_link(dst)?;
+        if paths::targets_equivalent(&target, src) && kind_matches(Some(kind), dst)? {
+            return Ok(TrySymlinkResult::ExistsEquivalent);
+        }
+        ExistingEntry::Symlink { target }
+    } else if file_type.is_file() {
+        ExistingEntry::File
+    } else if file_type.is_dir() {
+        ExistingEntry::Dir
+    } else {
+        ExistingEntry::Other
+    };
+    Ok(TrySymlinkResult::ExistsConflict(entry))
+}
+
+/// Create a symlink to a file, or say what’s in the way.
+///
+/// This is the “create it, and if it’s already there, look at what’s there” dance in one call.
+/// The link is created if nothing is at `dst`. If something is, it’s left alone and described: a
+/// symlink pointing at a target equivalent to `src` (compared with
+/// [`targets_equivalent`](crate::targets_equivalent)) is
+/// [`ExistsEquivalent`](TrySymlinkResult::ExistsEquivalent), and anything else is an
+/// [`ExistsConflict`](TrySymlinkResult::ExistsConflict) saying what it is. Unlike
+/// [`ensure_symlink_file`], this never replaces anything.
+///
+/// The creation is tried first, so in the usual case of nothing being there it costs no more
+/// than [`symlink_file`](crate::symlink_file).
+///
+/// # Errors
+///
+/// An error will be returned if the symlink cannot be created for any reason other than
+/// something being at `dst`, or if what’s there cannot be inspected.
+pub fn try_symlink_file<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q)
+                                                       -> io::Result<TrySymlinkResult> {
+    try_symlink(SymlinkKind::File, src.as_ref(), dst.as_ref())
+}
+
+/// Create a symlink to a directory, or say what’s in the way.
+///
+/// See [`try_symlink_file`] for details.
+///
+/// # Errors
+///
+/// As for [`try_symlink_file`].
+pub fn try_symlink_dir<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q)
+                                                      -> io::Result<TrySymlinkResult> {
+    try_symlink(SymlinkKind::Dir, src.as_ref(), dst.as_ref())
+}
+
+fn symlink_lazy<F: FnOnce() -> PathBuf>(kind: SymlinkKind, target_fn: F, dst: &Path)
+                                        -> io::Result<bool> {
+    match stats::symlink_metadata(dst) {
+        Ok(ref metadata) if metadata.file_type().is_symlink() => return Ok(false),
+        Ok(_) => return Err(not_a_symlink(dst)),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let src = target_fn();
+    create_symlink(Some(kind), &src, dst)?;
+    Ok(true)
+}
+
+/// Create a symlink to a file if there isn’t one already, computing its target only if needed.
+///
+/// This is for generators where working out the target is expensive, and most of the links are
+/// already there from last time: if `dst` is already a symlink, it is left alone and `target_fn`
+/// is never called. Note that this means *any* symlink at `dst` counts, wherever it points—that’s
+/// the price of not computing the target.
+///
+/// Returns `true` if the link was created, `false` if there was one already.
+///
+/// # Errors
+///
+/// An error will be returned if `dst` exists but is not a symlink
+/// ([`AlreadyExists`](io::ErrorKind::AlreadyExists)), if it cannot be inspected, or if the
+/// symlink cannot be created.
+pub fn symlink_file_lazy<F: FnOnce() -> PathBuf, Q: AsRef<Path>>(target_fn: F, dst: Q)
+                                                                -> io::Result<bool> {
+    symlink_lazy(SymlinkKind::File, target_fn, dst.as_ref())
+}
+
+/// Create a symlink to a directory if there isn’t one already, computing its target only if
+/// needed.
+///
+/// See [`symlink_file_lazy`] for details.
+///
+/// # Errors
+///
+/// As for [`symlink_file_lazy`].
+pub fn symlink_dir_lazy<F: FnOnce() -> PathBuf, Q: AsRef<Path>>(target_fn: F, dst: Q)
+                                                               -> io::Result<bool> {
+    symlink_lazy(SymlinkKind::Dir, target_fn, dst.as_ref())
+}
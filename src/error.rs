@@ -0,0 +1,114 @@
+// Adding context to OS errors without losing them.
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// An explanatory message, with the original error kept as its source.
+#[derive(Debug)]
+struct Context {
+    message: String,
+    source: io::Error,
+}
+
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.source)
+    }
+}
+
+impl error::Error for Context {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Wrap `source` in an error of the given kind that says `message` first. The original error
+/// stays reachable through [`get_ref`](io::Error::get_ref) and [`source`](error::Error::source).
+pub(crate) fn with_context<M: Into<String>>(kind: io::ErrorKind, message: M, source: io::Error)
+                                            -> io::Error {
+    io::Error::new(kind, Context { message: message.into(), source })
+}
+
+/// What an error of this crate’s own is about, where its [`ErrorKind`](io::ErrorKind) doesn’t
+/// say: for `SymlinkErrorKind` (feature `error-kind`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Cause {
+    /// Something that had to be a symlink isn’t one.
+    NotASymlink,
+    /// The directory a link was to go in doesn’t exist.
+    LinkParentMissing,
+    /// Something is in the way that the operation won’t replace.
+    Conflict,
+}
+
+/// An error marked with its cause. It shows as the error it marks, whose kind it has.
+#[derive(Debug)]
+#[cfg_attr(not(feature = "error-kind"), allow(dead_code))]
+struct Marked {
+    cause: Cause,
+    error: io::Error,
+}
+
+impl fmt::Display for Marked {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl error::Error for Marked {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+/// Mark `error` as being about `cause`, keeping its kind and message.
+pub(crate) fn marked(cause: Cause, error: io::Error) -> io::Error {
+    io::Error::new(error.kind(), Marked { cause, error })
+}
+
+/// The [`InvalidInput`](io::ErrorKind::InvalidInput) error for `path` not being a symlink, with
+/// `more` after the usual message (“, so it wasn’t removed”, say), if anything.
+pub(crate) fn not_a_symlink(path: &Path, more: &str) -> io::Error {
+    marked(Cause::NotASymlink,
+           io::Error::new(io::ErrorKind::InvalidInput,
+                          format!("{} is not a symlink{}", path.display(), more)))
+}
+
+/// The cause `error` was marked with, if it was.
+#[cfg(feature = "error-kind")]
+pub(crate) fn cause(error: &io::Error) -> Option<Cause> {
+    error.get_ref().and_then(|inner| inner.downcast_ref::<Marked>()).map(|marked| marked.cause)
+}
+
+/// The OS error code behind an error from this crate, however it was reworded.
+///
+/// Where the crate adds an explanation to an OS error or files it under a different
+/// [`ErrorKind`](io::ErrorKind)—see the [crate docs](crate#errors)—the result is a new error
+/// wrapping the original, and [`io::Error::raw_os_error`] on it is `None`. The original is always
+/// kept, though, and this digs it out: it returns the `raw_os_error` of the error itself if it
+/// has one, and otherwise of the first [`io::Error`] among those it wraps. The code is `errno` on
+/// Unix and WASI and `GetLastError` on Windows.
+///
+/// Errors that didn’t come from the OS at all—an empty path refused up front, say—have no code,
+/// and give `None`.
+pub fn raw_os_error(error: &io::Error) -> Option<i32> {
+    if let Some(code) = error.raw_os_error() {
+        return Some(code);
+    }
+    let mut next = error.get_ref().map(|inner| inner as &(dyn error::Error + 'static));
+    while let Some(current) = next {
+        if let Some(inner) = current.downcast_ref::<io::Error>() {
+            return raw_os_error(inner);
+        }
+        if let Some(context) = current.downcast_ref::<Context>() {
+            return raw_os_error(&context.source);
+        }
+        if let Some(marked) = current.downcast_ref::<Marked>() {
+            return raw_os_error(&marked.error);
+        }
+        next = current.source();
+    }
+    None
+}
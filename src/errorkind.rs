@@ -0,0 +1,176 @@
+// Errors sorted into the categories callers act on (feature `error-kind`), for an exhaustive match
+// rather than ErrorKinds and raw codes.
+
+use std::error;
+use std::fmt;
+use std::io;
+
+use error::{cause, raw_os_error, Cause};
+use internal;
+
+/// What went wrong, in the terms this crate’s callers usually care about (feature `error-kind`).
+///
+/// An [`io::Error`] says what the OS said, and its [`ErrorKind`](io::ErrorKind) is a long list
+/// that grows (so a `match` on it needs a catch-all), with the same kind meaning different things
+/// from different calls: `NotFound` from creating a link may be its target or its directory
+/// missing, `AlreadyExists` may be a link in the way or a real file [`ensure_symlink_file`]
+/// won’t replace. The crate knows which, for its own errors, and this says: it’s worked out from
+/// the error by [`SymlinkError`], which the crate’s `io::Error`s convert to.
+///
+/// The sorting is as follows, first match winning:
+///
+/// - `NotASymlink`: something that had to be a symlink isn’t—the link given to
+///   [`follow_one_hop`], say, or to a removal.
+/// - `LinkParentMissing`: the directory a link was to be created in doesn’t exist.
+/// - `Conflict`: something is in the way that the operation looked at and won’t replace—a real
+///   file where [`ensure_symlink_file`] was to put a link, a backup that would be overwritten,
+///   or a name differing only in case where those are refused.
+/// - `Loop`: a chain of symlinks loops or is too long, with the OS’s error for that.
+/// - `AlreadyExists`: any other [`AlreadyExists`](io::ErrorKind::AlreadyExists): there’s
+///   something at the link path already.
+/// - `TargetMissing`: any other [`NotFound`](io::ErrorKind::NotFound): something that was to be
+///   looked at isn’t there, most often the target of [`symlink_auto`], but it may be the link to
+///   remove or read.
+/// - `Unsupported`: [`Unsupported`](io::ErrorKind::Unsupported), which the crate reports for
+///   every way of saying symlinks can’t be made here (see the [crate docs](crate#errors)).
+/// - `PermissionDenied`: [`PermissionDenied`](io::ErrorKind::PermissionDenied), including the
+///   lack of the privilege to create symlinks on Windows.
+/// - `Io`: anything else, for which the [`io::Error`] is all there is to go on.
+///
+/// [`ensure_symlink_file`]: crate::ensure_symlink_file
+/// [`follow_one_hop`]: crate::follow_one_hop
+/// [`symlink_auto`]: crate::symlink_auto
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SymlinkErrorKind {
+    /// Something that had to be a symlink isn’t one.
+    NotASymlink,
+    /// Something that was to be looked at—usually the target—doesn’t exist.
+    TargetMissing,
+    /// The directory a link was to be created in doesn’t exist.
+    LinkParentMissing,
+    /// Symlinks can’t be created here.
+    Unsupported,
+    /// Permission was denied, or on Windows the privilege to create symlinks is lacking.
+    PermissionDenied,
+    /// There’s something at the link path already.
+    AlreadyExists,
+    /// A chain of symlinks loops, or is too long to follow.
+    Loop,
+    /// Something is in the way that the operation won’t replace.
+    Conflict,
+    /// Some other I/O error.
+    Io,
+}
+
+impl SymlinkErrorKind {
+    /// The category `error` falls in.
+    pub fn of(error: &io::Error) -> SymlinkErrorKind {
+        match cause(error) {
+            Some(Cause::NotASymlink) => return SymlinkErrorKind::NotASymlink,
+            Some(Cause::LinkParentMissing) => return SymlinkErrorKind::LinkParentMissing,
+            Some(Cause::Conflict) => return SymlinkErrorKind::Conflict,
+            None => {}
+        }
+        let code = raw_os_error(error);
+        if code.is_some() && code == internal::loop_error().raw_os_error() {
+            return SymlinkErrorKind::Loop;
+        }
+        // ERROR_NOT_A_REPARSE_POINT, from reading a link that isn’t one.
+        if cfg!(windows) && code == Some(4390) {
+            return SymlinkErrorKind::NotASymlink;
+        }
+        match error.kind() {
+            io::ErrorKind::AlreadyExists => SymlinkErrorKind::AlreadyExists,
+            io::ErrorKind::NotFound => SymlinkErrorKind::TargetMissing,
+            io::ErrorKind::Unsupported => SymlinkErrorKind::Unsupported,
+            io::ErrorKind::PermissionDenied => SymlinkErrorKind::PermissionDenied,
+            _ => SymlinkErrorKind::Io,
+        }
+    }
+}
+
+/// An [`io::Error`] from this crate with its [`SymlinkErrorKind`] (feature `error-kind`).
+///
+/// The functions return `io::Error`s, as they always have—a feature can’t change what they
+/// return without breaking code that doesn’t ask for it—and this is what they convert to, with
+/// `?` in a function returning `Result<_, SymlinkError>` or with `map_err(SymlinkError::from)`,
+/// for a `match` with no catch-all. It converts back just as readily, to the very error it came
+/// from, so nothing is lost either way: the message, the [`raw_os_error`](crate::raw_os_error)
+/// and the source are all still there.
+///
+/// ```
+/// use symlink_rs::{ensure_symlink_file, SymlinkError, SymlinkErrorKind};
+///
+/// fn link(target: &str, link: &str) -> Result<(), SymlinkError> {
+///     ensure_symlink_file(target, link)?;
+///     Ok(())
+/// }
+///
+/// # let dir = std::env::temp_dir().join("symlink-crate-error-kind-doctest");
+/// # let _ = std::fs::remove_dir_all(&dir);
+/// # std::fs::create_dir(&dir).unwrap();
+/// # std::fs::write(dir.join("config"), "").unwrap();
+/// # let config = dir.join("config");
+/// # let config = config.to_str().unwrap();
+/// match link("defaults/config", config) {
+///     Ok(()) => {}
+///     Err(e) => match e.kind() {
+///         SymlinkErrorKind::Conflict => println!("{} is a real file; leaving it be", config),
+///         SymlinkErrorKind::LinkParentMissing => println!("no such directory for {}", config),
+///         SymlinkErrorKind::NotASymlink
+///         | SymlinkErrorKind::TargetMissing
+///         | SymlinkErrorKind::Unsupported
+///         | SymlinkErrorKind::PermissionDenied
+///         | SymlinkErrorKind::AlreadyExists
+///         | SymlinkErrorKind::Loop
+///         | SymlinkErrorKind::Io => panic!("{}", e),
+///     },
+/// }
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct SymlinkError {
+    kind: SymlinkErrorKind,
+    error: io::Error,
+}
+
+impl SymlinkError {
+    /// What went wrong.
+    pub fn kind(&self) -> SymlinkErrorKind {
+        self.kind
+    }
+
+    /// The error itself.
+    pub fn io_error(&self) -> &io::Error {
+        &self.error
+    }
+
+    /// The error itself, as it came.
+    pub fn into_io_error(self) -> io::Error {
+        self.error
+    }
+}
+
+impl From<io::Error> for SymlinkError {
+    fn from(error: io::Error) -> SymlinkError {
+        SymlinkError { kind: SymlinkErrorKind::of(&error), error }
+    }
+}
+
+impl From<SymlinkError> for io::Error {
+    fn from(error: SymlinkError) -> io::Error {
+        error.error
+    }
+}
+
+impl fmt::Display for SymlinkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl error::Error for SymlinkError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        self.error.source()
+    }
+}
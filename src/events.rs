@@ -0,0 +1,100 @@
+// An in-memory record of what a SymlinkContext or Transaction did, for tests and reports to look
+// through afterwards, where an AuditLog would be a stream to parse.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use {CreateOutcome, LinkKind, PolicyOutcome};
+
+/// One operation recorded by [`SymlinkContext::events`](crate::SymlinkContext::events) or
+/// [`Transaction::events`](crate::Transaction::events).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SymlinkEvent {
+    /// What was asked for.
+    pub op: EventOp,
+    /// The kind of link asked for, after the method called; for an [`Undo`](EventOp::Undo), the
+    /// kind the undoing used, [`Auto`](LinkKind::Auto) where it wasn’t known.
+    pub kind: LinkKind,
+    /// The link path, as given.
+    pub link: PathBuf,
+    /// The target, as given; `None` for a removal.
+    pub target: Option<PathBuf>,
+    /// How it went.
+    pub outcome: EventOutcome,
+}
+
+/// What a [`SymlinkEvent`] was.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EventOp {
+    /// Creating a link: `symlink_file` and friends.
+    Create,
+    /// Making sure of a link: `ensure_symlink_file` and friends.
+    Ensure,
+    /// Removing a link: `remove_symlink_file` and friends.
+    Remove,
+    /// Undoing an earlier operation, in a [`Transaction::rollback`](crate::Transaction::rollback).
+    Undo,
+}
+
+/// How a [`SymlinkEvent`] went: what the call returned, or its error.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum EventOutcome {
+    /// A link was created where there was nothing.
+    Created,
+    /// Something was there, and the link took its place.
+    Replaced,
+    /// Something was there, and was moved aside to this path first.
+    BackedUp(PathBuf),
+    /// Something was there, and was left alone.
+    Skipped,
+    /// The link was already as asked; nothing was changed.
+    AlreadyCorrect,
+    /// A link that pointed elsewhere was repointed.
+    Repointed,
+    /// The link was removed.
+    Removed,
+    /// It failed with this error. Something may still have been done: a hook’s failure, say,
+    /// comes after the link was made.
+    Failed {
+        /// The error’s kind.
+        kind: io::ErrorKind,
+        /// The error’s message.
+        message: String,
+    },
+}
+
+impl From<PolicyOutcome> for EventOutcome {
+    fn from(outcome: PolicyOutcome) -> EventOutcome {
+        match outcome {
+            PolicyOutcome::Created => EventOutcome::Created,
+            PolicyOutcome::Replaced => EventOutcome::Replaced,
+            PolicyOutcome::BackedUp(backup) => EventOutcome::BackedUp(backup),
+            PolicyOutcome::Skipped => EventOutcome::Skipped,
+        }
+    }
+}
+
+impl From<CreateOutcome> for EventOutcome {
+    fn from(outcome: CreateOutcome) -> EventOutcome {
+        match outcome {
+            CreateOutcome::Created => EventOutcome::Created,
+            CreateOutcome::AlreadyCorrect => EventOutcome::AlreadyCorrect,
+            CreateOutcome::Repointed => EventOutcome::Repointed,
+        }
+    }
+}
+
+impl SymlinkEvent {
+    // The event for an operation that returned result, whose success means success.
+    pub(crate) fn new<T, F>(op: EventOp, kind: LinkKind, link: &Path, target: Option<&Path>,
+                            result: &io::Result<T>, success: F) -> SymlinkEvent
+        where F: FnOnce(&T) -> EventOutcome
+    {
+        let outcome = match *result {
+            Ok(ref value) => success(value),
+            Err(ref e) => EventOutcome::Failed { kind: e.kind(), message: e.to_string() },
+        };
+        SymlinkEvent { op, kind, link: link.to_owned(), target: target.map(Path::to_owned),
+                       outcome }
+    }
+}
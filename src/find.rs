@@ -0,0 +1,60 @@
+// Finding the symlinks already in a tree.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// List the symlinks in the tree under `root`.
+///
+/// Every directory under `root` is read, and each symlink found—to a file or a directory,
+/// dangling or not—is listed, as `root` joined with its path inside the tree. Symlinks are
+/// listed, never followed, so a link to a directory is one entry however much is behind it, and
+/// a loop can’t make this go round. `root` itself is read even if it’s a symlink to a directory,
+/// and isn’t listed. The list is sorted, so the same tree always gives the same list.
+///
+/// To go only so deep, use [`find_symlinks_max_depth`].
+///
+/// # Errors
+///
+/// An error will be returned if `root` or any directory in it can’t be read.
+pub fn find_symlinks<P: AsRef<Path>>(root: P) -> io::Result<Vec<PathBuf>> {
+    find(root.as_ref(), usize::MAX)
+}
+
+/// List the symlinks in the tree under `root`, going no more than `max_depth` levels down.
+///
+/// Depth is counted as `walkdir` counts it: the entries of `root` are at depth 1, those of its
+/// subdirectories at 2, and so on, so a `max_depth` of 1 lists the symlinks directly in `root`
+/// and 0 lists none. Otherwise this is [`find_symlinks`], symlinks listed and never followed.
+///
+/// # Errors
+///
+/// An error will be returned if `root` or any directory in it down to `max_depth` can’t be
+/// read.
+pub fn find_symlinks_max_depth<P: AsRef<Path>>(root: P, max_depth: usize)
+                                               -> io::Result<Vec<PathBuf>> {
+    find(root.as_ref(), max_depth)
+}
+
+// The walk goes by a stack of directories still to read, rather than by recursion, so that a tree
+// however deep can’t overflow the stack.
+fn find(root: &Path, max_depth: usize) -> io::Result<Vec<PathBuf>> {
+    let mut found = vec![];
+    let mut pending = vec![(root.to_owned(), 1)];
+    while let Some((dir, depth)) = pending.pop() {
+        if depth > max_depth {
+            continue;
+        }
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_symlink() {
+                found.push(entry.path());
+            } else if file_type.is_dir() {
+                pending.push((entry.path(), depth + 1));
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
@@ -0,0 +1,149 @@
+// Finding out whether symlinks can be created somewhere by asking what sort of filesystem it is,
+// rather than by trying (as probe.rs does).
+
+use std::io;
+use std::path::Path;
+#[cfg(any(target_os = "linux", windows))]
+use std::path::PathBuf;
+
+#[cfg(windows)]
+use internal;
+#[cfg(any(target_os = "linux", windows))]
+use {paths, stats};
+
+/// Whether the filesystem `path` is on supports symlinks, going by what sort of filesystem it is.
+///
+/// This is a best-effort answer, for choosing a fallback—copying, say, or a junction—before
+/// starting, rather than finding out from an [`Unsupported`](io::ErrorKind::Unsupported) error
+/// halfway through. `path` needn’t exist: the filesystem asked about is that of the nearest of
+/// it and its ancestors that does, so it can be the link about to be created.
+///
+/// - On Linux, the filesystem type is looked up in `/proc/self/mounts`, the mount containing
+///   `path` (with symlinks resolved) being the one with the longest mount point it’s under.
+///   FAT in its various forms (`vfat`, `msdos`, `exfat` and the like) is a no; anything else
+///   is a yes, including network and FUSE filesystems, whose answer depends on what’s at the
+///   other end, and that can’t be known from here. Without `/proc` mounted, it’s an error.
+/// - On Windows, the volume’s flags are asked for with `GetVolumeInformationW`, and the answer
+///   is whether they include `FILE_SUPPORTS_REPARSE_POINTS`, which symlinks are: NTFS and ReFS
+///   say yes, FAT and exFAT no. A yes doesn’t mean this process may create them, which takes a
+///   privilege or Developer Mode; see [`symlink_privilege`](crate::symlink_privilege). Network
+///   shares answer for the server’s filesystem, which may still refuse to follow links.
+/// - On other Unix systems and WASI there’s no portable way of asking, and the answer is yes;
+///   elsewhere, where this crate can’t create symlinks at all, no.
+///
+/// Nor is the type everything: a read-only mount, or one whose owner has forbidden symlinks,
+/// will turn a yes into an error all the same. For an answer that comes from actually creating
+/// a link, use [`symlinks_supported_in`](crate::symlinks_supported_in).
+///
+/// # Errors
+///
+/// An error will be returned if the filesystem can’t be asked: on Linux if `/proc/self/mounts`
+/// can’t be read, and on Windows if the volume information can’t be got; or if `path` can’t be
+/// made absolute.
+pub fn filesystem_supports_symlinks<P: AsRef<Path>>(path: P) -> io::Result<bool> {
+    supports(path.as_ref())
+}
+
+// The names in /proc/self/mounts of the filesystems that can’t hold symlinks.
+#[cfg(target_os = "linux")]
+const NO_SYMLINKS: &[&str] = &["vfat", "msdos", "umsdos", "fat", "exfat", "fuse.exfat"];
+
+#[cfg(target_os = "linux")]
+fn supports(path: &Path) -> io::Result<bool> {
+    let existing = nearest_existing(path)?;
+    let existing = existing.canonicalize().unwrap_or(existing);
+    let mounts = ::std::fs::read_to_string("/proc/self/mounts")?;
+    Ok(mount_type(&mounts, &existing).is_none_or(|fs_type| !NO_SYMLINKS.contains(&fs_type)))
+}
+
+#[cfg(windows)]
+fn supports(path: &Path) -> io::Result<bool> {
+    internal::volume_supports_reparse_points(&nearest_existing(path)?)
+}
+
+#[cfg(all(not(target_os = "linux"), any(unix, target_os = "wasi")))]
+fn supports(_path: &Path) -> io::Result<bool> {
+    Ok(true)
+}
+
+#[cfg(not(any(unix, windows, target_os = "wasi")))]
+fn supports(_path: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
+// path made absolute, less as many components as it takes to get to something that exists.
+#[cfg(any(target_os = "linux", windows))]
+fn nearest_existing(path: &Path) -> io::Result<PathBuf> {
+    let path = paths::absolute(path)?;
+    let existing = path.ancestors().find(|a| stats::symlink_metadata(a).is_ok()).unwrap_or(&path);
+    Ok(existing.to_owned())
+}
+
+// The type of the filesystem mounted at the longest mount point in mounts (the text of
+// /proc/self/mounts) that path is under; of two at the same point, the later, which hides the
+// earlier.
+#[cfg(target_os = "linux")]
+fn mount_type<'a>(mounts: &'a str, path: &Path) -> Option<&'a str> {
+    let mut best: Option<(usize, &str)> = None;
+    for line in mounts.lines() {
+        let mut fields = line.split(' ');
+        let (point, fs_type) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(_), Some(point), Some(fs_type)) => (unescape(point), fs_type),
+            _ => continue,
+        };
+        let depth = point.components().count();
+        if path.starts_with(&point) && best.is_none_or(|(best_depth, _)| depth >= best_depth) {
+            best = Some((depth, fs_type));
+        }
+    }
+    best.map(|(_, fs_type)| fs_type)
+}
+
+// A mount point as /proc/self/mounts writes it, with space, tab, newline and backslash as octal
+// escapes.
+#[cfg(target_os = "linux")]
+fn unescape(field: &str) -> PathBuf {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes.get(i + 1..i + 4).filter(|digits| {
+            bytes[i] == b'\\' && digits.iter().all(|d| (b'0'..=b'7').contains(d))
+        });
+        match octal {
+            Some(digits) => {
+                out.push(digits.iter().fold(0u8, |n, d| n.wrapping_mul(8) + (d - b'0')));
+                i += 4;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    PathBuf::from(OsStr::from_bytes(&out))
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_innermost_mount() {
+        let mounts = "/dev/sda1 / ext4 rw,relatime 0 0\n\
+                      /dev/sdb1 /media/usb\\040stick vfat rw 0 0\n\
+                      tmpfs /media/usb\\040stick/tmp tmpfs rw 0 0\n\
+                      /dev/sdc1 /media/usb vfat rw 0 0\n\
+                      /dev/sdd1 /media/usb ext4 rw 0 0\n";
+        let fs_type = |path: &str| mount_type(mounts, Path::new(path));
+        assert_eq!(fs_type("/home/me"), Some("ext4"));
+        assert_eq!(fs_type("/media/usb stick/photos"), Some("vfat"));
+        assert_eq!(fs_type("/media/usb stick/tmp/x"), Some("tmpfs"));
+        // Not under /media/usb at all, and /media/usb itself remounted.
+        assert_eq!(fs_type("/media/usb2"), Some("ext4"));
+        assert_eq!(fs_type("/media/usb/x"), Some("ext4"));
+        assert_eq!(mount_type("", Path::new("/")), None);
+    }
+}
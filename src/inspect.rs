@@ -0,0 +1,262 @@
+// Telling the kinds of link apart, for diagnosing mix-ups between them.
+
+use std::fs;
+use std::io;
+use std::path::{self, Path};
+
+#[cfg(windows)]
+use internal;
+use {error, stats, SymlinkKind};
+
+/// What kind of thing is at a path, as far as links are concerned; see [`link_type`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LinkType {
+    /// A symlink. On Windows the kind is the link’s own; elsewhere, where links have no kind,
+    /// it’s that of what the link leads to.
+    Symlink(SymlinkKind),
+    /// A symlink that leads nowhere—to nothing, or round a loop—where links have no kind of their
+    /// own (everywhere but Windows), so there’s no telling which kind it was meant to be. For an
+    /// answer all the same, there’s [`guess_dangling_kind`].
+    DanglingUnknown,
+    /// A junction or other mount point (Windows only).
+    MountPoint,
+    /// A regular file with more than one name: a hard link, or the original of one. They’re
+    /// indistinguishable—every name is equally the file—so removing this name leaves the others,
+    /// and the contents, alone.
+    HardLinkedFile {
+        /// How many names the file has.
+        nlink: u64,
+    },
+    /// A regular file with just the one name (or on a platform that doesn’t count them).
+    Regular,
+    /// A directory.
+    Directory,
+    /// Anything else: a socket, a device, a FIFO, a kind of reparse point that isn’t a link, and
+    /// so on.
+    Other,
+}
+
+/// Find out what kind of link, if any, is at `path`, without following it.
+///
+/// This is for diagnosing mix-ups, notably a hard link made with [`std::fs::hard_link`] where a
+/// symlink was meant. A hard link isn’t a link in the sense this crate deals in: it’s just
+/// another name for a regular file, so [`remove_symlink_file`](crate::remove_symlink_file)
+/// happily removes it and [`symlink_auto`](crate::symlink_auto) classifies it as the file it is.
+/// Its only trace is the file’s link count, which is reported here as
+/// [`HardLinkedFile`](LinkType::HardLinkedFile). (The count comes from `lstat` on Unix and from
+/// `GetFileInformationByHandle` on Windows; elsewhere there’s no way to ask, so every file is
+/// [`Regular`](LinkType::Regular).)
+///
+/// # Errors
+///
+/// An error will be returned if `path` can’t be inspected, including if nothing is there.
+pub fn link_type<P: AsRef<Path>>(path: P) -> io::Result<LinkType> {
+    let path = path.as_ref();
+    let metadata = stats::symlink_metadata(path)?;
+    let file_type = metadata.file_type();
+    Ok(if file_type.is_symlink() {
+        symlink_type(path, &metadata)?
+    } else if file_type.is_dir() {
+        LinkType::Directory
+    } else if file_type.is_file() {
+        match nlink(path, &metadata)? {
+            nlink if nlink > 1 => LinkType::HardLinkedFile { nlink },
+            _ => LinkType::Regular,
+        }
+    } else {
+        LinkType::Other
+    })
+}
+
+/// Guess which kind the symlink at `link` was meant to be, even if it leads nowhere.
+///
+/// This is a guess, and only a guess, for tools that have to report a kind for every link and
+/// would rather not stop at a dangling one: where [`link_type`] says
+/// [`DanglingUnknown`](LinkType::DanglingUnknown), this goes by what the target looks like. A
+/// target ending in a separator, or in `.` or `..`, names a directory, as `foo/` can’t be a file;
+/// any other is taken for a file, which is what a dangling link most often turns out to have been
+/// meant for, but may well be wrong. Only the target as stored is looked at; there’s no sidecar,
+/// and nothing is recorded anywhere.
+///
+/// Where there’s no need to guess, there’s no guessing: a link that leads somewhere has the kind
+/// of what it leads to, and on Windows every link has its own kind, dangling or not, which is
+/// what’s returned (a junction being a directory).
+///
+/// # Errors
+///
+/// An [`InvalidInput`](io::ErrorKind::InvalidInput) error will be returned if `link` isn’t a
+/// symlink, and other errors if it can’t be inspected or its target read.
+pub fn guess_dangling_kind<P: AsRef<Path>>(link: P) -> io::Result<SymlinkKind> {
+    let link = link.as_ref();
+    let metadata = stats::symlink_metadata(link)?;
+    if !metadata.file_type().is_symlink() {
+        return Err(error::not_a_symlink(link, ""));
+    }
+    match symlink_type(link, &metadata)? {
+        LinkType::Symlink(kind) => Ok(kind),
+        LinkType::MountPoint => Ok(SymlinkKind::Dir),
+        _ if names_a_dir(&stats::read_link(link)?) => Ok(SymlinkKind::Dir),
+        _ => Ok(SymlinkKind::File),
+    }
+}
+
+// Whether target can only be a directory, going by its last component alone.
+fn names_a_dir(target: &Path) -> bool {
+    let target = target.to_string_lossy();
+    let last = target.rsplit(path::is_separator).next().unwrap_or("");
+    last.is_empty() || last == "." || last == ".."
+}
+
+/// Whether the target stored in the symlink at `link` is relative: whether where the link leads
+/// depends on where the link is.
+///
+/// This is for checking that a link came out as meant—with
+/// [`target_style`](crate::SymlinkBuilder::target_style), say—and reads the target as stored,
+/// without following it. On Unix it’s relative unless it starts with `/`. On Windows, where there
+/// are forms in between—`\dir\target`, rooted but with no drive, and `C:target`, with a drive
+/// but no root—the answer isn’t a judgement on the string but the flag the OS itself goes by,
+/// `SYMLINK_FLAG_RELATIVE`, set on links whose target it resolves against the link’s directory:
+/// a rooted target among them, landing on the link’s own drive. UNC targets,
+/// `\\server\share\target`, and verbatim ones, `\\?\C:\target`, are absolute, as are junctions
+/// and other mount points, which always hold an absolute target.
+///
+/// ```
+/// # let dir = std::env::temp_dir().join("symlink-crate-target-is-relative-doctest");
+/// # let _ = std::fs::remove_dir_all(&dir);
+/// # std::fs::create_dir(&dir).unwrap();
+/// use symlink_rs::{symlink_file, symlink_target_is_relative};
+///
+/// symlink_file("target", dir.join("relative"))?;
+/// symlink_file(dir.join("target"), dir.join("absolute"))?;
+/// assert!(symlink_target_is_relative(dir.join("relative"))?);
+/// assert!(!symlink_target_is_relative(dir.join("absolute"))?);
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// An [`InvalidInput`](io::ErrorKind::InvalidInput) error will be returned if `link` isn’t a
+/// symlink (or on Windows a mount point), and other errors if it can’t be inspected or its
+/// target read.
+pub fn symlink_target_is_relative<P: AsRef<Path>>(link: P) -> io::Result<bool> {
+    let link = link.as_ref();
+    if !stats::symlink_metadata(link)?.file_type().is_symlink() {
+        return Err(error::not_a_symlink(link, ""));
+    }
+    target_is_relative(link)
+}
+
+#[cfg(windows)]
+fn target_is_relative(link: &Path) -> io::Result<bool> {
+    let names = internal::read_symlink_names(link)?;
+    Ok(names.relative && !names.mount_point)
+}
+
+#[cfg(not(windows))]
+fn target_is_relative(link: &Path) -> io::Result<bool> {
+    Ok(stats::read_link(link)?.is_relative())
+}
+
+/// An identifier for the symlink at `path` itself, not what it points to.
+///
+/// This is the link’s own identity, as `lstat` sees it: on Unix its device and inode numbers, the
+/// device in the high 64 bits and the inode in the low; on Windows its volume serial number and
+/// file index, likewise, read from the link opened as a reparse point. It stays the same when the
+/// link is renamed or moved within its filesystem, and when what it points to changes, so it can
+/// track a link across renames and tell two names for the same link from two links. Replacing a
+/// link, as [`SymlinkBuilder::replace_existing`](crate::SymlinkBuilder::replace_existing) does,
+/// makes a new one, with a new identity. Identifiers can be reused once a link is removed, and
+/// mean nothing across machines.
+///
+/// Elsewhere, there’s no stable way to ask, and this is an
+/// [`Unsupported`](io::ErrorKind::Unsupported) error.
+///
+/// # Errors
+///
+/// An [`InvalidInput`](io::ErrorKind::InvalidInput) error will be returned if `path` isn’t a
+/// symlink, and other errors if it can’t be inspected.
+pub fn symlink_id<P: AsRef<Path>>(path: P) -> io::Result<u128> {
+    let path = path.as_ref();
+    let metadata = stats::symlink_metadata(path)?;
+    if !metadata.file_type().is_symlink() {
+        return Err(error::not_a_symlink(path, ""));
+    }
+    id(path, &metadata)
+}
+
+#[cfg(windows)]
+fn id(path: &Path, _metadata: &fs::Metadata) -> io::Result<u128> {
+    internal::file_id(path)
+}
+
+#[cfg(not(windows))]
+fn id(_path: &Path, metadata: &fs::Metadata) -> io::Result<u128> {
+    #[cfg(unix)]
+    let id = {
+        use std::os::unix::fs::MetadataExt;
+        Ok((metadata.dev() as u128) << 64 | metadata.ino() as u128)
+    };
+    #[cfg(not(unix))]
+    let id = {
+        let _ = metadata;
+        Err(io::Error::new(io::ErrorKind::Unsupported,
+                           "symlink identifiers aren’t available on this platform"))
+    };
+    id
+}
+
+// The device the file at path is on, not following a symlink there: on Unix st_dev, on Windows
+// the volume serial number.
+#[cfg(windows)]
+pub(crate) fn device_id(path: &Path) -> io::Result<u64> {
+    Ok((internal::file_id(path)? >> 64) as u64)
+}
+
+#[cfg(not(windows))]
+pub(crate) fn device_id(path: &Path) -> io::Result<u64> {
+    #[cfg(unix)]
+    let dev = stats::symlink_metadata(path).map(|m| ::std::os::unix::fs::MetadataExt::dev(&m));
+    #[cfg(not(unix))]
+    let dev = {
+        let _ = path;
+        Err(io::Error::new(io::ErrorKind::Unsupported,
+                           "devices can’t be told apart on this platform"))
+    };
+    dev
+}
+
+#[cfg(windows)]
+fn symlink_type(path: &Path, metadata: &fs::Metadata) -> io::Result<LinkType> {
+    use std::os::windows::fs::FileTypeExt;
+    Ok(if internal::is_mount_point(path)? {
+        LinkType::MountPoint
+    } else if metadata.file_type().is_symlink_dir() {
+        LinkType::Symlink(SymlinkKind::Dir)
+    } else {
+        LinkType::Symlink(SymlinkKind::File)
+    })
+}
+
+#[cfg(not(windows))]
+fn symlink_type(path: &Path, _metadata: &fs::Metadata) -> io::Result<LinkType> {
+    Ok(match stats::metadata(path) {
+        Ok(ref target) if target.is_dir() => LinkType::Symlink(SymlinkKind::Dir),
+        Ok(_) => LinkType::Symlink(SymlinkKind::File),
+        Err(_) => LinkType::DanglingUnknown,
+    })
+}
+
+#[cfg(windows)]
+fn nlink(path: &Path, _metadata: &fs::Metadata) -> io::Result<u64> {
+    internal::number_of_links(path)
+}
+
+#[cfg(not(windows))]
+fn nlink(_path: &Path, metadata: &fs::Metadata) -> io::Result<u64> {
+    #[cfg(unix)]
+    let nlink = ::std::os::unix::fs::MetadataExt::nlink(metadata);
+    #[cfg(not(unix))]
+    let nlink = { let _ = metadata; 1 };
+    Ok(nlink)
+}
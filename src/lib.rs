@@ -2,6 +2,94 @@
 //!
 //! For efficiency, you should prefer to use [`symlink_file`] or [`symlink_dir`]—whichever is
 //! appropriate—rather than [`symlink_auto`].
+//!
+//! For the less common cases, [`SymlinkBuilder`] offers some extra options.
+//!
+//! # Atomicity
+//!
+//! What you can rely on another process seeing while a link is being made:
+//!
+//! - Creating a link—[`symlink_file`], [`symlink_dir`], [`symlink_auto`] and the rest—is one
+//!   call into the OS, so it’s there in full or not at all. Nothing already at the path is ever
+//!   replaced.
+//! - [`SymlinkBuilder::replace_existing`] replaces a link atomically where
+//!   [`ATOMIC_REPLACE_SUPPORTED`] is true (on Unix, by renaming a new link over the old one):
+//!   every look at the path finds either the old link or the new. Where it’s false (on Windows
+//!   and elsewhere), the old link is removed and then the new one created, and a look in between
+//!   finds nothing; if the new one can’t be created, the old one is put back.
+//! - [`change_symlink_kind`] is atomic, or not, on the same terms.
+//! - The [`ensure_symlink_file`] family repoints a link atomically, or not, on the same terms
+//!   again.
+//! - [`symlink_file_backup`] and [`symlink_dir_backup`] move the old entry aside and then create
+//!   the link, so a look in between finds nothing.
+//!
+//! # Replacing directory symlinks
+//!
+//! **Nothing in this crate that replaces or removes a directory symlink ever deletes what it
+//! points to.** [`SymlinkBuilder::replace_existing`], the [`ensure_symlink_dir`] family, every
+//! [`ConflictPolicy`] (including [`Overwrite`](ConflictPolicy::Overwrite)), [`remove_manifest`]
+//! and the `remove_symlink_*` functions all remove or rename the link itself—with `remove_dir`
+//! on Windows, where a directory symlink is removed as a directory, and `unlink` or `rename`
+//! elsewhere—and never recurse into it, so the directory at the other end keeps everything in
+//! it. The one exception is [`remove_symlink_dir_and_target`], whose whole purpose, as its name
+//! says, is to delete the target too.
+//!
+//! # Names ending in a dot or space
+//!
+//! Windows quietly drops a dot or space from the end of a file name, so `foo.` and `foo ` both
+//! mean `foo` there, while on Unix they’re three different names. A manifest written on Unix that
+//! links `foo.` would, taken at its word on Windows, make a link called `foo`, or one pointing
+//! at `foo`—and a later run given the same manifest would find things not as described. So on
+//! Windows, creating a link whose path or target has such a name is an
+//! [`InvalidInput`](io::ErrorKind::InvalidInput) error, unless the path is verbatim (`\\?\…`)
+//! already. To make such names anyway, exactly as written, use
+//! [`SymlinkBuilder::preserve_trailing_dots`]. Elsewhere names are taken as given.
+//!
+//! # Errors
+//!
+//! Errors are generally those of the underlying OS calls, passed through unchanged, with one
+//! exception: the various ways platforms have of saying “symlinks aren’t supported here” are all
+//! normalised to [`io::ErrorKind::Unsupported`], so that you can match on that rather than on OS
+//! error codes. (The original error is kept as the payload, for its message.)
+//!
+//! | Platform          | OS error                                        | Becomes            |
+//! |-------------------|-------------------------------------------------|--------------------|
+//! | All Unix          | `ENOSYS`                                        | `Unsupported`      |
+//! | Linux, Android    | `EPERM`*, `EOPNOTSUPP`                          | `Unsupported`      |
+//! | macOS, iOS        | `ENOTSUP`, `EOPNOTSUPP`                         | `Unsupported`      |
+//! | FreeBSD           | `EOPNOTSUPP`                                    | `Unsupported`      |
+//! | Windows           | `ERROR_NOT_SUPPORTED`, `ERROR_INVALID_FUNCTION` | `Unsupported`      |
+//! | Windows           | `ERROR_PRIVILEGE_NOT_HELD`                      | `PermissionDenied` |
+//! | WASI              | `ENOSYS`, `ENOTSUP`                             | `Unsupported`      |
+//! | Everything else   | (the standard library already says so)          | `Unsupported`      |
+//!
+//! \* For symlink(2), Linux uses `EPERM` to mean that the filesystem doesn’t support symlinks;
+//! permission problems are `EACCES`.
+//!
+//! The Windows privilege case isn’t a lack of support as such: the privilege can be granted, or
+//! Developer Mode enabled. But it’s a permissions matter, so it’s reported as one—with a message
+//! saying as much, since the OS’s own is rather unhelpful.
+//!
+//! Some things are refused before the OS is even asked: an empty target or link path, and a
+//! symlink to itself (its target, resolved against the link’s parent directory, being lexically
+//! the link). These are [`io::ErrorKind::InvalidInput`] errors everywhere instead of doing
+//! different things on different platforms.
+//!
+//! And some are explained, keeping the OS error as the source:
+//!
+//! - When a link can’t be created because its parent directory doesn’t exist, the
+//!   [`io::ErrorKind::NotFound`] error says so, so that it isn’t mistaken for a missing target.
+//! - [`io::ErrorKind::PermissionDenied`] and [`io::ErrorKind::ReadOnlyFilesystem`] (`EROFS`)
+//!   errors from creating a link name the link, and say what’s likely to be read-only.
+//!
+//! Errors passed through unchanged keep their [`io::Error::raw_os_error`]. Normalised and
+//! explained ones are new errors wrapping the original, so theirs is `None`; [`raw_os_error`]
+//! gets the original code back from any of them.
+//!
+//! With the `error-kind` feature, any of them converts to a `SymlinkError`, whose
+//! `SymlinkErrorKind` sorts it into the handful of categories callers usually act on—not a
+//! symlink, the link’s directory missing, something in the way, and so on—for an exhaustive
+//! `match`.
 
 // Building docs produces rustdoc::broken_intra_doc_links warnings on std::os::{windows, unix},
 // depending on your platform. This is unfortunate because I then can’t RUSTDOCFLAGS="-D warnings"
@@ -10,26 +98,193 @@
 
 // It’s generally nicer to produce an empty crate on unsupported platforms than to explode.
 
+use std::ffi::OsStr;
 use std::fs;
 use std::io;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+#[cfg(any(unix, target_os = "wasi"))]
+use std::os::fd::AsFd;
+
+#[cfg(feature = "audit")]
+mod audit;
+mod builder;
+mod cache;
+mod command;
+mod context;
+mod decisions;
+mod diagnose;
+mod dirlock;
+mod ensure;
+mod error;
+#[cfg(feature = "error-kind")]
+mod errorkind;
+mod events;
+mod find;
+mod fstype;
+mod inspect;
+mod manifest;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+mod mirror;
+#[cfg(feature = "mock")]
+mod mock;
+mod ops;
+mod paths;
+mod policy;
+mod probe;
+mod reflink;
+mod resolve;
+#[cfg(feature = "stats")]
+mod stats;
+mod transaction;
+
+#[cfg(unix)]
+mod unix;
+#[cfg(target_os = "wasi")]
+mod wasi;
+
+// Where the symlink_*_in functions find their directory-relative operations.
+#[cfg(unix)]
+use unix as beneath;
+#[cfg(target_os = "wasi")]
+use wasi as beneath;
+
+#[cfg(feature = "audit")]
+pub use audit::AuditLog;
+pub use builder::{ClassifyHeuristic, SymlinkBuilder, TargetSeparators, TargetStyle};
+pub use cache::CacheStats;
+pub use command::equivalent_command;
+pub use context::SymlinkContext;
+pub use decisions::DecisionLog;
+pub use diagnose::{diagnose, DiagnosticReport, Probe};
+pub use dirlock::LOCK_FILE_NAME;
+pub use error::raw_os_error;
+#[cfg(feature = "error-kind")]
+pub use errorkind::{SymlinkError, SymlinkErrorKind};
+pub use events::{EventOp, EventOutcome, SymlinkEvent};
+pub use find::{find_symlinks, find_symlinks_max_depth};
+pub use fstype::filesystem_supports_symlinks;
+pub use inspect::{guess_dangling_kind, link_type, symlink_id, symlink_target_is_relative,
+                  LinkType};
+pub use ensure::{ensure_symlink_auto, ensure_symlink_dir, ensure_symlink_dir_detailed,
+                 ensure_symlink_file, ensure_symlink_file_detailed, symlink_dir_lazy,
+                 symlink_file_lazy, try_symlink_dir, try_symlink_file, CreateOutcome,
+                 DetailedOutcome, ExistingEntry, TrySymlinkResult};
+pub use manifest::{apply, apply_from_reader, apply_with_progress, diff_manifest, read_lock,
+                   reconcile, remove_manifest, symlink_stream, symlink_stream_with_progress,
+                   validate_manifest, verify_against_lock, write_lock, LinkDiff, LinkKind,
+                   LinkSpec, Manifest, ManifestProblem, ReconcileAction};
+pub use mirror::{mirror_tree, mirror_tree_with_progress, MirrorAction, MirrorMode, MirrorPolicy,
+                 MirrorReport};
+#[cfg(feature = "mock")]
+pub use mock::{MockLink, MockSymlinks};
+pub use ops::{SymlinkOps, SystemSymlinks};
+pub use paths::{targets_equivalent, TargetComparator};
+pub use policy::{symlink, ConflictPolicy, PolicyOutcome};
+pub use probe::{symlinks_supported, symlinks_supported_in};
+pub use reflink::{reflink_or_symlink_file, LinkStrategy};
+pub use resolve::{follow_one_hop, readlink_f, resolve_symlink, resolve_symlink_until_missing,
+                  symlink_chain, target_relative_to, Resolution, MAX_RESOLVED_COMPONENTS,
+                  MAX_SYMLINK_HOPS};
+#[cfg(feature = "stats")]
+pub use stats::SymlinkStats;
+pub use transaction::{JournalEntry, Transaction};
 
 #[cfg(windows)]
 #[path = "windows/mod.rs"]
 mod internal;
 
+#[cfg(windows)]
+pub use internal::{SymlinkDetails, SymlinkFlags, SymlinkNames, SymlinkPrivilege};
+
+/// Whether [`SymlinkBuilder::replace_existing`] replaces a link atomically on this platform.
+///
+/// True on Unix, where the new link is renamed over the old one, and false elsewhere, where
+/// there’s a moment with no link at all. See [Atomicity](crate#atomicity).
+pub const ATOMIC_REPLACE_SUPPORTED: bool = cfg!(unix);
+
+// Without the feature, tracking is a no-op the optimiser can see straight through.
+#[cfg(not(feature = "metrics"))]
+mod metrics {
+    use std::io;
+
+    #[derive(Clone, Copy)]
+    pub enum Op {
+        Create,
+        Remove,
+    }
+
+    #[derive(Clone, Copy)]
+    pub enum Label {
+        File,
+        Dir,
+        Auto,
+        #[cfg_attr(not(windows), allow(dead_code))]
+        Junction,
+    }
+
+    #[inline(always)]
+    pub fn track<T>(_op: Op, _label: Label, result: io::Result<T>) -> io::Result<T> {
+        result
+    }
+}
+
+use metrics::{Label, Op};
+
+// The same again for the stats feature: without it, the counted lookups are just std’s.
+#[cfg(not(feature = "stats"))]
+mod stats {
+    pub use std::fs::{metadata, read_link, symlink_metadata};
+
+    #[inline(always)]
+    pub fn create() {}
+
+    #[inline(always)]
+    pub fn remove() {}
+
+    #[cfg_attr(not(windows), allow(dead_code))]
+    #[inline(always)]
+    pub fn stat() {}
+}
+
+// Count an operation for both features, passing the result through.
+fn track<T>(op: Op, label: Label, result: io::Result<T>) -> io::Result<T> {
+    match op {
+        Op::Create => stats::create(),
+        Op::Remove => stats::remove(),
+    }
+    metrics::track(op, label, result)
+}
+
 #[cfg(not(windows))]
 mod internal {
+    use std::io;
+    use std::path::Path;
+
     pub use std::fs::remove_file as remove_symlink_dir;
     pub use std::fs::remove_file as remove_symlink_auto;
+
+    pub use self::symlink as symlink_auto;
+    pub use self::symlink as symlink_file;
+    pub use self::symlink as symlink_dir;
+
+    #[cfg(unix)]
+    pub use unix::loop_error;
+
+    #[cfg(not(unix))]
+    pub fn loop_error() -> io::Error {
+        io::Error::other("too many levels of symbolic links")
+    }
+
     // Look, frankly, std::fs::soft_link and std::os::unix::fs::symlink call the same function,
     // so this probably whole separate mod probably isn’t even warranted.
     // But deprecated blah blah blah so I decided to use the std::os one anyway.
     #[cfg(unix)]
-    pub use std::os::unix::fs::{symlink as symlink_auto,
-                                symlink as symlink_file,
-                                symlink as symlink_dir};
-    #[cfg(not(unix))]
+    pub fn symlink(src: &Path, dst: &Path) -> io::Result<()> {
+        ::std::os::unix::fs::symlink(src, dst).map_err(::unix::normalize_error)
+    }
+
     // The compiler claims that std::fs::soft_link has been “replaced with
     // std::os::unix::fs::symlink and std::os::windows::fs::{symlink_file, symlink_dir}”
     // (rustc nightly 2021-12-26 deprecation warning message), but although that was true enough
@@ -39,10 +294,169 @@ mod internal {
     // (though imperfect) reason to *undeprecate* soft_link. Who knows what other platforms may in
     // the future stop returning std::io::ErrorKind::Unsupported errors and start supporting
     // std::fs::soft_link? (And for clarity, I note that no others do at the time of writing.)
+    #[cfg(not(unix))]
     #[allow(deprecated)]
-    pub use std::fs::{soft_link as symlink_auto,
-                      soft_link as symlink_file,
-                      soft_link as symlink_dir};
+    pub fn symlink(src: &Path, dst: &Path) -> io::Result<()> {
+        ::fallback_parity(::std::fs::soft_link(src, dst).map_err(normalize_error), dst)
+    }
+
+    // WASI’s ENOSYS and ENOTSUP.
+    #[cfg(not(unix))]
+    pub fn normalize_error(e: io::Error) -> io::Error {
+        match e.raw_os_error() {
+            Some(52) | Some(58) if cfg!(target_os = "wasi") => {
+                io::Error::new(io::ErrorKind::Unsupported, e)
+            }
+            _ => e,
+        }
+    }
+}
+
+// Where symlinks aren’t supported at all, soft_link fails with Unsupported before it looks at
+// anything, whereas Unix and Windows say AlreadyExists if there’s something at the link path
+// already. Programs shouldn’t have to care which branch they hit, so the fallback gives the same
+// answer. (Only once it has failed, so this costs nothing on success.)
+#[cfg_attr(any(unix, windows), allow(dead_code))]
+fn fallback_parity(result: io::Result<()>, dst: &Path) -> io::Result<()> {
+    match result {
+        Err(ref e) if e.kind() == io::ErrorKind::Unsupported
+                      && stats::symlink_metadata(dst).is_ok() => {
+            Err(io::Error::new(io::ErrorKind::AlreadyExists,
+                               format!("{} already exists", dst.display())))
+        }
+        result => result,
+    }
+}
+
+/// The kind of a symlink.
+///
+/// On Windows, file symlinks and directory symlinks are distinct things, created and removed in
+/// different ways. Elsewhere there’s no such distinction, but it’s still worth saying which you
+/// mean, for the sake of Windows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SymlinkKind {
+    /// A symlink to a file.
+    File,
+    /// A symlink to a directory.
+    Dir,
+}
+
+// Create a symlink of the given kind, or of whichever kind suits the target for None.
+fn create_symlink(kind: Option<SymlinkKind>, src: &Path, dst: &Path) -> io::Result<()> {
+    create_checked(kind, src, dst, |src, dst| match kind {
+        Some(SymlinkKind::File) => internal::symlink_file(src, dst),
+        Some(SymlinkKind::Dir) => internal::symlink_dir(src, dst),
+        None => internal::symlink_auto(src, dst),
+    })
+}
+
+// Create a symlink with create, having refused what’s refused everywhere, then explain and count
+// the result. However a link of the given kind is made, it goes through this, so that every way
+// of making one is held to the same rules.
+fn create_checked<F>(kind: Option<SymlinkKind>, src: &Path, dst: &Path, create: F)
+                     -> io::Result<()>
+    where F: FnOnce(&Path, &Path) -> io::Result<()>
+{
+    let label = match kind {
+        Some(SymlinkKind::File) => Label::File,
+        Some(SymlinkKind::Dir) => Label::Dir,
+        None => Label::Auto,
+    };
+    let result = check_not_empty(src, dst).and_then(|()| check_not_self_link(src, dst))
+        .and_then(|()| check_trailing_names(src, dst)).and_then(|()| create(src, dst));
+    track(Op::Create, label, result.map_err(|e| explain_error(e, dst)))
+}
+
+// The OS’s errors for creating a link don’t say which path was the problem, and some are
+// ambiguous, so the ones people trip over get explained. Errors this crate has already explained
+// are left alone. This is only looked into once creation has failed, so it costs nothing
+// otherwise.
+fn explain_error(error: io::Error, dst: &Path) -> io::Error {
+    match error.kind() {
+        io::ErrorKind::NotFound => explain_missing_parent(error, dst),
+        io::ErrorKind::PermissionDenied if error.raw_os_error().is_some() => {
+            error::with_context(io::ErrorKind::PermissionDenied,
+                                format!("permission denied creating symlink {} (is its parent \
+                                         directory read-only?)", dst.display()),
+                                error)
+        }
+        io::ErrorKind::ReadOnlyFilesystem if error.raw_os_error().is_some() => {
+            error::with_context(io::ErrorKind::ReadOnlyFilesystem,
+                                format!("cannot create symlink {}: it’s on a read-only mount",
+                                        dst.display()),
+                                error)
+        }
+        _ => error,
+    }
+}
+
+// A NotFound could mean the target (for symlink_auto) or the link’s parent directory is missing,
+// and people mistake one for the other; so when it’s the parent, say so.
+fn explain_missing_parent(error: io::Error, dst: &Path) -> io::Error {
+    let parent = match dst.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => return error,
+    };
+    match stats::metadata(parent) {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+            let message = format!("parent directory of the link does not exist: {} \
+                                   (SymlinkBuilder::create_parents can create it)",
+                                  parent.display());
+            error::marked(error::Cause::LinkParentMissing,
+                          error::with_context(io::ErrorKind::NotFound, message, error))
+        }
+        _ => error,
+    }
+}
+
+// What an empty target or link path does depends on the platform, too, so they’re also refused.
+fn check_not_empty(src: &Path, dst: &Path) -> io::Result<()> {
+    if src.as_os_str().is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "symlink target must not be empty"));
+    }
+    check_link_not_empty(dst)
+}
+
+fn check_link_not_empty(dst: &Path) -> io::Result<()> {
+    if dst.as_os_str().is_empty() {
+        Err(io::Error::new(io::ErrorKind::InvalidInput, "symlink path must not be empty"))
+    } else {
+        Ok(())
+    }
+}
+
+// What a link to itself does depends on the platform (Unix creates it, and it then fails every
+// lookup with ELOOP), so it’s refused up front, everywhere.
+fn check_not_self_link(src: &Path, dst: &Path) -> io::Result<()> {
+    if paths::is_self_link(dst, src)? {
+        Err(io::Error::new(io::ErrorKind::InvalidInput,
+                           format!("cannot create a symlink to itself: {}", dst.display())))
+    } else {
+        Ok(())
+    }
+}
+
+// Windows drops a dot or space from the end of a name, outside verbatim paths, so a link asked
+// for as foo. would be made as foo, and a link to foo. would lead to foo: not what a manifest
+// written on Unix means by them. Better to refuse than to do something else quietly. Elsewhere
+// names are taken as they are.
+#[cfg(windows)]
+fn check_trailing_names(src: &Path, dst: &Path) -> io::Result<()> {
+    for &(what, path) in &[("path", dst), ("target", src)] {
+        if let Some(name) = paths::trailing_dot_or_space(path) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      format!("symlink {} {} has a name ending in a dot or space, \
+                                               {:?}, which Windows would drop (see \
+                                               SymlinkBuilder::preserve_trailing_dots)",
+                                              what, path.display(), name)));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn check_trailing_names(_src: &Path, _dst: &Path) -> io::Result<()> {
+    Ok(())
 }
 
 /// Create a symlink (non-preferred way).
@@ -50,7 +464,14 @@ mod internal {
 /// On Windows, file and directory symlinks are created by distinct methods; to cope with that,
 /// this function checks whether the destination is a file or a folder and creates the appropriate
 /// type of symlink based on that result. Therefore, if the destination does not exist or if you do
-/// not have permission to fetch its metadata, this will return an error on Windows.
+/// not have permission to fetch its metadata, this will return an error on Windows. A relative
+/// destination is looked for relative to the link’s parent directory, not the working directory,
+/// since that’s where the new link will find it.
+///
+/// If the destination is itself a symlink or junction, it isn’t followed: a directory symlink or
+/// junction gets a directory symlink, and a file symlink a file symlink, whatever they point to,
+/// because that’s how the new link will be followed through them. (A dangling one is fine.)
+/// [`SymlinkBuilder::follow_target`] classifies by what’s at the end instead.
 ///
 /// On other platforms there is no distinction, so this isn’t magic: it’s precisely equivalent to
 /// calling [`std::os::unix::fs::symlink`] or [`std::fs::soft_link`].
@@ -64,10 +485,75 @@ mod internal {
 /// # Errors
 ///
 /// An error will be returned if the symlink cannot be created, or—on Windows—if the destination
-/// does not exist or cannot be read.
+/// does not exist ([`NotFound`](io::ErrorKind::NotFound)) or its metadata cannot be read
+/// ([`PermissionDenied`](io::ErrorKind::PermissionDenied), if that’s why); either way, the
+/// message says that it was the destination `symlink_auto` was looking at.
 #[inline]
 pub fn symlink_auto<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
-    internal::symlink_auto(src.as_ref(), dst.as_ref())
+    create_symlink(None, src.as_ref(), dst.as_ref())
+}
+
+/// Create a symlink of an already-known kind (non-preferred way).
+///
+/// This is [`symlink_auto`] for when you already know the answer to the question it would ask:
+/// say, a deploy tool that observed on a previous run that `src` was a directory. It trusts your
+/// classification and skips the metadata lookup, creating a file or directory symlink accordingly.
+/// On platforms other than Windows there’s no question to ask, and `assumed` is ignored.
+///
+/// **If the assumption is wrong, you get the wrong kind of symlink on Windows**, which will
+/// misbehave in one way or another when used. If you’re not sure, use [`symlink_auto`]; if you are
+/// sure because you know rather than because you remember, use [`symlink_file`] or
+/// [`symlink_dir`].
+///
+/// # Errors
+///
+/// An error will be returned if the symlink cannot be created.
+#[inline]
+pub fn symlink_auto_assume<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q, assumed: SymlinkKind)
+                                                          -> io::Result<()> {
+    create_symlink(Some(assumed), src.as_ref(), dst.as_ref())
+}
+
+/// Create a symlink, asking `classify` what kind it should be (non-preferred way).
+///
+/// This is [`symlink_auto`] with the question of whether `src` is a file or a directory put to
+/// you instead of to the filesystem, for when something else knows better: a build system with a
+/// virtual filesystem, say, that has the answer in an in-memory index, or a tool creating links
+/// to things that don’t exist yet. `classify` is given `src` exactly as passed in—if it’s
+/// relative, bear in mind that it’s relative to `dst`’s parent directory—and is called once, on
+/// every platform, so that its errors come out the same everywhere; any error it returns is
+/// returned as is, and nothing is created. Wiring it to an index looks like this:
+///
+/// ```no_run
+/// use std::collections::HashMap;
+/// use std::io;
+/// use std::path::{Path, PathBuf};
+/// use symlink_rs::{symlink_with, SymlinkKind};
+///
+/// # fn main() -> io::Result<()> {
+/// let mut index: HashMap<PathBuf, SymlinkKind> = HashMap::new();
+/// index.insert("/vfs/out/lib".into(), SymlinkKind::Dir);
+///
+/// symlink_with("/vfs/out/lib", "/vfs/stage/lib", |target: &Path| {
+///     index.get(target).cloned().ok_or_else(|| {
+///         let message = format!("{} isn’t in the index", target.display());
+///         io::Error::new(io::ErrorKind::NotFound, message)
+///     })
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// As with [`symlink_auto_assume`], a wrong answer gets you the wrong kind of symlink on Windows.
+///
+/// # Errors
+///
+/// An error will be returned if `classify` returns one, or if the symlink cannot be created.
+pub fn symlink_with<P, Q, F>(src: P, dst: Q, classify: F) -> io::Result<()>
+    where P: AsRef<Path>, Q: AsRef<Path>, F: FnOnce(&Path) -> io::Result<SymlinkKind>
+{
+    let kind = classify(src.as_ref())?;
+    create_symlink(Some(kind), src.as_ref(), dst.as_ref())
 }
 
 /// Create a symlink to a file.
@@ -80,6 +566,9 @@ pub fn symlink_auto<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Resul
 /// nothing bad will happen, but you’re ruining your cross-platform technique and ruining the point
 /// of this crate, so please don’t.
 ///
+/// The link is created atomically—it’s there in full or not at all—and nothing already at `dst`
+/// is replaced.
+///
 /// # Errors
 ///
 /// An error will be returned if the symlink cannot be created.
@@ -87,7 +576,7 @@ pub fn symlink_auto<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Resul
 /// [fow]: https://en.wikipedia.org/wiki/A_Fish_Out_of_Water_(book)
 #[inline]
 pub fn symlink_file<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
-    internal::symlink_file(src.as_ref(), dst.as_ref())
+    create_symlink(Some(SymlinkKind::File), src.as_ref(), dst.as_ref())
 }
 
 /// Create a symlink to a directory.
@@ -100,6 +589,9 @@ pub fn symlink_file<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Resul
 /// nothing bad will happen, but you’re ruining your cross-platform technique and ruining the point
 /// of this crate, so please don’t.
 ///
+/// The link is created atomically—it’s there in full or not at all—and nothing already at `dst`
+/// is replaced.
+///
 /// # Errors
 ///
 /// An error will be returned if the symlink cannot be created.
@@ -107,7 +599,668 @@ pub fn symlink_file<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Resul
 /// [fow]: https://en.wikipedia.org/wiki/A_Fish_Out_of_Water_(book)
 #[inline]
 pub fn symlink_dir<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
-    internal::symlink_dir(src.as_ref(), dst.as_ref())
+    create_symlink(Some(SymlinkKind::Dir), src.as_ref(), dst.as_ref())
+}
+
+/// Create a symlink to a file, and return the canonical path of what it points to.
+///
+/// This is [`symlink_file`] followed by [`fs::canonicalize`] on the new link, which follows it
+/// (and anything else along the way) to the real, absolute path of the target. It saves resolving
+/// the target yourself—relative to the link’s parent, not the working directory—when you want
+/// to log or use where the link actually leads.
+///
+/// # Errors
+///
+/// An error will be returned if the symlink cannot be created. If it is created but its target
+/// doesn’t exist, the link is left in place and a [`NotFound`](io::ErrorKind::NotFound) error
+/// saying so is returned; other failures to canonicalize are returned as they are.
+pub fn symlink_file_canonical<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q)
+                                                             -> io::Result<PathBuf> {
+    create_symlink(Some(SymlinkKind::File), src.as_ref(), dst.as_ref())?;
+    canonicalize_link(src.as_ref(), dst.as_ref())
+}
+
+/// Create a symlink to a directory, and return the canonical path of what it points to.
+///
+/// See [`symlink_file_canonical`] for details.
+///
+/// # Errors
+///
+/// As for [`symlink_file_canonical`].
+pub fn symlink_dir_canonical<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q)
+                                                            -> io::Result<PathBuf> {
+    create_symlink(Some(SymlinkKind::Dir), src.as_ref(), dst.as_ref())?;
+    canonicalize_link(src.as_ref(), dst.as_ref())
+}
+
+/// Create a symlink to a file, and say whether the target stored is exactly the one given.
+///
+/// This is [`symlink_file`] followed by [`fs::read_link`] on the new link, comparing what comes
+/// back with `src` byte for byte (or, on Windows, UTF-16 unit for unit), with no normalisation
+/// whatever. On Unix the answer is always `true`, since the kernel stores the bytes it’s given.
+/// On Windows it can be `false`: the stored target is a substitute name that may have gained an
+/// NT prefix, and [`fs::read_link`] gives it back as the OS has it. For most purposes that’s a
+/// distinction without a difference—[`targets_equivalent`] is the lenient comparison—but this
+/// is for callers who need to know, such as tools that reproduce links exactly.
+///
+/// # Errors
+///
+/// An error will be returned if the symlink cannot be created, or if it cannot be read back, in
+/// which case the link is left in place.
+pub fn symlink_file_verbatim_check<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q)
+                                                                  -> io::Result<bool> {
+    create_symlink(Some(SymlinkKind::File), src.as_ref(), dst.as_ref())?;
+    stored_verbatim(src.as_ref(), dst.as_ref())
+}
+
+/// Create a symlink to a directory, and say whether the target stored is exactly the one given.
+///
+/// See [`symlink_file_verbatim_check`] for details.
+///
+/// # Errors
+///
+/// As for [`symlink_file_verbatim_check`].
+pub fn symlink_dir_verbatim_check<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q)
+                                                                 -> io::Result<bool> {
+    create_symlink(Some(SymlinkKind::Dir), src.as_ref(), dst.as_ref())?;
+    stored_verbatim(src.as_ref(), dst.as_ref())
+}
+
+// OsStr’s == is on the underlying bytes or wide units, not on path components.
+fn stored_verbatim(src: &Path, dst: &Path) -> io::Result<bool> {
+    Ok(stats::read_link(dst)?.as_os_str() == src.as_os_str())
+}
+
+/// Create a symlink to a file named `name` in the directory `base_dir`.
+///
+/// This is [`symlink_file`] with the link path `base_dir.join(name)`, for when you’re generating
+/// links into a known directory and have the name separately. `name` must be a single file name:
+/// something with a separator in it, or `.` or `..`, or an absolute path (which [`Path::join`]
+/// would quietly put in place of `base_dir`), is rejected.
+///
+/// # Errors
+///
+/// An [`InvalidInput`](io::ErrorKind::InvalidInput) error will be returned if `name` isn’t a
+/// single file name, and other errors as for [`symlink_file`].
+pub fn symlink_file_named<P, B, N>(src: P, base_dir: B, name: N) -> io::Result<()>
+    where P: AsRef<Path>, B: AsRef<Path>, N: AsRef<OsStr>
+{
+    create_symlink(Some(SymlinkKind::File), src.as_ref(),
+                   &join_name(base_dir.as_ref(), name.as_ref())?)
+}
+
+/// Create a symlink to a directory named `name` in the directory `base_dir`.
+///
+/// See [`symlink_file_named`] for details.
+///
+/// # Errors
+///
+/// As for [`symlink_file_named`].
+pub fn symlink_dir_named<P, B, N>(src: P, base_dir: B, name: N) -> io::Result<()>
+    where P: AsRef<Path>, B: AsRef<Path>, N: AsRef<OsStr>
+{
+    create_symlink(Some(SymlinkKind::Dir), src.as_ref(),
+                   &join_name(base_dir.as_ref(), name.as_ref())?)
+}
+
+/// Create a symlink in the directory `dir` named after `prefix` and the current time, and return
+/// its path.
+///
+/// This is for rotation: a history of links to successive logs or backups, each named for when
+/// it was made. The name is `{prefix}-{timestamp}`, the timestamp being the time in UTC, to the
+/// second, as `YYYY-MM-DDTHHMMSSZ`—RFC 3339 without the colons, which Windows won’t have in a
+/// file name—so that the names sort in order of time: `log-2026-10-14T153012Z`, for example.
+/// That format won’t change. If there’s already something by that name, from another link in
+/// the same second, say, `-2`, `-3` and so on are appended until one is free, up to `-100`: a
+/// hundred names in all, after which it gives up.
+///
+/// `prefix` is checked as [`symlink_file_named`] checks a name, and the kind is as for
+/// [`symlink`].
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// use symlink_rs::{symlink_timestamped, LinkKind};
+///
+/// let link = symlink_timestamped("/var/log/app/current.log", "/var/log/app/history", "log",
+///                                LinkKind::File)?;
+/// println!("recorded as {}", link.display());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// An [`InvalidInput`](io::ErrorKind::InvalidInput) error will be returned if `prefix` would make
+/// the name something other than a single file name, and other errors as for [`symlink_file`],
+/// except that [`AlreadyExists`](io::ErrorKind::AlreadyExists) just means trying the next name,
+/// unless all hundred are taken, when it’s returned.
+pub fn symlink_timestamped<P, D, N>(src: P, dir: D, prefix: N, kind: LinkKind)
+                                    -> io::Result<PathBuf>
+    where P: AsRef<Path>, D: AsRef<Path>, N: AsRef<OsStr>
+{
+    let mut name = prefix.as_ref().to_owned();
+    name.push("-");
+    name.push(paths::utc_timestamp(SystemTime::now()));
+    let base = join_name(dir.as_ref(), &name)?;
+    let mut dst = base.clone();
+    for n in 2.. {
+        match create_symlink(kind.symlink_kind(), src.as_ref(), &dst) {
+            Ok(()) => break,
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists
+                          && n <= TIMESTAMPED_NAME_ATTEMPTS => {
+                let mut numbered = base.clone().into_os_string();
+                numbered.push(format!("-{}", n));
+                dst = PathBuf::from(numbered);
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                return Err(error::with_context(io::ErrorKind::AlreadyExists,
+                                               format!("no unused name for {} in {} tries",
+                                                       base.display(), n - 1),
+                                               e));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(dst)
+}
+
+// How many names symlink_timestamped tries, the unnumbered one included, before giving up.
+const TIMESTAMPED_NAME_ATTEMPTS: usize = 100;
+
+/// Create a symlink to a file, first moving aside anything that isn’t a symlink at `dst`.
+///
+/// If there’s a file or directory at `dst`, it is renamed to `dst` with `backup_suffix` appended
+/// (`.bashrc` becoming `.bashrc.bak`, say) and its new path returned; then the link is created.
+/// This is the dotfiles manager’s way of overwriting: nothing of the user’s is destroyed. If
+/// creating the link fails, the backup is moved back. If `dst` is already a symlink, nothing is
+/// moved and you get the usual [`AlreadyExists`](io::ErrorKind::AlreadyExists) error; and if
+/// nothing is there at all, the link is simply created, and `None` returned.
+///
+/// An existing backup is never overwritten. This is checked just before the rename, so something
+/// that creates the backup path in between could still be overwritten on platforms where rename
+/// replaces its destination (Unix and Windows both).
+///
+/// None of this is atomic: between the rename and the creation there’s nothing at `dst`.
+///
+/// # Errors
+///
+/// An [`AlreadyExists`](io::ErrorKind::AlreadyExists) error will be returned if the backup path
+/// is already taken, and an [`InvalidInput`](io::ErrorKind::InvalidInput) one if
+/// `backup_suffix` is empty; other errors are from the rename, or as for [`symlink_file`].
+pub fn symlink_file_backup<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q, backup_suffix: &str)
+                                                          -> io::Result<Option<PathBuf>> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    symlink_backup(dst, backup_suffix, || create_symlink(Some(SymlinkKind::File), src, dst))
+}
+
+/// Create a symlink to a directory, first moving aside anything that isn’t a symlink at `dst`.
+///
+/// See [`symlink_file_backup`] for details.
+///
+/// # Errors
+///
+/// As for [`symlink_file_backup`].
+pub fn symlink_dir_backup<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q, backup_suffix: &str)
+                                                         -> io::Result<Option<PathBuf>> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    symlink_backup(dst, backup_suffix, || create_symlink(Some(SymlinkKind::Dir), src, dst))
+}
+
+// Move whatever isn’t a symlink at dst aside, then create the link with create.
+pub(crate) fn symlink_backup<F>(dst: &Path, backup_suffix: &str, create: F)
+                                -> io::Result<Option<PathBuf>>
+    where F: FnOnce() -> io::Result<()>
+{
+    if backup_suffix.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "backup suffix must not be empty"));
+    }
+    let backup = match stats::symlink_metadata(dst) {
+        Ok(ref metadata) if !metadata.file_type().is_symlink() => {
+            let backup = backup_path(dst, backup_suffix)?;
+            fs::rename(dst, &backup)?;
+            Some(backup)
+        }
+        _ => None,
+    };
+    match create() {
+        Ok(()) => Ok(backup),
+        Err(e) => {
+            if let Some(ref backup) = backup {
+                let _ = fs::rename(backup, dst);
+            }
+            Err(e)
+        }
+    }
+}
+
+// Where symlink_backup would move what’s at dst, unless something is there already.
+pub(crate) fn backup_path(dst: &Path, backup_suffix: &str) -> io::Result<PathBuf> {
+    let mut backup = dst.as_os_str().to_owned();
+    backup.push(backup_suffix);
+    let backup = PathBuf::from(backup);
+    if stats::symlink_metadata(&backup).is_ok() {
+        let message = format!("backup {} already exists", backup.display());
+        return Err(error::marked(error::Cause::Conflict,
+                                 io::Error::new(io::ErrorKind::AlreadyExists, message)));
+    }
+    Ok(backup)
+}
+
+/// Replace the symlink at `link` with one of another kind, pointing at `new_target`.
+///
+/// On Windows, turning a file symlink into a directory symlink or the other way about means
+/// removing the old link the way its kind requires—[`remove_symlink_file`] won’t touch a
+/// directory symlink, nor [`remove_symlink_dir`] a file one—and then creating the new one, and
+/// it’s easy to get the first half wrong. This does both: the old link’s kind is looked up to
+/// remove it, and the new link created with `new_kind`, whatever kind the old one was. (It works
+/// just as well when the kind stays the same and only the target changes.)
+///
+/// On Unix, where the kinds are all one, the new link is made beside the old and renamed over
+/// it, so the change is atomic, as with [`SymlinkBuilder::replace_existing`]. Windows has no
+/// such rename for symlinks of different kinds, so there the old link is removed and then the
+/// new one created, leaving a moment with nothing at `link`; if creating the new one fails, the
+/// old one is put back as it was, as far as that’s possible, before the error is returned.
+///
+/// # Errors
+///
+/// An [`InvalidInput`](io::ErrorKind::InvalidInput) error will be returned if `link` isn’t a
+/// symlink, without anything being changed; otherwise errors are as for [`symlink_file`] and
+/// [`remove_symlink_auto`].
+pub fn change_symlink_kind<P: AsRef<Path>, Q: AsRef<Path>>(link: P, new_target: Q,
+                                                           new_kind: SymlinkKind)
+                                                           -> io::Result<()> {
+    let (link, new_target) = (link.as_ref(), new_target.as_ref());
+    if !stats::symlink_metadata(link)?.file_type().is_symlink() {
+        return Err(error::not_a_symlink(link, ", so it has no kind to change"));
+    }
+    repoint_symlink(link, new_target, Some(new_kind))
+}
+
+// Replace the symlink at link with one to target, of the given kind (None being auto), as
+// change_symlink_kind describes: atomically on Unix, and elsewhere with the old link put back if
+// the new one can’t be made.
+#[cfg(unix)]
+pub(crate) fn repoint_symlink(link: &Path, target: &Path, kind: Option<SymlinkKind>)
+                              -> io::Result<()> {
+    let mut builder = SymlinkBuilder::new();
+    builder.replace_existing(true);
+    builder.create(kind, target, link)
+}
+
+// Remove and recreate, having checked everything that can be checked first and noted what it
+// takes to put the old link back.
+#[cfg(not(unix))]
+pub(crate) fn repoint_symlink(link: &Path, target: &Path, kind: Option<SymlinkKind>)
+                              -> io::Result<()> {
+    check_not_empty(target, link).and_then(|()| check_not_self_link(target, link))
+        .and_then(|()| check_trailing_names(target, link))?;
+    let old_target = stats::read_link(link)?;
+    #[cfg(windows)]
+    let old_kind = internal::symlink_kind(link)?;
+    #[cfg(not(windows))]
+    let old_kind = None;
+    remove_symlink_auto(link)?;
+    create_symlink(kind, target, link).map_err(|e| {
+        let _ = create_symlink(old_kind, &old_target, link);
+        e
+    })
+}
+
+/// Create a symlink to a file at `dst`, a path relative to `base` rather than to the working
+/// directory.
+///
+/// A relative link path is ordinarily resolved against the process’s working directory, which
+/// any thread can change at any moment; in a multithreaded program that makes relative paths a
+/// hazard. This resolves `dst` against `base` instead. On Unix, `base` is opened as a directory
+/// and the link created relative to it with `symlinkat`, so `base` is looked up just the once;
+/// elsewhere, the link is created at `base.join(dst)`. For the operation to be independent of
+/// the working directory, `base` should of course be absolute. (An absolute `dst` ignores `base`
+/// altogether, as with [`Path::join`].)
+///
+/// Unlike [`symlink_file_in`], this is no sandbox: `dst` may contain `..`, and symlinks along the
+/// way are followed as usual. `src` is stored as given, and a relative one is relative to the
+/// link’s directory as ever.
+///
+/// # Errors
+///
+/// An error will be returned if `base` can’t be opened as a directory, and otherwise as for
+/// [`symlink_file`].
+pub fn symlink_file_rel_to<B, P, Q>(base: B, src: P, dst: Q) -> io::Result<()>
+    where B: AsRef<Path>, P: AsRef<Path>, Q: AsRef<Path>
+{
+    symlink_rel_to(SymlinkKind::File, base.as_ref(), src.as_ref(), dst.as_ref())
+}
+
+/// Create a symlink to a directory at `dst`, a path relative to `base` rather than to the working
+/// directory.
+///
+/// See [`symlink_file_rel_to`] for details.
+///
+/// # Errors
+///
+/// As for [`symlink_file_rel_to`].
+pub fn symlink_dir_rel_to<B, P, Q>(base: B, src: P, dst: Q) -> io::Result<()>
+    where B: AsRef<Path>, P: AsRef<Path>, Q: AsRef<Path>
+{
+    symlink_rel_to(SymlinkKind::Dir, base.as_ref(), src.as_ref(), dst.as_ref())
+}
+
+#[cfg(unix)]
+fn symlink_rel_to(kind: SymlinkKind, base: &Path, src: &Path, dst: &Path) -> io::Result<()> {
+    let label = if kind == SymlinkKind::Dir { Label::Dir } else { Label::File };
+    // Only for the checks and messages; the link itself is made relative to the open directory.
+    let joined = base.join(dst);
+    let result = check_not_empty(src, dst)
+        .and_then(|()| check_not_self_link(src, &joined))
+        .and_then(|()| unix::symlink_relative_to(base, src, dst));
+    track(Op::Create, label, result.map_err(|e| explain_error(e, &joined)))
+}
+
+#[cfg(not(unix))]
+fn symlink_rel_to(kind: SymlinkKind, base: &Path, src: &Path, dst: &Path) -> io::Result<()> {
+    check_link_not_empty(dst)?;
+    create_symlink(Some(kind), src, &base.join(dst))
+}
+
+fn join_name(base_dir: &Path, name: &OsStr) -> io::Result<PathBuf> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(n)), None) if n == name => Ok(base_dir.join(name)),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                format!("{:?} is not a single file name", name))),
+    }
+}
+
+fn canonicalize_link(src: &Path, dst: &Path) -> io::Result<PathBuf> {
+    fs::canonicalize(dst).map_err(|e| {
+        if e.kind() == io::ErrorKind::NotFound {
+            error::with_context(io::ErrorKind::NotFound,
+                                format!("created {}, but its target {} doesn’t exist",
+                                        dst.display(), src.display()), e)
+        } else {
+            e
+        }
+    })
+}
+
+/// Create a symlink to a file, with the target given as a wide string (Windows only).
+///
+/// This is for interoperating with other Win32 code that already has the target as UTF-16: it is
+/// passed straight to `CreateSymbolicLinkW`, with no round trip through [`Path`], so unpaired
+/// surrogates and the like survive intact. A trailing NUL is optional; any other NUL is an error.
+///
+/// # Errors
+///
+/// An error will be returned if the symlink cannot be created, or if `src` contains a NUL before
+/// its end.
+#[cfg(windows)]
+#[inline]
+pub fn symlink_file_wide<Q: AsRef<Path>>(src: &[u16], dst: Q) -> io::Result<()> {
+    let result = check_not_empty_wide(src, dst.as_ref())
+        .and_then(|()| internal::symlink_wide(src, dst.as_ref(), false));
+    track(Op::Create, Label::File, result)
+}
+
+/// Create a symlink to a directory, with the target given as a wide string (Windows only).
+///
+/// See [`symlink_file_wide`] for details.
+///
+/// # Errors
+///
+/// An error will be returned if the symlink cannot be created, or if `src` contains a NUL before
+/// its end.
+#[cfg(windows)]
+#[inline]
+pub fn symlink_dir_wide<Q: AsRef<Path>>(src: &[u16], dst: Q) -> io::Result<()> {
+    let result = check_not_empty_wide(src, dst.as_ref())
+        .and_then(|()| internal::symlink_wide(src, dst.as_ref(), true));
+    track(Op::Create, Label::Dir, result)
+}
+
+/// Create a symlink with exactly the given `CreateSymbolicLinkW` flags (Windows only).
+///
+/// This is the escape hatch for when the choices [`symlink_file`] and [`symlink_dir`] make
+/// aren’t the ones wanted: to insist on creating the link with the privilege, say, rather than
+/// have Developer Mode let it through, or to pass a flag newer than this crate. The flags are
+/// passed as they are, once; there’s no falling back to other flags if Windows rejects them,
+/// and whether the link is a file or a directory symlink is up to
+/// [`SymlinkFlags::DIRECTORY`], not the target. Otherwise it’s as [`symlink_file`]: the target
+/// is stored as given, and nothing at `dst` is replaced.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// use symlink_rs::{symlink_with_flags, SymlinkFlags};
+///
+/// // A directory symlink, without Developer Mode’s help: only with the privilege itself.
+/// symlink_with_flags("C:\\data", "data", SymlinkFlags::DIRECTORY)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// An error will be returned if the symlink cannot be created, including an
+/// [`InvalidInput`](io::ErrorKind::InvalidInput) error from Windows for flags it doesn’t know.
+#[cfg(windows)]
+pub fn symlink_with_flags<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q, flags: SymlinkFlags)
+                                                          -> io::Result<()> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    let label = if flags.contains(SymlinkFlags::DIRECTORY) { Label::Dir } else { Label::File };
+    let result = check_not_empty(src, dst).and_then(|()| check_not_self_link(src, dst))
+        .and_then(|()| check_trailing_names(src, dst))
+        .and_then(|()| internal::symlink_with_flags(src, dst, flags));
+    track(Op::Create, label, result.map_err(|e| explain_error(e, dst)))
+}
+
+#[cfg(windows)]
+fn check_not_empty_wide(src: &[u16], dst: &Path) -> io::Result<()> {
+    // A lone NUL is as empty as it gets, too.
+    if matches!(src, [] | [0]) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "symlink target must not be empty"));
+    }
+    check_link_not_empty(dst)
+}
+
+/// Create a directory junction (Windows only).
+///
+/// Junctions are the older cousin of directory symlinks: they can only point at local
+/// directories, by absolute path, but they can be created without any special privilege, which
+/// makes them a handy fallback where symlinks are unavailable. `src` is made absolute (against
+/// the working directory) if it isn’t already. An empty directory is created at `dst` and then
+/// turned into a mount point reparse point with `FSCTL_SET_REPARSE_POINT`.
+///
+/// Remove a junction with [`remove_symlink_dir`] (or [`remove_mount_point`]).
+///
+/// # Errors
+///
+/// An error will be returned if `dst` already exists, or if the junction cannot be created; in
+/// the latter case the directory is removed again.
+#[cfg(windows)]
+#[inline]
+pub fn symlink_junction<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    // The target is relative to the working directory, not to the junction.
+    let result = check_not_empty(src, dst)
+        .and_then(|()| paths::absolute(src))
+        .and_then(|absolute| check_not_self_link(&absolute, dst))
+        .and_then(|()| internal::symlink_junction(src, dst));
+    track(Op::Create, Label::Junction, result)
+}
+
+/// Mount a volume at a directory (Windows only).
+///
+/// `volume_guid_path` is a volume GUID path of the form `\\?\Volume{GUID}\`, as returned by
+/// `GetVolumeNameForVolumeMountPointW` and listed by `mountvol`. Like [`symlink_junction`], this
+/// creates an empty directory at `dst` and writes a mount point reparse point to it; the
+/// difference is only in the target. Note that this bypasses the mount manager, which
+/// `SetVolumeMountPointW` informs; if you need the mount point to be registered there, use that.
+///
+/// Remove it with [`remove_mount_point`], which clears the reparse point before removing the
+/// directory.
+///
+/// # Errors
+///
+/// An [`InvalidInput`](io::ErrorKind::InvalidInput) error will be returned if
+/// `volume_guid_path` isn’t a volume GUID path, and other errors as for [`symlink_junction`].
+#[cfg(windows)]
+#[inline]
+pub fn symlink_mount_point<P: AsRef<Path>, Q: AsRef<Path>>(volume_guid_path: P, dst: Q)
+                                                          -> io::Result<()> {
+    let result = internal::symlink_mount_point(volume_guid_path.as_ref(), dst.as_ref());
+    track(Op::Create, Label::Junction, result)
+}
+
+/// Remove a mount point or junction (Windows only).
+///
+/// This clears the reparse point with `FSCTL_DELETE_REPARSE_POINT`, leaving an ordinary empty
+/// directory, and then removes that. It never touches the mounted volume or the junction’s
+/// target.
+///
+/// # Errors
+///
+/// An error will be returned if `path` is not a mount point or junction, or cannot be removed.
+#[cfg(windows)]
+#[inline]
+pub fn remove_mount_point<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    track(Op::Remove, Label::Junction, internal::remove_mount_point(path.as_ref()))
+}
+
+/// Create a symlink (non-preferred way), and report exactly what was created (Windows only).
+///
+/// This is [`symlink_auto`], but it returns the kind of symlink it chose and the reparse tag the
+/// new link actually has, read back from it after creation, for tools such as backups that
+/// record links precisely enough to recreate them.
+///
+/// # Errors
+///
+/// As for [`symlink_auto`], and an error will also be returned if the new link’s reparse point
+/// can’t be read; the link is left in place in that case.
+#[cfg(windows)]
+pub fn symlink_auto_detailed<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q)
+                                                             -> io::Result<SymlinkDetails> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    let kind = match internal::target_kind(src, dst, false) {
+        Ok(kind) => kind,
+        Err(e) => return track(Op::Create, Label::Auto, Err(e)),
+    };
+    create_symlink(Some(kind), src, dst)?;
+    Ok(SymlinkDetails { kind, reparse_tag: internal::read_reparse_tag(dst)? })
+}
+
+/// Read both names stored in a symlink, junction or mount point (Windows only).
+///
+/// Windows links store two strings: the substitute name, which is what the OS actually follows,
+/// and the print name, meant for display. [`fs::read_link`] returns only one of them, so a tool
+/// that needs to reproduce a link faithfully—backup and restore, say—should use this instead.
+///
+/// # Errors
+///
+/// An [`InvalidInput`](io::ErrorKind::InvalidInput) error will be returned if `path` is not a
+/// symlink or mount point (including if it’s some other kind of reparse point), and an
+/// [`InvalidData`](io::ErrorKind::InvalidData) one if the reparse data is malformed; other errors
+/// are from opening or reading the reparse point.
+#[cfg(windows)]
+pub fn read_symlink_names<P: AsRef<Path>>(path: P) -> io::Result<SymlinkNames> {
+    internal::read_symlink_names(path.as_ref())
+}
+
+/// Find out whether this process can create symlinks, and how (Windows only).
+///
+/// On Windows, creating a symlink takes `SeCreateSymbolicLinkPrivilege`, which by default only
+/// elevated administrators hold, unless Developer Mode is on, when anyone can. Rather than find
+/// out from a [`PermissionDenied`](io::ErrorKind::PermissionDenied) error halfway through a
+/// run, a program can ask first, and choose between symlinks and junctions (or tell the user
+/// what to change) before it starts. This checks the process token’s privileges, and then the
+/// registry value the Settings app keeps for Developer Mode
+/// (`AllowDevelopmentWithoutDevLicense`, under
+/// `HKLM\SOFTWARE\Microsoft\Windows\CurrentVersion\AppModelUnlock`).
+///
+/// This is a forecast, not a guarantee: the filesystem may still not support symlinks, or group
+/// policy may forbid following them. Anything that can’t be read counts as not there, so the
+/// answer errs towards [`NeitherUseJunctions`](SymlinkPrivilege::NeitherUseJunctions).
+#[cfg(windows)]
+pub fn symlink_privilege() -> SymlinkPrivilege {
+    internal::symlink_privilege()
+}
+
+/// Create a symlink to a file within a directory (Unix and WASI only).
+///
+/// `dst` is interpreted relative to `dir`, an open directory—anything implementing [`AsFd`],
+/// which notably includes [`cap_std::fs::Dir`][cap-std]—and the link is created with
+/// `symlinkat`, so the operation is independent of the working directory and, with care, of
+/// the rest of the filesystem. That care is taken: `dst` must be a relative path without any `..`
+/// components, and its parent components are opened one by one with `O_NOFOLLOW`, so the link
+/// can’t be created anywhere but beneath `dir`. This makes these functions safe to use with
+/// capability-based filesystem access, without breaking out of the sandbox.
+///
+/// `src` is stored as given. Bear in mind that what it means is up to whoever follows the link:
+/// a cap-std `Dir` won’t follow an absolute or escaping target out of the sandbox, but ordinary
+/// path resolution will.
+///
+/// # Errors
+///
+/// An error will be returned if the symlink cannot be created, and an
+/// [`InvalidInput`](io::ErrorKind::InvalidInput) error if `dst` is absolute, contains `..`, or
+/// has a parent component that is a symlink.
+///
+/// On WASI, `dir` is typically a preopened directory (opened with [`fs::File::open`] on its
+/// preopened path), and the runtime does the sandboxing: `dst` is resolved beneath `dir` and
+/// can’t leave it, though symlinks within it may be followed on the way. The absolute and `..`
+/// checks are made all the same, for a consistent error. Paths must be valid Unicode there.
+/// Runtimes refuse to follow an absolute target, so one doesn’t get you anywhere.
+///
+/// [cap-std]: https://docs.rs/cap-std/latest/cap_std/fs/struct.Dir.html
+#[cfg(any(unix, target_os = "wasi"))]
+#[inline]
+pub fn symlink_file_in<D: AsFd, P: AsRef<Path>, Q: AsRef<Path>>(dir: &D, src: P, dst: Q)
+                                                               -> io::Result<()> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    let result = check_not_self_link_in(src, dst)
+        .and_then(|()| beneath::symlink_beneath(dir.as_fd(), src, dst));
+    track(Op::Create, Label::File, result)
+}
+
+/// Create a symlink to a directory within a directory (Unix and WASI only).
+///
+/// On Unix and WASI there’s no distinction between file and directory symlinks, so this is the
+/// same as [`symlink_file_in`], which see. It’s provided for the same reason as [`symlink_dir`]
+/// is.
+///
+/// # Errors
+///
+/// As for [`symlink_file_in`].
+#[cfg(any(unix, target_os = "wasi"))]
+#[inline]
+pub fn symlink_dir_in<D: AsFd, P: AsRef<Path>, Q: AsRef<Path>>(dir: &D, src: P, dst: Q)
+                                                              -> io::Result<()> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    let result = check_not_self_link_in(src, dst)
+        .and_then(|()| beneath::symlink_beneath(dir.as_fd(), src, dst));
+    track(Op::Create, Label::Dir, result)
+}
+
+// Beneath a directory only relative targets can be compared with the link; the working
+// directory has nothing to do with it.
+#[cfg(any(unix, target_os = "wasi"))]
+fn check_not_self_link_in(src: &Path, dst: &Path) -> io::Result<()> {
+    check_not_empty(src, dst)?;
+    if src.is_relative() { check_not_self_link(src, dst) } else { Ok(()) }
+}
+
+/// Remove a symlink within a directory (Unix and WASI only).
+///
+/// `path` is interpreted relative to `dir` with the same restrictions as in [`symlink_file_in`],
+/// and the symlink is removed with `unlinkat`.
+///
+/// # Errors
+///
+/// An error will be returned if the symlink cannot be removed, and an
+/// [`InvalidInput`](io::ErrorKind::InvalidInput) error if `path` is absolute, contains `..`, or
+/// has a parent component that is a symlink.
+#[cfg(any(unix, target_os = "wasi"))]
+#[inline]
+pub fn remove_symlink_in<D: AsFd, P: AsRef<Path>>(dir: &D, path: P) -> io::Result<()> {
+    track(Op::Remove, Label::Auto, beneath::remove_symlink_beneath(dir.as_fd(), path.as_ref()))
 }
 
 /// Remove a symlink (non-preferred way).
@@ -127,7 +1280,7 @@ pub fn symlink_dir<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result
 /// An error will be returned if the symlink cannot be removed.
 #[inline]
 pub fn remove_symlink_auto<P: AsRef<Path>>(path: P) -> io::Result<()> {
-    internal::remove_symlink_auto(path)
+    track(Op::Remove, Label::Auto, internal::remove_symlink_auto(path))
 }
 
 /// Remove a directory symlink.
@@ -137,7 +1290,39 @@ pub fn remove_symlink_auto<P: AsRef<Path>>(path: P) -> io::Result<()> {
 /// On Unix, this corresponds to [`std::fs::remove_file`].
 #[inline]
 pub fn remove_symlink_dir<P: AsRef<Path>>(path: P) -> io::Result<()> {
-    internal::remove_symlink_dir(path)
+    track(Op::Remove, Label::Dir, internal::remove_symlink_dir(path))
+}
+
+/// Remove a directory symlink, and then the directory it points to, with all its contents.
+///
+/// **This is destructive**: unlike every other removal function in this crate, it deletes real
+/// data, namely the whole tree at the link’s resolved target, as [`fs::remove_dir_all`] would.
+/// It’s meant for cleanup tooling that owns both a link and the data behind it.
+///
+/// The target is resolved with [`fs::canonicalize`] first, then the link is removed (with
+/// [`remove_symlink_dir`]), and only then is the target removed—by its real path, so nothing is
+/// ever deleted *through* the link. Junctions count as directory symlinks here. As a last line of
+/// defence, a target that resolves to a filesystem root is refused.
+///
+/// # Errors
+///
+/// An [`InvalidInput`](io::ErrorKind::InvalidInput) error will be returned if `link` is not a
+/// symlink, or its target is not a directory or is a root; in these cases nothing is removed.
+/// Otherwise errors are from resolving the target, removing the link (after which the target is
+/// left alone) or removing the target (which may then be partly removed).
+pub fn remove_symlink_dir_and_target<P: AsRef<Path>>(link: P) -> io::Result<()> {
+    let link = link.as_ref();
+    if !stats::symlink_metadata(link)?.file_type().is_symlink() {
+        return Err(error::not_a_symlink(link, ""));
+    }
+    let target = fs::canonicalize(link)?;
+    if !stats::metadata(&target)?.is_dir() || target.parent().is_none() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                  format!("target {} of {} is not a directory, or is a root",
+                                          target.display(), link.display())));
+    }
+    remove_symlink_dir(link)?;
+    fs::remove_dir_all(&target)
 }
 
 /// Remove a file symlink.
@@ -146,5 +1331,46 @@ pub fn remove_symlink_dir<P: AsRef<Path>>(path: P) -> io::Result<()> {
 /// [`remove_symlink_dir`].
 #[inline]
 pub fn remove_symlink_file<P: AsRef<Path>>(path: P) -> io::Result<()> {
-    fs::remove_file(path)
+    track(Op::Remove, Label::File, fs::remove_file(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+    use std::fs;
+    use std::io;
+
+    // The fallback branch only runs on platforms without symlinks, so its shim is tested here,
+    // everywhere, with the errors soft_link gives there.
+    #[test]
+    fn fallback_parity() {
+        let unsupported = || Err(io::Error::new(io::ErrorKind::Unsupported, "unsupported"));
+        let dir = temp_dir().join("symlink-crate-unit-fallback");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir(&dir).unwrap();
+        let (existing, missing) = (dir.join("existing"), dir.join("missing"));
+        fs::write(&existing, b"").unwrap();
+
+        let e = super::fallback_parity(unsupported(), &existing).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::AlreadyExists);
+        let e = super::fallback_parity(unsupported(), &missing).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::Unsupported);
+        // Whatever else a platform says is left alone.
+        let denied = io::Error::new(io::ErrorKind::PermissionDenied, "denied");
+        let e = super::fallback_parity(Err(denied), &existing).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+        super::fallback_parity(Ok(()), &existing).unwrap();
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn utc_timestamp() {
+        use std::time::{Duration, UNIX_EPOCH};
+        let at = |secs| super::paths::utc_timestamp(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(at(0), "1970-01-01T000000Z");
+        assert_eq!(at(951_782_400), "2000-02-29T000000Z");
+        assert_eq!(at(1_791_991_812), "2026-10-14T153012Z");
+        assert_eq!(at(4_102_444_799), "2099-12-31T235959Z");
+    }
 }
@@ -0,0 +1,714 @@
+// Sets of links, described declaratively and applied in one go.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use ensure::{ensure_symlink, kind_matches, plan_symlink};
+use error;
+use find::find_symlinks;
+use paths;
+use stats;
+use transaction::Transaction;
+use {remove_symlink_auto, symlink_auto, symlink_dir, symlink_file, CreateOutcome, SymlinkKind};
+
+/// What kind of symlink a [`LinkSpec`] asks for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LinkKind {
+    /// A file symlink, as with [`symlink_file`](crate::symlink_file).
+    File,
+    /// A directory symlink, as with [`symlink_dir`](crate::symlink_dir).
+    Dir,
+    /// Whichever suits the target, as with [`symlink_auto`](crate::symlink_auto).
+    Auto,
+}
+
+impl LinkKind {
+    // The kind as the rest of the crate wants it, None being auto.
+    pub(crate) fn symlink_kind(self) -> Option<SymlinkKind> {
+        match self {
+            LinkKind::File => Some(SymlinkKind::File),
+            LinkKind::Dir => Some(SymlinkKind::Dir),
+            LinkKind::Auto => None,
+        }
+    }
+}
+
+/// A description of a symlink: what kind it is, where it points, and where it goes.
+///
+/// The [`links!`](crate::links) macro is a tidy way of writing a fixed set of these.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LinkSpec {
+    /// The kind of symlink.
+    pub kind: LinkKind,
+    /// The target, stored in the link as given.
+    pub target: PathBuf,
+    /// The path of the link itself.
+    pub link: PathBuf,
+}
+
+impl LinkSpec {
+    /// Describe a symlink at `link` pointing to `target`.
+    pub fn new<P: Into<PathBuf>, Q: Into<PathBuf>>(kind: LinkKind, target: P, link: Q) -> LinkSpec {
+        LinkSpec { kind, target: target.into(), link: link.into() }
+    }
+
+    /// Create the symlink described, with [`symlink_file`](crate::symlink_file),
+    /// [`symlink_dir`](crate::symlink_dir) or [`symlink_auto`](crate::symlink_auto) according to
+    /// its kind.
+    pub fn create(&self) -> io::Result<()> {
+        match self.kind {
+            LinkKind::File => symlink_file(&self.target, &self.link),
+            LinkKind::Dir => symlink_dir(&self.target, &self.link),
+            LinkKind::Auto => symlink_auto(&self.target, &self.link),
+        }
+    }
+
+    fn symlink_kind(&self) -> Option<SymlinkKind> {
+        self.kind.symlink_kind()
+    }
+}
+
+/// Write a slice of [`LinkSpec`]s.
+///
+/// Each entry is a kind—`file`, `dir` or `auto`—followed by `target => link`, where the target
+/// and link are anything that converts into a [`PathBuf`]. Any other kind is a compile-time error.
+///
+/// ```
+/// # #[macro_use] extern crate symlink_rs;
+/// # fn main() {
+/// let specs: &[symlink_rs::LinkSpec] = links![
+///     file "../shared/config.toml" => "app/config.toml",
+///     dir "../shared/assets" => "app/assets",
+/// ];
+/// assert_eq!(specs[1].kind, symlink_rs::LinkKind::Dir);
+/// # }
+/// ```
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate symlink_rs;
+/// # fn main() {
+/// let specs = links![folder "../shared/assets" => "app/assets"];
+/// # }
+/// ```
+#[macro_export]
+macro_rules! links {
+    (@kind file) => { $crate::LinkKind::File };
+    (@kind dir) => { $crate::LinkKind::Dir };
+    (@kind auto) => { $crate::LinkKind::Auto };
+    (@kind $other:ident) => {
+        compile_error!(concat!("unknown link kind `", stringify!($other),
+                               "`; expected `file`, `dir` or `auto`"))
+    };
+    ($($kind:ident $target:expr => $link:expr),* $(,)*) => {
+        &[$($crate::LinkSpec::new($crate::links!(@kind $kind), $target, $link)),*]
+    };
+}
+
+/// A problem found in a set of [`LinkSpec`]s by [`validate_manifest`].
+///
+/// Indexes are into the slice that was validated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ManifestProblem {
+    /// The target or the link path is empty.
+    EmptyPath(usize),
+    /// The link points to itself.
+    SelfLink(usize),
+    /// Two entries have the same link path (compared lexically).
+    DuplicateLink(usize, usize),
+    /// Something other than a symlink already exists at the link path.
+    Occupied(usize),
+}
+
+/// Check a set of [`LinkSpec`]s for problems, without changing anything.
+///
+/// This finds the problems that would make [`apply`] fail or behave surprisingly: empty paths,
+/// links to themselves, several entries for the same link path, and link paths occupied by
+/// something other than a symlink. The first three are purely lexical; the last looks at the
+/// filesystem. An empty result means nothing was found, not that applying is bound to succeed.
+///
+/// # Errors
+///
+/// An error is returned if a link path cannot be inspected for some reason other than not
+/// existing.
+pub fn validate_manifest(specs: &[LinkSpec]) -> io::Result<Vec<ManifestProblem>> {
+    let mut problems = vec![];
+    let mut seen: HashMap<PathBuf, usize> = HashMap::new();
+    for (i, spec) in specs.iter().enumerate() {
+        if spec.target.as_os_str().is_empty() || spec.link.as_os_str().is_empty() {
+            problems.push(ManifestProblem::EmptyPath(i));
+            continue;
+        }
+        let link = paths::normalize_lexically(&spec.link);
+        if paths::normalize_lexically(&paths::resolve_target(&spec.link, &spec.target)) == link {
+            problems.push(ManifestProblem::SelfLink(i));
+        }
+        if let Some(&first) = seen.get(&link) {
+            problems.push(ManifestProblem::DuplicateLink(first, i));
+        } else {
+            seen.insert(link, i);
+        }
+        if is_occupied(&spec.link)? {
+            problems.push(ManifestProblem::Occupied(i));
+        }
+    }
+    Ok(problems)
+}
+
+fn is_occupied(path: &Path) -> io::Result<bool> {
+    match stats::symlink_metadata(path) {
+        Ok(metadata) => Ok(!metadata.file_type().is_symlink()),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// A set of symlinks to be brought into being, with a separate plan and apply.
+///
+/// [`plan`](Self::plan) says what [`apply`](Self::apply) would do to each link—create it, leave
+/// it, or repoint it, just as [`ensure_symlink_file`](crate::ensure_symlink_file) and friends
+/// would—without changing anything, so that it can be shown to someone before they go ahead.
+///
+/// ```no_run
+/// # #[macro_use] extern crate symlink_rs;
+/// # fn main() -> std::io::Result<()> {
+/// let manifest = symlink_rs::Manifest {
+///     specs: links![file "../shared/config.toml" => "app/config.toml"].to_vec(),
+/// };
+/// for (spec, outcome) in manifest.plan()? {
+///     println!("{}: {:?}", spec.link.display(), outcome);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Manifest {
+    /// The links, in the order they’re planned and applied.
+    pub specs: Vec<LinkSpec>,
+}
+
+impl Manifest {
+    /// Check the manifest for problems, as [`validate_manifest`] does.
+    pub fn validate(&self) -> io::Result<Vec<ManifestProblem>> {
+        validate_manifest(&self.specs)
+    }
+
+    /// Work out what [`apply`](Self::apply) would do to each link, without changing anything.
+    ///
+    /// Nothing is written to the filesystem; links are only looked at. The plan holds for as long
+    /// as nothing else changes the links in the meantime.
+    ///
+    /// # Errors
+    ///
+    /// An [`InvalidInput`](io::ErrorKind::InvalidInput) error, listing them, is returned if
+    /// [`validate`](Self::validate) finds any problems; otherwise errors are from inspecting the
+    /// links.
+    pub fn plan(&self) -> io::Result<Vec<(LinkSpec, CreateOutcome)>> {
+        self.check()?;
+        self.specs.iter().map(|spec| {
+            plan_symlink(spec.symlink_kind(), &spec.target, &spec.link).map(|o| (spec.clone(), o))
+        }).collect()
+    }
+
+    /// Bring each link into the state described, and report what was done to it.
+    ///
+    /// Unlike the free function [`apply`], which only creates, each link is ensured: created if
+    /// missing, left alone if already correct, and repointed if it points elsewhere. A failure
+    /// doesn’t stop the rest from being attempted; the result for each is returned alongside it,
+    /// in order.
+    ///
+    /// # Errors
+    ///
+    /// As for [`plan`](Self::plan), nothing at all is done if the manifest has problems.
+    pub fn apply(&self) -> io::Result<Vec<(LinkSpec, io::Result<CreateOutcome>)>> {
+        self.check()?;
+        Ok(self.specs.iter().map(|spec| {
+            (spec.clone(), ensure_symlink(spec.symlink_kind(), &spec.target, &spec.link))
+        }).collect())
+    }
+
+    fn check(&self) -> io::Result<()> {
+        let problems = self.validate()?;
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::InvalidInput,
+                               format!("manifest has problems: {:?}", problems)))
+        }
+    }
+}
+
+/// Create each of a set of symlinks.
+///
+/// Each link is created in turn with [`LinkSpec::create`]; a failure doesn’t stop the rest from
+/// being attempted. The result for each is returned alongside it, in order.
+pub fn apply(specs: &[LinkSpec]) -> Vec<(LinkSpec, io::Result<()>)> {
+    apply_with_progress(specs, |_, _, _| {})
+}
+
+/// Create each of a set of symlinks, as [`apply`] does, calling `on_progress` as each is done.
+///
+/// `on_progress` is called after each link is attempted, successfully or not, with how many
+/// have been so far, how many there are in all (always known here, so always `Some`), and the
+/// link just attempted: enough for a progress bar or a `--verbose` line, without taking the loop
+/// apart. What came of each is in the results, as from [`apply`].
+///
+/// ```
+/// # let dir = std::env::temp_dir().join("symlink-crate-apply-progress-doctest");
+/// # let _ = std::fs::remove_dir_all(&dir);
+/// # std::fs::create_dir(&dir).unwrap();
+/// use symlink_rs::{apply_with_progress, LinkKind, LinkSpec};
+///
+/// let specs = [LinkSpec::new(LinkKind::File, "target", dir.join("a")),
+///              LinkSpec::new(LinkKind::File, "target", dir.join("b"))];
+/// apply_with_progress(&specs, |done, total, link| {
+///     eprintln!("[{}/{}] {}", done, total.unwrap(), link.display());
+/// });
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub fn apply_with_progress<F>(specs: &[LinkSpec], mut on_progress: F)
+                              -> Vec<(LinkSpec, io::Result<()>)>
+    where F: FnMut(usize, Option<usize>, &Path)
+{
+    let total = Some(specs.len());
+    specs.iter().enumerate().map(|(i, spec)| {
+        let result = spec.create();
+        on_progress(i + 1, total, &spec.link);
+        (spec.clone(), result)
+    }).collect()
+}
+
+/// Remove each of a set of symlinks, as the teardown to [`apply`] or [`Manifest::apply`].
+///
+/// Each path is removed in turn with [`remove_symlink_auto`](crate::remove_symlink_auto), so
+/// the kind of link doesn’t matter, and a failure doesn’t stop the rest from being attempted.
+/// The result for each is returned alongside it, in order: `Ok(true)` if the link was removed,
+/// `Ok(false)` if there was nothing there to remove. To tear down a manifest, pass the links of
+/// its specs; their targets don’t come into it.
+///
+/// Anything at one of the paths that isn’t a symlink is left alone, and the result for it is an
+/// [`InvalidInput`](io::ErrorKind::InvalidInput) error: a file or directory where a link was
+/// expected is something to look into, not something to delete.
+pub fn remove_manifest<P: AsRef<Path>>(links: &[P]) -> Vec<(PathBuf, io::Result<bool>)> {
+    links.iter().map(|link| (link.as_ref().to_owned(), remove_link(link.as_ref()))).collect()
+}
+
+fn remove_link(link: &Path) -> io::Result<bool> {
+    match stats::symlink_metadata(link) {
+        Ok(ref metadata) if metadata.file_type().is_symlink() => {}
+        Ok(_) => {
+            return Err(error::not_a_symlink(link, ", so it wasn’t removed"));
+        }
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    }
+    remove_symlink_auto(link).map(|()| true)
+}
+
+/// One way a tree differs from a manifest, as found by [`diff_manifest`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LinkDiff {
+    /// There’s nothing at the link path of this spec.
+    Missing(LinkSpec),
+    /// There’s a symlink at `link`, but pointing at `current` rather than `desired`. (On
+    /// Windows, a link of the wrong kind counts as this too, though the targets then agree.)
+    WrongTarget {
+        /// The link path.
+        link: PathBuf,
+        /// The target the link holds.
+        current: PathBuf,
+        /// The target the manifest asks for.
+        desired: PathBuf,
+    },
+    /// There’s something at this link path, but not a symlink.
+    NotASymlink(PathBuf),
+    /// There’s a symlink here that isn’t in the manifest.
+    Extra(PathBuf),
+}
+
+/// Compare a manifest with the links actually in the tree under `root`, without changing
+/// anything.
+///
+/// The specs’ link paths are taken relative to `root` (an absolute one is used as it is, as with
+/// [`Path::join`]). Each spec whose link isn’t as described gives a [`LinkDiff`]—missing, pointing
+/// elsewhere, or not a symlink at all—in the manifest’s order; targets are compared as
+/// [`Manifest::plan`] compares them, with [`targets_equivalent`](crate::targets_equivalent), and
+/// a spec whose link is already right gives nothing. Then every symlink under `root` that no
+/// spec describes, found with [`find_symlinks`](crate::find_symlinks), is an
+/// [`Extra`](LinkDiff::Extra), in sorted order. So an empty list means the tree is exactly as
+/// described, as far as symlinks go; what’s still to do is the `Missing` and `WrongTarget`
+/// entries (which [`Manifest::apply`] would see to), and the `Extra` ones (which
+/// [`remove_manifest`] would).
+///
+/// Link paths are matched with those found lexically, after tidying away `.` and `..`, so a link
+/// reached through a symlinked directory under `root` isn’t matched with the path it’s found at.
+///
+/// # Errors
+///
+/// An error will be returned if `root` or a directory in it can’t be read, or a link can’t be
+/// inspected.
+pub fn diff_manifest<P: AsRef<Path>>(specs: &[LinkSpec], root: P) -> io::Result<Vec<LinkDiff>> {
+    let root = root.as_ref();
+    let mut diffs = vec![];
+    let mut described = HashSet::new();
+    for spec in specs {
+        let link = root.join(&spec.link);
+        described.insert(paths::normalize_lexically(&link));
+        match stats::symlink_metadata(&link) {
+            Ok(ref metadata) if metadata.file_type().is_symlink() => {}
+            Ok(_) => {
+                diffs.push(LinkDiff::NotASymlink(link));
+                continue;
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                diffs.push(LinkDiff::Missing(spec.clone()));
+                continue;
+            }
+            Err(e) => return Err(e),
+        }
+        let current = stats::read_link(&link)?;
+        if !paths::targets_equivalent(&current, &spec.target)
+           || !kind_matches(spec.symlink_kind(), &link)? {
+            diffs.push(LinkDiff::WrongTarget { link, current, desired: spec.target.clone() });
+        }
+    }
+    for path in find_symlinks(root)? {
+        if !described.contains(&paths::normalize_lexically(&path)) {
+            diffs.push(LinkDiff::Extra(path));
+        }
+    }
+    Ok(diffs)
+}
+
+/// Check that the links under `root` are exactly as a lockfile says, for a deploy to go ahead.
+///
+/// This is [`diff_manifest`] with the lock taken as the whole truth: the result is every way the
+/// tree differs from it—a link missing, pointing elsewhere (or on Windows of the wrong kind),
+/// something other than a link in the way, or a link under `root` the lock doesn’t mention—and
+/// an empty list means the tree matches. Targets are compared as written, with
+/// [`targets_equivalent`](crate::targets_equivalent), so `a/b` and `a/./b` agree but a relative
+/// and an absolute path to the same place don’t: a lockfile pins the link as stored, not merely
+/// where it leads. A lock entry of kind [`Auto`](LinkKind::Auto) matches a link of either
+/// kind. [`read_lock`] reads a lockfile, and [`write_lock`] writes one.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// use std::fs::File;
+/// use std::io::BufReader;
+/// use symlink_rs::{read_lock, verify_against_lock};
+///
+/// let lock = read_lock(BufReader::new(File::open("links.lock")?))?;
+/// let diffs = verify_against_lock(&lock, "/srv/app")?;
+/// if !diffs.is_empty() {
+///     eprintln!("not deploying; the links aren’t as locked: {:?}", diffs);
+///     std::process::exit(1);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// An [`InvalidInput`](io::ErrorKind::InvalidInput) error will be returned if the lock lists
+/// the same link path (compared lexically, under `root`) more than once, since then it doesn’t
+/// describe one layout; otherwise errors are as for [`diff_manifest`].
+pub fn verify_against_lock<P: AsRef<Path>>(lock: &[LinkSpec], root: P)
+                                           -> io::Result<Vec<LinkDiff>> {
+    let root = root.as_ref();
+    if let Some(spec) = listed_twice(lock, root) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                  format!("the lock lists the link {} more than once",
+                                          spec.link.display())));
+    }
+    diff_manifest(lock, root)
+}
+
+// The first spec whose link path, compared lexically under root, an earlier one has too.
+fn listed_twice<'a>(specs: &'a [LinkSpec], root: &Path) -> Option<&'a LinkSpec> {
+    let mut seen = HashSet::new();
+    specs.iter().find(|spec| !seen.insert(paths::normalize_lexically(&root.join(&spec.link))))
+}
+
+/// What [`reconcile`] did to a link.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ReconcileAction {
+    /// There was nothing there, and the link was created.
+    Created,
+    /// There was a link pointing elsewhere (or on Windows of the wrong kind), and it was
+    /// repointed.
+    Repointed,
+    /// The link wasn’t in the manifest, and was removed.
+    Removed,
+    /// The link was already as the manifest says, and was left alone.
+    Unchanged,
+}
+
+/// Make the links under `root` exactly what a manifest says: create those missing, repoint those
+/// pointing elsewhere, and, if `remove_extra`, remove those it doesn’t mention.
+///
+/// This is [`diff_manifest`] and the acting on it in one call. The specs’ link paths are taken
+/// relative to `root`, as there, and so are the paths returned. What’s to do is worked out first,
+/// with nothing changed, and then done: each spec in the manifest’s order, ensured as
+/// [`Manifest::apply`] ensures it (so a link found already right is
+/// [`Unchanged`](ReconcileAction::Unchanged), and left alone), and then each
+/// [`Extra`](LinkDiff::Extra) in sorted order, removed with
+/// [`remove_symlink_auto`](crate::remove_symlink_auto). Without `remove_extra`, extra links are
+/// neither touched nor reported.
+///
+/// It’s all or nothing, as far as that can be had: the changes are made through a
+/// [`Transaction`](crate::Transaction), and if one fails, those already made are rolled back
+/// before the error is returned, leaving the tree as it was found. That’s no stronger than a
+/// transaction’s rollback, though—see there for its limits—and nothing is locked, so another
+/// process changing the same links meanwhile can still leave a mixture.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// use symlink_rs::{reconcile, LinkKind, LinkSpec, ReconcileAction};
+///
+/// let manifest = [LinkSpec::new(LinkKind::Dir, "releases/42", "current"),
+///                 LinkSpec::new(LinkKind::File, "releases/42/app.conf", "config/app.conf")];
+/// for (link, action) in reconcile(&manifest, "/srv/app", true)? {
+///     if action != ReconcileAction::Unchanged {
+///         println!("{:?} {}", action, link.display());
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Before anything is changed, an [`InvalidInput`](io::ErrorKind::InvalidInput) error will be
+/// returned if the manifest lists the same link path more than once, or if there’s something
+/// other than a symlink at one of its link paths, which is left for the caller, as elsewhere in
+/// the crate; and errors from [`diff_manifest`]. After, the error of the first change that failed
+/// is returned, once the rest have been undone; if undoing them fails too, the error says so,
+/// and what couldn’t be undone is left as it is.
+pub fn reconcile<P: AsRef<Path>>(manifest: &[LinkSpec], root: P, remove_extra: bool)
+                                 -> io::Result<Vec<(PathBuf, ReconcileAction)>> {
+    let root = root.as_ref();
+    if let Some(spec) = listed_twice(manifest, root) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                  format!("the manifest lists the link {} more than once",
+                                          spec.link.display())));
+    }
+    let mut stale = HashSet::new();
+    let mut extra = vec![];
+    for diff in diff_manifest(manifest, root)? {
+        match diff {
+            LinkDiff::Missing(spec) => {
+                stale.insert(root.join(&spec.link));
+            }
+            LinkDiff::WrongTarget { link, .. } => {
+                stale.insert(link);
+            }
+            LinkDiff::NotASymlink(link) => {
+                return Err(error::not_a_symlink(&link, ", so nothing was changed"));
+            }
+            LinkDiff::Extra(link) => extra.push(link),
+        }
+    }
+    let mut tx = Transaction::new();
+    let mut actions = vec![];
+    let mut result = manifest.iter().try_for_each(|spec| {
+        let link = root.join(&spec.link);
+        let action = if stale.contains(&link) {
+            let outcome = match spec.kind {
+                LinkKind::File => tx.ensure_symlink_file(&spec.target, &link),
+                LinkKind::Dir => tx.ensure_symlink_dir(&spec.target, &link),
+                LinkKind::Auto => tx.ensure_symlink_auto(&spec.target, &link),
+            }?;
+            match outcome {
+                CreateOutcome::Created => ReconcileAction::Created,
+                CreateOutcome::Repointed => ReconcileAction::Repointed,
+                CreateOutcome::AlreadyCorrect => ReconcileAction::Unchanged,
+            }
+        } else {
+            ReconcileAction::Unchanged
+        };
+        actions.push((link, action));
+        Ok(())
+    });
+    if remove_extra {
+        result = result.and_then(|()| extra.into_iter().try_for_each(|link| {
+            tx.remove_symlink_auto(&link)?;
+            actions.push((link, ReconcileAction::Removed));
+            Ok(())
+        }));
+    }
+    match result {
+        Ok(()) => {
+            tx.commit();
+            Ok(actions)
+        }
+        Err(e) => match tx.rollback() {
+            Ok(()) => Err(e),
+            Err(undo) => {
+                Err(error::with_context(e.kind(), format!("undoing the changes already made \
+                                                           failed too: {}", undo), e))
+            }
+        },
+    }
+}
+
+/// Read a lockfile: a set of symlinks as text, one per line, each its kind, target and link.
+///
+/// Each line is a kind—`file`, `dir` or `auto`—a tab, the target, another tab, and the link, as
+/// [`write_lock`] writes them. As with [`apply_from_reader`], blank lines and lines starting
+/// with `#` are ignored, and nothing else is trimmed, so paths may contain spaces but not tabs
+/// or line breaks. It’s plain text so that it diffs well in version control, and needs nothing
+/// beyond the standard library to read.
+///
+/// # Errors
+///
+/// An [`InvalidData`](io::ErrorKind::InvalidData) error, giving the line number, is returned for
+/// a line that isn’t a kind, a target and a link separated by tabs; other errors are from
+/// reading.
+pub fn read_lock<R: BufRead>(reader: R) -> io::Result<Vec<LinkSpec>> {
+    read_lines(reader, "a kind, a target and a link separated by tabs", |line| {
+        let mut fields = line.split('\t');
+        let kind = match fields.next() {
+            Some("file") => LinkKind::File,
+            Some("dir") => LinkKind::Dir,
+            Some("auto") => LinkKind::Auto,
+            _ => return None,
+        };
+        match (fields.next(), fields.next(), fields.next()) {
+            (Some(target), Some(link), None) if !target.is_empty() && !link.is_empty() => {
+                Some(LinkSpec::new(kind, target, link))
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Write a lockfile, in the form [`read_lock`] reads: one line per spec, in order.
+///
+/// # Errors
+///
+/// An [`InvalidInput`](io::ErrorKind::InvalidInput) error will be returned, before anything is
+/// written, for a path that isn’t valid Unicode or that contains a tab or a line break, as the
+/// format has no way of writing it; other errors are from writing.
+pub fn write_lock<W: Write>(specs: &[LinkSpec], mut writer: W) -> io::Result<()> {
+    let mut text = String::new();
+    for spec in specs {
+        let kind = match spec.kind {
+            LinkKind::File => "file",
+            LinkKind::Dir => "dir",
+            LinkKind::Auto => "auto",
+        };
+        let field = |path: &Path| match path.to_str() {
+            Some(s) if !s.is_empty() && !s.contains(['\t', '\n', '\r']) => Ok(s.to_owned()),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                    format!("{:?} can’t be written in a lockfile", path))),
+        };
+        text.push_str(&format!("{}\t{}\t{}\n", kind, field(&spec.target)?, field(&spec.link)?));
+    }
+    writer.write_all(text.as_bytes())
+}
+
+/// Ensure a set of symlinks read as text, one per line: the target, a tab, and the link.
+///
+/// Every link is of the one `kind`. Blank lines and lines starting with `#` are ignored; nothing
+/// else is trimmed, so paths may contain spaces (but not tabs or line breaks). The whole input is
+/// read and checked first, and then the links are ensured as [`Manifest::apply`] does, with the
+/// result for each returned alongside it, in order. That makes it a dependency-free way of
+/// taking links from a shell pipeline:
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// // printf '../shared/config.toml\tapp/config.toml\n' | this program
+/// use symlink_rs::{apply_from_reader, LinkKind};
+///
+/// let stdin = std::io::stdin();
+/// for (spec, result) in apply_from_reader(stdin.lock(), LinkKind::File)? {
+///     println!("{}: {:?}", spec.link.display(), result);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// An [`InvalidData`](io::ErrorKind::InvalidData) error, giving the line number, is returned for
+/// a line that isn’t a target and a link separated by a single tab, and nothing is created. As
+/// for [`Manifest::apply`], nothing is done if the links have problems; other errors are from
+/// reading.
+pub fn apply_from_reader<R: BufRead>(reader: R, kind: LinkKind)
+                                     -> io::Result<Vec<(LinkSpec, io::Result<CreateOutcome>)>> {
+    let specs = read_lines(reader, "a target and a link separated by a tab", |line| {
+        match line.split_once('\t') {
+            Some((target, link)) if !target.is_empty() && !link.is_empty()
+                                    && !link.contains('\t') => {
+                Some(LinkSpec::new(kind, target, link))
+            }
+            _ => None,
+        }
+    })?;
+    Manifest { specs }.apply()
+}
+
+// The specs parsed from each line that isn’t blank or a comment, or an error for the first line
+// parse makes nothing of, saying it isn’t what.
+fn read_lines<R, F>(reader: R, what: &str, mut parse: F) -> io::Result<Vec<LinkSpec>>
+    where R: BufRead, F: FnMut(&str) -> Option<LinkSpec>
+{
+    let mut specs = vec![];
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse(line) {
+            Some(spec) => specs.push(spec),
+            None => {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                          format!("line {} isn’t {}: {:?}", i + 1, what, line)));
+            }
+        }
+    }
+    Ok(specs)
+}
+
+/// Create symlinks lazily, one for each item, as the returned iterator is consumed.
+///
+/// This is [`apply`] for link sets too large to hold in memory: nothing is created until the
+/// result for it is asked for, and each spec is moved through rather than cloned, so memory use
+/// stays bounded however many links there are, and a slow consumer holds up creation rather
+/// than the other way round. Dropping the iterator stops creating links.
+///
+/// ```no_run
+/// # #[macro_use] extern crate symlink_rs;
+/// # fn main() {
+/// let specs = (0..1_000_000).map(|i| {
+///     symlink_rs::LinkSpec::new(symlink_rs::LinkKind::File, "../blob", format!("links/{}", i))
+/// });
+/// for (spec, result) in symlink_rs::symlink_stream(specs) {
+///     if let Err(e) = result {
+///         eprintln!("{}: {}", spec.link.display(), e);
+///     }
+/// }
+/// # }
+/// ```
+pub fn symlink_stream<I>(links: I) -> impl Iterator<Item = (LinkSpec, io::Result<()>)>
+    where I: IntoIterator<Item = LinkSpec>
+{
+    symlink_stream_with_progress(links, |_, _, _| {})
+}
+
+/// Create symlinks lazily, as [`symlink_stream`] does, calling `on_progress` as each is done.
+///
+/// `on_progress` is called after each link is attempted, just before its result is yielded,
+/// with how many have been so far, `None` for how many there are in all—an iterator may not
+/// know, and may never end—and the link just attempted.
+pub fn symlink_stream_with_progress<I, F>(links: I, mut on_progress: F)
+                                          -> impl Iterator<Item = (LinkSpec, io::Result<()>)>
+    where I: IntoIterator<Item = LinkSpec>, F: FnMut(usize, Option<usize>, &Path)
+{
+    links.into_iter().enumerate().map(move |(i, spec)| {
+        let result = spec.create();
+        on_progress(i + 1, None, &spec.link);
+        (spec, result)
+    })
+}
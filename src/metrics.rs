@@ -0,0 +1,90 @@
+//! Cumulative counters of symlink operations (feature `metrics`).
+//!
+//! Every creation and removal this crate performs is counted, whether it succeeded or failed,
+//! and labelled with the kind of link: `file`, `dir`, `auto` or `junction`. The counters are
+//! process-wide atomics; read them with [`counters`] and feed them to whatever exporter you use.
+//! With the [`metrics`](https://docs.rs/metrics) crate, for example:
+//!
+//! ```ignore
+//! for c in symlink_rs::metrics::counters() {
+//!     metrics::counter!(c.name, "kind" => c.kind).absolute(c.value);
+//! }
+//! ```
+//!
+//! (This crate doesn’t depend on `metrics` itself, so that you can use any version of it, or
+//! something else entirely.) Without the feature, none of this exists and the operations don’t
+//! count anything, at no cost.
+
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Links created.
+pub const CREATED: &str = "symlink.created";
+/// Links removed.
+pub const REMOVED: &str = "symlink.removed";
+/// Creations and removals that failed.
+pub const FAILED: &str = "symlink.failed";
+
+const NAMES: [&str; 3] = [CREATED, REMOVED, FAILED];
+const KINDS: [&str; 4] = ["file", "dir", "auto", "junction"];
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicU64 = AtomicU64::new(0);
+#[allow(clippy::declare_interior_mutable_const)]
+const ROW: [AtomicU64; 4] = [ZERO; 4];
+static COUNTERS: [[AtomicU64; 4]; 3] = [ROW; 3];
+
+/// The value of one counter for one kind of link.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Counter {
+    /// The counter: [`CREATED`], [`REMOVED`] or [`FAILED`].
+    pub name: &'static str,
+    /// The kind of link: `"file"`, `"dir"`, `"auto"` or `"junction"`.
+    pub kind: &'static str,
+    /// How many there have been since the process started.
+    pub value: u64,
+}
+
+/// Read all the counters: each of [`CREATED`], [`REMOVED`] and [`FAILED`] for each kind.
+pub fn counters() -> Vec<Counter> {
+    let mut out = Vec::with_capacity(NAMES.len() * KINDS.len());
+    for (name, row) in NAMES.iter().zip(COUNTERS.iter()) {
+        for (kind, value) in KINDS.iter().zip(row.iter()) {
+            out.push(Counter { name, kind, value: value.load(Ordering::Relaxed) });
+        }
+    }
+    out
+}
+
+/// Read one counter.
+///
+/// Returns None if `name` or `kind` isn’t one of those listed on [`Counter`].
+pub fn counter(name: &str, kind: &str) -> Option<u64> {
+    let name = NAMES.iter().position(|&n| n == name)?;
+    let kind = KINDS.iter().position(|&k| k == kind)?;
+    Some(COUNTERS[name][kind].load(Ordering::Relaxed))
+}
+
+/// What was attempted.
+#[derive(Clone, Copy)]
+pub(crate) enum Op {
+    Create = 0,
+    Remove = 1,
+}
+
+/// The kind of link it was attempted on, as an index into a row of COUNTERS.
+#[derive(Clone, Copy)]
+pub(crate) enum Label {
+    File = 0,
+    Dir = 1,
+    Auto = 2,
+    #[cfg_attr(not(windows), allow(dead_code))]
+    Junction = 3,
+}
+
+/// Count the outcome of an operation, passing the result through.
+pub(crate) fn track<T>(op: Op, label: Label, result: io::Result<T>) -> io::Result<T> {
+    let name = if result.is_ok() { op as usize } else { 2 };
+    COUNTERS[name][label as usize].fetch_add(1, Ordering::Relaxed);
+    result
+}
@@ -0,0 +1,321 @@
+// Link farms: a tree of real directories with symlinks in it to everything in another tree.
+
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ensure::{ensure_symlink, CreateOutcome};
+use paths;
+use {create_symlink, remove_symlink_auto, stats, SymlinkKind};
+
+/// How far [`mirror_tree`] recreates the source tree’s directories before linking to them whole.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MirrorPolicy {
+    /// Recreate every directory, however deep, and link every file. This is the default.
+    #[default]
+    LinkFiles,
+    /// Recreate directories this many levels down, and link to any directory deeper than that
+    /// rather than going into it; files are linked wherever they are. `LinkDirsBelow(0)` links
+    /// each entry at the top of the source tree, directory or not, and recreates nothing.
+    LinkDirsBelow(usize),
+}
+
+/// Whether [`mirror_tree`] only adds to the destination, or brings it into line with the source.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MirrorMode {
+    /// Create what’s missing, and report anything already in the way as an error. This is the
+    /// default, and suits a destination being mirrored into for the first time.
+    #[default]
+    Create,
+    /// Reconcile the destination with the source, for a second run and every one after: leave
+    /// links that are right alone, repoint ones that point somewhere else, and remove links to
+    /// source entries that have gone. Only symlinks are ever removed; see [`mirror_tree`].
+    Sync,
+}
+
+/// What [`mirror_tree`] did to one destination path.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MirrorAction {
+    /// A symlink was created, where there was nothing.
+    Created,
+    /// A directory was created, where there was nothing.
+    CreatedDir,
+    /// The symlink was already there, pointing at its source entry; nothing was changed.
+    AlreadyCorrect,
+    /// A symlink pointing somewhere else was replaced with one to its source entry.
+    Repointed,
+    /// A symlink into the source tree was removed, its source entry having gone, or a
+    /// directory being wanted in its place.
+    Removed,
+}
+
+/// What [`mirror_tree`] did.
+#[derive(Debug, Default)]
+pub struct MirrorReport {
+    /// Symlinks created or repointed.
+    pub links: usize,
+    /// Directories created. One that already existed isn’t counted.
+    pub dirs: usize,
+    /// Symlinks removed, which only [`MirrorMode::Sync`] does.
+    pub removed: usize,
+    /// Every destination path dealt with, and how, in the order they were dealt with.
+    pub actions: Vec<(PathBuf, MirrorAction)>,
+    /// The entries that couldn’t be mirrored, by their path in the destination tree, and why.
+    pub errors: Vec<(PathBuf, io::Error)>,
+}
+
+/// Build a link farm: recreate the tree at `src_root` under `dst_root` as real directories, with
+/// a symlink to each file in it.
+///
+/// This is the usual way of overlaying one tree on another, or of populating a directory that
+/// mostly mirrors another but needs a few entries of its own (GNU Stow’s job, say). `dst_root` is
+/// created if need be, and so is each directory under it, as per `policy`; each entry that isn’t
+/// recreated becomes a link to its counterpart under `src_root`. Directories already present in
+/// the destination are used as they are, so mirroring into a tree that’s already been mirrored
+/// into—or has entries of its own—works. A symlink to a directory there isn’t used, though, but
+/// reported as in the way, so that nothing gets written through it into the tree it points to.
+///
+/// The links’ targets are absolute, `src_root` being made absolute (against the working
+/// directory, and lexically, without resolving symlinks) first, so the farm can be moved
+/// independently of the tree it points into. Each link is the kind of the entry it points to:
+/// on Windows a directory gets a directory symlink, a file a file symlink, and a symlink in the
+/// source tree a link of its own kind; symlinks in the source are linked to, never followed
+/// into, so a loop in the source can’t make this loop.
+///
+/// With [`MirrorMode::Sync`], running this again keeps the farm up to date. A link that already
+/// points at its source entry is left alone, and one that points elsewhere is repointed (as
+/// [`ensure_symlink_file`](crate::ensure_symlink_file) and friends would); where a directory
+/// is now wanted but a directory link into the source tree was left—by a shallower `policy`
+/// last time, say—the link is removed and the directory made. Then, in each destination
+/// directory, symlinks into the source tree with no source entry any more are removed, and so
+/// are any in destination directories whose whole source directory has gone. Nothing but
+/// symlinks pointing into `src_root` is ever removed: files, directories (even ones left
+/// empty) and links elsewhere are the destination’s own, and a file or directory where a link
+/// should be is reported as an error, not replaced.
+///
+/// A failure with one entry—something already in the way, say, or a subdirectory that can’t be
+/// read—doesn’t stop the rest; it’s recorded in the [`MirrorReport`] with the destination path
+/// it concerned, as is everything done.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// use symlink_rs::{mirror_tree, MirrorAction, MirrorMode, MirrorPolicy};
+///
+/// let report = mirror_tree("/opt/app/1.4.2", "/srv/app/overlay", MirrorPolicy::LinkFiles,
+///                          MirrorMode::Sync)?;
+/// for (path, action) in &report.actions {
+///     if *action != MirrorAction::AlreadyCorrect {
+///         println!("{:?} {}", action, path.display());
+///     }
+/// }
+/// for (path, error) in &report.errors {
+///     eprintln!("{}: {}", path.display(), error);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// An error will be returned if `src_root` can’t be read as a directory, or `dst_root` can’t be
+/// created; errors with anything inside either are in the report instead.
+pub fn mirror_tree<P: AsRef<Path>, Q: AsRef<Path>>(src_root: P, dst_root: Q, policy: MirrorPolicy,
+                                                   mode: MirrorMode)
+                                                   -> io::Result<MirrorReport> {
+    mirror(src_root.as_ref(), dst_root.as_ref(), policy, mode, None)
+}
+
+/// Build a link farm, as [`mirror_tree`] does, calling `on_progress` as each entry is done.
+///
+/// `on_progress` is called once for each entry of the source tree, after it’s been dealt
+/// with—linked, or its directory made before going into it, or failed—with how many have been
+/// so far, `None` for how many there are in all (the tree is read as it’s walked, so that isn’t
+/// known until the end), and the entry’s destination path. Removals by [`MirrorMode::Sync`]
+/// aren’t entries of the source tree, and aren’t counted; they’re in the report with the rest.
+///
+/// # Errors
+///
+/// As for [`mirror_tree`].
+pub fn mirror_tree_with_progress<P, Q, F>(src_root: P, dst_root: Q, policy: MirrorPolicy,
+                                          mode: MirrorMode, mut on_progress: F)
+                                          -> io::Result<MirrorReport>
+    where P: AsRef<Path>, Q: AsRef<Path>, F: FnMut(usize, Option<usize>, &Path)
+{
+    mirror(src_root.as_ref(), dst_root.as_ref(), policy, mode, Some(&mut on_progress))
+}
+
+fn mirror<'p>(src_root: &Path, dst_root: &Path, policy: MirrorPolicy, mode: MirrorMode,
+              progress: Option<&'p mut Progress<'p>>)
+              -> io::Result<MirrorReport> {
+    let src_root = paths::absolute(src_root)?;
+    let entries = fs::read_dir(&src_root)?;
+    let mut mirror = Mirror { src_root: &src_root, policy, mode, report: MirrorReport::default(),
+                              progress, done: 0 };
+    if !dst_root.is_dir() {
+        fs::create_dir_all(dst_root)?;
+        mirror.record(dst_root.to_owned(), MirrorAction::CreatedDir);
+    }
+    mirror.entries(entries, dst_root, 0);
+    Ok(mirror.report)
+}
+
+type Progress<'a> = dyn FnMut(usize, Option<usize>, &Path) + 'a;
+
+struct Mirror<'a, 'p> {
+    src_root: &'a Path,
+    policy: MirrorPolicy,
+    mode: MirrorMode,
+    report: MirrorReport,
+    progress: Option<&'p mut Progress<'p>>,
+    // How many source entries have been dealt with, for progress.
+    done: usize,
+}
+
+impl<'a, 'p> Mirror<'a, 'p> {
+    fn entries(&mut self, entries: fs::ReadDir, dst: &Path, depth: usize) {
+        let mut mirrored = HashSet::new();
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    self.report.errors.push((dst.to_owned(), e));
+                    continue;
+                }
+            };
+            mirrored.insert(entry.file_name());
+            let (src, dst) = (entry.path(), dst.join(entry.file_name()));
+            let file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(e) => {
+                    self.progress(&dst);
+                    self.report.errors.push((dst, e));
+                    continue;
+                }
+            };
+            let recurse = file_type.is_dir() && match self.policy {
+                MirrorPolicy::LinkFiles => true,
+                MirrorPolicy::LinkDirsBelow(levels) => depth < levels,
+            };
+            if recurse {
+                let result = fs::read_dir(&src)
+                    .and_then(|entries| self.dir(&dst).map(|()| entries));
+                self.progress(&dst);
+                match result {
+                    Ok(entries) => self.entries(entries, &dst, depth + 1),
+                    Err(e) => self.report.errors.push((dst, e)),
+                }
+            } else {
+                // A symlink in the source tree is linked to as what it is, not what it leads to.
+                let kind = if file_type.is_symlink() {
+                    None
+                } else if file_type.is_dir() {
+                    Some(SymlinkKind::Dir)
+                } else {
+                    Some(SymlinkKind::File)
+                };
+                self.link(kind, &src, dst);
+            }
+        }
+        if self.mode == MirrorMode::Sync {
+            self.prune(dst, &mirrored);
+        }
+    }
+
+    fn link(&mut self, kind: Option<SymlinkKind>, src: &Path, dst: PathBuf) {
+        let result = match self.mode {
+            MirrorMode::Create => create_symlink(kind, src, &dst).map(|()| MirrorAction::Created),
+            MirrorMode::Sync => ensure_symlink(kind, src, &dst).map(|outcome| match outcome {
+                CreateOutcome::Created => MirrorAction::Created,
+                CreateOutcome::AlreadyCorrect => MirrorAction::AlreadyCorrect,
+                CreateOutcome::Repointed => MirrorAction::Repointed,
+            }),
+        };
+        self.progress(&dst);
+        match result {
+            Ok(action) => self.record(dst, action),
+            Err(e) => self.report.errors.push((dst, e)),
+        }
+    }
+
+    // Count a source entry as done, at its destination path.
+    fn progress(&mut self, dst: &Path) {
+        self.done += 1;
+        if let Some(ref mut on_progress) = self.progress {
+            on_progress(self.done, None, dst);
+        }
+    }
+
+    // Create a directory, or use the real one there: not a symlink to one, which would be a link
+    // left by mirroring less deeply before, and would have this write into the source tree. When
+    // syncing, such a link into the source tree is removed to make way.
+    fn dir(&mut self, path: &Path) -> io::Result<()> {
+        let is_link = stats::symlink_metadata(path).map(|m| m.file_type().is_symlink());
+        if self.mode == MirrorMode::Sync && is_link.unwrap_or(false) && self.is_ours(path) {
+            remove_symlink_auto(path)?;
+            self.record(path.to_owned(), MirrorAction::Removed);
+        }
+        match fs::create_dir(path) {
+            Ok(()) => {
+                self.record(path.to_owned(), MirrorAction::CreatedDir);
+                Ok(())
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists
+                          && fs::symlink_metadata(path).map(|m| m.is_dir()).unwrap_or(false) => {
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // Remove the symlinks into the source tree in dst that aren’t named in mirrored, and those
+    // in directories there not named in it, whose source has gone.
+    fn prune(&mut self, dst: &Path, mirrored: &HashSet<OsString>) {
+        // Read in full first, so as not to remove entries from under the iterator.
+        let entries = fs::read_dir(dst).and_then(|entries| entries.collect::<io::Result<Vec<_>>>());
+        let entries = match entries {
+            Ok(entries) => entries,
+            Err(e) => {
+                self.report.errors.push((dst.to_owned(), e));
+                return;
+            }
+        };
+        for entry in entries {
+            if mirrored.contains(&entry.file_name()) {
+                continue;
+            }
+            let path = entry.path();
+            match entry.file_type() {
+                Ok(ref file_type) if file_type.is_symlink() => {
+                    if self.is_ours(&path) {
+                        match remove_symlink_auto(&path) {
+                            Ok(()) => self.record(path, MirrorAction::Removed),
+                            Err(e) => self.report.errors.push((path, e)),
+                        }
+                    }
+                }
+                Ok(ref file_type) if file_type.is_dir() => self.prune(&path, &HashSet::new()),
+                Ok(_) => {}
+                Err(e) => self.report.errors.push((path, e)),
+            }
+        }
+    }
+
+    // Whether the symlink at path points into the source tree, and so is one of the farm’s.
+    fn is_ours(&self, path: &Path) -> bool {
+        stats::read_link(path).and_then(|target| {
+            paths::absolute(&paths::resolve_target(path, &target))
+        }).map(|target| target.starts_with(self.src_root)).unwrap_or(false)
+    }
+
+    fn record(&mut self, path: PathBuf, action: MirrorAction) {
+        match action {
+            MirrorAction::Created | MirrorAction::Repointed => self.report.links += 1,
+            MirrorAction::CreatedDir => self.report.dirs += 1,
+            MirrorAction::Removed => self.report.removed += 1,
+            MirrorAction::AlreadyCorrect => {}
+        }
+        self.report.actions.push((path, action));
+    }
+}
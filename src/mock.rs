@@ -0,0 +1,195 @@
+// An in-memory SymlinkOps, for testing code built on this crate without touching the filesystem.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use paths;
+use {SymlinkKind, SymlinkOps};
+
+/// A symlink recorded by [`MockSymlinks`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MockLink {
+    /// The target, as given.
+    pub target: PathBuf,
+    /// The kind of link. This is `None` for a link made with `symlink_auto` when kinds aren’t
+    /// distinguished and the target isn’t known, which is to say the one case where the kind
+    /// isn’t knowable.
+    pub kind: Option<SymlinkKind>,
+}
+
+#[derive(Clone, Debug)]
+enum Entry {
+    Link(MockLink),
+    Target(SymlinkKind),
+}
+
+/// An in-memory [`SymlinkOps`], with no real I/O at all (feature `mock`).
+///
+/// Links created through it are recorded in a map, removals take them out again, and queries
+/// are answered from it, so you can exercise code written against `&dyn SymlinkOps` and then
+/// check [`links`](Self::links). Paths are compared after lexical normalisation, so `a/./b` and
+/// `a/b` are the same path, but nothing is ever resolved: this models a flat namespace of
+/// paths, not a directory tree, and it doesn’t check that parent directories exist.
+///
+/// It does model what matters to callers of this crate:
+///
+/// - Creating a link where there’s already something fails with
+///   [`AlreadyExists`](io::ErrorKind::AlreadyExists).
+/// - Removing or reading a link that isn’t there fails with
+///   [`NotFound`](io::ErrorKind::NotFound); removing or reading something that isn’t a link
+///   fails with [`InvalidInput`](io::ErrorKind::InvalidInput).
+/// - With [`distinguish_kinds`](Self::distinguish_kinds) on—the default on Windows—file and
+///   directory links behave as on Windows: removing one with the wrong function fails, and
+///   `symlink_auto` needs its target to be known, failing with `NotFound` otherwise. Off, any
+///   removal function removes any link and `symlink_auto` doesn’t look at the target, as on
+///   Unix.
+///
+/// Targets—the real files and directories links point at—are declared with
+/// [`add_target`](Self::add_target).
+#[derive(Debug)]
+pub struct MockSymlinks {
+    entries: Mutex<HashMap<PathBuf, Entry>>,
+    distinguish_kinds: bool,
+}
+
+impl Default for MockSymlinks {
+    fn default() -> MockSymlinks {
+        MockSymlinks::new()
+    }
+}
+
+impl MockSymlinks {
+    /// Create an empty mock, distinguishing kinds if the host platform does.
+    pub fn new() -> MockSymlinks {
+        MockSymlinks { entries: Mutex::new(HashMap::new()), distinguish_kinds: cfg!(windows) }
+    }
+
+    /// Whether to treat file and directory links as different, as Windows does.
+    pub fn distinguish_kinds(&mut self, distinguish_kinds: bool) -> &mut MockSymlinks {
+        self.distinguish_kinds = distinguish_kinds;
+        self
+    }
+
+    /// Declare that there’s a real file or directory at `path`.
+    ///
+    /// This gives `symlink_auto` something to look at, and occupies the path: creating a link
+    /// there fails with `AlreadyExists`. It replaces anything previously at `path`.
+    pub fn add_target<P: AsRef<Path>>(&self, path: P, kind: SymlinkKind) {
+        self.lock().insert(key(path.as_ref()), Entry::Target(kind));
+    }
+
+    /// All the links currently recorded, by normalised path.
+    pub fn links(&self) -> HashMap<PathBuf, MockLink> {
+        self.lock().iter().filter_map(|(path, entry)| match *entry {
+            Entry::Link(ref link) => Some((path.clone(), link.clone())),
+            Entry::Target(_) => None,
+        }).collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<PathBuf, Entry>> {
+        // A panic while holding the lock can’t leave the map half-updated, so carry on.
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn create(&self, kind: Option<SymlinkKind>, src: &Path, dst: &Path) -> io::Result<()> {
+        let mut entries = self.lock();
+        let dst_key = key(dst);
+        if entries.contains_key(&dst_key) {
+            return Err(error(io::ErrorKind::AlreadyExists, "already exists", dst));
+        }
+        let kind = match kind {
+            Some(kind) => Some(kind),
+            None => {
+                let target = match entries.get(&key(&paths::resolve_target(dst, src))) {
+                    Some(&Entry::Target(kind)) => Some(kind),
+                    Some(Entry::Link(link)) => link.kind,
+                    None => None,
+                };
+                if target.is_none() && self.distinguish_kinds {
+                    return Err(error(io::ErrorKind::NotFound, "symlink_auto target not known",
+                                     dst));
+                }
+                target
+            }
+        };
+        entries.insert(dst_key, Entry::Link(MockLink { target: src.to_owned(), kind }));
+        Ok(())
+    }
+
+    fn remove(&self, kind: Option<SymlinkKind>, path: &Path) -> io::Result<()> {
+        let mut entries = self.lock();
+        let path_key = key(path);
+        match entries.get(&path_key) {
+            None => return Err(error(io::ErrorKind::NotFound, "not found", path)),
+            Some(&Entry::Target(_)) => {
+                return Err(error(io::ErrorKind::InvalidInput, "not a symlink", path));
+            }
+            Some(Entry::Link(link)) => {
+                if self.distinguish_kinds && kind.is_some() && link.kind != kind {
+                    return Err(match kind {
+                        Some(SymlinkKind::Dir) => {
+                            error(io::ErrorKind::NotADirectory, "file symlink removed as a \
+                                                                 directory symlink", path)
+                        }
+                        _ => {
+                            error(io::ErrorKind::PermissionDenied, "directory symlink removed \
+                                                                    as a file symlink", path)
+                        }
+                    });
+                }
+            }
+        }
+        entries.remove(&path_key);
+        Ok(())
+    }
+}
+
+impl SymlinkOps for MockSymlinks {
+    fn symlink_file(&self, src: &Path, dst: &Path) -> io::Result<()> {
+        self.create(Some(SymlinkKind::File), src, dst)
+    }
+
+    fn symlink_dir(&self, src: &Path, dst: &Path) -> io::Result<()> {
+        self.create(Some(SymlinkKind::Dir), src, dst)
+    }
+
+    fn symlink_auto(&self, src: &Path, dst: &Path) -> io::Result<()> {
+        self.create(None, src, dst)
+    }
+
+    fn remove_symlink_file(&self, path: &Path) -> io::Result<()> {
+        self.remove(Some(SymlinkKind::File), path)
+    }
+
+    fn remove_symlink_dir(&self, path: &Path) -> io::Result<()> {
+        self.remove(Some(SymlinkKind::Dir), path)
+    }
+
+    fn remove_symlink_auto(&self, path: &Path) -> io::Result<()> {
+        self.remove(None, path)
+    }
+
+    fn read_symlink(&self, path: &Path) -> io::Result<PathBuf> {
+        match self.lock().get(&key(path)) {
+            Some(Entry::Link(link)) => Ok(link.target.clone()),
+            Some(&Entry::Target(_)) => {
+                Err(error(io::ErrorKind::InvalidInput, "not a symlink", path))
+            }
+            None => Err(error(io::ErrorKind::NotFound, "not found", path)),
+        }
+    }
+
+    fn is_symlink(&self, path: &Path) -> bool {
+        matches!(self.lock().get(&key(path)), Some(&Entry::Link(_)))
+    }
+}
+
+fn key(path: &Path) -> PathBuf {
+    paths::normalize_lexically(path)
+}
+
+fn error(kind: io::ErrorKind, what: &str, path: &Path) -> io::Error {
+    io::Error::new(kind, format!("mock: {}: {}", path.display(), what))
+}
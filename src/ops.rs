@@ -0,0 +1,89 @@
+// An object-safe interface to the crate’s operations, for code that wants to be tested without
+// touching the filesystem.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use stats;
+
+/// The crate’s symlink operations, as a trait.
+///
+/// Write code that creates and inspects symlinks against `&dyn SymlinkOps` (or a generic
+/// `O: SymlinkOps`), pass it [`SystemSymlinks`] for real, and pass it a fake in tests. The methods
+/// take plain `&Path`s rather than `AsRef<Path>` so that the trait can be made into an object.
+///
+/// ```
+/// use std::io;
+/// use std::path::Path;
+/// use symlink_rs::{SymlinkOps, SystemSymlinks};
+///
+/// fn link_current(ops: &dyn SymlinkOps, release: &Path) -> io::Result<()> {
+///     ops.symlink_dir(release, Path::new("current"))
+/// }
+/// # let _ = link_current;
+/// # let _: &dyn SymlinkOps = &SystemSymlinks;
+/// ```
+pub trait SymlinkOps {
+    /// Create a symlink to a file; see [`symlink_file`](crate::symlink_file).
+    fn symlink_file(&self, src: &Path, dst: &Path) -> io::Result<()>;
+
+    /// Create a symlink to a directory; see [`symlink_dir`](crate::symlink_dir).
+    fn symlink_dir(&self, src: &Path, dst: &Path) -> io::Result<()>;
+
+    /// Create a symlink of whichever kind suits the target; see
+    /// [`symlink_auto`](crate::symlink_auto).
+    fn symlink_auto(&self, src: &Path, dst: &Path) -> io::Result<()>;
+
+    /// Remove a file symlink; see [`remove_symlink_file`](crate::remove_symlink_file).
+    fn remove_symlink_file(&self, path: &Path) -> io::Result<()>;
+
+    /// Remove a directory symlink; see [`remove_symlink_dir`](crate::remove_symlink_dir).
+    fn remove_symlink_dir(&self, path: &Path) -> io::Result<()>;
+
+    /// Remove a symlink of either kind; see [`remove_symlink_auto`](crate::remove_symlink_auto).
+    fn remove_symlink_auto(&self, path: &Path) -> io::Result<()>;
+
+    /// Read a symlink’s target, as with [`std::fs::read_link`].
+    fn read_symlink(&self, path: &Path) -> io::Result<PathBuf>;
+
+    /// Whether there is a symlink at `path`. Anything else, including nothing, is `false`.
+    fn is_symlink(&self, path: &Path) -> bool;
+}
+
+/// The real filesystem: each method calls the corresponding free function.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SystemSymlinks;
+
+impl SymlinkOps for SystemSymlinks {
+    fn symlink_file(&self, src: &Path, dst: &Path) -> io::Result<()> {
+        ::symlink_file(src, dst)
+    }
+
+    fn symlink_dir(&self, src: &Path, dst: &Path) -> io::Result<()> {
+        ::symlink_dir(src, dst)
+    }
+
+    fn symlink_auto(&self, src: &Path, dst: &Path) -> io::Result<()> {
+        ::symlink_auto(src, dst)
+    }
+
+    fn remove_symlink_file(&self, path: &Path) -> io::Result<()> {
+        ::remove_symlink_file(path)
+    }
+
+    fn remove_symlink_dir(&self, path: &Path) -> io::Result<()> {
+        ::remove_symlink_dir(path)
+    }
+
+    fn remove_symlink_auto(&self, path: &Path) -> io::Result<()> {
+        ::remove_symlink_auto(path)
+    }
+
+    fn read_symlink(&self, path: &Path) -> io::Result<PathBuf> {
+        stats::read_link(path)
+    }
+
+    fn is_symlink(&self, path: &Path) -> bool {
+        stats::symlink_metadata(path).map(|m| m.file_type().is_symlink()).unwrap_or(false)
+    }
+}
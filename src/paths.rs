@@ -0,0 +1,423 @@
+// Pure path manipulation, shared by the various options that need to reason about where things
+// are without (or before) touching the filesystem.
+
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::path::{self, Component, Path, PathBuf};
+#[cfg(windows)]
+use std::path::Prefix;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Collapse `.` components and `..` components that follow a normal component.
+///
+/// This is purely lexical, so it gives the wrong answer if a component before a `..` is a
+/// symlink; callers need to bear that in mind. A `..` at the root is dropped (`/..` is `/`), and
+/// leading `..` components in a relative path are kept.
+pub(crate) fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match out.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    out.pop();
+                }
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                Some(Component::ParentDir) | Some(Component::CurDir) | None => {
+                    out.push("..");
+                }
+            },
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
+/// Where a link at `link` with target `target` points, lexically: relative targets are relative
+/// to the link’s parent directory, not to the working directory.
+pub(crate) fn resolve_target(link: &Path, target: &Path) -> PathBuf {
+    match link.parent() {
+        Some(parent) if target.is_relative() => parent.join(target),
+        _ => target.to_owned(),
+    }
+}
+
+/// Whether a link at `link` with target `target` would point straight at itself, judged
+/// lexically after resolving `target` against the link’s parent. Relative and absolute spellings
+/// of the same path are compared against the working directory; empty paths are never
+/// self-links (they’re errors of their own).
+pub(crate) fn is_self_link(link: &Path, target: &Path) -> io::Result<bool> {
+    if link.as_os_str().is_empty() || target.as_os_str().is_empty() {
+        return Ok(false);
+    }
+    let target = resolve_target(link, target);
+    if target.is_absolute() == link.is_absolute() {
+        Ok(normalize_lexically(&target) == normalize_lexically(link))
+    } else {
+        Ok(absolute(&target)? == absolute(link)?)
+    }
+}
+
+/// Make a path absolute (against the working directory) and normalise it lexically.
+pub(crate) fn absolute(path: &Path) -> io::Result<PathBuf> {
+    Ok(normalize_lexically(&path::absolute(path)?))
+}
+
+/// The path from directory `base` to `path`, both absolute and normalised, using `..` to climb
+/// out of `base` as far as needed. Returns None when there’s no such path, as between drives on
+/// Windows. `.` is returned for `base` itself.
+pub(crate) fn relative_to(path: &Path, base: &Path) -> Option<PathBuf> {
+    let mut path_components = path.components().peekable();
+    let mut base_components = base.components().peekable();
+    match (path_components.peek(), base_components.peek()) {
+        (Some(&Component::Prefix(a)), Some(&Component::Prefix(b))) if a != b => return None,
+        _ => {}
+    }
+    while let (Some(a), Some(b)) = (path_components.peek(), base_components.peek()) {
+        if a != b {
+            break;
+        }
+        path_components.next();
+        base_components.next();
+    }
+    let mut out = PathBuf::new();
+    for _ in base_components {
+        out.push("..");
+    }
+    out.extend(path_components);
+    if out.as_os_str().is_empty() {
+        out.push(".");
+    }
+    Some(out)
+}
+
+/// Whether two symlink targets are the same target, spelt perhaps differently.
+///
+/// Targets are compared as paths, component by component, which is what
+/// [`Path`]’s `==` does: repeated separators and `.` components other than a leading one make no
+/// difference, so `a//b`, `a/./b` and `a/b` are all equivalent, and so are `a/b/` and `a/b`. On
+/// Windows, where both `/` and `\` are separators, that already makes `C:/x` and `C:\x`
+/// equivalent; in addition, a verbatim prefix (`\\?\`, or the NT `\??\` that shows up in
+/// reparse points) is dropped, along with its requirement that separators be `\`, so that
+/// `\\?\C:\x` and `C:/x` are equivalent and `\\?\UNC\server\share` is `\\server\share`.
+///
+/// Case matters or not as it does by default on the platform: not on Windows and macOS, whose
+/// usual filesystems ignore it, so `Docs/A` and `docs/a` are equivalent there; and on Linux and
+/// elsewhere it does. But it’s the filesystem that decides, not the platform—macOS can have
+/// case-sensitive APFS volumes, Windows per-directory case sensitivity, Linux a FAT stick or
+/// ext4 with casefolding—so for a filesystem that differs from its platform’s default, use a
+/// [`TargetComparator`] with [`case_sensitive`](TargetComparator::case_sensitive) set.
+///
+/// Nothing else is normalised: `..` isn’t collapsed, and nothing is resolved against the
+/// filesystem. On Unix `\` is an ordinary character in a file name, so it isn’t a separator
+/// there. Targets that differ in any of these ways may or may not point to the same place, and
+/// this says they aren’t equivalent.
+pub fn targets_equivalent(a: &Path, b: &Path) -> bool {
+    TargetComparator::new().equivalent(a, b)
+}
+
+/// Whether case matters in file names by default here: not on Windows and macOS, and elsewhere
+/// yes.
+const CASE_SENSITIVE_BY_DEFAULT: bool = !cfg!(any(windows, target_os = "macos"));
+
+/// How to compare symlink targets: [`targets_equivalent`], with the case sensitivity a setting.
+///
+/// `TargetComparator::new()` compares as `targets_equivalent` does, with case sensitivity the
+/// platform’s default; set [`case_sensitive`](Self::case_sensitive) for a filesystem that differs
+/// from that. [`SymlinkBuilder::target_comparator`](crate::SymlinkBuilder::target_comparator)
+/// takes one too, for checking links it has made.
+///
+/// ```
+/// use std::path::Path;
+/// use symlink_rs::TargetComparator;
+///
+/// // A case-sensitive APFS volume on macOS, say.
+/// let mut comparator = TargetComparator::new();
+/// comparator.case_sensitive(true);
+/// assert!(!comparator.equivalent(Path::new("Docs/A"), Path::new("docs/a")));
+/// comparator.case_sensitive(false);
+/// assert!(comparator.equivalent(Path::new("Docs/A"), Path::new("docs/a")));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TargetComparator {
+    case_sensitive: bool,
+}
+
+impl Default for TargetComparator {
+    fn default() -> TargetComparator {
+        TargetComparator { case_sensitive: CASE_SENSITIVE_BY_DEFAULT }
+    }
+}
+
+impl TargetComparator {
+    /// A comparator with the platform’s case sensitivity.
+    pub fn new() -> TargetComparator {
+        TargetComparator::default()
+    }
+
+    /// Whether case matters.
+    ///
+    /// Without it, names are compared with both sides lowercased, character by character, as
+    /// Unicode has it, which is close to what Windows and macOS do but not exactly it: each has
+    /// its own table, and macOS also treats differently normalised forms of the same name as the
+    /// same, which this doesn’t. Names that aren’t valid Unicode are compared exactly.
+    pub fn case_sensitive(&mut self, case_sensitive: bool) -> &mut TargetComparator {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    /// Whether `a` and `b` are the same target, as [`targets_equivalent`] describes but with
+    /// this comparator’s case sensitivity.
+    pub fn equivalent(&self, a: &Path, b: &Path) -> bool {
+        let (a, b) = (strip_verbatim(a), strip_verbatim(b));
+        if self.case_sensitive {
+            return a == b;
+        }
+        let (mut a, mut b) = (a.components(), b.components());
+        loop {
+            match (a.next(), b.next()) {
+                (None, None) => return true,
+                (Some(x), Some(y)) if same_ignoring_case(x.as_os_str(), y.as_os_str()) => {}
+                _ => return false,
+            }
+        }
+    }
+}
+
+fn same_ignoring_case(a: &OsStr, b: &OsStr) -> bool {
+    match (a.to_str(), b.to_str()) {
+        (Some(a), Some(b)) => {
+            a.chars().flat_map(char::to_lowercase).eq(b.chars().flat_map(char::to_lowercase))
+        }
+        _ => a == b,
+    }
+}
+
+#[cfg(windows)]
+pub(crate) fn strip_verbatim(path: &Path) -> PathBuf {
+    use std::os::windows::ffi::{OsStrExt, OsStringExt};
+    let wide: Vec<u16> = path.as_os_str().encode_wide().collect();
+    let starts_with = |prefix: &str| {
+        wide.len() >= prefix.len()
+            && prefix.encode_utf16().eq(wide[..prefix.len()].iter().cloned())
+    };
+    let rest = if starts_with(r"\\?\UNC\") || starts_with(r"\??\UNC\") {
+        // \\?\UNC\server\share is \\server\share, keeping one of the backslashes.
+        let mut rest = vec![b'\\' as u16];
+        rest.extend_from_slice(&wide[7..]);
+        rest
+    } else if starts_with(r"\\?\") || starts_with(r"\??\") {
+        wide[4..].to_vec()
+    } else {
+        return path.to_owned();
+    };
+    PathBuf::from(OsString::from_wide(&rest))
+}
+
+#[cfg(not(windows))]
+pub(crate) fn strip_verbatim(path: &Path) -> &Path {
+    path
+}
+
+/// The path with every separator written as `/` if `forward`, or as `\` if not. Verbatim paths are
+/// left alone, since `/` isn’t a separator in them. Only Windows has a choice of separators, so
+/// elsewhere this changes nothing.
+#[cfg(windows)]
+pub(crate) fn with_separators(path: &Path, forward: bool) -> PathBuf {
+    use std::os::windows::ffi::{OsStrExt, OsStringExt};
+    let s = path.as_os_str().to_string_lossy();
+    if s.starts_with(r"\\?\") || s.starts_with(r"\??\") {
+        return path.to_owned();
+    }
+    let separator = (if forward { b'/' } else { b'\\' }) as u16;
+    let wide: Vec<u16> = path.as_os_str().encode_wide()
+        .map(|c| if c == b'/' as u16 || c == b'\\' as u16 { separator } else { c })
+        .collect();
+    PathBuf::from(OsString::from_wide(&wide))
+}
+
+#[cfg(not(windows))]
+pub(crate) fn with_separators(path: &Path, _forward: bool) -> PathBuf {
+    path.to_owned()
+}
+
+/// The first name in `path` that ends in a dot or a space, which Windows quietly drops (`foo.`
+/// is taken to mean `foo`), or None. `.` and `..` aren’t names, and a verbatim path is taken as
+/// it is, so neither counts.
+#[cfg(windows)]
+pub(crate) fn trailing_dot_or_space(path: &Path) -> Option<&OsStr> {
+    if is_verbatim(path) {
+        return None;
+    }
+    path.components().find_map(|component| match component {
+        Component::Normal(name) => {
+            let name_str = name.to_string_lossy();
+            if name_str.ends_with('.') || name_str.ends_with(' ') { Some(name) } else { None }
+        }
+        _ => None,
+    })
+}
+
+#[cfg(windows)]
+fn is_verbatim(path: &Path) -> bool {
+    match path.components().next() {
+        Some(Component::Prefix(prefix)) => prefix.kind().is_verbatim(),
+        _ => false,
+    }
+}
+
+/// `path` made absolute and verbatim (`\\?\C:\…` or `\\?\UNC\server\share\…`), so that Windows
+/// takes every name in it exactly as written. It’s made absolute against the working directory
+/// and normalised lexically, since a verbatim path gets no tidying of `.`, `..` or `/`, and not
+/// with `path::absolute`, which on Windows drops the very dots and spaces this is for. A path
+/// that’s verbatim already is left alone.
+#[cfg(windows)]
+pub(crate) fn verbatim(path: &Path) -> io::Result<PathBuf> {
+    use std::os::windows::ffi::{OsStrExt, OsStringExt};
+    if is_verbatim(path) {
+        return Ok(path.to_owned());
+    }
+    let full = normalize_lexically(&env::current_dir()?.join(path));
+    let wide: Vec<u16> = full.as_os_str().encode_wide().collect();
+    let mut out: Vec<u16> = r"\\?\".encode_utf16().collect();
+    match full.components().next() {
+        Some(Component::Prefix(prefix)) if full.has_root() => match prefix.kind() {
+            Prefix::Disk(_) => out.extend_from_slice(&wide),
+            Prefix::UNC(..) => {
+                // \\server\share becomes \\?\UNC\server\share.
+                out.extend("UNC".encode_utf16());
+                out.extend_from_slice(&wide[1..]);
+            }
+            _ => return Err(not_verbatim(path)),
+        },
+        _ => return Err(not_verbatim(path)),
+    }
+    Ok(PathBuf::from(OsString::from_wide(&out)))
+}
+
+/// `path` without its verbatim prefix (`\\?\C:\…` becoming `C:\…`, and `\\?\UNC\server\…`
+/// becoming `\\server\…`), for showing to people, so long as Windows would read the plain form
+/// as the same path: one with a name ending in a dot or space, or as long as `MAX_PATH`, is left
+/// verbatim, as are paths that aren’t verbatim to begin with.
+#[cfg(windows)]
+pub(crate) fn presentable(path: &Path) -> PathBuf {
+    let mut components = path.components();
+    let mut plain = match components.next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::VerbatimDisk(disk) => OsString::from(format!("{}:", disk as char)),
+            Prefix::VerbatimUNC(server, share) => {
+                let mut plain = OsString::from(r"\\");
+                plain.push(server);
+                plain.push(r"\");
+                plain.push(share);
+                plain
+            }
+            _ => return path.to_owned(),
+        },
+        _ => return path.to_owned(),
+    };
+    plain.push(components.as_path());
+    let plain = PathBuf::from(plain);
+    if plain.as_os_str().len() >= 260 || trailing_dot_or_space(&plain).is_some() {
+        path.to_owned()
+    } else {
+        plain
+    }
+}
+
+#[cfg(not(windows))]
+pub(crate) fn presentable(path: &Path) -> PathBuf {
+    path.to_owned()
+}
+
+#[cfg(windows)]
+fn not_verbatim(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput,
+                   format!("{} can’t be made into a verbatim path", path.display()))
+}
+
+/// Whether a path looks like it names a directory, judging by its shape alone: it ends with a
+/// separator, or in `.` or `..`, or is a root or drive. This is only a guess, and a poor one in the
+/// negative (`bin`, `src` and `Makefile` all look like files to it).
+#[cfg(windows)]
+pub(crate) fn looks_like_dir(path: &Path) -> bool {
+    let s = path.as_os_str().to_string_lossy();
+    s.ends_with('/') || s.ends_with('\\') || match path.components().next_back() {
+        Some(Component::Normal(_)) => false,
+        Some(_) => true,
+        None => false,
+    }
+}
+
+/// A path beside `path`, in the same directory, that nothing else should be using: a hidden name
+/// made from `path`’s own, the process ID, a counter and the clock’s nanoseconds. The last is
+/// for processes in different PID namespaces sharing a directory, whose IDs and counters may
+/// well agree; it makes a collision unlikely, not impossible, so whatever is created at the path
+/// should be created exclusively.
+pub(crate) fn temp_sibling(path: &Path) -> io::Result<PathBuf> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let name = path.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "link path has no file name")
+    })?;
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.subsec_nanos());
+    let mut temp = OsString::from(".");
+    temp.push(name);
+    temp.push(format!(".{}.{}.{}.tmp", process::id(), COUNTER.fetch_add(1, Ordering::Relaxed),
+                      nanos));
+    Ok(path.with_file_name(temp))
+}
+
+/// `path` with a leading `~` component replaced by the home directory, or `None` if it doesn’t
+/// start with one. `~user` can’t be looked up without the password database, and is an error
+/// rather than a directory of that name.
+pub(crate) fn expand_tilde(path: &Path) -> io::Result<Option<PathBuf>> {
+    let mut components = path.components();
+    let first = match components.next() {
+        Some(Component::Normal(first)) => first,
+        _ => return Ok(None),
+    };
+    if first != "~" {
+        return match first.to_str() {
+            Some(first) if first.starts_with('~') => {
+                Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                   format!("cannot expand {} in {}: only ~ alone, for the current \
+                                            user’s home directory, is expanded", first,
+                                           path.display())))
+            }
+            _ => Ok(None),
+        };
+    }
+    let home = env::var_os(HOME).filter(|home| !home.is_empty()).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound,
+                       format!("cannot expand ~ in {}: {} is not set", path.display(), HOME))
+    })?;
+    Ok(Some(Path::new(&home).join(components.as_path())))
+}
+
+// The environment variable that names the home directory.
+#[cfg(windows)]
+const HOME: &str = "USERPROFILE";
+#[cfg(not(windows))]
+const HOME: &str = "HOME";
+
+/// `time` in UTC, to the second, as `YYYY-MM-DDTHHMMSSZ`: RFC 3339 without the colons, so that
+/// it can go in a file name on Windows. Times before 1970 come out as 1970.
+pub(crate) fn utc_timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (days, rest) = (secs / 86400, secs % 86400);
+    // Howard Hinnant’s civil_from_days, for days since 1970-01-01 (which are never negative
+    // here).
+    let z = days + 719468;
+    let (era, doe) = (z / 146097, z % 146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}T{:02}{:02}{:02}Z", year, month, day, rest / 3600, rest / 60 % 60,
+            rest % 60)
+}
@@ -0,0 +1,163 @@
+// One entry point for creating a link over whatever might already be there, with what to do about
+// it spelt out as a value rather than chosen by picking a function.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use {backup_path, stats, symlink_backup, LinkKind, SymlinkBuilder, SymlinkKind};
+
+/// What [`symlink`] does when something is already at the link path.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ConflictPolicy {
+    /// Fail with [`AlreadyExists`](io::ErrorKind::AlreadyExists), as
+    /// [`symlink_file`](crate::symlink_file) and friends do. This is the default.
+    #[default]
+    Fail,
+    /// Replace a symlink, atomically where
+    /// [`ATOMIC_REPLACE_SUPPORTED`](crate::ATOMIC_REPLACE_SUPPORTED) says so, as
+    /// [`SymlinkBuilder::replace_existing`] does; fail with
+    /// [`AlreadyExists`](io::ErrorKind::AlreadyExists) on anything else.
+    ReplaceSymlinkOnly,
+    /// Replace a symlink as `ReplaceSymlinkOnly` does, and remove anything else first: a file, or
+    /// an empty directory. A directory with something in it is never removed; that’s an error
+    /// from the OS instead.
+    Overwrite,
+    /// Move a file or directory aside to the link path with `suffix` appended, as
+    /// [`symlink_file_backup`](crate::symlink_file_backup) does, never overwriting an existing
+    /// backup; fail with [`AlreadyExists`](io::ErrorKind::AlreadyExists) on a symlink.
+    Backup {
+        /// What to append to the link path to make the backup path: `.bak`, say.
+        suffix: String,
+    },
+    /// Leave whatever is there alone, and report that nothing was done.
+    Skip,
+}
+
+/// What [`symlink`] did.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PolicyOutcome {
+    /// There was nothing at the link path, so the link was created.
+    Created,
+    /// Something was there, and the link took its place.
+    Replaced,
+    /// Something was there, and was moved aside to this path before the link was created.
+    BackedUp(PathBuf),
+    /// Something was there, and was left alone; no link was created.
+    Skipped,
+}
+
+/// Create a symlink of the given kind, dealing with anything already at `dst` as `policy` says.
+///
+/// This is the one function that does what the various narrower ones do between them: with
+/// [`ConflictPolicy::Fail`] it’s [`symlink_file`](crate::symlink_file),
+/// [`symlink_dir`](crate::symlink_dir) or [`symlink_auto`](crate::symlink_auto), with
+/// [`ConflictPolicy::ReplaceSymlinkOnly`] it’s a [`SymlinkBuilder`] with
+/// [`replace_existing`](SymlinkBuilder::replace_existing), and with [`ConflictPolicy::Backup`]
+/// it’s [`symlink_file_backup`](crate::symlink_file_backup) or
+/// [`symlink_dir_backup`](crate::symlink_dir_backup). The [`PolicyOutcome`] says what happened.
+///
+/// Except with `Fail` and `Skip`, which just try to create the link, what’s at `dst` is looked at
+/// first and the policy applied to what was found; something appearing or changing in between
+/// can make the creation fail with [`AlreadyExists`](io::ErrorKind::AlreadyExists) after all.
+/// Only `ReplaceSymlinkOnly`, and `Overwrite` on a symlink, replace atomically, and only where
+/// the platform can (see [Atomicity](crate#atomicity)).
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// use symlink_rs::{symlink, ConflictPolicy, LinkKind, PolicyOutcome};
+///
+/// let policy = ConflictPolicy::Backup { suffix: ".bak".into() };
+/// if let PolicyOutcome::BackedUp(backup) = symlink(LinkKind::File, "dotfiles/bashrc",
+///                                                  ".bashrc", &policy)? {
+///     println!("moved the old .bashrc to {}", backup.display());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// An error will be returned if the policy says to fail, and otherwise as for the functions
+/// named above, or if something in the way can’t be inspected or removed.
+pub fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(kind: LinkKind, src: P, dst: Q,
+                                               policy: &ConflictPolicy)
+                                               -> io::Result<PolicyOutcome> {
+    apply_policy(&SymlinkBuilder::new(), kind.symlink_kind(), src.as_ref(), dst.as_ref(), policy)
+}
+
+// symlink, with the link created by builder, whose replace_existing is overridden as the policy
+// requires.
+pub(crate) fn apply_policy(builder: &SymlinkBuilder, kind: Option<SymlinkKind>, src: &Path,
+                           dst: &Path, policy: &ConflictPolicy) -> io::Result<PolicyOutcome> {
+    let mut builder = builder.clone();
+    if *policy != ConflictPolicy::ReplaceSymlinkOnly && *policy != ConflictPolicy::Overwrite {
+        builder.replace_existing(false);
+    }
+    match *policy {
+        ConflictPolicy::Fail => builder.create(kind, src, dst).map(|()| PolicyOutcome::Created),
+        ConflictPolicy::Skip => match builder.create(kind, src, dst) {
+            Ok(()) => Ok(PolicyOutcome::Created),
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => Ok(PolicyOutcome::Skipped),
+            Err(e) => Err(e),
+        },
+        ConflictPolicy::ReplaceSymlinkOnly => replace(&mut builder, kind, src, dst, false),
+        ConflictPolicy::Overwrite => replace(&mut builder, kind, src, dst, true),
+        ConflictPolicy::Backup { ref suffix } => {
+            Ok(match symlink_backup(dst, suffix, || builder.create(kind, src, dst))? {
+                Some(backup) => PolicyOutcome::BackedUp(backup),
+                None => PolicyOutcome::Created,
+            })
+        }
+    }
+}
+
+// What apply_policy would most likely do, found by looking at dst without changing anything. Only
+// what’s at dst is considered: the builder’s options, and whether the OS would let the link be
+// made at all, aren’t.
+pub(crate) fn predict_policy(dst: &Path, policy: &ConflictPolicy) -> io::Result<PolicyOutcome> {
+    if *policy == (ConflictPolicy::Backup { suffix: String::new() }) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "backup suffix must not be empty"));
+    }
+    let existing = match stats::symlink_metadata(dst) {
+        Ok(metadata) => metadata.file_type(),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(PolicyOutcome::Created),
+        Err(e) => return Err(e),
+    };
+    let in_the_way = || {
+        io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", dst.display()))
+    };
+    match *policy {
+        ConflictPolicy::Fail => Err(in_the_way()),
+        ConflictPolicy::Skip => Ok(PolicyOutcome::Skipped),
+        ConflictPolicy::ReplaceSymlinkOnly if existing.is_symlink() => Ok(PolicyOutcome::Replaced),
+        ConflictPolicy::ReplaceSymlinkOnly => Err(in_the_way()),
+        ConflictPolicy::Overwrite if existing.is_dir() && fs::read_dir(dst)?.next().is_some() => {
+            Err(io::Error::new(io::ErrorKind::DirectoryNotEmpty,
+                               format!("{} is a directory with something in it", dst.display())))
+        }
+        ConflictPolicy::Overwrite => Ok(PolicyOutcome::Replaced),
+        ConflictPolicy::Backup { .. } if existing.is_symlink() => Err(in_the_way()),
+        ConflictPolicy::Backup { ref suffix } => {
+            backup_path(dst, suffix).map(PolicyOutcome::BackedUp)
+        }
+    }
+}
+
+fn replace(builder: &mut SymlinkBuilder, kind: Option<SymlinkKind>, src: &Path, dst: &Path,
+           overwrite: bool) -> io::Result<PolicyOutcome> {
+    // The builder’s options may rule out replacing; find out before anything is removed.
+    builder.replace_existing(true).check_options()?;
+    let existing = match stats::symlink_metadata(dst) {
+        Ok(metadata) => Some(metadata.file_type()),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    match existing {
+        Some(ref file_type) if overwrite && file_type.is_dir() => fs::remove_dir(dst)?,
+        Some(ref file_type) if overwrite && !file_type.is_symlink() => fs::remove_file(dst)?,
+        _ => {}
+    }
+    builder.create(kind, src, dst)?;
+    Ok(if existing.is_some() { PolicyOutcome::Replaced } else { PolicyOutcome::Created })
+}
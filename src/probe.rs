@@ -0,0 +1,71 @@
+// Finding out whether symlinks can be created somewhere, by trying.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use error::raw_os_error;
+use internal;
+use paths;
+
+// The answers so far, by absolute directory.
+static PROBED: Mutex<Option<HashMap<PathBuf, bool>>> = Mutex::new(None);
+
+/// Whether symlinks can be created in the temporary directory.
+///
+/// This is [`symlinks_supported_in`] for [`env::temp_dir()`], which on Unix is `$TMPDIR` if
+/// that’s set and `/tmp` otherwise, and on Windows the first of `%TMP%`, `%TEMP%` and
+/// `%USERPROFILE%` that’s set, or else the Windows directory. That’s only a fair guess at
+/// whether links can be created anywhere else: the temporary directory is often a separate
+/// filesystem (a tmpfs, say), and one filesystem may allow symlinks where another doesn’t. If you
+/// know where the links will go, ask about there instead.
+///
+/// # Errors
+///
+/// As for [`symlinks_supported_in`].
+pub fn symlinks_supported() -> io::Result<bool> {
+    symlinks_supported_in(env::temp_dir())
+}
+
+/// Whether symlinks can be created in `dir`, found out by creating one.
+///
+/// A dangling file symlink with a name no one else will be using is created in `dir` and
+/// removed again straight away. If the platform or filesystem doesn’t support symlinks, or (on
+/// Windows) the process lacks the privilege to create them and Developer Mode is off, the
+/// answer is no; if the link is created, yes.
+///
+/// The answer is remembered for each directory, by its absolute path, for the life of the
+/// process, so asking again costs a lookup rather than a link. Errors aren’t remembered.
+///
+/// # Errors
+///
+/// An error will be returned if creating the link fails for some other reason—`dir` doesn’t
+/// exist, or can’t be written to—since then there’s no telling, or if removing it again fails.
+pub fn symlinks_supported_in<P: AsRef<Path>>(dir: P) -> io::Result<bool> {
+    let dir = paths::absolute(dir.as_ref())?;
+    if let Some(&supported) = lock().get_or_insert_with(HashMap::new).get(&dir) {
+        return Ok(supported);
+    }
+    let supported = probe(&dir)?;
+    lock().get_or_insert_with(HashMap::new).insert(dir, supported);
+    Ok(supported)
+}
+
+fn lock() -> ::std::sync::MutexGuard<'static, Option<HashMap<PathBuf, bool>>> {
+    PROBED.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// Not through create_symlink, so that a probe doesn’t count as a link created.
+fn probe(dir: &Path) -> io::Result<bool> {
+    let link = paths::temp_sibling(&dir.join("symlink-probe"))?;
+    match internal::symlink_file(Path::new("symlink-probe-target"), &link) {
+        Ok(()) => fs::remove_file(&link).map(|()| true),
+        Err(ref e) if e.kind() == io::ErrorKind::Unsupported => Ok(false),
+        // ERROR_PRIVILEGE_NOT_HELD.
+        Err(ref e) if cfg!(windows) && raw_os_error(e) == Some(1314) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
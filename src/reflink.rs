@@ -0,0 +1,75 @@
+// Reflinks, for callers who want a file’s contents at a path without the indirection of a link,
+// and can have them for nearly nothing where the filesystem does copy-on-write.
+
+use std::io;
+use std::path::Path;
+
+#[cfg(unix)]
+use unix::reflink;
+use {check_not_empty, create_symlink, paths, SymlinkKind};
+
+/// How [`reflink_or_symlink_file`] put the file at its destination.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LinkStrategy {
+    /// A copy-on-write clone: an independent file, sharing its data with the source until either
+    /// is written to.
+    Reflink,
+    /// A symlink, because the filesystem can’t clone.
+    Symlink,
+}
+
+/// Clone a file to `dst` copy-on-write where the filesystem can, or else symlink to it.
+///
+/// On filesystems with copy-on-write (btrfs, XFS, bcachefs and ZFS 2.2 on Linux, APFS on macOS) a
+/// reflink costs about as little as a symlink, and what you get is an ordinary file with the
+/// same contents: nothing to dangle if the source goes, nothing to follow, and no way to change
+/// the source by writing through it. That suits content caches well. Where a clone isn’t
+/// possible, this makes the symlink that [`symlink_file`](crate::symlink_file) would, and the
+/// [`LinkStrategy`] says which you got.
+///
+/// - On Linux, the clone is the `FICLONE` ioctl on a newly created file, which is then given the
+///   source’s permissions. Falling back is for the errors meaning the filesystem can’t clone:
+///   `EOPNOTSUPP`, `EXDEV` (source and destination on different filesystems), `EINVAL` and
+///   `ENOTTY`.
+/// - On macOS, it’s `clonefile`, which copies the permissions itself; falling back is for
+///   `ENOTSUP` and `EXDEV`.
+/// - Everywhere else, including Windows (whose block cloning on ReFS this doesn’t attempt), it’s
+///   always a symlink.
+///
+/// As ever, a relative `src` is relative to the link’s directory, and the clone is made from the
+/// same file the symlink would point to. The symlink stores `src` as given.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// use symlink_rs::{reflink_or_symlink_file, LinkStrategy};
+///
+/// match reflink_or_symlink_file("../objects/3f/a1c9", "checkout/README")? {
+///     LinkStrategy::Reflink => println!("cloned"),
+///     LinkStrategy::Symlink => println!("linked"),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Unlike a symlink, a clone needs the source to exist, so where one is attempted any error
+/// opening the source is returned rather than falling back to a dangling link; so is any other
+/// error from the clone. Otherwise
+/// errors are as for [`symlink_file`](crate::symlink_file), including
+/// [`AlreadyExists`](io::ErrorKind::AlreadyExists) if there’s something at `dst` already.
+pub fn reflink_or_symlink_file<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q)
+                                                              -> io::Result<LinkStrategy> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    check_not_empty(src, dst)?;
+    if reflink(&paths::resolve_target(dst, src), dst)? {
+        Ok(LinkStrategy::Reflink)
+    } else {
+        create_symlink(Some(SymlinkKind::File), src, dst).map(|()| LinkStrategy::Symlink)
+    }
+}
+
+#[cfg(not(unix))]
+fn reflink(_src: &Path, _dst: &Path) -> io::Result<bool> {
+    Ok(false)
+}
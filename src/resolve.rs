@@ -0,0 +1,353 @@
+use std::collections::HashSet;
+use std::env;
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use error;
+use internal;
+use paths;
+use stats;
+
+/// The most links [`resolve_symlink`] and friends will follow before giving up, matching Linux’s
+/// `MAXSYMLINKS`.
+pub const MAX_SYMLINK_HOPS: usize = 40;
+
+/// The most components a path along a chain of symlinks may have before [`resolve_symlink`] and
+/// friends give up on it; a [`SymlinkContext`](crate::SymlinkContext) can be given another
+/// bound with [`max_resolved_components`](crate::SymlinkContext::max_resolved_components).
+///
+/// Each hop’s target is joined to the link’s directory as it stands, `..` and all, so a chain of
+/// links with targets like `../../../../x` makes longer and longer paths. A thousand components
+/// is far more than any real layout needs—Linux’s `PATH_MAX` of 4096 bytes allows at most about
+/// 2000—while stopping a hostile chain long before the paths it makes are a problem.
+pub const MAX_RESOLVED_COMPONENTS: usize = 1024;
+
+/// Where a walk along a chain of symlinks ended up.
+pub(crate) struct Walk {
+    /// The last path in the chain: not a symlink, or nonexistent.
+    pub end: PathBuf,
+    /// How many links were followed to get there.
+    pub hops: usize,
+    /// Whether `end` exists.
+    pub exists: bool,
+}
+
+/// Follow the chain of symlinks starting at `path`, calling `visit` on each path in it, starting
+/// with `path` itself.
+///
+/// Relative targets are resolved against the directory containing the link, as the OS would.
+/// Only the final component is followed at each step; symlinks among the parent components are
+/// left to the OS. If the chain revisits a path or exceeds [`MAX_SYMLINK_HOPS`], this fails with
+/// the platform’s “too many levels of symbolic links” error; if a path in it has more than
+/// `max_components` components, with an [`InvalidFilename`](io::ErrorKind::InvalidFilename)
+/// error.
+pub(crate) fn walk_chain<F: FnMut(&Path)>(path: &Path, max_components: usize, mut visit: F)
+                                          -> io::Result<Walk> {
+    let mut current = path.to_owned();
+    let mut seen = HashSet::new();
+    let mut hops = 0;
+    loop {
+        if current.components().count() > max_components {
+            return Err(too_many_components(path, hops, max_components));
+        }
+        visit(&current);
+        let metadata = match stats::symlink_metadata(&current) {
+            Ok(metadata) => metadata,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                return Ok(Walk { end: current, hops, exists: false });
+            }
+            Err(e) => return Err(e),
+        };
+        if !metadata.file_type().is_symlink() {
+            return Ok(Walk { end: current, hops, exists: true });
+        }
+        if hops == MAX_SYMLINK_HOPS || !seen.insert(paths::absolute(&current)?) {
+            return Err(internal::loop_error());
+        }
+        let target = stats::read_link(&current)?;
+        current = paths::resolve_target(&current, &target);
+        hops += 1;
+    }
+}
+
+/// `path`, made absolute, with every symlink among its parent components followed as the OS
+/// would follow it: a `..` after a symlink climbs out of the link’s target, not back to the
+/// directory holding the link. The final component is left as it is, as are components that
+/// don’t exist (or can’t be looked at), which are taken lexically.
+///
+/// This is what says where a link made at `path` would physically end up, for checks that can’t
+/// be fooled by `dir/link/../name`, which `paths::absolute` puts in `dir`. Fails with the
+/// platform’s “too many levels of symbolic links” error after [`MAX_SYMLINK_HOPS`] links.
+pub(crate) fn resolve_parents(path: &Path) -> io::Result<PathBuf> {
+    // Not path::absolute, which on Windows collapses the very `..` components that matter here.
+    let path = if path.is_absolute() { path.to_owned() } else { env::current_dir()?.join(path) };
+    let mut pending: Vec<OsString> =
+        path.components().rev().map(|c| c.as_os_str().to_owned()).collect();
+    let mut resolved = PathBuf::new();
+    let mut hops = 0;
+    while let Some(component) = pending.pop() {
+        if component == "." {
+            continue;
+        }
+        if component == ".." {
+            resolved.pop();
+            continue;
+        }
+        resolved.push(&component);
+        let is_symlink = stats::symlink_metadata(&resolved)
+            .map(|metadata| metadata.file_type().is_symlink()).unwrap_or(false);
+        if pending.is_empty() || !is_symlink {
+            continue;
+        }
+        if hops == MAX_SYMLINK_HOPS {
+            return Err(internal::loop_error());
+        }
+        hops += 1;
+        let target = paths::resolve_target(&resolved, &stats::read_link(&resolved)?);
+        pending.extend(target.components().rev().map(|c| c.as_os_str().to_owned()));
+        resolved = PathBuf::new();
+    }
+    Ok(resolved)
+}
+
+// The error for a chain from start whose hop’th path has more than max components. The path
+// itself is left out of the message, as it’s likely to be enormous.
+fn too_many_components(start: &Path, hop: usize, max: usize) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidFilename,
+                   format!("symlink chain from {} leads to a path of more than {} components \
+                            after {} hops", start.display(), max, hop))
+}
+
+/// Follow a chain of symlinks to its end.
+///
+/// Starting at `path`, this follows symlink after symlink until it reaches something that isn’t
+/// one, and returns the path to that. Relative targets are resolved against the directory
+/// containing each link, so the paths on the way are those the OS would look up; they are not
+/// canonicalized, so relative inputs produce relative outputs. Only the final component is
+/// followed at each step: symlinks in parent directories are left in place.
+///
+/// The end of the chain need not exist: for a dangling link, you get the path it dangles at. (See
+/// [`resolve_symlink_until_missing`] if you want to know where things went wrong.) If `path`
+/// isn’t a symlink at all, you get it back unchanged.
+///
+/// # Errors
+///
+/// An error will be returned if any link in the chain cannot be read, or if the chain loops or is
+/// more than [`MAX_SYMLINK_HOPS`] long (the platform’s “too many levels of symbolic links” error).
+/// An [`InvalidFilename`](io::ErrorKind::InvalidFilename) error will be returned if a path along
+/// the chain has more than [`MAX_RESOLVED_COMPONENTS`] components.
+pub fn resolve_symlink<P: AsRef<Path>>(path: P) -> io::Result<PathBuf> {
+    resolve_symlink_limited(path.as_ref(), MAX_RESOLVED_COMPONENTS)
+}
+
+pub(crate) fn resolve_symlink_limited(path: &Path, max_components: usize) -> io::Result<PathBuf> {
+    walk_chain(path, max_components, |_| ()).map(|walk| walk.end)
+}
+
+/// Follow one symlink, one hop, and say where it leads, as an absolute path.
+///
+/// This is what people usually mean by “where does this link go”: the link’s target, read as
+/// [`fs::read_link`](std::fs::read_link) does, but relative targets resolved against the link’s
+/// directory, as the OS resolves them, and the result made absolute against the working
+/// directory. The path is tidied lexically—`.` and `..` taken out—but not canonicalized, and
+/// whatever is there isn’t looked at: it may be another link (which [`resolve_symlink`] would
+/// go on to follow), or nothing at all.
+///
+/// # Errors
+///
+/// An [`InvalidInput`](io::ErrorKind::InvalidInput) error will be returned if `link` exists but
+/// isn’t a symlink; otherwise, an error will be returned if it can’t be read.
+pub fn follow_one_hop<P: AsRef<Path>>(link: P) -> io::Result<PathBuf> {
+    let link = link.as_ref();
+    let target = stats::read_link(link).map_err(|e| {
+        match stats::symlink_metadata(link) {
+            Ok(ref metadata) if !metadata.file_type().is_symlink() => {
+                error::not_a_symlink(link, "")
+            }
+            _ => e,
+        }
+    })?;
+    paths::absolute(&paths::resolve_target(link, &target))
+}
+
+/// Say where a symlink leads, as a path relative to `base`: a project root, say.
+///
+/// The target is found as [`follow_one_hop`] finds it, and then written relative to `base`
+/// (made absolute against the working directory), with `..` to climb out of `base` as far as
+/// need be, as [`TargetStyle::Relative`](crate::TargetStyle::Relative) writes a target relative
+/// to the link’s directory. This is for showing links to people—`docs/current -> docs/v2`
+/// rather than a path from the root, or one relative to wherever the link happens to be—and,
+/// like `follow_one_hop`, is purely lexical: no symlinks in `base` or along the way are
+/// resolved, and nothing at the target is looked at. The target being `base` itself gives `.`.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// use symlink_rs::target_relative_to;
+///
+/// // With /src/app/docs/current -> v2:
+/// let target = target_relative_to("/src/app/docs/current", "/src/app")?;
+/// assert_eq!(target, std::path::Path::new("docs/v2"));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// As for [`follow_one_hop`], and an [`InvalidInput`](io::ErrorKind::InvalidInput) error if
+/// there’s no relative path from `base` to the target, as between drives on Windows.
+pub fn target_relative_to<P: AsRef<Path>, Q: AsRef<Path>>(link: P, base: Q)
+                                                          -> io::Result<PathBuf> {
+    let target = follow_one_hop(link)?;
+    let base = paths::absolute(base.as_ref())?;
+    paths::relative_to(&target, &base).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput,
+                       format!("symlink target {} can’t be made relative to {}",
+                               target.display(), base.display()))
+    })
+}
+
+/// Resolve `path` as GNU `readlink -e` does: to the canonical absolute path of what it names,
+/// which must exist.
+///
+/// Every symlink in every component is followed, recursively, and `.` and `..` are resolved
+/// physically as they’re met, so `link/..` is the parent of whatever `link` leads to, not the
+/// directory `link` is in. The result has no symlinks, `.` or `..` in it, and is absolute. Every
+/// component must exist, the last included: that’s one respect in which this is stricter than
+/// `readlink -f`, which lets the last be missing, and the same as `readlink -e`. Callers after
+/// `-f`’s leniency, or a path that’s never been canonicalized, want [`resolve_symlink`], which
+/// only follows the last component, does so lexically, and lets the end of the chain be
+/// missing.
+///
+/// This is [`fs::canonicalize`](std::fs::canonicalize), but for what it gives on Windows: a
+/// verbatim path, `\\?\C:\dir\file`, which few programs know to show as `C:\dir\file`. The
+/// prefix is taken off here, becoming `C:\…` or `\\server\share\…`, wherever Windows would read
+/// the plain path as the same one; a path with a name ending in a dot or space, or as long as
+/// `MAX_PATH`, is left verbatim, as that’s the only way of naming it exactly.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// use symlink_rs::readlink_f;
+///
+/// // With /usr/bin/python3 -> python3.12, and the working directory /usr/share:
+/// assert_eq!(readlink_f("../bin/python3")?, std::path::Path::new("/usr/bin/python3.12"));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// A [`NotFound`](io::ErrorKind::NotFound) error will be returned if any component of `path`,
+/// or of a target on the way, doesn’t exist; the platform’s “too many levels of symbolic links”
+/// error if a chain loops; and other errors if a component can’t be looked at.
+pub fn readlink_f<P: AsRef<Path>>(path: P) -> io::Result<PathBuf> {
+    Ok(paths::presentable(&path.as_ref().canonicalize()?))
+}
+
+/// List every path in a chain of symlinks, in order.
+///
+/// This returns `path` itself, then the path each link leads to, ending with the first path that
+/// isn’t a symlink (or doesn’t exist), so a path that isn’t a symlink gives a list of one. The
+/// paths are those [`resolve_symlink`] walks through, resolved against each link’s directory but
+/// not canonicalized. It’s meant for showing people how a multi-hop structure actually fits
+/// together.
+///
+/// # Errors
+///
+/// As for [`resolve_symlink`], except that when the chain loops the error’s message lists the
+/// paths seen, up to and including the first repeated one. (The error’s kind is still the
+/// platform’s: `ELOOP` on Unix, whose kind is `FilesystemLoop`—not yet nameable on stable Rust—and
+/// [`Other`](io::ErrorKind::Other) elsewhere.)
+pub fn symlink_chain<P: AsRef<Path>>(path: P) -> io::Result<Vec<PathBuf>> {
+    symlink_chain_limited(path.as_ref(), MAX_RESOLVED_COMPONENTS)
+}
+
+pub(crate) fn symlink_chain_limited(path: &Path, max_components: usize)
+                                    -> io::Result<Vec<PathBuf>> {
+    let mut chain = vec![];
+    match walk_chain(path, max_components, |path| chain.push(path.to_owned())) {
+        Ok(_) => Ok(chain),
+        Err(e) if looped(&chain) => {
+            let hops = chain.iter().map(|p| p.display().to_string()).collect::<Vec<_>>();
+            Err(error::with_context(e.kind(), format!("symlink chain loops: {}", hops.join(" → ")),
+                                    e))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+// Whether walk_chain gave up on the chain it visited because it loops (or is too long), rather
+// than because of some other error.
+fn looped(chain: &[PathBuf]) -> bool {
+    let absolute = |path: &PathBuf| paths::absolute(path).ok();
+    match chain.split_last() {
+        Some(_) if chain.len() > MAX_SYMLINK_HOPS => true,
+        Some((last, rest)) => {
+            let last = absolute(last);
+            last.is_some() && rest.iter().any(|path| absolute(path) == last)
+        }
+        None => false,
+    }
+}
+
+/// The result of [`resolve_symlink_until_missing`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    /// Every link in the chain resolved; this is the path of its final, existing, non-symlink end.
+    Resolved(PathBuf),
+    /// The chain is broken: at some point there’s nothing at the path it leads to.
+    Broken {
+        /// How many links were followed before reaching the missing path: 0 means the path passed
+        /// in doesn’t exist at all, 1 means its target doesn’t exist, and so forth.
+        hop: usize,
+        /// The path that doesn’t exist.
+        path: PathBuf,
+        /// The longest prefix of `path` that does exist. (Empty if not even the first component
+        /// of a relative path exists.)
+        existing: PathBuf,
+        /// The first component of `path`, after `existing`, that doesn’t exist. This is the thing
+        /// to go and create (or the place to fix the link that points here).
+        missing: OsString,
+    },
+}
+
+/// Follow a chain of symlinks, reporting where it breaks.
+///
+/// This is [`resolve_symlink`] for diagnostics: rather than just returning the nonexistent
+/// path a broken chain leads to, it says how far along the chain it was, and which component of
+/// the path was missing.
+///
+/// ```no_run
+/// use symlink_rs::{resolve_symlink_until_missing, Resolution};
+/// # fn main() -> std::io::Result<()> {
+/// if let Resolution::Broken { hop, existing, missing, .. } =
+///     resolve_symlink_until_missing("config/current")?
+/// {
+///     println!("hop {}: {} has no {:?}", hop, existing.display(), missing);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// As for [`resolve_symlink`].
+pub fn resolve_symlink_until_missing<P: AsRef<Path>>(path: P) -> io::Result<Resolution> {
+    let walk = walk_chain(path.as_ref(), MAX_RESOLVED_COMPONENTS, |_| ())?;
+    if walk.exists {
+        return Ok(Resolution::Resolved(walk.end));
+    }
+    // Find the longest prefix that exists. Parent components are looked up following symlinks,
+    // because that’s what the OS did when it failed to find the whole thing.
+    let mut existing = walk.end.clone();
+    let mut missing = OsString::new();
+    while let Some(name) = existing.file_name() {
+        missing = name.to_owned();
+        existing.pop();
+        let probe = if existing.as_os_str().is_empty() { Path::new(".") } else { &existing };
+        if probe.exists() {
+            break;
+        }
+    }
+    Ok(Resolution::Broken { hop: walk.hops, path: walk.end, existing, missing })
+}
@@ -0,0 +1,96 @@
+//! Per-thread counts of the filesystem calls this crate makes (feature `stats`).
+
+use std::cell::Cell;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+thread_local! {
+    static COUNTS: Cell<SymlinkStats> = const { Cell::new(SymlinkStats { creates: 0, stats: 0,
+                                                                        removes: 0 }) };
+}
+
+/// How many filesystem calls of each sort this crate has made on the current thread.
+///
+/// This is for profiling: run a batch of operations, then [`take`](Self::take) the counts to see
+/// what they cost. It’s most telling on Windows, where [`symlink_auto`](crate::symlink_auto) has
+/// to look at the target before it can create a link, and the ensure functions look at what’s
+/// there already. The counts are attempts, successful or not.
+///
+/// They’re kept per thread, so that concurrent batches don’t muddle each other’s; the odd look at
+/// a target that [`SymlinkBuilder::classify_timeout`](crate::SymlinkBuilder::classify_timeout)
+/// moves to another thread is counted on the thread that asked for it. Calls to the mock
+/// (feature `mock`) aren’t counted, not being filesystem calls. Unlike the counters of the
+/// `metrics` feature, which are for the life of the process, these are meant to be taken and
+/// reset as you go.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use symlink_rs::SymlinkStats;
+///
+/// SymlinkStats::take();
+/// # let dir = std::env::temp_dir().join("symlink-crate-stats-doctest");
+/// # let _ = std::fs::remove_dir_all(&dir);
+/// # std::fs::create_dir(&dir)?;
+/// # let link = dir.join("link");
+/// symlink_rs::symlink_file("target", &link)?;
+/// symlink_rs::remove_symlink_file(&link)?;
+/// let stats = SymlinkStats::take();
+/// assert_eq!((stats.creates, stats.removes), (1, 1));
+/// # std::fs::remove_dir(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SymlinkStats {
+    /// Symlinks (and junctions and mount points) created.
+    pub creates: u64,
+    /// Looks at the filesystem: stat, lstat and readlink, or what Windows has instead.
+    pub stats: u64,
+    /// Symlinks (and mount points) removed.
+    pub removes: u64,
+}
+
+impl SymlinkStats {
+    /// The counts for the current thread since they were last taken, resetting them to zero.
+    pub fn take() -> SymlinkStats {
+        COUNTS.with(|counts| counts.replace(SymlinkStats::default()))
+    }
+}
+
+fn bump(f: fn(&mut SymlinkStats)) {
+    COUNTS.with(|counts| {
+        let mut current = counts.get();
+        f(&mut current);
+        counts.set(current);
+    });
+}
+
+pub(crate) fn create() {
+    bump(|s| s.creates += 1);
+}
+
+pub(crate) fn stat() {
+    bump(|s| s.stats += 1);
+}
+
+pub(crate) fn remove() {
+    bump(|s| s.removes += 1);
+}
+
+// Counted versions of the std functions the crate looks at the filesystem with.
+
+pub(crate) fn metadata<P: AsRef<Path>>(path: P) -> io::Result<fs::Metadata> {
+    stat();
+    fs::metadata(path)
+}
+
+pub(crate) fn symlink_metadata<P: AsRef<Path>>(path: P) -> io::Result<fs::Metadata> {
+    stat();
+    fs::symlink_metadata(path)
+}
+
+pub(crate) fn read_link<P: AsRef<Path>>(path: P) -> io::Result<PathBuf> {
+    stat();
+    fs::read_link(path)
+}
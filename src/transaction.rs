@@ -0,0 +1,323 @@
+// A batch of link operations that can be undone: each one journaled with what it takes to reverse
+// it, for installers that want all of a batch or none of it.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ensure::{ensure_symlink_detailed, CreateOutcome, ExistingEntry};
+use error;
+use events::{EventOp, EventOutcome, SymlinkEvent};
+#[cfg(windows)]
+use internal;
+use {create_symlink, paths, remove_symlink_auto, remove_symlink_dir, remove_symlink_file, stats,
+     LinkKind, SymlinkBuilder, SymlinkKind};
+
+/// One change a [`Transaction`] made, with what it took to be able to undo it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum JournalEntry {
+    /// A symlink was created where there was nothing.
+    Created {
+        /// The link.
+        link: PathBuf,
+        /// Its target, as given.
+        target: PathBuf,
+    },
+    /// A symlink was repointed.
+    Repointed {
+        /// The link.
+        link: PathBuf,
+        /// Its new target, as given.
+        target: PathBuf,
+        /// Its old target, as it was stored.
+        previous: PathBuf,
+        /// Its old kind, on Windows, where file and directory symlinks differ; `None` elsewhere.
+        previous_kind: Option<SymlinkKind>,
+    },
+    /// A symlink was removed.
+    Removed {
+        /// The link.
+        link: PathBuf,
+        /// Its target, as it was stored.
+        target: PathBuf,
+        /// Its kind, on Windows; `None` elsewhere.
+        kind: Option<SymlinkKind>,
+    },
+}
+
+/// A batch of link operations, journaled so that they can be [rolled back](Self::rollback).
+///
+/// Each creation, repointing and removal made through a transaction is recorded with what it
+/// takes to reverse it—the old target of a link repointed or removed, and on Windows its
+/// kind—and [`rollback`](Self::rollback) undoes them, last first: a link created is removed,
+/// one repointed is pointed back, and one removed is recreated. That suits an installer that
+/// makes a batch of links, then does something that may fail, and wants to leave things as they
+/// were if it does.
+///
+/// It deals in symlinks only, and that sets its limits. None of its operations replaces a real
+/// file or directory (creating over one, or repointing one, is an error, and removing one is
+/// refused), so there’s nothing of that kind it would need to put back; what’s done to the
+/// filesystem another way—a [`ConflictPolicy::Overwrite`](crate::ConflictPolicy::Overwrite),
+/// say, or a backup moved aside—isn’t in the journal, and can’t be undone by it. Nor is anything
+/// locked: if something else changes a link between the operation and the rollback, undoing it
+/// may fail, and a link created that has since been repointed by someone else is left alone
+/// rather than removed (an error says so). And a rollback is as fallible as the operations
+/// were; see [`rollback`](Self::rollback) for what happens when it fails part way.
+///
+/// Everything attempted through it, and each undoing, is also recorded as a [`SymlinkEvent`],
+/// failures included, for [`events`](Self::events) to return—for a test to assert on, or a
+/// report.
+///
+/// Dropping a transaction keeps its changes, as does [`commit`](Self::commit), which says so
+/// more plainly: there’s no rolling back on drop, which couldn’t report its errors.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// # fn run_migrations() -> std::io::Result<()> { Ok(()) }
+/// use symlink_rs::Transaction;
+///
+/// let mut tx = Transaction::new();
+/// tx.ensure_symlink_dir("releases/42", "current")?;
+/// tx.symlink_file("releases/42/app.conf", "config/app.conf")?;
+/// tx.remove_symlink_file("config/legacy.conf")?;
+/// match run_migrations() {
+///     Ok(()) => tx.commit(),
+///     Err(e) => {
+///         tx.rollback()?;
+///         return Err(e);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct Transaction {
+    journal: Vec<JournalEntry>,
+    events: Vec<SymlinkEvent>,
+}
+
+impl Transaction {
+    /// A transaction with nothing done yet.
+    pub fn new() -> Transaction {
+        Transaction::default()
+    }
+
+    /// What’s been done so far (and not rolled back), first first.
+    pub fn journal(&self) -> &[JournalEntry] {
+        &self.journal
+    }
+
+    /// Every operation attempted so far, and every undoing by [`rollback`](Self::rollback),
+    /// oldest first, whether it succeeded or not.
+    ///
+    /// Unlike the [`journal`](Self::journal), this keeps what was rolled back, and what failed.
+    pub fn events(&self) -> Vec<SymlinkEvent> {
+        self.events.clone()
+    }
+
+    /// Create a symlink to a file, as [`symlink_file`](crate::symlink_file) does.
+    pub fn symlink_file<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, src: P, dst: Q)
+                                                        -> io::Result<()> {
+        self.create(Some(SymlinkKind::File), src.as_ref(), dst.as_ref())
+    }
+
+    /// Create a symlink to a directory, as [`symlink_dir`](crate::symlink_dir) does.
+    pub fn symlink_dir<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, src: P, dst: Q)
+                                                       -> io::Result<()> {
+        self.create(Some(SymlinkKind::Dir), src.as_ref(), dst.as_ref())
+    }
+
+    /// Create a symlink (non-preferred way), as [`symlink_auto`](crate::symlink_auto) does.
+    pub fn symlink_auto<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, src: P, dst: Q)
+                                                        -> io::Result<()> {
+        self.create(None, src.as_ref(), dst.as_ref())
+    }
+
+    /// Make sure there’s a symlink to a file at `dst` pointing to `src`, creating or repointing
+    /// it as [`ensure_symlink_file`](crate::ensure_symlink_file) does.
+    pub fn ensure_symlink_file<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, src: P, dst: Q)
+                                                               -> io::Result<CreateOutcome> {
+        self.ensure(Some(SymlinkKind::File), src.as_ref(), dst.as_ref())
+    }
+
+    /// Make sure there’s a symlink to a directory at `dst` pointing to `src`, creating or
+    /// repointing it as [`ensure_symlink_dir`](crate::ensure_symlink_dir) does.
+    pub fn ensure_symlink_dir<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, src: P, dst: Q)
+                                                              -> io::Result<CreateOutcome> {
+        self.ensure(Some(SymlinkKind::Dir), src.as_ref(), dst.as_ref())
+    }
+
+    /// Make sure there’s a symlink at `dst` pointing to `src`, creating or repointing it as
+    /// [`ensure_symlink_auto`](crate::ensure_symlink_auto) does.
+    pub fn ensure_symlink_auto<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, src: P, dst: Q)
+                                                               -> io::Result<CreateOutcome> {
+        self.ensure(None, src.as_ref(), dst.as_ref())
+    }
+
+    /// Remove a file symlink, as [`remove_symlink_file`](crate::remove_symlink_file) does.
+    pub fn remove_symlink_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.remove(LinkKind::File, path.as_ref(), |path| remove_symlink_file(path))
+    }
+
+    /// Remove a directory symlink, as [`remove_symlink_dir`](crate::remove_symlink_dir) does.
+    pub fn remove_symlink_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.remove(LinkKind::Dir, path.as_ref(), |path| remove_symlink_dir(path))
+    }
+
+    /// Remove a symlink of either kind, as [`remove_symlink_auto`](crate::remove_symlink_auto)
+    /// does.
+    pub fn remove_symlink_auto<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.remove(LinkKind::Auto, path.as_ref(), |path| remove_symlink_auto(path))
+    }
+
+    /// Keep everything done, and forget how to undo it.
+    pub fn commit(self) {}
+
+    /// Undo everything done, last first.
+    ///
+    /// Each entry is undone and dropped from the journal in turn. If undoing one fails, the
+    /// rollback stops there and returns the error, with that entry and those before it still in
+    /// the [`journal`](Self::journal), so that the cause can be dealt with and the rollback
+    /// tried again. A link this created is only removed if it still points where it was made
+    /// to, and a link this repointed only pointed back if it still points where this pointed it;
+    /// otherwise something else has changed it since, and it’s left for the caller, with an
+    /// [`Other`](io::ErrorKind::Other) error saying so.
+    ///
+    /// # Errors
+    ///
+    /// As above, and as for the operations that do the undoing.
+    pub fn rollback(&mut self) -> io::Result<()> {
+        while let Some(entry) = self.journal.pop() {
+            let result = undo(&entry);
+            self.events.push(undo_event(&entry, &result));
+            if let Err(e) = result {
+                self.journal.push(entry);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    fn create(&mut self, kind: Option<SymlinkKind>, src: &Path, dst: &Path) -> io::Result<()> {
+        let result = create_symlink(kind, src, dst);
+        self.events.push(SymlinkEvent::new(EventOp::Create, requested(kind), dst, Some(src),
+                                           &result, |()| EventOutcome::Created));
+        result?;
+        self.journal.push(JournalEntry::Created { link: dst.to_owned(), target: src.to_owned() });
+        Ok(())
+    }
+
+    fn ensure(&mut self, kind: Option<SymlinkKind>, src: &Path, dst: &Path)
+              -> io::Result<CreateOutcome> {
+        // The old kind has to be found before the old link goes; where there isn’t one, or it
+        // can’t be told, the rollback will make do with symlink_auto’s guess.
+        let previous_kind = link_kind(dst).unwrap_or(None);
+        let previous_target = stats::read_link(dst).ok();
+        let result = ensure_symlink_detailed(kind, src, dst);
+        self.events.push(SymlinkEvent::new(EventOp::Ensure, requested(kind), dst, Some(src),
+                                           &result, |outcome| outcome.action.into()));
+        let outcome = match result {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                // A repoint that failed part way, where the old link couldn’t be put back, has
+                // still removed it; so the rollback has to know, to put it back itself.
+                if let Some(target) = previous_target {
+                    if stats::symlink_metadata(dst).is_err() {
+                        self.journal.push(JournalEntry::Removed { link: dst.to_owned(), target,
+                                                                  kind: previous_kind });
+                    }
+                }
+                return Err(e);
+            }
+        };
+        let (link, target) = (dst.to_owned(), src.to_owned());
+        match (outcome.action, outcome.before) {
+            (CreateOutcome::Created, _) => {
+                self.journal.push(JournalEntry::Created { link, target })
+            }
+            (CreateOutcome::Repointed, Some(ExistingEntry::Symlink { target: previous })) => {
+                self.journal.push(JournalEntry::Repointed { link, target, previous, previous_kind })
+            }
+            _ => {}
+        }
+        Ok(outcome.action)
+    }
+
+    fn remove<F>(&mut self, kind: LinkKind, path: &Path, remove: F) -> io::Result<()>
+        where F: FnOnce(&Path) -> io::Result<()>
+    {
+        let result = remove_journaled(path, remove);
+        self.events.push(SymlinkEvent::new(EventOp::Remove, kind, path, None, &result,
+                                           |_| EventOutcome::Removed));
+        self.journal.push(result?);
+        Ok(())
+    }
+}
+
+fn remove_journaled<F>(path: &Path, remove: F) -> io::Result<JournalEntry>
+    where F: FnOnce(&Path) -> io::Result<()>
+{
+    if !stats::symlink_metadata(path)?.file_type().is_symlink() {
+        return Err(error::not_a_symlink(path, ""));
+    }
+    let target = stats::read_link(path)?;
+    let kind = link_kind(path)?;
+    remove(path)?;
+    Ok(JournalEntry::Removed { link: path.to_owned(), target, kind })
+}
+
+// The kind of link asked for, None being auto.
+fn requested(kind: Option<SymlinkKind>) -> LinkKind {
+    match kind {
+        Some(SymlinkKind::File) => LinkKind::File,
+        Some(SymlinkKind::Dir) => LinkKind::Dir,
+        None => LinkKind::Auto,
+    }
+}
+
+fn undo_event(entry: &JournalEntry, result: &io::Result<()>) -> SymlinkEvent {
+    let (kind, link, target, outcome) = match *entry {
+        JournalEntry::Created { ref link, ref target } => {
+            (None, link, target, EventOutcome::Removed)
+        }
+        JournalEntry::Repointed { ref link, ref previous, previous_kind, .. } => {
+            (previous_kind, link, previous, EventOutcome::Repointed)
+        }
+        JournalEntry::Removed { ref link, ref target, kind } => {
+            (kind, link, target, EventOutcome::Created)
+        }
+    };
+    SymlinkEvent::new(EventOp::Undo, requested(kind), link, Some(target), result, |()| outcome)
+}
+
+fn undo(entry: &JournalEntry) -> io::Result<()> {
+    match *entry {
+        JournalEntry::Created { ref link, ref target } => {
+            check_unchanged(link, target)?;
+            remove_symlink_auto(link)
+        }
+        JournalEntry::Repointed { ref link, ref target, ref previous, previous_kind } => {
+            check_unchanged(link, target)?;
+            SymlinkBuilder::new().replace_existing(true).create(previous_kind, previous, link)
+        }
+        JournalEntry::Removed { ref link, ref target, kind } => create_symlink(kind, target, link),
+    }
+}
+
+fn check_unchanged(link: &Path, target: &Path) -> io::Result<()> {
+    if paths::targets_equivalent(&stats::read_link(link)?, target) {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("{} has been changed since this transaction made it, so it \
+                                      was left alone", link.display())))
+    }
+}
+
+#[cfg(windows)]
+fn link_kind(path: &Path) -> io::Result<Option<SymlinkKind>> {
+    internal::symlink_kind(path)
+}
+
+#[cfg(not(windows))]
+fn link_kind(_path: &Path) -> io::Result<Option<SymlinkKind>> {
+    Ok(None)
+}
@@ -0,0 +1,167 @@
+// The handful of libc declarations the directory-fd machinery, directory creation, attribute
+// copying and reflinks need. As on Windows, I’d rather copy a few constants than take a
+// dependency for them; but unlike the Windows ones these vary from platform to platform (and on
+// Linux, from architecture to architecture), so they’re only provided where I’ve checked them.
+// Elsewhere SUPPORTED is false and the callers bail out with an Unsupported error rather than
+// passing garbage flags to open.
+
+#![allow(non_camel_case_types)]
+
+use std::os::raw::{c_char, c_int, c_long, c_void};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod consts {
+    use std::os::raw::c_int;
+    pub const SUPPORTED: bool = true;
+    pub const AT_FDCWD: c_int = -100;
+    pub const O_CLOEXEC: c_int = 0o2000000;
+    #[cfg(any(target_arch = "arm", target_arch = "aarch64",
+              target_arch = "powerpc", target_arch = "powerpc64"))]
+    pub const O_DIRECTORY: c_int = 0o40000;
+    #[cfg(any(target_arch = "arm", target_arch = "aarch64",
+              target_arch = "powerpc", target_arch = "powerpc64"))]
+    pub const O_NOFOLLOW: c_int = 0o100000;
+    #[cfg(not(any(target_arch = "arm", target_arch = "aarch64",
+                  target_arch = "powerpc", target_arch = "powerpc64")))]
+    pub const O_DIRECTORY: c_int = 0o200000;
+    #[cfg(not(any(target_arch = "arm", target_arch = "aarch64",
+                  target_arch = "powerpc", target_arch = "powerpc64")))]
+    pub const O_NOFOLLOW: c_int = 0o400000;
+    // symlink(2): “EPERM: The filesystem containing linkpath does not support the creation of
+    // symbolic links.” EOPNOTSUPP is numbered differently on mips and sparc, so there we
+    // just make do with EPERM.
+    #[cfg(not(any(target_arch = "mips", target_arch = "mips64",
+                  target_arch = "sparc", target_arch = "sparc64")))]
+    pub const E_SYMLINK_UNSUPPORTED: &[c_int] = &[1, 95];
+    #[cfg(any(target_arch = "mips", target_arch = "mips64",
+              target_arch = "sparc", target_arch = "sparc64"))]
+    pub const E_SYMLINK_UNSUPPORTED: &[c_int] = &[1];
+    #[cfg(not(any(target_arch = "mips", target_arch = "mips64",
+                  target_arch = "sparc", target_arch = "sparc64")))]
+    pub const ELOOP: c_int = 40;
+    #[cfg(any(target_arch = "mips", target_arch = "mips64"))]
+    pub const ELOOP: c_int = 90;
+    #[cfg(any(target_arch = "sparc", target_arch = "sparc64"))]
+    pub const ELOOP: c_int = 62;
+    pub const AT_SYMLINK_NOFOLLOW: c_int = 0x100;
+    pub type time_t = ::std::os::raw::c_long;
+    pub type mode_t = u32;
+    // _IOW(0x94, 9, int), whose direction bits are the other way round on these.
+    #[cfg(any(target_arch = "powerpc", target_arch = "powerpc64", target_arch = "mips",
+              target_arch = "mips64", target_arch = "sparc", target_arch = "sparc64"))]
+    pub const FICLONE: ::std::os::raw::c_ulong = 0x80049409;
+    #[cfg(not(any(target_arch = "powerpc", target_arch = "powerpc64", target_arch = "mips",
+                  target_arch = "mips64", target_arch = "sparc", target_arch = "sparc64")))]
+    pub const FICLONE: ::std::os::raw::c_ulong = 0x40049409;
+    // ioctl_ficlone(2): EOPNOTSUPP for a filesystem without reflinks, EXDEV across filesystems,
+    // EINVAL for a filesystem that can’t clone these particular files, and ENOTTY for one that
+    // knows nothing of the ioctl at all.
+    #[cfg(not(any(target_arch = "mips", target_arch = "mips64",
+                  target_arch = "sparc", target_arch = "sparc64")))]
+    pub const E_CLONE_UNSUPPORTED: &[c_int] = &[95, 18, 22, 25];
+    #[cfg(any(target_arch = "mips", target_arch = "mips64"))]
+    pub const E_CLONE_UNSUPPORTED: &[c_int] = &[122, 18, 22, 25];
+    #[cfg(any(target_arch = "sparc", target_arch = "sparc64"))]
+    pub const E_CLONE_UNSUPPORTED: &[c_int] = &[45, 18, 22, 25];
+    // EOPNOTSUPP, for a filesystem without extended attributes, and ERANGE, for a buffer too
+    // small for them.
+    #[cfg(not(any(target_arch = "mips", target_arch = "mips64",
+                  target_arch = "sparc", target_arch = "sparc64")))]
+    pub const ENOTSUP: c_int = 95;
+    #[cfg(any(target_arch = "mips", target_arch = "mips64"))]
+    pub const ENOTSUP: c_int = 122;
+    #[cfg(any(target_arch = "sparc", target_arch = "sparc64"))]
+    pub const ENOTSUP: c_int = 45;
+    pub const ERANGE: c_int = 34;
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+mod consts {
+    use std::os::raw::c_int;
+    pub const SUPPORTED: bool = true;
+    pub const AT_FDCWD: c_int = -2;
+    pub const O_CLOEXEC: c_int = 0x1000000;
+    pub const O_DIRECTORY: c_int = 0x100000;
+    pub const O_NOFOLLOW: c_int = 0x100;
+    // ENOTSUP and EOPNOTSUPP.
+    pub const E_SYMLINK_UNSUPPORTED: &[c_int] = &[45, 102];
+    pub const ELOOP: c_int = 62;
+    pub const AT_SYMLINK_NOFOLLOW: c_int = 0x20;
+    pub type time_t = ::std::os::raw::c_long;
+    pub type mode_t = u16;
+    // clonefile(2): ENOTSUP for a filesystem without clones, EXDEV across filesystems.
+    pub const E_CLONE_UNSUPPORTED: &[c_int] = &[45, 18];
+}
+
+#[cfg(target_os = "freebsd")]
+mod consts {
+    use std::os::raw::c_int;
+    pub const SUPPORTED: bool = true;
+    pub const AT_FDCWD: c_int = -100;
+    pub const O_CLOEXEC: c_int = 0x100000;
+    pub const O_DIRECTORY: c_int = 0x20000;
+    pub const O_NOFOLLOW: c_int = 0x100;
+    // EOPNOTSUPP.
+    pub const E_SYMLINK_UNSUPPORTED: &[c_int] = &[45];
+    pub const ELOOP: c_int = 62;
+    pub const AT_SYMLINK_NOFOLLOW: c_int = 0x200;
+    #[cfg(target_arch = "x86")]
+    pub type time_t = i32;
+    #[cfg(not(target_arch = "x86"))]
+    pub type time_t = i64;
+    pub type mode_t = u16;
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos",
+              target_os = "ios", target_os = "freebsd")))]
+mod consts {
+    use std::os::raw::c_int;
+    pub const SUPPORTED: bool = false;
+    pub const AT_FDCWD: c_int = 0;
+    pub const O_CLOEXEC: c_int = 0;
+    pub const O_DIRECTORY: c_int = 0;
+    pub const O_NOFOLLOW: c_int = 0;
+    pub const E_SYMLINK_UNSUPPORTED: &[c_int] = &[];
+    pub const ELOOP: c_int = 0;
+    pub const AT_SYMLINK_NOFOLLOW: c_int = 0;
+    pub type time_t = i64;
+    pub type mode_t = u32;
+}
+
+pub use self::consts::*;
+
+pub const O_RDONLY: c_int = 0;
+
+#[repr(C)]
+pub struct timespec {
+    pub tv_sec: time_t,
+    pub tv_nsec: c_long,
+}
+
+extern "C" {
+    pub fn openat(dirfd: c_int, pathname: *const c_char, flags: c_int, ...) -> c_int;
+    pub fn mkdirat(dirfd: c_int, pathname: *const c_char, mode: mode_t) -> c_int;
+    pub fn unlinkat(dirfd: c_int, pathname: *const c_char, flags: c_int) -> c_int;
+    pub fn symlinkat(target: *const c_char, newdirfd: c_int, linkpath: *const c_char) -> c_int;
+    pub fn utimensat(dirfd: c_int, pathname: *const c_char, times: *const timespec, flags: c_int)
+                     -> c_int;
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+extern "C" {
+    pub fn ioctl(fd: c_int, request: ::std::os::raw::c_ulong, ...) -> c_int;
+}
+
+#[cfg(target_os = "linux")]
+extern "C" {
+    pub fn llistxattr(path: *const c_char, list: *mut c_char, size: usize) -> isize;
+    pub fn lgetxattr(path: *const c_char, name: *const c_char, value: *mut c_void, size: usize)
+                     -> isize;
+    pub fn lsetxattr(path: *const c_char, name: *const c_char, value: *const c_void, size: usize,
+                     flags: c_int) -> c_int;
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+extern "C" {
+    pub fn clonefile(src: *const c_char, dst: *const c_char, flags: u32) -> c_int;
+}
@@ -0,0 +1,425 @@
+// The directory-fd machinery: opening directories one component at a time with O_NOFOLLOW, and
+// then doing things relative to the resulting file descriptor with the *at family of calls, so
+// that nothing gets to re-resolve a path behind our back.
+
+use std::ffi::{CString, OsStr};
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{self as unix_fs, DirBuilderExt, MetadataExt, PermissionsExt};
+use std::os::unix::io::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+use std::path::{Component, Path, PathBuf};
+
+#[cfg(target_os = "linux")]
+use error;
+
+mod c;
+
+fn cstr(s: &OsStr) -> io::Result<CString> {
+    CString::new(s.as_bytes()).map_err(|_| {
+        io::Error::new(io::ErrorKind::InvalidInput, "paths cannot contain NULs")
+    })
+}
+
+fn unsupported() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported,
+                   "directory file descriptors are not supported on this platform")
+}
+
+/// Normalise a symlink creation error, so that “this filesystem doesn’t do symlinks” always comes
+/// out as `ErrorKind::Unsupported`, whichever errno the platform chose to express it with. (ENOSYS
+/// the standard library already takes care of.) The original error is kept as the payload.
+pub fn normalize_error(e: io::Error) -> io::Error {
+    match e.raw_os_error() {
+        Some(code) if c::E_SYMLINK_UNSUPPORTED.contains(&code) => {
+            io::Error::new(io::ErrorKind::Unsupported, e)
+        }
+        _ => e,
+    }
+}
+
+/// The error for a chain of symlinks that loops (or is just too long), as the OS would report it.
+pub fn loop_error() -> io::Error {
+    if c::SUPPORTED {
+        io::Error::from_raw_os_error(c::ELOOP)
+    } else {
+        io::Error::other("too many levels of symbolic links")
+    }
+}
+
+/// A directory opened without following symlinks.
+pub struct Dir {
+    fd: OwnedFd,
+}
+
+impl Dir {
+    /// Open the directory at `path`, refusing to follow a symlink in any of its components.
+    ///
+    /// Each component is opened relative to the previous one with `O_NOFOLLOW`, so if any of them
+    /// is a symlink (or is swapped for one while we’re walking) this fails.
+    pub fn open_nofollow(path: &Path) -> io::Result<Dir> {
+        match walk(None, path)? {
+            Some(dir) => Ok(dir),
+            None => openat_dir(c::AT_FDCWD, OsStr::new(".")),
+        }
+    }
+
+    /// Open the directory at `path`, following symlinks as usual.
+    pub fn open(path: &Path) -> io::Result<Dir> {
+        if !c::SUPPORTED {
+            return Err(unsupported());
+        }
+        let path = cstr(path.as_os_str())?;
+        let fd = unsafe {
+            c::openat(c::AT_FDCWD, path.as_ptr(), c::O_RDONLY | c::O_DIRECTORY | c::O_CLOEXEC)
+        };
+        if fd == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(Dir { fd: unsafe { OwnedFd::from_raw_fd(fd) } })
+        }
+    }
+
+    /// Open the directory at `path` beneath `base`, refusing to follow a symlink in any of its
+    /// components or to leave `base` by way of `..` or an absolute path.
+    pub fn open_beneath(base: BorrowedFd, path: &Path) -> io::Result<Dir> {
+        if path.has_root() || path.components().any(|c| c == Component::ParentDir) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      format!("path {} escapes the directory", path.display())));
+        }
+        match walk(Some(base), path)? {
+            Some(dir) => Ok(dir),
+            None => Ok(Dir { fd: base.try_clone_to_owned()? }),
+        }
+    }
+
+    /// Create the directory `name` in this directory with `mode`, or accept one that’s already
+    /// there, and open it without following a symlink.
+    ///
+    /// The open is what makes this safe: if the new directory is swapped for a symlink before it’s
+    /// opened, `O_NOFOLLOW` refuses it, so the caller never carries on into wherever the symlink
+    /// leads. It also means that for an exact mode, the directory is changed through the
+    /// descriptor, not by path; and only if this created it.
+    fn mkdir(&self, name: &OsStr, mode: DirMode) -> io::Result<Dir> {
+        let cname = cstr(name)?;
+        let created = unsafe {
+            c::mkdirat(self.fd.as_raw_fd(), cname.as_ptr(), mode.mode as c::mode_t)
+        } != -1;
+        if !created {
+            let e = io::Error::last_os_error();
+            if e.kind() != io::ErrorKind::AlreadyExists {
+                return Err(e);
+            }
+        }
+        let dir = openat_dir(self.fd.as_raw_fd(), name)?;
+        if created && mode.exact {
+            fs::File::from(dir.fd.try_clone()?)
+                .set_permissions(fs::Permissions::from_mode(mode.mode))?;
+        }
+        Ok(dir)
+    }
+
+    /// Remove the symlink (or other non-directory) named `name` in this directory.
+    pub fn unlink(&self, name: &OsStr) -> io::Result<()> {
+        let name = cstr(name)?;
+        if unsafe { c::unlinkat(self.fd.as_raw_fd(), name.as_ptr(), 0) } == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Create a symlink named `name` in this directory, pointing at `src`. (`name` may in fact be
+    /// any path, which is resolved relative to this directory.)
+    pub fn symlink(&self, src: &Path, name: &OsStr) -> io::Result<()> {
+        let src = cstr(src.as_os_str())?;
+        let name = cstr(name)?;
+        if unsafe { c::symlinkat(src.as_ptr(), self.fd.as_raw_fd(), name.as_ptr()) } == -1 {
+            Err(normalize_error(io::Error::last_os_error()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// Walk down path one component at a time from base (or the working directory), yielding the last
+// directory opened, or None if there were no components to open.
+fn walk(base: Option<BorrowedFd>, path: &Path) -> io::Result<Option<Dir>> {
+    if !c::SUPPORTED {
+        return Err(unsupported());
+    }
+    let mut dir: Option<Dir> = None;
+    let mut walked = PathBuf::new();
+    for component in path.components() {
+        let name = match component {
+            Component::RootDir => OsStr::new("/"),
+            Component::CurDir => continue,
+            Component::ParentDir => OsStr::new(".."),
+            Component::Normal(name) => name,
+            Component::Prefix(_) => unreachable!("path prefixes only exist on Windows"),
+        };
+        walked.push(name);
+        let at = match (&dir, base) {
+            (Some(dir), _) => dir.fd.as_raw_fd(),
+            (None, Some(base)) => base.as_raw_fd(),
+            (None, None) => c::AT_FDCWD,
+        };
+        dir = Some(match openat_dir(at, name) {
+            Ok(next) => next,
+            // The error is ELOOP on some platforms and EMLINK or ENOTDIR on others, so rather
+            // than guess, take another look at what was there. This is purely for the sake of a
+            // clearer message; the open has already failed safe. (Relative to some other
+            // directory, there’s no path to look at, so the raw error will have to do.)
+            Err(e) => return Err(if base.is_none() && is_symlink(&walked) {
+                io::Error::new(io::ErrorKind::InvalidInput,
+                               format!("parent path component {} is a symlink", walked.display()))
+            } else {
+                e
+            }),
+        });
+    }
+    Ok(dir)
+}
+
+fn openat_dir(at: RawFd, name: &OsStr) -> io::Result<Dir> {
+    let name = cstr(name)?;
+    let fd = unsafe {
+        c::openat(at, name.as_ptr(), c::O_RDONLY | c::O_DIRECTORY | c::O_NOFOLLOW | c::O_CLOEXEC)
+    };
+    if fd == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(Dir { fd: unsafe { OwnedFd::from_raw_fd(fd) } })
+    }
+}
+
+fn is_symlink(path: &Path) -> bool {
+    path.symlink_metadata().map(|m| m.file_type().is_symlink()).unwrap_or(false)
+}
+
+/// Create a symlink at `dst` pointing at `src`, without following symlinks in `dst`’s parents.
+pub fn symlink_nofollow_parents(src: &Path, dst: &Path) -> io::Result<()> {
+    let (parent, name) = split_link(dst)?;
+    Dir::open_nofollow(parent)?.symlink(src, name)
+}
+
+fn split_link(dst: &Path) -> io::Result<(&Path, &OsStr)> {
+    let name = dst.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "link path has no file name")
+    })?;
+    Ok((dst.parent().unwrap_or_else(|| Path::new("")), name))
+}
+
+/// Create a symlink at `dst` beneath `base`, pointing at `src`.
+pub fn symlink_beneath(base: BorrowedFd, src: &Path, dst: &Path) -> io::Result<()> {
+    let (parent, name) = split_link(dst)?;
+    Dir::open_beneath(base, parent)?.symlink(src, name)
+}
+
+/// Create a symlink at `dst`, resolved relative to the directory `base` rather than the working
+/// directory, pointing at `src`. Without the fd machinery, this has to make do with joining them.
+pub fn symlink_relative_to(base: &Path, src: &Path, dst: &Path) -> io::Result<()> {
+    if c::SUPPORTED {
+        Dir::open(base)?.symlink(src, dst.as_os_str())
+    } else {
+        unix_fs::symlink(src, base.join(dst)).map_err(normalize_error)
+    }
+}
+
+/// The mode to create directories with, and whether it’s to be exactly that, rather than less
+/// whatever the umask takes away.
+#[derive(Clone, Copy, Debug)]
+pub struct DirMode {
+    pub mode: u32,
+    pub exact: bool,
+}
+
+/// Create the directories `dirs`, outermost first, each the parent of the next.
+///
+/// This starts from the parent of the outermost one, which must exist and is opened as usual
+/// (following a symlink, if that’s what it is: it was there before we started). Each directory
+/// after that is created and then opened relative to the one before with `O_NOFOLLOW`, so if one
+/// is swapped for a symlink along the way, creation stops there rather than building the rest of
+/// the tree wherever the symlink leads. Without the fd machinery, this has to make do with
+/// creating them by path.
+pub fn create_dirs_nofollow(dirs: &[&Path], mode: DirMode) -> io::Result<()> {
+    if !c::SUPPORTED {
+        for dir in dirs {
+            match fs::DirBuilder::new().mode(mode.mode).create(dir) {
+                Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists && dir.is_dir() => {}
+                Err(e) => return Err(e),
+                Ok(()) if mode.exact => {
+                    fs::set_permissions(dir, fs::Permissions::from_mode(mode.mode))?
+                }
+                Ok(()) => {}
+            }
+        }
+        return Ok(());
+    }
+    let first = match dirs.first() {
+        Some(first) => first,
+        None => return Ok(()),
+    };
+    let mut dir = match first.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => Dir::open(parent)?,
+        _ => Dir::open(Path::new("."))?,
+    };
+    for path in dirs {
+        let name = match path.components().next_back() {
+            Some(Component::Normal(name)) => name,
+            Some(Component::ParentDir) => OsStr::new(".."),
+            _ => OsStr::new("."),
+        };
+        dir = dir.mkdir(name, mode).map_err(|e| if is_symlink(path) {
+            io::Error::new(io::ErrorKind::InvalidInput,
+                           format!("parent directory {} was replaced by a symlink while being \
+                                    created", path.display()))
+        } else {
+            e
+        })?;
+    }
+    Ok(())
+}
+
+/// Remove the symlink at `path` beneath `base`.
+pub fn remove_symlink_beneath(base: BorrowedFd, path: &Path) -> io::Result<()> {
+    let (parent, name) = split_link(path)?;
+    Dir::open_beneath(base, parent)?.unlink(name)
+}
+
+/// Give the symlink at `path` the owner and the access and modification times in `metadata`,
+/// which should be from lstat. (A symlink’s mode means nothing on most platforms, so it’s left
+/// alone.) The times are only set where utimensat is known; the owner is set everywhere.
+pub fn copy_link_attrs(metadata: &fs::Metadata, path: &Path) -> io::Result<()> {
+    unix_fs::lchown(path, Some(metadata.uid()), Some(metadata.gid()))?;
+    if !c::SUPPORTED {
+        return Ok(());
+    }
+    let times = [
+        c::timespec { tv_sec: metadata.atime() as c::time_t, tv_nsec: metadata.atime_nsec() as _ },
+        c::timespec { tv_sec: metadata.mtime() as c::time_t, tv_nsec: metadata.mtime_nsec() as _ },
+    ];
+    let path = cstr(path.as_os_str())?;
+    if unsafe {
+        c::utimensat(c::AT_FDCWD, path.as_ptr(), times.as_ptr(), c::AT_SYMLINK_NOFOLLOW)
+    } == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// The extended attributes of the symlink at `path` itself, names and values, for
+/// [`write_link_xattrs`]. A filesystem without them has none. Linux only; elsewhere, none.
+#[cfg(target_os = "linux")]
+pub fn read_link_xattrs(path: &Path) -> io::Result<Vec<(CString, Vec<u8>)>> {
+    let path = cstr(path.as_os_str())?;
+    let names = match xattr_buffer(|buf, size| unsafe {
+        c::llistxattr(path.as_ptr(), buf as *mut _, size)
+    }) {
+        Err(ref e) if e.raw_os_error() == Some(c::ENOTSUP) => return Ok(vec![]),
+        result => result?,
+    };
+    names.split(|&b| b == 0).filter(|name| !name.is_empty()).map(|name| {
+        let name = CString::new(name).expect("names are split at NULs");
+        let value = xattr_buffer(|buf, size| unsafe {
+            c::lgetxattr(path.as_ptr(), name.as_ptr(), buf as *mut _, size)
+        })?;
+        Ok((name, value))
+    }).collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn read_link_xattrs(_path: &Path) -> io::Result<Vec<(CString, Vec<u8>)>> {
+    Ok(vec![])
+}
+
+// Ask for something as l*xattr calls give it, first how big it is and then it, again if it grew in
+// between.
+#[cfg(target_os = "linux")]
+fn xattr_buffer<F: FnMut(*mut u8, usize) -> isize>(mut call: F) -> io::Result<Vec<u8>> {
+    loop {
+        let size = call(::std::ptr::null_mut(), 0);
+        if size == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut buf = vec![0; size as usize];
+        match call(buf.as_mut_ptr(), buf.len()) {
+            -1 if io::Error::last_os_error().raw_os_error() == Some(c::ERANGE) => continue,
+            -1 => return Err(io::Error::last_os_error()),
+            len => {
+                buf.truncate(len as usize);
+                return Ok(buf);
+            }
+        }
+    }
+}
+
+/// Give the symlink at `path` itself the extended attributes in `xattrs`, from
+/// [`read_link_xattrs`].
+#[cfg(target_os = "linux")]
+pub fn write_link_xattrs(path: &Path, xattrs: &[(CString, Vec<u8>)]) -> io::Result<()> {
+    let path = cstr(path.as_os_str())?;
+    for (name, value) in xattrs {
+        if unsafe {
+            c::lsetxattr(path.as_ptr(), name.as_ptr(), value.as_ptr() as *const _, value.len(), 0)
+        } == -1 {
+            let e = io::Error::last_os_error();
+            let message = format!("cannot set extended attribute {} on {}",
+                                  name.to_string_lossy(), path.to_string_lossy());
+            return Err(error::with_context(e.kind(), message, e));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn write_link_xattrs(_path: &Path, _xattrs: &[(CString, Vec<u8>)]) -> io::Result<()> {
+    Ok(())
+}
+
+/// Clone the file `src` to a new file `dst` sharing its data, copy-on-write: FICLONE on Linux,
+/// clonefile on macOS. `Ok(false)` means the filesystem (or the platform) can’t, and nothing was
+/// left behind at `dst`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn reflink(src: &Path, dst: &Path) -> io::Result<bool> {
+    let source = fs::File::open(src)?;
+    let dest = fs::OpenOptions::new().write(true).create_new(true).open(dst)?;
+    let result = if unsafe { c::ioctl(dest.as_raw_fd(), c::FICLONE, source.as_raw_fd()) } == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        // The data is shared, but the new file was created with the default mode.
+        source.metadata().and_then(|m| dest.set_permissions(m.permissions()))
+    };
+    drop(dest);
+    match result {
+        Ok(()) => Ok(true),
+        Err(e) => {
+            let _ = fs::remove_file(dst);
+            match e.raw_os_error() {
+                Some(code) if c::E_CLONE_UNSUPPORTED.contains(&code) => Ok(false),
+                _ => Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub fn reflink(src: &Path, dst: &Path) -> io::Result<bool> {
+    let (src, dst) = (cstr(src.as_os_str())?, cstr(dst.as_os_str())?);
+    if unsafe { c::clonefile(src.as_ptr(), dst.as_ptr(), 0) } == -1 {
+        let e = io::Error::last_os_error();
+        match e.raw_os_error() {
+            Some(code) if c::E_CLONE_UNSUPPORTED.contains(&code) => Ok(false),
+            _ => Err(e),
+        }
+    } else {
+        Ok(true)
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos",
+              target_os = "ios")))]
+pub fn reflink(_src: &Path, _dst: &Path) -> io::Result<bool> {
+    Ok(false)
+}
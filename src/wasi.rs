@@ -0,0 +1,59 @@
+// The WASI side of the symlink_*_in functions. Under WASI every path is already resolved beneath
+// a directory handle: std finds the preopened directory a path lies in and hands the rest to the
+// runtime, which won’t let it out again. So here there’s no walking to do, just a call to
+// wasi-libc’s symlinkat or unlinkat against the handle we were given. (std::os::wasi::fs has
+// symlink_path, which would do, but it’s still unstable.)
+
+use std::ffi::CString;
+use std::io;
+use std::os::fd::{AsRawFd, BorrowedFd};
+use std::os::raw::{c_char, c_int};
+use std::path::{Component, Path};
+
+extern "C" {
+    fn symlinkat(target: *const c_char, newdirfd: c_int, linkpath: *const c_char) -> c_int;
+    fn unlinkat(dirfd: c_int, pathname: *const c_char, flags: c_int) -> c_int;
+}
+
+// WASI paths are strings, and std::os::wasi::ffi::OsStrExt is unstable anyway.
+fn cstr(path: &Path) -> io::Result<CString> {
+    let s = path.to_str().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "paths must be valid Unicode on WASI")
+    })?;
+    CString::new(s).map_err(|_| {
+        io::Error::new(io::ErrorKind::InvalidInput, "paths cannot contain NULs")
+    })
+}
+
+// The runtime would refuse these too, but with a less useful error, and not necessarily the same
+// one from runtime to runtime.
+fn check_beneath(path: &Path) -> io::Result<()> {
+    if path.has_root() || path.components().any(|c| c == Component::ParentDir) {
+        Err(io::Error::new(io::ErrorKind::InvalidInput,
+                           format!("path {} escapes the directory", path.display())))
+    } else {
+        Ok(())
+    }
+}
+
+/// Create a symlink at `dst` beneath `base`, pointing at `src`.
+pub fn symlink_beneath(base: BorrowedFd, src: &Path, dst: &Path) -> io::Result<()> {
+    check_beneath(dst)?;
+    let (src, dst) = (cstr(src)?, cstr(dst)?);
+    if unsafe { symlinkat(src.as_ptr(), base.as_raw_fd(), dst.as_ptr()) } == -1 {
+        Err(::internal::normalize_error(io::Error::last_os_error()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Remove the symlink at `path` beneath `base`.
+pub fn remove_symlink_beneath(base: BorrowedFd, path: &Path) -> io::Result<()> {
+    check_beneath(path)?;
+    let path = cstr(path)?;
+    if unsafe { unlinkat(base.as_raw_fd(), path.as_ptr(), 0) } == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
@@ -5,15 +5,17 @@
 
 #![allow(non_snake_case, non_camel_case_types)]
 
-use std::os::raw::{c_int, c_uint, c_ushort, c_ulong, c_void};
+use std::os::raw::{c_int, c_long, c_uint, c_ushort, c_ulong, c_void};
 use std::os::windows::raw::HANDLE;
 
 pub type WCHAR = u16;
 pub type DWORD = c_ulong;
 pub type BOOL = c_int;
+pub type BOOLEAN = u8;
 pub type LPVOID = *mut c_void;
 pub type LPDWORD = *mut DWORD;
 pub type LPCWSTR = *const WCHAR;
+pub type LPWSTR = *mut WCHAR;
 pub type LPOVERLAPPED = *mut OVERLAPPED;
 pub type LPBY_HANDLE_FILE_INFORMATION = *mut BY_HANDLE_FILE_INFORMATION;
 pub type LPSECURITY_ATTRIBUTES = *mut SECURITY_ATTRIBUTES;
@@ -23,6 +25,7 @@ pub const FILE_ATTRIBUTE_REPARSE_POINT: DWORD = 0x400;
 
 pub const IO_REPARSE_TAG_SYMLINK: DWORD = 0xa000000c;
 pub const IO_REPARSE_TAG_MOUNT_POINT: DWORD = 0xa0000003;
+pub const SYMLINK_FLAG_RELATIVE: DWORD = 0x1;
 
 pub const FILE_SHARE_DELETE: DWORD = 0x4;
 pub const FILE_SHARE_READ: DWORD = 0x1;
@@ -30,11 +33,37 @@ pub const FILE_SHARE_WRITE: DWORD = 0x2;
 
 pub const OPEN_EXISTING: DWORD = 3;
 pub const FILE_FLAG_BACKUP_SEMANTICS: DWORD = 0x02000000;
+pub const FILE_FLAG_OPEN_REPARSE_POINT: DWORD = 0x00200000;
+
+pub const GENERIC_WRITE: DWORD = 0x40000000;
 
 pub const INVALID_HANDLE_VALUE: HANDLE = !0 as HANDLE;
 
+pub const ERROR_INVALID_FUNCTION: DWORD = 1;
+pub const ERROR_NOT_SUPPORTED: DWORD = 50;
+pub const ERROR_INVALID_PARAMETER: DWORD = 87;
+pub const ERROR_PRIVILEGE_NOT_HELD: DWORD = 1314;
+pub const ERROR_CANT_RESOLVE_FILENAME: DWORD = 1921;
+pub const ERROR_NOT_A_REPARSE_POINT: DWORD = 4390;
+
+pub const SYMBOLIC_LINK_FLAG_DIRECTORY: DWORD = 0x1;
+pub const SYMBOLIC_LINK_FLAG_ALLOW_UNPRIVILEGED_CREATE: DWORD = 0x2;
+
+pub const TOKEN_QUERY: DWORD = 0x8;
+// TokenPrivileges, of the TOKEN_INFORMATION_CLASS enumeration.
+pub const TOKEN_INFORMATION_PRIVILEGES: c_int = 3;
+
+pub type HKEY = *mut c_void;
+pub const HKEY_LOCAL_MACHINE: HKEY = -0x7ffffffe_isize as HKEY;
+pub const RRF_RT_REG_DWORD: DWORD = 0x18;
+
+pub const FILE_SUPPORTS_REPARSE_POINTS: DWORD = 0x80;
+pub const MAX_PATH: usize = 260;
+
 pub const MAXIMUM_REPARSE_DATA_BUFFER_SIZE: usize = 16 * 1024;
 pub const FSCTL_GET_REPARSE_POINT: DWORD = 0x900a8;
+pub const FSCTL_SET_REPARSE_POINT: DWORD = 0x900a4;
+pub const FSCTL_DELETE_REPARSE_POINT: DWORD = 0x900ac;
 
 #[repr(C)]
 pub struct BY_HANDLE_FILE_INFORMATION {
@@ -74,6 +103,26 @@ pub struct OVERLAPPED {
     pub hEvent: HANDLE,
 }
 
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct LUID {
+    pub LowPart: DWORD,
+    pub HighPart: c_long,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct LUID_AND_ATTRIBUTES {
+    pub Luid: LUID,
+    pub Attributes: DWORD,
+}
+
+#[repr(C)]
+pub struct TOKEN_PRIVILEGES {
+    pub PrivilegeCount: DWORD,
+    pub Privileges: [LUID_AND_ATTRIBUTES; 1],
+}
+
 #[repr(C)]
 pub struct SECURITY_ATTRIBUTES {
     pub nLength: DWORD,
@@ -102,4 +151,43 @@ extern "system" {
                            lpBytesReturned: LPDWORD,
                            lpOverlapped: LPOVERLAPPED) -> BOOL;
     pub fn CloseHandle(hObject: HANDLE) -> BOOL;
+    pub fn CreateSymbolicLinkW(lpSymlinkFileName: LPCWSTR,
+                               lpTargetFileName: LPCWSTR,
+                               dwFlags: DWORD)
+                               -> BOOLEAN;
+    pub fn GetCurrentProcess() -> HANDLE;
+    pub fn GetVolumePathNameW(lpszFileName: LPCWSTR, lpszVolumePathName: LPWSTR,
+                              cchBufferLength: DWORD)
+                              -> BOOL;
+    pub fn GetVolumeInformationW(lpRootPathName: LPCWSTR,
+                                 lpVolumeNameBuffer: LPWSTR,
+                                 nVolumeNameSize: DWORD,
+                                 lpVolumeSerialNumber: LPDWORD,
+                                 lpMaximumComponentLength: LPDWORD,
+                                 lpFileSystemFlags: LPDWORD,
+                                 lpFileSystemNameBuffer: LPWSTR,
+                                 nFileSystemNameSize: DWORD)
+                                 -> BOOL;
+}
+
+#[link(name = "advapi32")]
+extern "system" {
+    pub fn OpenProcessToken(ProcessHandle: HANDLE, DesiredAccess: DWORD, TokenHandle: *mut HANDLE)
+                            -> BOOL;
+    pub fn GetTokenInformation(TokenHandle: HANDLE,
+                               TokenInformationClass: c_int,
+                               TokenInformation: LPVOID,
+                               TokenInformationLength: DWORD,
+                               ReturnLength: LPDWORD)
+                               -> BOOL;
+    pub fn LookupPrivilegeValueW(lpSystemName: LPCWSTR, lpName: LPCWSTR, lpLuid: *mut LUID)
+                                 -> BOOL;
+    pub fn RegGetValueW(hkey: HKEY,
+                        lpSubKey: LPCWSTR,
+                        lpValue: LPCWSTR,
+                        dwFlags: DWORD,
+                        pdwType: LPDWORD,
+                        pvData: LPVOID,
+                        pcbData: LPDWORD)
+                        -> c_long;
 }
@@ -1,22 +1,546 @@
-pub use std::os::windows::fs::{symlink_file, symlink_dir};
 pub use std::fs::remove_dir as remove_symlink_dir;
 use std::fs;
 use std::io::{self, Error};
 use std::mem;
-use std::ffi::OsStr;
-use std::os::windows::ffi::OsStrExt;
+use std::ops;
+use std::ffi::{OsStr, OsString};
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::os::windows::fs::{self as windows_fs, MetadataExt};
 use std::os::windows::raw::HANDLE;
 use std::path::Path;
 use std::ptr;
+use std::slice;
+
+use error;
+use paths;
+use stats;
+use SymlinkKind;
 
 mod c;
+mod reparse;
+
+#[inline]
+pub fn symlink_file<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
+    windows_fs::symlink_file(src, dst).map_err(normalize_error)
+}
+
+#[inline]
+pub fn symlink_dir<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
+    windows_fs::symlink_dir(src, dst).map_err(normalize_error)
+}
+
+// Modelled on std’s symlink_inner (libstd/sys/windows/fs.rs), minus the path conversion.
+pub fn symlink_wide(src: &[u16], dst: &Path, dir: bool) -> io::Result<()> {
+    let src = nul_terminated(src)?;
+    let dst = to_u16s(dst)?;
+    let flags = if dir { c::SYMBOLIC_LINK_FLAG_DIRECTORY } else { 0 };
+    unsafe {
+        // Try first with the unprivileged flag (Windows 10 Creators Update onwards, given
+        // Developer Mode); older versions reject the unknown flag with ERROR_INVALID_PARAMETER,
+        // in which case try again without it.
+        if c::CreateSymbolicLinkW(dst.as_ptr(), src.as_ptr(),
+                                  flags | c::SYMBOLIC_LINK_FLAG_ALLOW_UNPRIVILEGED_CREATE) != 0 {
+            return Ok(());
+        }
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() != Some(c::ERROR_INVALID_PARAMETER as i32) {
+            return Err(normalize_error(err));
+        }
+        if c::CreateSymbolicLinkW(dst.as_ptr(), src.as_ptr(), flags) != 0 {
+            Ok(())
+        } else {
+            Err(normalize_error(io::Error::last_os_error()))
+        }
+    }
+}
+
+// CreateSymbolicLinkW with exactly these flags, and no second try.
+pub fn symlink_with_flags(src: &Path, dst: &Path, flags: SymlinkFlags) -> io::Result<()> {
+    let (src, dst) = (to_u16s(src)?, to_u16s(dst)?);
+    if unsafe { c::CreateSymbolicLinkW(dst.as_ptr(), src.as_ptr(), flags.bits()) } != 0 {
+        Ok(())
+    } else {
+        Err(normalize_error(io::Error::last_os_error()))
+    }
+}
+
+fn nul_terminated(s: &[u16]) -> io::Result<Vec<u16>> {
+    match s.iter().position(|&u| u == 0) {
+        None => Ok(s.iter().cloned().chain(Some(0)).collect()),
+        Some(i) if i == s.len() - 1 => Ok(s.to_vec()),
+        Some(_) => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "strings passed to the Windows API cannot contain NULs")),
+    }
+}
+
+/// The flags [`symlink_with_flags`](crate::symlink_with_flags) passes to `CreateSymbolicLinkW`
+/// (Windows only).
+///
+/// Combine them with `|`. The other functions choose for themselves: a directory symlink gets
+/// [`DIRECTORY`](Self::DIRECTORY), and every symlink is tried first with
+/// [`ALLOW_UNPRIVILEGED_CREATE`](Self::ALLOW_UNPRIVILEGED_CREATE) and then, if Windows is too
+/// old to know it, without.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SymlinkFlags(u32);
+
+impl SymlinkFlags {
+    /// No flags: a file symlink, created only with `SeCreateSymbolicLinkPrivilege`.
+    pub const NONE: SymlinkFlags = SymlinkFlags(0);
+
+    /// `SYMBOLIC_LINK_FLAG_DIRECTORY`: the link is a directory symlink, rather than a file one.
+    /// Windows Vista and later, like symlinks themselves.
+    pub const DIRECTORY: SymlinkFlags = SymlinkFlags(c::SYMBOLIC_LINK_FLAG_DIRECTORY);
+
+    /// `SYMBOLIC_LINK_FLAG_ALLOW_UNPRIVILEGED_CREATE`: create the link without
+    /// `SeCreateSymbolicLinkPrivilege`, which works if Developer Mode is on. Windows 10 version
+    /// 1703 (the Creators Update) and later; earlier versions reject it with
+    /// `ERROR_INVALID_PARAMETER`, and this won’t try again without it.
+    pub const ALLOW_UNPRIVILEGED_CREATE: SymlinkFlags =
+        SymlinkFlags(c::SYMBOLIC_LINK_FLAG_ALLOW_UNPRIVILEGED_CREATE);
+
+    /// Flags from their Win32 value, any bits at all, for flags newer than this crate. Windows
+    /// rejects bits it doesn’t know with `ERROR_INVALID_PARAMETER`.
+    pub const fn from_bits_retain(bits: u32) -> SymlinkFlags {
+        SymlinkFlags(bits)
+    }
+
+    /// The Win32 value of the flags.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Whether all of `other`’s flags are set in these.
+    pub const fn contains(self, other: SymlinkFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl ops::BitOr for SymlinkFlags {
+    type Output = SymlinkFlags;
+
+    fn bitor(self, other: SymlinkFlags) -> SymlinkFlags {
+        SymlinkFlags(self.0 | other.0)
+    }
+}
+
+impl ops::BitOrAssign for SymlinkFlags {
+    fn bitor_assign(&mut self, other: SymlinkFlags) {
+        self.0 |= other.0;
+    }
+}
+
+/// An open handle, closed on drop.
+struct Handle(HANDLE);
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        unsafe { let _ = c::CloseHandle(self.0); }
+    }
+}
+
+// Open the reparse point itself (or whatever is at path), not what it points to.
+fn open_reparse_point(path: &Path, access: c::DWORD) -> io::Result<Handle> {
+    let path = to_u16s(path)?;
+    let handle = unsafe {
+        c::CreateFileW(path.as_ptr(),
+                       access,
+                       c::FILE_SHARE_READ | c::FILE_SHARE_WRITE | c::FILE_SHARE_DELETE,
+                       ptr::null_mut(),
+                       c::OPEN_EXISTING,
+                       c::FILE_FLAG_BACKUP_SEMANTICS | c::FILE_FLAG_OPEN_REPARSE_POINT,
+                       ptr::null_mut())
+    };
+    if handle == c::INVALID_HANDLE_VALUE {
+        Err(Error::last_os_error())
+    } else {
+        Ok(Handle(handle))
+    }
+}
+
+// Send a reparse point buffer to a handle with DeviceIoControl.
+fn send_reparse_buffer(handle: &Handle, code: c::DWORD, input: &[u8]) -> io::Result<()> {
+    let mut aligned = reparse::Buffer([0; c::MAXIMUM_REPARSE_DATA_BUFFER_SIZE]);
+    aligned.0[..input.len()].copy_from_slice(input);
+    let mut bytes = 0;
+    if unsafe {
+        c::DeviceIoControl(handle.0,
+                           code,
+                           aligned.0.as_mut_ptr() as *mut _,
+                           input.len() as c::DWORD,
+                           ptr::null_mut(),
+                           0,
+                           &mut bytes,
+                           ptr::null_mut())
+    } == 0 {
+        Err(Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+// Read a handle’s reparse point buffer with DeviceIoControl.
+fn get_reparse_buffer(handle: &Handle) -> io::Result<Vec<u8>> {
+    let mut aligned = reparse::Buffer([0; c::MAXIMUM_REPARSE_DATA_BUFFER_SIZE]);
+    let mut bytes = 0;
+    if unsafe {
+        c::DeviceIoControl(handle.0,
+                           c::FSCTL_GET_REPARSE_POINT,
+                           ptr::null_mut(),
+                           0,
+                           aligned.0.as_mut_ptr() as *mut _,
+                           aligned.0.len() as c::DWORD,
+                           &mut bytes,
+                           ptr::null_mut())
+    } == 0 {
+        Err(Error::last_os_error())
+    } else {
+        Ok(aligned.0[..bytes as usize].to_vec())
+    }
+}
+
+/// Both names stored in a symlink or mount point.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SymlinkNames {
+    /// The substitute name, which is what the OS follows: for an absolute target, an NT path such
+    /// as `\??\C:\target`.
+    pub substitute_name: OsString,
+    /// The print name, for showing to people: usually the target as it was given, `C:\target`.
+    /// Some tools leave it empty.
+    pub print_name: OsString,
+    /// Whether this is a symlink with a relative target (`SYMLINK_FLAG_RELATIVE`).
+    pub relative: bool,
+    /// Whether this is a mount point—a junction or volume mount point—rather than a symlink.
+    pub mount_point: bool,
+}
+
+pub fn read_symlink_names(path: &Path) -> io::Result<SymlinkNames> {
+    let handle = open_reparse_point(path, 0)?;
+    let names = match get_reparse_buffer(&handle) {
+        Ok(buffer) => reparse::parse(&buffer)?,
+        // Not a reparse point at all.
+        Err(ref e) if e.raw_os_error() == Some(c::ERROR_NOT_A_REPARSE_POINT as i32) => {
+            return Err(error::marked(error::Cause::NotASymlink,
+                                     io::Error::new(io::ErrorKind::InvalidInput,
+                                                    "path is not a symlink")));
+        }
+        Err(e) => return Err(e),
+    };
+    Ok(SymlinkNames {
+        substitute_name: OsString::from_wide(&names.substitute_name),
+        print_name: OsString::from_wide(&names.print_name),
+        relative: names.flags & c::SYMLINK_FLAG_RELATIVE != 0,
+        mount_point: names.tag == c::IO_REPARSE_TAG_MOUNT_POINT,
+    })
+}
+
+/// What [`symlink_auto_detailed`](crate::symlink_auto_detailed) created.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SymlinkDetails {
+    /// The kind of symlink, as decided by looking at the target.
+    pub kind: SymlinkKind,
+    /// The reparse tag of the link, as read back from it: `IO_REPARSE_TAG_SYMLINK`
+    /// (`0xA000000C`) for any symlink, but recorded rather than assumed.
+    pub reparse_tag: u32,
+}
+
+pub fn read_reparse_tag(path: &Path) -> io::Result<u32> {
+    reparse::tag(&get_reparse_buffer(&open_reparse_point(path, 0)?)?)
+}
+
+/// What this process needs to create symlinks, as found by
+/// [`symlink_privilege`](crate::symlink_privilege) (Windows only).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SymlinkPrivilege {
+    /// The process holds `SeCreateSymbolicLinkPrivilege`, as an elevated administrator does, so
+    /// it can create symlinks whatever else is the case.
+    Privileged,
+    /// The process lacks the privilege, but Developer Mode is on, so it can create symlinks
+    /// without it.
+    Unprivileged,
+    /// Neither: creating a symlink will fail with `PermissionDenied`. Junctions
+    /// ([`symlink_junction`](crate::symlink_junction)) need no privilege, for directories on
+    /// local volumes, and are the usual fallback.
+    NeitherUseJunctions,
+}
+
+pub fn symlink_privilege() -> SymlinkPrivilege {
+    if holds_symlink_privilege() {
+        SymlinkPrivilege::Privileged
+    } else if developer_mode() {
+        SymlinkPrivilege::Unprivileged
+    } else {
+        SymlinkPrivilege::NeitherUseJunctions
+    }
+}
+
+// Whether SeCreateSymbolicLinkPrivilege is among the process token’s privileges. Any failure to
+// find out counts as no.
+pub fn holds_symlink_privilege() -> bool {
+    let name = match to_u16s("SeCreateSymbolicLinkPrivilege") {
+        Ok(name) => name,
+        Err(_) => return false,
+    };
+    unsafe {
+        let mut luid: c::LUID = mem::zeroed();
+        if c::LookupPrivilegeValueW(ptr::null(), name.as_ptr(), &mut luid) == 0 {
+            return false;
+        }
+        let mut token = ptr::null_mut();
+        if c::OpenProcessToken(c::GetCurrentProcess(), c::TOKEN_QUERY, &mut token) == 0 {
+            return false;
+        }
+        let token = Handle(token);
+        // Once to learn the size, once to fill a buffer of it, DWORD-aligned as the
+        // TOKEN_PRIVILEGES in it needs.
+        let class = c::TOKEN_INFORMATION_PRIVILEGES;
+        let mut size = 0;
+        c::GetTokenInformation(token.0, class, ptr::null_mut(), 0, &mut size);
+        if (size as usize) < mem::size_of::<c::TOKEN_PRIVILEGES>() {
+            return false;
+        }
+        let mut buffer = vec![0u32; (size as usize).div_ceil(4)];
+        if c::GetTokenInformation(token.0, class, buffer.as_mut_ptr() as c::LPVOID, size,
+                                  &mut size) == 0 {
+            return false;
+        }
+        // The array runs on past the one element the struct declares, so it’s taken from the
+        // buffer rather than through a reference to the struct.
+        let count = (*(buffer.as_ptr() as *const c::TOKEN_PRIVILEGES)).PrivilegeCount as usize;
+        let offset = mem::offset_of!(c::TOKEN_PRIVILEGES, Privileges);
+        if offset + count * mem::size_of::<c::LUID_AND_ATTRIBUTES>() > buffer.len() * 4 {
+            return false;
+        }
+        let first = (buffer.as_ptr() as *const u8).add(offset) as *const c::LUID_AND_ATTRIBUTES;
+        slice::from_raw_parts(first, count).iter().any(|p| {
+            p.Luid.LowPart == luid.LowPart && p.Luid.HighPart == luid.HighPart
+        })
+    }
+}
+
+// Whether Developer Mode is on, as the Settings app records it.
+pub fn developer_mode() -> bool {
+    let key = to_u16s(r"SOFTWARE\Microsoft\Windows\CurrentVersion\AppModelUnlock");
+    let (key, value) = match (key, to_u16s("AllowDevelopmentWithoutDevLicense")) {
+        (Ok(key), Ok(value)) => (key, value),
+        _ => return false,
+    };
+    let mut data: c::DWORD = 0;
+    let mut size = mem::size_of::<c::DWORD>() as c::DWORD;
+    let status = unsafe {
+        c::RegGetValueW(c::HKEY_LOCAL_MACHINE, key.as_ptr(), value.as_ptr(), c::RRF_RT_REG_DWORD,
+                        ptr::null_mut(), &mut data as *mut c::DWORD as c::LPVOID, &mut size)
+    };
+    status == 0 && data != 0
+}
+
+// Check that a link just created is still a symlink reparse point, for
+// SymlinkBuilder::verify_reparse_point.
+pub fn verify_reparse_point(path: &Path) -> io::Result<()> {
+    let problem = match read_reparse_tag(path) {
+        Ok(c::IO_REPARSE_TAG_SYMLINK) => return Ok(()),
+        Ok(tag) => format!("now has reparse tag {:#010X} rather than IO_REPARSE_TAG_SYMLINK", tag),
+        Err(ref e) if e.raw_os_error() == Some(c::ERROR_NOT_A_REPARSE_POINT as i32) => {
+            "is no longer a reparse point".to_owned()
+        }
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => "has disappeared".to_owned(),
+        Err(e) => return Err(e),
+    };
+    Err(io::Error::new(io::ErrorKind::InvalidData,
+                       format!("symlink {} was created but {} (has security software quarantined \
+                                or altered it?)", path.display(), problem)))
+}
+
+// Whether the volume path is on says it supports reparse points, which symlinks are.
+pub fn volume_supports_reparse_points(path: &Path) -> io::Result<bool> {
+    let path = to_u16s(path)?;
+    // A volume path is a drive, a mount point or a share, so long paths are the exception; the
+    // buffer grows for them.
+    let mut root = vec![0u16; c::MAX_PATH + 1];
+    loop {
+        let len = root.len() as c::DWORD;
+        if unsafe { c::GetVolumePathNameW(path.as_ptr(), root.as_mut_ptr(), len) } != 0 {
+            break;
+        }
+        let err = io::Error::last_os_error();
+        // ERROR_FILENAME_EXCED_RANGE, for a buffer too small.
+        if err.raw_os_error() != Some(206) || root.len() > 32 * 1024 {
+            return Err(err);
+        }
+        let len = root.len();
+        root.resize(len * 2, 0);
+    }
+    let mut flags: c::DWORD = 0;
+    let ok = unsafe {
+        c::GetVolumeInformationW(root.as_ptr(), ptr::null_mut(), 0, ptr::null_mut(),
+                                 ptr::null_mut(), &mut flags, ptr::null_mut(), 0)
+    };
+    if ok != 0 {
+        Ok(flags & c::FILE_SUPPORTS_REPARSE_POINTS != 0)
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+pub fn is_mount_point(path: &Path) -> io::Result<bool> {
+    Ok(read_reparse_tag(path)? == c::IO_REPARSE_TAG_MOUNT_POINT)
+}
+
+// What GetFileInformationByHandle says about the file at path, not following a reparse point.
+fn file_information(path: &Path) -> io::Result<c::BY_HANDLE_FILE_INFORMATION> {
+    let handle = open_reparse_point(path, 0)?;
+    let mut info: c::BY_HANDLE_FILE_INFORMATION = unsafe { mem::zeroed() };
+    if unsafe { c::GetFileInformationByHandle(handle.0, &mut info) } == 0 {
+        Err(Error::last_os_error())
+    } else {
+        Ok(info)
+    }
+}
+
+// How many hard links the file at path has, not following a reparse point.
+pub fn number_of_links(path: &Path) -> io::Result<u64> {
+    file_information(path).map(|info| info.nNumberOfLinks as u64)
+}
+
+// The volume serial number and file index of the file at path, not following a reparse point.
+pub fn file_id(path: &Path) -> io::Result<u128> {
+    let info = file_information(path)?;
+    let index = (info.nFileIndexHigh as u64) << 32 | info.nFileIndexLow as u64;
+    Ok((info.dwVolumeSerialNumber as u128) << 64 | index as u128)
+}
+
+// Create an empty directory at dst and turn it into a mount point with the given names. If that
+// fails, the directory is removed again.
+fn create_mount_point(substitute_name: &[u16], print_name: &[u16], dst: &Path)
+                      -> io::Result<()> {
+    let buffer = reparse::mount_point(substitute_name, print_name)?;
+    fs::create_dir(dst)?;
+    let result = open_reparse_point(dst, c::GENERIC_WRITE).and_then(|handle| {
+        send_reparse_buffer(&handle, c::FSCTL_SET_REPARSE_POINT, &buffer)
+    });
+    if result.is_err() {
+        let _ = fs::remove_dir(dst);
+    }
+    result.map_err(normalize_error)
+}
+
+fn wide(s: &str) -> Vec<u16> {
+    OsStr::new(s).encode_wide().collect()
+}
+
+pub fn symlink_junction(src: &Path, dst: &Path) -> io::Result<()> {
+    // Junction targets are NT paths, \??\C:\foo; the print name is the plain C:\foo. A verbatim
+    // \\?\ prefix is the Win32 spelling of \??\, so it’s swapped rather than doubled.
+    let target: Vec<u16> = ::std::path::absolute(src)?.as_os_str().encode_wide().collect();
+    let verbatim = wide(r"\\?\");
+    let print_name = if target.starts_with(&verbatim) {
+        &target[verbatim.len()..]
+    } else {
+        &target[..]
+    };
+    let mut substitute_name = wide(r"\??\");
+    substitute_name.extend_from_slice(print_name);
+    create_mount_point(&substitute_name, print_name, dst)
+}
+
+pub fn symlink_mount_point(volume_guid_path: &Path, dst: &Path) -> io::Result<()> {
+    let volume: Vec<u16> = volume_guid_path.as_os_str().encode_wide().collect();
+    let prefix = wide(r"\\?\Volume{");
+    if !volume.starts_with(&prefix) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                  r"volume mount point target must be a \\?\Volume{GUID}\ path"));
+    }
+    // \\?\Volume{GUID}\ becomes \??\Volume{GUID}\, trailing backslash and all; the print name
+    // is empty, as mountvol leaves it.
+    let mut substitute_name = wide(r"\??\");
+    substitute_name.extend_from_slice(&volume[4..]);
+    if substitute_name.last() != Some(&(b'\\' as u16)) {
+        substitute_name.push(b'\\' as u16);
+    }
+    create_mount_point(&substitute_name, &[], dst)
+}
+
+pub fn remove_mount_point(path: &Path) -> io::Result<()> {
+    {
+        let handle = open_reparse_point(path, c::GENERIC_WRITE)?;
+        send_reparse_buffer(&handle, c::FSCTL_DELETE_REPARSE_POINT,
+                            &reparse::delete(c::IO_REPARSE_TAG_MOUNT_POINT))?;
+    }
+    fs::remove_dir(path)
+}
+
+// The error for a chain of symlinks that loops, as Windows would report it.
+pub fn loop_error() -> io::Error {
+    io::Error::from_raw_os_error(c::ERROR_CANT_RESOLVE_FILENAME as i32)
+}
+
+const PRIVILEGE_NOT_HELD: &str =
+    "creating symlinks requires SeCreateSymbolicLinkPrivilege, which by default only \
+     administrators have; either run elevated, or enable Developer Mode (Settings → For \
+     developers), which lets the SYMBOLIC_LINK_FLAG_ALLOW_UNPRIVILEGED_CREATE flag this crate \
+     passes take effect";
+
+// See the table in the crate docs. The original error is kept as the payload (for the privilege
+// case, as the source of the payload, because its own message—“A required privilege is not held
+// by the client”—is little help to someone who has never heard of SeCreateSymbolicLinkPrivilege).
+fn normalize_error(e: io::Error) -> io::Error {
+    match e.raw_os_error().map(|code| code as c::DWORD) {
+        Some(c::ERROR_NOT_SUPPORTED) | Some(c::ERROR_INVALID_FUNCTION) => {
+            io::Error::new(io::ErrorKind::Unsupported, e)
+        }
+        Some(c::ERROR_PRIVILEGE_NOT_HELD) => {
+            error::with_context(io::ErrorKind::PermissionDenied, PRIVILEGE_NOT_HELD, e)
+        }
+        _ => e,
+    }
+}
 
 #[inline]
 pub fn symlink_auto<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
-    if fs::metadata(src.as_ref())?.is_dir() {
-        symlink_dir(src.as_ref(), dst.as_ref())
+    match target_kind(src.as_ref(), dst.as_ref(), false)? {
+        SymlinkKind::Dir => symlink_dir(src.as_ref(), dst.as_ref()),
+        SymlinkKind::File => symlink_file(src.as_ref(), dst.as_ref()),
+    }
+}
+
+// The kind of symlink symlink_auto would make for src, linked from dst. A relative src is looked
+// at relative to dst’s parent, not the working directory, since that’s how the link will be
+// followed. If src is itself a symlink or junction, that’s what it’s classified by, unless told
+// to follow it: a file symlink to a directory is still a file as far as the new link is
+// concerned, since following the new link means following that one too, as a file. The
+// directory attribute is on the reparse point itself.
+pub fn target_kind(src: &Path, dst: &Path, follow: bool) -> io::Result<SymlinkKind> {
+    let src = &paths::resolve_target(dst, src);
+    let is_dir = if follow {
+        stats::metadata(src).map(|metadata| metadata.is_dir())
     } else {
-        symlink_file(src.as_ref(), dst.as_ref())
+        stats::symlink_metadata(src)
+            .map(|metadata| metadata.file_attributes() & c::FILE_ATTRIBUTE_DIRECTORY != 0)
+    };
+    if is_dir.map_err(|e| target_error(src, e))? {
+        Ok(SymlinkKind::Dir)
+    } else {
+        Ok(SymlinkKind::File)
+    }
+}
+
+// symlink_auto’s look at the target went wrong: say what it was trying to do. Not finding the
+// target and not being allowed to look at it are different fixes, so they get different
+// messages.
+fn target_error(src: &Path, e: io::Error) -> io::Error {
+    match e.kind() {
+        io::ErrorKind::NotFound => {
+            error::with_context(io::ErrorKind::NotFound,
+                                format!("symlink target {} does not exist, so symlink_auto can’t \
+                                         tell whether to make a file or a directory symlink",
+                                        src.display()), e)
+        }
+        io::ErrorKind::PermissionDenied => {
+            error::with_context(io::ErrorKind::PermissionDenied,
+                                format!("permission denied reading the metadata of symlink \
+                                         target {}, so symlink_auto can’t tell whether to make \
+                                         a file or a directory symlink", src.display()), e)
+        }
+        _ => e,
     }
 }
 
@@ -31,11 +555,21 @@ pub fn remove_symlink_auto<P: AsRef<Path>>(path: P) -> io::Result<()> {
     match symlink_type(path.as_ref())? {
         SymlinkType::Dir => fs::remove_dir(path),
         SymlinkType::File => fs::remove_file(path),
-        SymlinkType::Not => Err(io::Error::new(io::ErrorKind::InvalidInput,
-                                               "path is not a symlink")),
+        SymlinkType::Not => Err(error::marked(error::Cause::NotASymlink,
+                                              io::Error::new(io::ErrorKind::InvalidInput,
+                                                             "path is not a symlink"))),
     }
 }
 
+// The kind of the symlink at path, or None if it isn’t one.
+pub fn symlink_kind(path: &Path) -> io::Result<Option<SymlinkKind>> {
+    Ok(match symlink_type(path)? {
+        SymlinkType::Not => None,
+        SymlinkType::File => Some(SymlinkKind::File),
+        SymlinkType::Dir => Some(SymlinkKind::Dir),
+    })
+}
+
 pub enum SymlinkType {
     Not,
     File,
@@ -110,3 +644,28 @@ pub fn symlink_type(path: &Path) -> io::Result<SymlinkType> {
         out
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::path::Path;
+
+    // Denying access to a file’s metadata takes ACL fiddling that a test can’t rely on getting
+    // away with, so this checks the classification on the error itself.
+    #[test]
+    fn target_error_classification() {
+        const ERROR_FILE_NOT_FOUND: i32 = 2;
+        const ERROR_ACCESS_DENIED: i32 = 5;
+        let src = Path::new(r"C:\target");
+
+        let e = super::target_error(src, io::Error::from_raw_os_error(ERROR_ACCESS_DENIED));
+        assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+        let message = e.to_string();
+        assert!(message.starts_with(r"permission denied reading the metadata of symlink target"));
+        assert!(message.contains(r"C:\target"));
+
+        let e = super::target_error(src, io::Error::from_raw_os_error(ERROR_FILE_NOT_FOUND));
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+        assert!(e.to_string().starts_with(r"symlink target C:\target does not exist"));
+    }
+}
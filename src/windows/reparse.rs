@@ -0,0 +1,206 @@
+// Reparse point buffers, written out by hand. The layouts are those of REPARSE_DATA_BUFFER’s
+// MountPointReparseBuffer and SymbolicLinkReparseBuffer arms; every field is little-endian, and
+// names are UTF-16 without NULs, though by convention each is followed by one anyway.
+
+use std::io;
+
+use super::c;
+
+/// The size of the ReparseTag, ReparseDataLength and Reserved fields.
+pub const HEADER_SIZE: usize = 8;
+
+/// A buffer aligned suitably for DeviceIoControl.
+#[repr(C, align(8))]
+pub struct Buffer(pub [u8; c::MAXIMUM_REPARSE_DATA_BUFFER_SIZE]);
+
+fn too_long() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "reparse point target is too long")
+}
+
+/// Write a mount point buffer (used for both junctions and volume mount points).
+pub fn mount_point(substitute_name: &[u16], print_name: &[u16]) -> io::Result<Vec<u8>> {
+    // Each name is followed by a NUL.
+    let substitute_bytes = substitute_name.len() * 2;
+    let print_bytes = print_name.len() * 2;
+    let path_buffer_len = substitute_bytes + 2 + print_bytes + 2;
+    let data_len = 8 + path_buffer_len;
+    if HEADER_SIZE + data_len > c::MAXIMUM_REPARSE_DATA_BUFFER_SIZE {
+        return Err(too_long());
+    }
+    let mut buf = Vec::with_capacity(HEADER_SIZE + data_len);
+    buf.extend_from_slice(&c::IO_REPARSE_TAG_MOUNT_POINT.to_le_bytes());
+    buf.extend_from_slice(&(data_len as u16).to_le_bytes());
+    buf.extend_from_slice(&0u16.to_le_bytes());
+    // SubstituteNameOffset, SubstituteNameLength, PrintNameOffset, PrintNameLength, all in bytes
+    // and relative to the start of the path buffer, with lengths excluding the NULs.
+    buf.extend_from_slice(&0u16.to_le_bytes());
+    buf.extend_from_slice(&(substitute_bytes as u16).to_le_bytes());
+    buf.extend_from_slice(&((substitute_bytes + 2) as u16).to_le_bytes());
+    buf.extend_from_slice(&(print_bytes as u16).to_le_bytes());
+    for &unit in substitute_name.iter().chain(&[0]).chain(print_name).chain(&[0]) {
+        buf.extend_from_slice(&unit.to_le_bytes());
+    }
+    Ok(buf)
+}
+
+/// Write a header-only buffer, as FSCTL_DELETE_REPARSE_POINT wants.
+pub fn delete(tag: c::DWORD) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(HEADER_SIZE);
+    buf.extend_from_slice(&tag.to_le_bytes());
+    buf.extend_from_slice(&0u16.to_le_bytes());
+    buf.extend_from_slice(&0u16.to_le_bytes());
+    buf
+}
+
+/// The names in a symlink or mount point buffer, as read back.
+pub struct Names {
+    pub tag: c::DWORD,
+    pub substitute_name: Vec<u16>,
+    pub print_name: Vec<u16>,
+    /// The symlink Flags field; 0 for mount points, which have none.
+    pub flags: c::DWORD,
+}
+
+fn malformed() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "malformed reparse point buffer")
+}
+
+fn u16_at(buf: &[u8], at: usize) -> io::Result<u16> {
+    buf.get(at..at + 2).map(|b| u16::from_le_bytes([b[0], b[1]])).ok_or_else(malformed)
+}
+
+fn u32_at(buf: &[u8], at: usize) -> io::Result<u32> {
+    buf.get(at..at + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).ok_or_else(malformed)
+}
+
+/// Read the tag out of any reparse point buffer.
+pub fn tag(buf: &[u8]) -> io::Result<c::DWORD> {
+    u32_at(buf, 0)
+}
+
+/// Read the names out of a symlink or mount point buffer, as FSCTL_GET_REPARSE_POINT returns.
+///
+/// Other tags are an InvalidInput error: the path is a reparse point, but not a link. Anything
+/// that doesn’t fit—a ReparseDataLength longer than the buffer, a name reaching past it or
+/// starting or ending halfway through a UTF-16 unit—is an InvalidData error, never a panic.
+pub fn parse(buf: &[u8]) -> io::Result<Names> {
+    let tag = tag(buf)?;
+    // Only the ReparseDataLength bytes after the header are the buffer’s; what’s after is not to
+    // be read, whatever the names’ fields say.
+    let data_len = u16_at(buf, 4)? as usize;
+    let buf = buf.get(..HEADER_SIZE + data_len).ok_or_else(malformed)?;
+    // The path buffer follows the four name fields, and for symlinks the Flags field too.
+    let (flags, path_buffer) = match tag {
+        c::IO_REPARSE_TAG_SYMLINK => (u32_at(buf, HEADER_SIZE + 8)?, HEADER_SIZE + 12),
+        c::IO_REPARSE_TAG_MOUNT_POINT => (0, HEADER_SIZE + 8),
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                       "reparse point is not a symlink or mount point")),
+    };
+    let name = |field: usize| -> io::Result<Vec<u16>> {
+        let offset = u16_at(buf, HEADER_SIZE + field)? as usize;
+        let len = u16_at(buf, HEADER_SIZE + field + 2)? as usize;
+        let start = path_buffer + offset;
+        let bytes = buf.get(start..start + len)
+            .filter(|_| offset % 2 == 0 && len % 2 == 0)
+            .ok_or_else(malformed)?;
+        Ok(bytes.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]])).collect())
+    };
+    Ok(Names { tag, substitute_name: name(0)?, print_name: name(4)?, flags })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+
+    // A symlink buffer as the OS writes one: the names fields, Flags, then the path buffer.
+    fn symlink(substitute_name: &str, print_name: &str, flags: u32) -> Vec<u8> {
+        let substitute: Vec<u16> = substitute_name.encode_utf16().collect();
+        let print: Vec<u16> = print_name.encode_utf16().collect();
+        let mut buf = vec![];
+        buf.extend_from_slice(&c::IO_REPARSE_TAG_SYMLINK.to_le_bytes());
+        buf.extend_from_slice(&((12 + (substitute.len() + print.len()) * 2) as u16).to_le_bytes());
+        buf.extend_from_slice(&0u16.to_le_bytes());
+        // The print name first this time, as the OS tends to.
+        buf.extend_from_slice(&((print.len() * 2) as u16).to_le_bytes());
+        buf.extend_from_slice(&((substitute.len() * 2) as u16).to_le_bytes());
+        buf.extend_from_slice(&0u16.to_le_bytes());
+        buf.extend_from_slice(&((print.len() * 2) as u16).to_le_bytes());
+        buf.extend_from_slice(&flags.to_le_bytes());
+        for &unit in print.iter().chain(&substitute) {
+            buf.extend_from_slice(&unit.to_le_bytes());
+        }
+        buf
+    }
+
+    fn kind(buf: &[u8]) -> io::ErrorKind {
+        match parse(buf) {
+            Ok(_) => panic!("{:?} parsed", buf),
+            Err(e) => e.kind(),
+        }
+    }
+
+    #[test]
+    fn parses_symlinks_and_mount_points() {
+        let names = parse(&symlink(r"..\target", r"..\target", 1)).unwrap();
+        assert_eq!(names.tag, c::IO_REPARSE_TAG_SYMLINK);
+        assert_eq!(String::from_utf16(&names.substitute_name).unwrap(), r"..\target");
+        assert_eq!(String::from_utf16(&names.print_name).unwrap(), r"..\target");
+        assert_eq!(names.flags, 1);
+
+        let substitute: Vec<u16> = r"\??\C:\target".encode_utf16().collect();
+        let print: Vec<u16> = r"C:\target".encode_utf16().collect();
+        let buf = mount_point(&substitute, &print).unwrap();
+        assert_eq!(&buf[..4], &[0x03, 0x00, 0x00, 0xa0]);
+        let names = parse(&buf).unwrap();
+        assert_eq!(names.tag, c::IO_REPARSE_TAG_MOUNT_POINT);
+        assert_eq!(names.substitute_name, substitute);
+        assert_eq!(names.print_name, print);
+        assert_eq!(names.flags, 0);
+
+        // Trailing bytes past ReparseDataLength are ignored.
+        let mut padded = buf.clone();
+        padded.extend_from_slice(&[0xff; 6]);
+        assert_eq!(parse(&padded).unwrap().print_name, print);
+        // As is anything with no names to speak of.
+        let names = parse(&symlink("", "", 0)).unwrap();
+        assert!(names.substitute_name.is_empty() && names.print_name.is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_buffers() {
+        let good = symlink("target", "target", 1);
+        // Every truncation, header and all.
+        for len in 0..good.len() {
+            assert_eq!(kind(&good[..len]), io::ErrorKind::InvalidData, "{}", len);
+        }
+        // A data length longer than what’s there.
+        let mut bad = good.clone();
+        bad[4..6].copy_from_slice(&(good.len() as u16).to_le_bytes());
+        assert_eq!(kind(&bad), io::ErrorKind::InvalidData);
+        // A name running past the data length, into bytes that are there but not the buffer’s.
+        let mut bad = good.clone();
+        bad[4..6].copy_from_slice(&((good.len() - HEADER_SIZE - 2) as u16).to_le_bytes());
+        assert_eq!(kind(&bad), io::ErrorKind::InvalidData);
+        // A name offset or length as large as they go.
+        for &field in &[8, 10, 12, 14] {
+            let mut bad = good.clone();
+            bad[field..field + 2].copy_from_slice(&u16::MAX.to_le_bytes());
+            assert_eq!(kind(&bad), io::ErrorKind::InvalidData, "{}", field);
+        }
+        // Halfway through a UTF-16 unit, at either end.
+        let mut bad = good.clone();
+        bad[8..10].copy_from_slice(&1u16.to_le_bytes());
+        assert_eq!(kind(&bad), io::ErrorKind::InvalidData);
+        let mut bad = good.clone();
+        bad[10..12].copy_from_slice(&3u16.to_le_bytes());
+        assert_eq!(kind(&bad), io::ErrorKind::InvalidData);
+        // Some other kind of reparse point entirely.
+        let mut other = good.clone();
+        other[..4].copy_from_slice(&0x8000_0017u32.to_le_bytes());
+        assert_eq!(kind(&other), io::ErrorKind::InvalidInput);
+        assert_eq!(tag(&other).unwrap(), 0x8000_0017);
+        assert_eq!(tag(&[0x0c, 0x00]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
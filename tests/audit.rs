@@ -0,0 +1,36 @@
+#![cfg(feature = "audit")]
+
+use std::env::temp_dir;
+use std::fs;
+use std::path::Path;
+
+extern crate symlink_rs as symlink;
+use symlink::{SymlinkOps, SystemSymlinks};
+
+#[test]
+fn test_audit_log() {
+    let dir = temp_dir().join("symlink-crate-audit");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir(&dir).unwrap();
+    let link = dir.join("link \"quoted\"\tand tabbed");
+
+    let ops = SystemSymlinks.with_audit_log(Vec::new());
+    ops.symlink_dir(Path::new("releases\\42"), &link).unwrap();
+    assert!(ops.symlink_dir(Path::new("elsewhere"), &link).is_err());
+    assert!(ops.is_symlink(&link));
+    ops.remove_symlink_dir(&link).unwrap();
+    let log = String::from_utf8(ops.into_writer()).unwrap();
+
+    // The link path needs escaping: the quotes and the tab, and on Windows the separators.
+    let link = link.to_str().unwrap().replace('\\', "\\\\").replace('"', "\\\"")
+        .replace('\t', "\\t");
+    let link = format!(r#""link":"{}""#, link);
+    let create = r#"{"op":"create","kind":"dir","#;
+    let lines: Vec<&str> = log.lines().collect();
+    assert_eq!(lines.len(), 3, "{}", log);
+    assert_eq!(lines[0], format!(r#"{}"target":"releases\\42",{},"result":"ok"}}"#, create, link));
+    let failed = format!(r#"{}"target":"elsewhere",{},"result":"error","error":""#, create, link);
+    assert!(lines[1].starts_with(&failed), "{}", lines[1]);
+    assert_eq!(lines[2], format!(r#"{{"op":"remove","kind":"dir",{},"result":"ok"}}"#, link));
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,373 @@
+// These tests pin down the documented behaviour of each public function on the current platform,
+// including the places where the platforms deliberately differ, so that changes to the internal
+// module can’t quietly change the contract.
+
+use std::env::temp_dir;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+extern crate symlink_rs as symlink;
+use symlink::{symlink_auto, symlink_file, symlink_dir, remove_symlink_auto, remove_symlink_file,
+              remove_symlink_dir, SymlinkBuilder};
+
+fn test_dir(name: &str) -> PathBuf {
+    let dir = temp_dir().join(format!("symlink-crate-conformance-{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir(&dir).unwrap();
+    dir
+}
+
+fn is_symlink(path: &Path) -> bool {
+    path.symlink_metadata().map(|m| m.file_type().is_symlink()).unwrap_or(false)
+}
+
+/// `symlink_auto` needs the destination to exist on Windows; elsewhere it’s plain `symlink`, which
+/// is happy to create a dangling link.
+#[test]
+fn symlink_auto_missing_destination() {
+    let dir = test_dir("auto-missing");
+    let link = dir.join("link");
+    let result = symlink_auto(dir.join("nonexistent"), &link);
+    if cfg!(windows) {
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert!(!is_symlink(&link));
+    } else {
+        result.unwrap();
+        assert!(is_symlink(&link));
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// `symlink_file` and `symlink_dir` don’t look at the destination, so dangling links are fine.
+#[test]
+fn symlink_file_and_dir_dangling() {
+    let dir = test_dir("dangling");
+    symlink_file(dir.join("nonexistent"), dir.join("file-link")).unwrap();
+    symlink_dir(dir.join("nonexistent"), dir.join("dir-link")).unwrap();
+    assert!(is_symlink(&dir.join("file-link")));
+    assert!(is_symlink(&dir.join("dir-link")));
+    remove_symlink_file(dir.join("file-link")).unwrap();
+    remove_symlink_dir(dir.join("dir-link")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Creating a symlink where something already exists is an error, and leaves it alone.
+#[test]
+fn symlink_existing_destination() {
+    let dir = test_dir("existing");
+    let link = dir.join("link");
+    fs::write(&link, b"original").unwrap();
+    for create in &[symlink_file::<&str, &PathBuf>, symlink_dir, symlink_auto] {
+        assert_eq!(create(".", &link).unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read(&link).unwrap(), b"original");
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// An empty target or link path is refused everywhere, rather than doing whatever the OS does.
+#[test]
+fn symlink_empty_paths() {
+    let dir = test_dir("empty");
+    let (link, empty) = (dir.join("link"), PathBuf::new());
+    for create in &[symlink_file::<&str, &PathBuf>, symlink_dir, symlink_auto] {
+        let err = create("", &link).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(err.to_string(), "symlink target must not be empty");
+        assert!(!is_symlink(&link));
+        let err = create("target", &empty).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(err.to_string(), "symlink path must not be empty");
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// A missing parent directory of the link is NotFound, saying so, where a missing target isn’t.
+#[test]
+fn symlink_missing_parent() {
+    let dir = test_dir("missing-parent");
+    let link = dir.join("missing").join("link");
+    for create in &[symlink_file::<&str, &PathBuf>, symlink_dir, symlink_auto] {
+        let err = create(".", &link).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(err.to_string().contains("parent directory of the link does not exist"), "{}", err);
+    }
+    if cfg!(windows) {
+        let err = symlink_auto("missing", dir.join("link")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(!err.to_string().contains("parent directory"), "{}", err);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// A failed creation keeps the OS’s error code: on the error itself when it’s passed through, and
+/// reachable with `raw_os_error` when it has been explained. Refusals the OS never saw have none.
+#[test]
+fn symlink_raw_os_error() {
+    use symlink::raw_os_error;
+    let dir = test_dir("raw-os-error");
+    let link = dir.join("link");
+    symlink_file("target", &link).unwrap();
+    let err = symlink_file("target", &link).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    assert!(err.raw_os_error().is_some());
+    assert_eq!(raw_os_error(&err), err.raw_os_error());
+
+    let err = symlink_file("target", dir.join("missing").join("link")).unwrap_err();
+    assert_eq!(err.raw_os_error(), None);
+    assert!(raw_os_error(&err).is_some(), "{:?}", err);
+
+    let err = symlink_file("", dir.join("empty")).unwrap_err();
+    assert_eq!(raw_os_error(&err), None);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// A symlink to itself is refused everywhere, however the target is spelt, and nothing is created.
+#[test]
+fn symlink_to_itself() {
+    let dir = test_dir("itself");
+    let link = dir.join("link");
+    let targets = [link.clone(), PathBuf::from("link"), PathBuf::from("./sub/../link"),
+                   dir.join(".").join("link")];
+    for target in &targets {
+        for create in &[symlink_file::<&PathBuf, &PathBuf>, symlink_dir, symlink_auto] {
+            let err = create(target, &link).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{}", target.display());
+            assert!(!is_symlink(&link));
+        }
+    }
+    // Nor may a link be replaced by one to itself.
+    symlink_file("elsewhere", &link).unwrap();
+    for target in &targets {
+        let err = SymlinkBuilder::new().replace_existing(true).symlink_file(target, &link)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{}", target.display());
+        assert_eq!(fs::read_link(&link).unwrap(), Path::new("elsewhere"));
+    }
+    remove_symlink_file(&link).unwrap();
+    // A target spelt like the link but relative to its parent is somewhere else entirely.
+    let nested = dir.join("sub").join("link");
+    fs::create_dir(dir.join("sub")).unwrap();
+    symlink_file("sub/link", &nested).unwrap();
+    remove_symlink_file(&nested).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// A relative link path is resolved against the working directory before being compared with an
+/// absolute target.
+#[cfg(unix)]
+#[test]
+fn symlink_to_itself_relative_link() {
+    let dir = fs::canonicalize(test_dir("itself-relative")).unwrap();
+    let cwd = std::env::current_dir().unwrap();
+    let mut relative = PathBuf::new();
+    for _ in cwd.components().skip(1) {
+        relative.push("..");
+    }
+    relative.push(dir.strip_prefix("/").unwrap());
+    let link = relative.join("link");
+    let err = symlink_file(dir.join("link"), &link).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(!is_symlink(&link));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Creating a link in a read-only directory is PermissionDenied, and the error names the link.
+#[cfg(unix)]
+#[test]
+fn symlink_read_only_parent() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = test_dir("read-only");
+    let parent = dir.join("parent");
+    fs::create_dir(&parent).unwrap();
+    fs::set_permissions(&parent, fs::Permissions::from_mode(0o555)).unwrap();
+    let link = parent.join("link");
+    let result = symlink_file("target", &link);
+    fs::set_permissions(&parent, fs::Permissions::from_mode(0o755)).unwrap();
+    // With privilege (as root, say) the permissions don’t stop anything.
+    if let Err(err) = result {
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(err.to_string().contains(&*link.to_string_lossy()), "{}", err);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Where `ATOMIC_REPLACE_SUPPORTED` promises it, a reader watching a link being replaced over and
+/// over never catches it missing. (Best-effort: a non-atomic replacement could get lucky, but
+/// hundreds of times running is unlikely.) Plain creation never replaces at all.
+#[test]
+fn replace_existing_atomicity() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use symlink::ATOMIC_REPLACE_SUPPORTED;
+
+    assert_eq!(ATOMIC_REPLACE_SUPPORTED, cfg!(unix));
+    let dir = test_dir("atomic-replace");
+    let link = dir.join("link");
+    symlink_file("a", &link).unwrap();
+    assert_eq!(symlink_file("b", &link).unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+
+    let done = Arc::new(AtomicBool::new(false));
+    let reader = {
+        let (done, link) = (done.clone(), link.clone());
+        thread::spawn(move || {
+            let mut missing = 0;
+            while !done.load(Ordering::Relaxed) {
+                match fs::read_link(&link) {
+                    Ok(ref target) if target == Path::new("a") || target == Path::new("b") => {}
+                    Ok(target) => panic!("link read back as {:?}", target),
+                    Err(_) => missing += 1,
+                }
+            }
+            missing
+        })
+    };
+    let mut builder = SymlinkBuilder::new();
+    builder.replace_existing(true);
+    for i in 0..500 {
+        builder.symlink_file(if i % 2 == 0 { "b" } else { "a" }, &link).unwrap();
+    }
+    done.store(true, Ordering::Relaxed);
+    let missing = reader.join().unwrap();
+    if ATOMIC_REPLACE_SUPPORTED {
+        assert_eq!(missing, 0);
+    }
+    assert_eq!(fs::read_link(&link).unwrap(), Path::new("a"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// `remove_symlink_dir` is `remove_dir` on Windows and `remove_file` elsewhere, which shows when
+/// it’s pointed at things that aren’t symlinks at all.
+#[test]
+fn remove_symlink_dir_semantics() {
+    let dir = test_dir("remove-dir");
+    let real_dir = dir.join("real-dir");
+    let real_file = dir.join("real-file");
+    fs::create_dir(&real_dir).unwrap();
+    fs::write(&real_file, b"").unwrap();
+    if cfg!(windows) {
+        remove_symlink_dir(&real_dir).unwrap();
+        assert!(!real_dir.exists());
+        assert!(remove_symlink_dir(&real_file).is_err());
+        assert!(real_file.exists());
+    } else {
+        assert!(remove_symlink_dir(&real_dir).is_err());
+        assert!(real_dir.exists());
+        remove_symlink_dir(&real_file).unwrap();
+        assert!(!real_file.exists());
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// `remove_symlink_file` is `remove_file` everywhere, so on Windows it can’t remove a directory
+/// symlink, while elsewhere it can.
+#[test]
+fn remove_symlink_file_on_dir_symlink() {
+    let dir = test_dir("remove-file-dir");
+    let link = dir.join("link");
+    symlink_dir(&dir, &link).unwrap();
+    if cfg!(windows) {
+        assert!(remove_symlink_file(&link).is_err());
+        assert!(is_symlink(&link));
+        remove_symlink_dir(&link).unwrap();
+    } else {
+        remove_symlink_file(&link).unwrap();
+    }
+    assert!(!is_symlink(&link));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// `remove_symlink_auto` inspects the path on Windows, refusing non-symlinks; elsewhere it’s
+/// `remove_file`.
+#[test]
+fn remove_symlink_auto_semantics() {
+    let dir = test_dir("remove-auto");
+    let file_link = dir.join("file-link");
+    let dir_link = dir.join("dir-link");
+    let real_file = dir.join("real-file");
+    fs::write(&real_file, b"").unwrap();
+    symlink_file(&real_file, &file_link).unwrap();
+    symlink_dir(&dir, &dir_link).unwrap();
+
+    remove_symlink_auto(&file_link).unwrap();
+    remove_symlink_auto(&dir_link).unwrap();
+    assert!(!is_symlink(&file_link));
+    assert!(!is_symlink(&dir_link));
+
+    if cfg!(windows) {
+        assert_eq!(remove_symlink_auto(&real_file).unwrap_err().kind(),
+                   io::ErrorKind::InvalidInput);
+        assert!(real_file.exists());
+    } else {
+        remove_symlink_auto(&real_file).unwrap();
+        assert!(!real_file.exists());
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Removing a symlink never touches what it points to.
+#[test]
+fn remove_leaves_target() {
+    let dir = test_dir("remove-target");
+    let target = dir.join("target");
+    fs::create_dir(&target).unwrap();
+    fs::write(target.join("file"), b"contents").unwrap();
+    symlink_dir(&target, dir.join("link")).unwrap();
+    remove_symlink_dir(dir.join("link")).unwrap();
+    assert_eq!(fs::read(target.join("file")).unwrap(), b"contents");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Replacing a directory symlink, by any of the ways there are of doing it, replaces only the
+/// link: the directory it pointed to keeps everything in it.
+#[test]
+fn replace_dir_symlink_leaves_target() {
+    use symlink::{ensure_symlink_dir, remove_manifest, symlink, ConflictPolicy, LinkKind,
+                  SymlinkBuilder, SymlinkContext};
+    let dir = test_dir("replace-target");
+    let (old, new) = (dir.join("old"), dir.join("new"));
+    fs::create_dir_all(old.join("nested")).unwrap();
+    fs::write(old.join("file"), b"contents").unwrap();
+    fs::write(old.join("nested").join("file"), b"nested").unwrap();
+    fs::create_dir(&new).unwrap();
+    let replace = |how: &str, link: &Path| -> io::Result<()> {
+        let policy = |policy| symlink(LinkKind::Dir, &new, link, &policy).map(|_| ());
+        match how {
+            "replace_existing" => {
+                SymlinkBuilder::new().replace_existing(true).symlink_dir(&new, link)
+            }
+            "ensure" => ensure_symlink_dir(&new, link).map(|_| ()),
+            "replace policy" => policy(ConflictPolicy::ReplaceSymlinkOnly),
+            "overwrite policy" => policy(ConflictPolicy::Overwrite),
+            "context" => {
+                let mut ctx = SymlinkContext::new();
+                ctx.conflict_policy(ConflictPolicy::Overwrite).symlink_dir(&new, link).map(|_| ())
+            }
+            "remove_manifest" => remove_manifest(&[link]).remove(0).1.map(|_| ()),
+            _ => remove_symlink_auto(link),
+        }
+    };
+    for how in &["replace_existing", "ensure", "replace policy", "overwrite policy", "context",
+                 "remove_manifest", "remove_symlink_auto"] {
+        let link = dir.join("link");
+        symlink_dir(&old, &link).unwrap();
+        replace(how, &link).unwrap();
+        assert_eq!(fs::read(old.join("file")).unwrap(), b"contents", "{}", how);
+        assert_eq!(fs::read(old.join("nested").join("file")).unwrap(), b"nested", "{}", how);
+        let _ = remove_symlink_dir(&link);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// The target is stored exactly as given, relative or not.
+#[test]
+fn target_stored_as_given() {
+    let dir = test_dir("stored");
+    for (i, target) in ["relative", "../up/and/over", "./dot"].iter().enumerate() {
+        let link = dir.join(format!("link{}", i));
+        symlink_file(target, &link).unwrap();
+        assert_eq!(fs::read_link(&link).unwrap(), PathBuf::from(target));
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
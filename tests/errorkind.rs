@@ -0,0 +1,48 @@
+#![cfg(feature = "error-kind")]
+
+use std::env::temp_dir;
+use std::fs;
+use std::io;
+
+extern crate symlink_rs as symlink;
+use symlink::{ensure_symlink_file, follow_one_hop, raw_os_error, resolve_symlink, symlink_file,
+              SymlinkError, SymlinkErrorKind};
+
+fn kind<T>(result: io::Result<T>) -> SymlinkErrorKind {
+    SymlinkError::from(result.err().expect("an error")).kind()
+}
+
+#[test]
+fn test_symlink_error_kind() {
+    let dir = temp_dir().join("symlink-crate-error-kind");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir(&dir).unwrap();
+    fs::write(dir.join("file"), "").unwrap();
+
+    assert_eq!(kind(follow_one_hop(dir.join("file"))), SymlinkErrorKind::NotASymlink);
+    assert_eq!(kind(ensure_symlink_file("elsewhere", dir.join("file"))),
+               SymlinkErrorKind::Conflict);
+    assert_eq!(kind(symlink_file("elsewhere", dir.join("file"))),
+               SymlinkErrorKind::AlreadyExists);
+    assert_eq!(kind(symlink_file("file", dir.join("missing").join("link"))),
+               SymlinkErrorKind::LinkParentMissing);
+    assert_eq!(kind(follow_one_hop(dir.join("missing"))), SymlinkErrorKind::TargetMissing);
+    assert_eq!(kind::<()>(Err(io::Error::other("nothing in particular"))), SymlinkErrorKind::Io);
+    if cfg!(unix) {
+        symlink_file("loop-b", dir.join("loop-a")).unwrap();
+        symlink_file("loop-a", dir.join("loop-b")).unwrap();
+        assert_eq!(kind(resolve_symlink(dir.join("loop-a"))), SymlinkErrorKind::Loop);
+    }
+
+    // Back to the very error, message, code and all.
+    let error = SymlinkError::from(ensure_symlink_file("elsewhere", dir.join("file")).unwrap_err());
+    let message = error.to_string();
+    let error = io::Error::from(error);
+    assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+    assert_eq!(error.to_string(), message);
+    assert!(message.contains("exists and is not a symlink"), "{}", message);
+    assert_eq!(SymlinkErrorKind::of(&error), SymlinkErrorKind::Conflict);
+    assert_eq!(raw_os_error(&error), None);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,317 @@
+use std::env::temp_dir;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[macro_use]
+extern crate symlink_rs as symlink;
+use symlink::{apply, apply_from_reader, apply_with_progress, diff_manifest, read_lock, reconcile,
+              remove_manifest, symlink_file, symlink_stream, symlink_stream_with_progress,
+              validate_manifest, verify_against_lock, write_lock, CreateOutcome, LinkDiff,
+              LinkKind, LinkSpec, Manifest, ManifestProblem, ReconcileAction};
+
+fn test_dir(name: &str) -> PathBuf {
+    let dir = temp_dir().join(format!("symlink-crate-manifest-{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_links_macro() {
+    let specs: &[LinkSpec] = links![
+        file "a" => "link-a",
+        dir PathBuf::from("b") => Path::new("link-b"),
+        auto "c" => "link-c",
+    ];
+    assert_eq!(specs, &[LinkSpec::new(LinkKind::File, "a", "link-a"),
+                        LinkSpec::new(LinkKind::Dir, "b", "link-b"),
+                        LinkSpec::new(LinkKind::Auto, "c", "link-c")][..]);
+    let empty: &[LinkSpec] = links![];
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn test_validate_and_apply() {
+    let dir = test_dir("apply");
+    fs::create_dir(dir.join("target-dir")).unwrap();
+    fs::write(dir.join("target-file"), b"").unwrap();
+    fs::write(dir.join("occupied"), b"").unwrap();
+
+    let specs = links![
+        file "target-file" => dir.join("file-link"),
+        dir "target-dir" => dir.join("dir-link"),
+        file "" => dir.join("empty"),
+        file "self" => dir.join("self"),
+        file "elsewhere" => dir.join("./file-link"),
+        file "target-file" => dir.join("occupied"),
+    ];
+    assert_eq!(validate_manifest(specs).unwrap(), vec![ManifestProblem::EmptyPath(2),
+                                                       ManifestProblem::SelfLink(3),
+                                                       ManifestProblem::DuplicateLink(0, 4),
+                                                       ManifestProblem::Occupied(5)]);
+
+    let results = apply(&specs[..2]);
+    assert_eq!(results.len(), 2);
+    for (spec, result) in results {
+        result.unwrap();
+        assert_eq!(fs::read_link(&spec.link).unwrap(), spec.target);
+    }
+    assert!(validate_manifest(&specs[..2]).unwrap().is_empty());
+    assert!(apply(&specs[5..]).pop().unwrap().1.is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_symlink_stream() {
+    let dir = test_dir("stream");
+    let specs = (0..10).map(|i| {
+        LinkSpec::new(LinkKind::File, "target", dir.join(format!("link-{}", i % 5)))
+    });
+    let mut stream = symlink_stream(specs);
+    // Nothing happens until it’s asked for.
+    assert!(dir.join("link-0").symlink_metadata().is_err());
+    let (spec, result) = stream.next().unwrap();
+    result.unwrap();
+    assert_eq!(fs::read_link(&spec.link).unwrap(), Path::new("target"));
+    assert!(dir.join("link-1").symlink_metadata().is_err());
+    let results: Vec<_> = stream.map(|(_, result)| result.is_ok()).collect();
+    assert_eq!(results, [true, true, true, true, false, false, false, false, false]);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_progress() {
+    let dir = test_dir("progress");
+    let specs = links![
+        file "target" => dir.join("a"),
+        file "target" => dir.join("a"),
+        file "target" => dir.join("b"),
+    ];
+    let mut seen = vec![];
+    let results = apply_with_progress(specs, |done, total, link| {
+        seen.push((done, total, link.to_owned()));
+    });
+    assert!(results[1].1.is_err());
+    assert_eq!(seen, [(1, Some(3), dir.join("a")), (2, Some(3), dir.join("a")),
+                      (3, Some(3), dir.join("b"))]);
+
+    let mut seen = vec![];
+    {
+        let specs = (0..2).map(|i| {
+            LinkSpec::new(LinkKind::File, "target", dir.join(i.to_string()))
+        });
+        let mut stream = symlink_stream_with_progress(specs, |done, total, link| {
+            seen.push((done, total, link.to_owned()));
+        });
+        stream.next().unwrap().1.unwrap();
+        stream.next().unwrap().1.unwrap();
+        assert!(stream.next().is_none());
+    }
+    assert_eq!(seen, [(1, None, dir.join("0")), (2, None, dir.join("1"))]);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_manifest_plan_and_apply() {
+    let dir = test_dir("plan");
+    fs::create_dir(dir.join("target-dir")).unwrap();
+    symlink_file("target-file", dir.join("correct")).unwrap();
+    symlink_file("elsewhere", dir.join("wrong")).unwrap();
+
+    let manifest = Manifest {
+        specs: links![
+            file "target-file" => dir.join("new"),
+            file "target-file" => dir.join("correct"),
+            file "target-file" => dir.join("wrong"),
+            dir "target-dir" => dir.join("dir"),
+        ].to_vec(),
+    };
+    let outcomes = [CreateOutcome::Created, CreateOutcome::AlreadyCorrect,
+                    CreateOutcome::Repointed, CreateOutcome::Created];
+    let plan = manifest.plan().unwrap();
+    assert_eq!(plan.iter().map(|&(_, o)| o).collect::<Vec<_>>(), outcomes);
+    // Planning changed nothing.
+    assert!(dir.join("new").symlink_metadata().is_err());
+    assert_eq!(fs::read_link(dir.join("wrong")).unwrap(), Path::new("elsewhere"));
+
+    let applied = manifest.apply().unwrap();
+    assert_eq!(applied.into_iter().map(|(_, r)| r.unwrap()).collect::<Vec<_>>(), outcomes);
+    assert_eq!(fs::read_link(dir.join("wrong")).unwrap(), Path::new("target-file"));
+    assert!(manifest.plan().unwrap().iter().all(|&(_, o)| o == CreateOutcome::AlreadyCorrect));
+
+    // A manifest with problems is neither planned nor applied.
+    let manifest = Manifest { specs: links![file "self" => dir.join("self")].to_vec() };
+    assert_eq!(manifest.validate().unwrap(), vec![ManifestProblem::SelfLink(0)]);
+    assert_eq!(manifest.plan().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(manifest.apply().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_apply_from_reader() {
+    let dir = test_dir("from-reader");
+    let input = format!("# links for the app\n\nconfig.toml\t{}\r\nother target\t{}\n",
+                        dir.join("config").display(), dir.join("with space").display());
+    let results = apply_from_reader(input.as_bytes(), LinkKind::File).unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].0, LinkSpec::new(LinkKind::File, "config.toml", dir.join("config")));
+    assert_eq!(*results[0].1.as_ref().unwrap(), CreateOutcome::Created);
+    assert_eq!(fs::read_link(dir.join("with space")).unwrap(), Path::new("other target"));
+    let results = apply_from_reader(input.as_bytes(), LinkKind::File).unwrap();
+    assert_eq!(*results[1].1.as_ref().unwrap(), CreateOutcome::AlreadyCorrect);
+
+    // A bad line stops everything, and says where it was.
+    let input = format!("a\t{}\nno tab here\n", dir.join("never").display());
+    let err = apply_from_reader(input.as_bytes(), LinkKind::File).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains("line 2"), "{}", err);
+    assert!(fs::symlink_metadata(dir.join("never")).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_remove_manifest() {
+    let dir = test_dir("remove");
+    let specs = links![
+        file "target" => dir.join("file-link"),
+        dir "." => dir.join("dir-link"),
+    ];
+    assert!(apply(specs).iter().all(|(_, result)| result.is_ok()));
+    fs::write(dir.join("not-a-link"), b"keep").unwrap();
+
+    let mut links: Vec<PathBuf> = specs.iter().map(|spec| spec.link.clone()).collect();
+    links.push(dir.join("not-a-link"));
+    let results = remove_manifest(&links);
+    assert_eq!(results.len(), 3);
+    assert!(*results[0].1.as_ref().unwrap() && *results[1].1.as_ref().unwrap());
+    assert_eq!(results[2].0, dir.join("not-a-link"));
+    assert_eq!(results[2].1.as_ref().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(fs::read(dir.join("not-a-link")).unwrap(), b"keep");
+    assert!(fs::symlink_metadata(dir.join("dir-link")).is_err());
+
+    // Gone already is fine.
+    let results = remove_manifest(&links[..2]);
+    assert!(results.iter().all(|(_, result)| !*result.as_ref().unwrap()));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_diff_manifest() {
+    let dir = test_dir("diff");
+    fs::create_dir(dir.join("sub")).unwrap();
+    symlink_file("target", dir.join("right")).unwrap();
+    symlink_file("elsewhere", dir.join("sub/wrong")).unwrap();
+    fs::write(dir.join("file"), b"").unwrap();
+    symlink_file("whatever", dir.join("sub/extra")).unwrap();
+    let specs = links![
+        file "target" => "right",
+        file "target" => "./sub/wrong",
+        file "target" => "missing",
+        file "target" => "file",
+    ];
+    assert_eq!(diff_manifest(specs, &dir).unwrap(), [
+        LinkDiff::WrongTarget {
+            link: dir.join("./sub/wrong"),
+            current: "elsewhere".into(),
+            desired: "target".into(),
+        },
+        LinkDiff::Missing(specs[2].clone()),
+        LinkDiff::NotASymlink(dir.join("file")),
+        LinkDiff::Extra(dir.join("sub/extra")),
+    ]);
+
+    // Once applied and the extra link gone, there’s no difference left.
+    let specs = specs[..3].iter()
+        .map(|spec| LinkSpec::new(spec.kind, &spec.target, dir.join(&spec.link)))
+        .collect();
+    let manifest = Manifest { specs };
+    assert!(manifest.apply().unwrap().iter().all(|(_, result)| result.is_ok()));
+    remove_manifest(&[dir.join("sub/extra")]);
+    assert_eq!(diff_manifest(&manifest.specs, &dir).unwrap(), []);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_verify_against_lock() {
+    let dir = test_dir("lock");
+    let lock = links![
+        file "target" => "a",
+        dir "../elsewhere" => "sub/b",
+    ];
+    let mut text = vec![];
+    write_lock(lock, &mut text).unwrap();
+    assert_eq!(text, b"file\ttarget\ta\ndir\t../elsewhere\tsub/b\n");
+    let text = b"# pinned\n\nfile\ttarget\ta\r\ndir\t../elsewhere\tsub/b\n";
+    let lock = read_lock(&text[..]).unwrap();
+    assert_eq!(lock, links![file "target" => "a", dir "../elsewhere" => "sub/b"]);
+    let err = read_lock(&b"file\ttarget\n"[..]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err.to_string().starts_with("line 1 isn’t a kind"), "{}", err);
+    assert!(write_lock(&[LinkSpec::new(LinkKind::File, "a\tb", "c")], vec![]).is_err());
+
+    assert_eq!(verify_against_lock(&lock, &dir).unwrap(), [
+        LinkDiff::Missing(lock[0].clone()),
+        LinkDiff::Missing(lock[1].clone()),
+    ]);
+    fs::create_dir(dir.join("sub")).unwrap();
+    symlink_file("target", dir.join("a")).unwrap();
+    symlink::symlink_dir("../elsewhere", dir.join("sub/b")).unwrap();
+    assert!(verify_against_lock(&lock, &dir).unwrap().is_empty());
+    symlink_file("target", dir.join("stray")).unwrap();
+    assert_eq!(verify_against_lock(&lock, &dir).unwrap(), [LinkDiff::Extra(dir.join("stray"))]);
+
+    let twice = links![file "target" => "a", file "other" => "./a"];
+    assert_eq!(verify_against_lock(twice, &dir).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_reconcile() {
+    let dir = test_dir("reconcile");
+    fs::create_dir(dir.join("sub")).unwrap();
+    symlink_file("target", dir.join("right")).unwrap();
+    symlink_file("elsewhere", dir.join("sub/wrong")).unwrap();
+    symlink_file("whatever", dir.join("sub/extra")).unwrap();
+    let specs = links![
+        file "target" => "right",
+        file "target" => "sub/wrong",
+        file "target" => "missing",
+    ];
+    assert_eq!(reconcile(specs, &dir, false).unwrap(), [
+        (dir.join("right"), ReconcileAction::Unchanged),
+        (dir.join("sub/wrong"), ReconcileAction::Repointed),
+        (dir.join("missing"), ReconcileAction::Created),
+    ]);
+    assert_eq!(diff_manifest(specs, &dir).unwrap(), [LinkDiff::Extra(dir.join("sub/extra"))]);
+    assert_eq!(reconcile(specs, &dir, true).unwrap(), [
+        (dir.join("right"), ReconcileAction::Unchanged),
+        (dir.join("sub/wrong"), ReconcileAction::Unchanged),
+        (dir.join("missing"), ReconcileAction::Unchanged),
+        (dir.join("sub/extra"), ReconcileAction::Removed),
+    ]);
+    assert_eq!(diff_manifest(specs, &dir).unwrap(), []);
+
+    // A failure part way undoes what was done before it.
+    let failing = links![
+        file "elsewhere" => "right",
+        file "target" => "new",
+        file "target" => "no-such-dir/link",
+    ];
+    assert!(reconcile(failing, &dir, true).is_err());
+    assert_eq!(fs::read_link(dir.join("right")).unwrap(), Path::new("target"));
+    assert!(fs::symlink_metadata(dir.join("new")).is_err());
+    assert!(fs::symlink_metadata(dir.join("missing")).is_ok());
+
+    // Nothing is done at all if a real file is in the way, or a link is listed twice.
+    fs::write(dir.join("file"), b"").unwrap();
+    let blocked = links![file "target" => "new", file "target" => "file"];
+    let err = reconcile(blocked, &dir, true).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(fs::symlink_metadata(dir.join("new")).is_err());
+    let twice = links![file "target" => "new", file "other" => "./new"];
+    assert_eq!(reconcile(twice, &dir, true).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    fs::remove_dir_all(&dir).unwrap();
+}
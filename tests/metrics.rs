@@ -0,0 +1,48 @@
+#![cfg(feature = "metrics")]
+
+use std::env::temp_dir;
+use std::fs;
+
+extern crate symlink_rs as symlink;
+use symlink::metrics::{counter, counters, CREATED, FAILED, REMOVED};
+use symlink::{remove_symlink_dir, remove_symlink_file, symlink_dir, symlink_file, SymlinkBuilder,
+              SymlinkKind};
+
+// The counters are process-wide, so everything that touches them is in this one test.
+#[test]
+fn test_counters() {
+    let dir = temp_dir().join("symlink-crate-metrics");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir(&dir).unwrap();
+    assert_eq!(counters().len(), 12);
+    assert_eq!(counter("symlink.nonsense", "file"), None);
+    assert_eq!(counter(CREATED, "nonsense"), None);
+
+    let before = counters();
+    symlink_file("target", dir.join("file-link")).unwrap();
+    symlink_dir("target", dir.join("dir-link")).unwrap();
+    assert!(symlink_file("target", dir.join("file-link")).is_err());
+    // One call, one attempt, one failure, even with a preferred kind.
+    assert!(SymlinkBuilder::new().prefer_kind(SymlinkKind::File)
+        .symlink_auto("target", dir.join("file-link")).is_err());
+    remove_symlink_file(dir.join("file-link")).unwrap();
+    remove_symlink_dir(dir.join("dir-link")).unwrap();
+
+    let delta = |name: &str, kind: &str| {
+        let old = before.iter().find(|c| c.name == name && c.kind == kind).unwrap().value;
+        counter(name, kind).unwrap() - old
+    };
+    assert_eq!(delta(CREATED, "file"), 1);
+    assert_eq!(delta(CREATED, "dir"), 1);
+    assert_eq!(delta(FAILED, "file"), 2);
+    assert_eq!(delta(FAILED, "auto"), 0);
+    assert_eq!(delta(REMOVED, "file"), 1);
+    assert_eq!(delta(REMOVED, "dir"), 1);
+    assert_eq!(delta(CREATED, "auto"), 0);
+
+    // A diagnosis makes and removes links of its own, but they aren’t the program’s to count.
+    let before = counters();
+    symlink::diagnose(&dir).unwrap();
+    assert_eq!(counters(), before);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,70 @@
+#![cfg(feature = "mock")]
+
+use std::io;
+use std::path::Path;
+
+extern crate symlink_rs as symlink;
+use symlink::{MockLink, MockSymlinks, SymlinkKind, SymlinkOps};
+
+#[test]
+fn test_mock_create_and_remove() {
+    let mock = MockSymlinks::new();
+    let ops: &dyn SymlinkOps = &mock;
+    ops.symlink_file(Path::new("target"), Path::new("out/./link")).unwrap();
+    assert!(ops.is_symlink(Path::new("out/link")));
+    assert_eq!(ops.read_symlink(Path::new("out/link")).unwrap(), Path::new("target"));
+    assert_eq!(mock.links()[Path::new("out/link")],
+               MockLink { target: "target".into(), kind: Some(SymlinkKind::File) });
+
+    let err = ops.symlink_dir(Path::new("other"), Path::new("out/link")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+    ops.remove_symlink_file(Path::new("out/link")).unwrap();
+    assert!(mock.links().is_empty());
+    let err = ops.remove_symlink_file(Path::new("out/link")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert_eq!(ops.read_symlink(Path::new("out/link")).unwrap_err().kind(),
+               io::ErrorKind::NotFound);
+}
+
+#[test]
+fn test_mock_targets() {
+    let mock = MockSymlinks::new();
+    mock.add_target("real", SymlinkKind::Dir);
+    assert_eq!(mock.symlink_file(Path::new("x"), Path::new("real")).unwrap_err().kind(),
+               io::ErrorKind::AlreadyExists);
+    assert_eq!(mock.remove_symlink_auto(Path::new("real")).unwrap_err().kind(),
+               io::ErrorKind::InvalidInput);
+    assert!(!mock.is_symlink(Path::new("real")));
+
+    // Relative targets are looked up relative to the link’s parent, as the OS would.
+    mock.symlink_auto(Path::new("../real"), Path::new("sub/link")).unwrap();
+    assert_eq!(mock.links()[Path::new("sub/link")].kind, Some(SymlinkKind::Dir));
+}
+
+#[test]
+fn test_mock_windows_like() {
+    let mut mock = MockSymlinks::new();
+    mock.distinguish_kinds(true);
+    mock.symlink_dir(Path::new("target"), Path::new("link")).unwrap();
+    assert_eq!(mock.remove_symlink_file(Path::new("link")).unwrap_err().kind(),
+               io::ErrorKind::PermissionDenied);
+    mock.remove_symlink_dir(Path::new("link")).unwrap();
+    mock.symlink_file(Path::new("target"), Path::new("link")).unwrap();
+    assert_eq!(mock.remove_symlink_dir(Path::new("link")).unwrap_err().kind(),
+               io::ErrorKind::NotADirectory);
+    mock.remove_symlink_auto(Path::new("link")).unwrap();
+
+    assert_eq!(mock.symlink_auto(Path::new("unknown"), Path::new("link")).unwrap_err().kind(),
+               io::ErrorKind::NotFound);
+}
+
+#[test]
+fn test_mock_unix_like() {
+    let mut mock = MockSymlinks::new();
+    mock.distinguish_kinds(false);
+    mock.symlink_dir(Path::new("target"), Path::new("link")).unwrap();
+    mock.remove_symlink_file(Path::new("link")).unwrap();
+    mock.symlink_auto(Path::new("unknown"), Path::new("link")).unwrap();
+    assert_eq!(mock.links()[Path::new("link")].kind, None);
+}
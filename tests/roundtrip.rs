@@ -0,0 +1,150 @@
+// Property tests: arbitrary (valid) targets must read back exactly as they were written, for both
+// kinds of link. There’s no proptest here, so that the crate keeps no dependencies at all; a small
+// seeded generator and a greedy shrinker do the job. Set SYMLINK_ROUNDTRIP_SEED to reproduce a
+// failure, or to try a different corner of the space.
+
+use std::env::{self, temp_dir};
+use std::fs;
+use std::path::{Path, PathBuf, MAIN_SEPARATOR_STR};
+
+extern crate symlink_rs as symlink;
+use symlink::{remove_symlink_dir, remove_symlink_file, symlink_dir, symlink_file, SymlinkKind};
+
+const CASES: usize = 64;
+
+/// Characters to build names from: plain ones, punctuation that’s legal everywhere, and some
+/// from outside ASCII, up to the astral planes.
+const CHARS: &[char] = &['a', 'b', 'Z', '0', '9', '-', '_', '.', ' ', '~', '+', '=', ',', ';',
+                         '\'', '(', ']', '{', 'é', 'ß', 'я', '中', '文', '\u{200b}', '🦀', '𝄞'];
+
+/// xorshift64*, which is plenty for picking test cases.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// A target as a list of components, joined with the platform separator.
+type Case = Vec<String>;
+
+fn valid_component(c: &str) -> bool {
+    // Windows won’t have names ending in a space or a dot, and . and .. aren’t names at all.
+    !c.is_empty() && c != "." && c != ".." && !c.ends_with(' ') && !c.ends_with('.')
+}
+
+fn generate(rng: &mut Rng) -> Case {
+    let components = 1 + rng.below(4);
+    (0..components).map(|_| {
+        loop {
+            let len = 1 + rng.below(12);
+            let c: String = (0..len).map(|_| CHARS[rng.below(CHARS.len())]).collect();
+            if valid_component(&c) {
+                return c;
+            }
+        }
+    }).collect()
+}
+
+fn target(case: &Case) -> PathBuf {
+    PathBuf::from(case.join(MAIN_SEPARATOR_STR))
+}
+
+/// Create a link of each kind to the case’s target and check what comes back, returning a
+/// description of the first discrepancy.
+fn check(dir: &Path, case: &Case) -> Result<(), String> {
+    let target = target(case);
+    for &kind in &[SymlinkKind::File, SymlinkKind::Dir] {
+        let link = dir.join("link");
+        let created = match kind {
+            SymlinkKind::File => symlink_file(&target, &link),
+            SymlinkKind::Dir => symlink_dir(&target, &link),
+        };
+        created.map_err(|e| format!("{:?} link to {:?}: {}", kind, target, e))?;
+        let read = fs::read_link(&link);
+        let kind_ok = read_kind_matches(&link, kind);
+        match kind {
+            SymlinkKind::File => remove_symlink_file(&link),
+            SymlinkKind::Dir => remove_symlink_dir(&link),
+        }.map_err(|e| format!("removing {:?} link to {:?}: {}", kind, target, e))?;
+        match read {
+            Ok(ref read) if *read == target => {}
+            other => {
+                return Err(format!("{:?} link to {:?} read back as {:?}", kind, target, other));
+            }
+        }
+        if !kind_ok {
+            return Err(format!("{:?} link to {:?} read back as the other kind", kind, target));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn read_kind_matches(link: &Path, kind: SymlinkKind) -> bool {
+    use std::os::windows::fs::FileTypeExt;
+    match fs::symlink_metadata(link) {
+        Ok(m) => m.file_type().is_symlink_dir() == (kind == SymlinkKind::Dir),
+        Err(_) => false,
+    }
+}
+
+#[cfg(not(windows))]
+fn read_kind_matches(_link: &Path, _kind: SymlinkKind) -> bool {
+    true
+}
+
+/// Greedily make a failing case smaller: drop components, then characters, for as long as it
+/// keeps failing.
+fn shrink(dir: &Path, mut case: Case) -> Case {
+    'outer: loop {
+        for i in 0..case.len() {
+            if case.len() > 1 {
+                let mut smaller = case.clone();
+                smaller.remove(i);
+                if check(dir, &smaller).is_err() {
+                    case = smaller;
+                    continue 'outer;
+                }
+            }
+            let chars: Vec<char> = case[i].chars().collect();
+            for j in 0..chars.len() {
+                let mut smaller = case.clone();
+                smaller[i] = chars.iter().enumerate().filter(|&(k, _)| k != j).map(|(_, &c)| c)
+                    .collect();
+                if valid_component(&smaller[i]) && check(dir, &smaller).is_err() {
+                    case = smaller;
+                    continue 'outer;
+                }
+            }
+        }
+        return case;
+    }
+}
+
+#[test]
+fn test_target_round_trips() {
+    let seed = env::var("SYMLINK_ROUNDTRIP_SEED").ok().and_then(|s| s.parse().ok())
+        .unwrap_or(0x5eed_f00d_5ec7_1e55);
+    let dir = temp_dir().join("symlink-crate-roundtrip");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir(&dir).unwrap();
+    let mut rng = Rng(seed);
+    for _ in 0..CASES {
+        let case = generate(&mut rng);
+        if check(&dir, &case).is_err() {
+            let minimal = shrink(&dir, case);
+            let error = check(&dir, &minimal).unwrap_err();
+            panic!("round trip failed (seed {}); minimal case {:?}: {}", seed, minimal, error);
+        }
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
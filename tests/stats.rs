@@ -0,0 +1,45 @@
+#![cfg(feature = "stats")]
+
+use std::env::temp_dir;
+use std::fs;
+use std::thread;
+
+extern crate symlink_rs as symlink;
+use symlink::{ensure_symlink_file, remove_symlink_file, symlink_auto, symlink_file, SymlinkStats};
+
+#[test]
+fn test_stats() {
+    let dir = temp_dir().join("symlink-crate-stats");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir(&dir).unwrap();
+    fs::write(dir.join("target"), b"").unwrap();
+    SymlinkStats::take();
+
+    symlink_file("target", dir.join("file-link")).unwrap();
+    assert!(symlink_file("target", dir.join("file-link")).is_err());
+    remove_symlink_file(dir.join("file-link")).unwrap();
+    let stats = SymlinkStats::take();
+    assert_eq!(stats, SymlinkStats { creates: 2, stats: 0, removes: 1 });
+    // Taking them resets them.
+    assert_eq!(SymlinkStats::take(), SymlinkStats::default());
+
+    // symlink_auto only needs to look at the target on Windows.
+    symlink_auto(dir.join("target"), dir.join("auto-link")).unwrap();
+    let stats = SymlinkStats::take();
+    assert_eq!(stats.creates, 1);
+    assert_eq!(stats.stats, if cfg!(windows) { 1 } else { 0 });
+
+    // Finding a link already correct takes looks but no creation.
+    ensure_symlink_file("target", dir.join("ensured")).unwrap();
+    SymlinkStats::take();
+    ensure_symlink_file("target", dir.join("ensured")).unwrap();
+    let stats = SymlinkStats::take();
+    assert_eq!(stats.creates, 0);
+    assert!(stats.stats > 0);
+
+    // Other threads have counts of their own.
+    let link = dir.join("thread-link");
+    thread::spawn(move || symlink_file("target", link).unwrap()).join().unwrap();
+    assert_eq!(SymlinkStats::take(), SymlinkStats::default());
+    fs::remove_dir_all(&dir).unwrap();
+}
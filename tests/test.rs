@@ -2,12 +2,15 @@ use std::env::temp_dir;
 use std::fs::{self, File};
 use std::io;
 use std::io::prelude::*;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-extern crate symlink;
-use symlink::{symlink_auto, symlink_file, symlink_dir, remove_symlink_file, remove_symlink_dir};
+extern crate symlink_rs as symlink;
+use symlink::{symlink_auto, symlink_file, symlink_dir, remove_symlink_file, remove_symlink_dir,
+              symlink_auto_assume, SymlinkBuilder, SymlinkKind, resolve_symlink,
+              resolve_symlink_until_missing, symlink_chain, follow_one_hop, target_relative_to,
+              Resolution};
 
-const TEST_FILE_CONTENTS: &'static [u8] =
+const TEST_FILE_CONTENTS: &[u8] =
     b"This file was created for the purpose of testing the symlink crate.";
 
 #[test]
@@ -102,3 +105,2036 @@ where F: for<'a> FnOnce(&'a Path, &'a Path) -> io::Result<()> {
     assert!(!file_path.exists());
     assert!(!dir_path.exists());
 }
+
+/// A fresh, empty directory for a test to play in.
+fn test_dir(name: &str) -> PathBuf {
+    let dir = temp_dir().join(format!("symlink-crate-test-{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir(&dir).unwrap();
+    dir
+}
+
+#[cfg(unix)]
+#[test]
+fn test_nofollow_parents() {
+    let dir = test_dir("nofollow-parents");
+    let real = dir.join("real");
+    fs::create_dir(&real).unwrap();
+    symlink_dir(&real, dir.join("sneaky")).unwrap();
+
+    let mut builder = SymlinkBuilder::new();
+    builder.nofollow_parents(true);
+
+    let err = builder.symlink_file("target", dir.join("sneaky").join("link")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(real.join("link").symlink_metadata().is_err());
+
+    builder.symlink_file("target", real.join("link")).unwrap();
+    assert_eq!(fs::read_link(real.join("link")).unwrap(), Path::new("target"));
+
+    // The usual refusals apply this way too.
+    let err = builder.symlink_file("itself", real.join("itself")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(real.join("itself").symlink_metadata().is_err());
+
+    // Replacing can’t be done without following parents, and is refused whatever is there.
+    builder.replace_existing(true);
+    for name in &["link", "fresh"] {
+        let err = builder.symlink_file("new", real.join(name)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{}", name);
+    }
+    assert_eq!(fs::read_link(real.join("link")).unwrap(), Path::new("target"));
+    assert!(real.join("fresh").symlink_metadata().is_err());
+    // Nor does a policy that replaces remove anything first.
+    fs::write(real.join("file"), TEST_FILE_CONTENTS).unwrap();
+    let mut ctx = symlink::SymlinkContext::new();
+    ctx.options(builder.clone()).conflict_policy(symlink::ConflictPolicy::Overwrite);
+    let err = ctx.symlink_file("new", real.join("file")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(fs::read(real.join("file")).unwrap(), TEST_FILE_CONTENTS);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// Names that have been known to get mangled somewhere along the way: spaces, non-ASCII, and
+// characters outside the Basic Multilingual Plane (which need surrogate pairs in UTF-16).
+#[cfg(not(unix))]
+const TRICKY_NAMES: &[&str] = &["with spaces", " leading and trailing ", "ünïcödé ñame",
+                                "日本語のファイル", "emoji 🦀🎉", "math 𝔘𝔫𝔦𝔠𝔬𝔡𝔢"];
+// On Unix, a backslash is just another character in a file name.
+#[cfg(unix)]
+const TRICKY_NAMES: &[&str] = &["with spaces", " leading and trailing ", "ünïcödé ñame",
+                                "日本語のファイル", "emoji 🦀🎉", "math 𝔘𝔫𝔦𝔠𝔬𝔡𝔢",
+                                "back\\slash", "tab\tand\nnewline"];
+
+#[test]
+fn test_tricky_names_file() {
+    let dir = test_dir("tricky-names-file");
+    for name in TRICKY_NAMES {
+        let target = dir.join(format!("{} target", name));
+        let link = dir.join(format!("{} link", name));
+        fs::write(&target, TEST_FILE_CONTENTS).unwrap();
+        symlink_file(&target, &link).unwrap();
+        assert_eq!(fs::read_link(&link).unwrap(), target, "{:?}", name);
+        assert_eq!(fs::read(&link).unwrap(), TEST_FILE_CONTENTS, "{:?}", name);
+        remove_symlink_file(&link).unwrap();
+        assert!(!link.exists());
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_tricky_names_dir() {
+    let dir = test_dir("tricky-names-dir");
+    for name in TRICKY_NAMES {
+        let target = dir.join(format!("{} target", name));
+        let link = dir.join(format!("{} link", name));
+        fs::create_dir(&target).unwrap();
+        fs::write(target.join(name), TEST_FILE_CONTENTS).unwrap();
+        symlink_dir(&target, &link).unwrap();
+        assert_eq!(fs::read_link(&link).unwrap(), target, "{:?}", name);
+        assert_eq!(fs::read(link.join(name)).unwrap(), TEST_FILE_CONTENTS, "{:?}", name);
+        remove_symlink_dir(&link).unwrap();
+        assert!(!link.exists());
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_reject_self_reference() {
+    let dir = test_dir("self-reference");
+    let sub = dir.join("sub");
+    fs::create_dir(&sub).unwrap();
+    let mut builder = SymlinkBuilder::new();
+    builder.reject_self_reference(true);
+
+    let link = sub.join("link");
+    for target in &[Path::new(".."), Path::new("."), Path::new("../sub/./link"), &dir, &sub, &link] {
+        let err = builder.symlink_dir(target, &link).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{:?}", target);
+        assert!(link.symlink_metadata().is_err());
+    }
+
+    // Siblings and children are fine, as are targets that don’t exist.
+    builder.symlink_dir("../other", &link).unwrap();
+    remove_symlink_dir(&link).unwrap();
+    builder.symlink_dir("link/child", &link).unwrap();
+    remove_symlink_dir(&link).unwrap();
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(windows)]
+#[test]
+fn test_symlink_file_wide() {
+    use std::ffi::OsString;
+    use std::os::windows::ffi::{OsStrExt, OsStringExt};
+    let dir = test_dir("wide");
+    let target = dir.join("target");
+    fs::write(&target, TEST_FILE_CONTENTS).unwrap();
+    let wide: Vec<u16> = target.as_os_str().encode_wide().collect();
+    let link = dir.join("link");
+    symlink::symlink_file_wide(&wide, &link).unwrap();
+    let read: Vec<u16> = fs::read_link(&link).unwrap().as_os_str().encode_wide().collect();
+    assert_eq!(OsString::from_wide(&read), OsString::from_wide(&wide));
+    assert_eq!(fs::read(&link).unwrap(), TEST_FILE_CONTENTS);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_resolve_symlink() {
+    let dir = test_dir("resolve");
+    fs::create_dir(dir.join("sub")).unwrap();
+    fs::write(dir.join("sub").join("file"), TEST_FILE_CONTENTS).unwrap();
+    symlink_file("sub/file", dir.join("one")).unwrap();
+    symlink_file("../one", dir.join("sub").join("two")).unwrap();
+    symlink_file(dir.join("sub").join("two"), dir.join("three")).unwrap();
+
+    assert_eq!(resolve_symlink(dir.join("three")).unwrap(), dir.join("sub/../sub/file"));
+    assert_eq!(resolve_symlink_until_missing(dir.join("three")).unwrap(),
+               Resolution::Resolved(dir.join("sub/../sub/file")));
+    assert_eq!(resolve_symlink(dir.join("sub")).unwrap(), dir.join("sub"));
+    assert_eq!(symlink_chain(dir.join("three")).unwrap(),
+               [dir.join("three"), dir.join("sub/two"), dir.join("sub/../one"),
+                dir.join("sub/../sub/file")]);
+    assert_eq!(symlink_chain(dir.join("sub")).unwrap(), [dir.join("sub")]);
+    assert_eq!(follow_one_hop(dir.join("sub/two")).unwrap(), dir.join("one"));
+    assert_eq!(follow_one_hop(dir.join("three")).unwrap(), dir.join("sub/two"));
+    assert_eq!(follow_one_hop(dir.join("sub")).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(target_relative_to(dir.join("sub/two"), &dir).unwrap(), Path::new("one"));
+    assert_eq!(target_relative_to(dir.join("three"), dir.join("sub/deeper")).unwrap(),
+               Path::new("../two"));
+    assert_eq!(target_relative_to(dir.join("three"), dir.join("sub/two")).unwrap(), Path::new("."));
+
+    symlink_file("nowhere/at/all", dir.join("sub").join("broken")).unwrap();
+    symlink_file("sub/broken", dir.join("four")).unwrap();
+    assert_eq!(resolve_symlink(dir.join("four")).unwrap(), dir.join("sub/nowhere/at/all"));
+    assert_eq!(resolve_symlink_until_missing(dir.join("four")).unwrap(), Resolution::Broken {
+        hop: 2,
+        path: dir.join("sub/nowhere/at/all"),
+        existing: dir.join("sub"),
+        missing: "nowhere".into(),
+    });
+
+    symlink_file("loop-b", dir.join("loop-a")).unwrap();
+    symlink_file("loop-a", dir.join("loop-b")).unwrap();
+    assert!(resolve_symlink(dir.join("loop-a")).is_err());
+    let err = symlink_chain(dir.join("loop-a")).unwrap_err();
+    assert!(err.to_string().contains("loop-a → ") && err.to_string().contains("loop-b → "),
+            "{}", err);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_max_resolved_components() {
+    use symlink::SymlinkContext;
+
+    let dir = test_dir("resolve-components");
+    fs::create_dir(dir.join("sub")).unwrap();
+    fs::write(dir.join("c"), TEST_FILE_CONTENTS).unwrap();
+    // Each hop adds sub/.. twice over to the path it leads to.
+    symlink_file("sub/../sub/../b", dir.join("a")).unwrap();
+    symlink_file("sub/../sub/../c", dir.join("b")).unwrap();
+    let end = dir.join("sub/../sub/../sub/../sub/../c");
+    assert_eq!(resolve_symlink(dir.join("a")).unwrap(), end);
+
+    let depth = dir.components().count();
+    let mut ctx = SymlinkContext::new();
+    ctx.max_resolved_components(depth + 9);
+    assert_eq!(ctx.resolve_symlink(dir.join("a")).unwrap(), end);
+    ctx.max_resolved_components(depth + 8);
+    let err = ctx.resolve_symlink(dir.join("a")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidFilename);
+    assert!(err.to_string().contains("after 2 hops"), "{}", err);
+    assert_eq!(ctx.symlink_chain(dir.join("a")).unwrap_err().kind(),
+               io::ErrorKind::InvalidFilename);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_diagnose() {
+    use symlink::{diagnose, Probe};
+
+    let dir = test_dir("diagnose");
+    let report = diagnose(&dir).unwrap();
+    assert!(report.file_symlinks.works(), "{}", report);
+    assert!(report.dir_symlinks.works(), "{}", report);
+    assert!(report.dangling_symlinks.works(), "{}", report);
+    assert!(report.replace_existing.works(), "{}", report);
+    assert!(report.long_paths.works(), "{}", report);
+    assert_eq!(report.cleanup, Probe::Works);
+    assert_eq!(report.kinds_distinct, cfg!(windows));
+    if !cfg!(windows) {
+        assert_eq!(report.junctions, Probe::NotApplicable);
+        assert_eq!(report.privilege_held, None);
+    }
+    assert!(report.to_string().contains("file symlinks: works\n"), "{}", report);
+    // Nothing is left behind.
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_symlink_auto_assume() {
+    let temp = temp_dir();
+    let dir_path = temp.join("symlink-crate-test-assume-dir");
+    let symlink_path = temp.join("symlink-crate-test-assume-dir-symlink");
+    test_dir_symlink(&dir_path, &symlink_path,
+                     |src, dst| symlink_auto_assume(src, dst, SymlinkKind::Dir));
+    let file_path = temp.join("symlink-crate-test-assume-file");
+    let symlink_path = temp.join("symlink-crate-test-assume-file-symlink");
+    test_file_symlink(&file_path, &symlink_path,
+                      |src, dst| symlink_auto_assume(src, dst, SymlinkKind::File));
+}
+
+#[test]
+fn test_symlink_with() {
+    use symlink::symlink_with;
+    let temp = temp_dir();
+    let dir_path = temp.join("symlink-crate-test-with-dir");
+    let symlink_path = temp.join("symlink-crate-test-with-dir-symlink");
+    test_dir_symlink(&dir_path, &symlink_path,
+                     |src, dst| symlink_with(src, dst, |_| Ok(SymlinkKind::Dir)));
+
+    let dir = test_dir("with");
+    let err = symlink_with("target", dir.join("link"), |target| {
+        assert_eq!(target, Path::new("target"));
+        Err(io::Error::new(io::ErrorKind::Unsupported, "no idea"))
+    }).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    assert!(fs::symlink_metadata(dir.join("link")).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_prefer_kind() {
+    let temp = temp_dir();
+    let dir_path = temp.join("symlink-crate-test-prefer-dir");
+    let symlink_path = temp.join("symlink-crate-test-prefer-dir-symlink");
+    test_dir_symlink(&dir_path, &symlink_path,
+                     |src, dst| SymlinkBuilder::new().prefer_kind(SymlinkKind::Dir)
+                                                     .symlink_auto(src, dst));
+}
+
+#[test]
+fn test_create_parents() {
+    let dir = test_dir("parents");
+    let mut builder = SymlinkBuilder::new();
+    let err = builder.symlink_file("target", dir.join("a/b/link")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+    builder.create_parents(true);
+    builder.symlink_file("target", dir.join("a/b/link")).unwrap();
+    assert_eq!(fs::read_link(dir.join("a/b/link")).unwrap(), Path::new("target"));
+
+    builder.max_parent_creation(2);
+    let err = builder.symlink_dir("target", dir.join("c/d/e/link")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert!(!dir.join("c").exists());
+    builder.symlink_dir("target", dir.join("a/c/d/link")).unwrap();
+    remove_symlink_dir(dir.join("a/c/d/link")).unwrap();
+
+    // A symlink to a directory that’s already there is followed, and .. is fine too.
+    symlink_dir("a", dir.join("alias")).unwrap();
+    SymlinkBuilder::new().create_parents(true)
+        .symlink_file("target", dir.join("alias/e/../f/link")).unwrap();
+    assert_eq!(fs::read_link(dir.join("a/f/link")).unwrap(), Path::new("target"));
+    remove_symlink_dir(dir.join("alias")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_parent_dir_mode() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = test_dir("parent-mode");
+    let mode = |path: &str| fs::metadata(dir.join(path)).unwrap().permissions().mode() & 0o7777;
+    let mut builder = SymlinkBuilder::new();
+    builder.create_parents(true).parent_dir_mode(0o700);
+    builder.symlink_file("target", dir.join("a/b/link")).unwrap();
+    // The umask can only take bits away.
+    assert_eq!(mode("a") & !0o700, 0);
+    assert_eq!(mode("a/b") & !0o700, 0);
+
+    builder.parent_dir_mode(0o775).parent_dir_mode_exact(true);
+    builder.symlink_file("target", dir.join("a/c/d/link")).unwrap();
+    assert_eq!(mode("a/c"), 0o775);
+    assert_eq!(mode("a/c/d"), 0o775);
+    // Directories already there are left alone.
+    assert_eq!(mode("a") & !0o700, 0);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_expand_tilde() {
+    let dir = test_dir("expand-tilde");
+    let mut builder = SymlinkBuilder::new();
+    builder.symlink_file("~/dotfiles/vimrc", dir.join("literal")).unwrap();
+    assert_eq!(fs::read_link(dir.join("literal")).unwrap(), Path::new("~/dotfiles/vimrc"));
+
+    builder.expand_tilde(true);
+    let home = std::env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" });
+    if let Some(home) = home.filter(|home| !home.is_empty()) {
+        builder.symlink_file("~/dotfiles/vimrc", dir.join("expanded")).unwrap();
+        assert_eq!(fs::read_link(dir.join("expanded")).unwrap(),
+                   Path::new(&home).join("dotfiles/vimrc"));
+    }
+    // Only a leading ~ of its own.
+    builder.symlink_file("a/~/b", dir.join("inner")).unwrap();
+    assert_eq!(fs::read_link(dir.join("inner")).unwrap(), Path::new("a/~/b"));
+    let err = builder.symlink_file("~someone/vimrc", dir.join("other-user")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(!dir.join("other-user").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_replace_existing() {
+    let dir = test_dir("replace");
+    let link = dir.join("link");
+    symlink_file("old", &link).unwrap();
+    let mut builder = SymlinkBuilder::new();
+    let err = builder.symlink_file("new", &link).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    builder.replace_existing(true);
+    builder.symlink_file("new", &link).unwrap();
+    assert_eq!(fs::read_link(&link).unwrap(), Path::new("new"));
+    // Nothing is left behind.
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+    fs::write(dir.join("file"), b"").unwrap();
+    let err = builder.symlink_file("new", dir.join("file")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_preserve_attrs() {
+    use std::os::unix::fs::MetadataExt;
+    let dir = test_dir("preserve");
+    let link = dir.join("link");
+    symlink_file("old", &link).unwrap();
+    let old = fs::symlink_metadata(&link).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    SymlinkBuilder::new().replace_existing(true).preserve_attrs(true)
+        .symlink_file("new", &link).unwrap();
+    let new = fs::symlink_metadata(&link).unwrap();
+    assert_eq!(fs::read_link(&link).unwrap(), Path::new("new"));
+    assert_eq!((new.mtime(), new.mtime_nsec()), (old.mtime(), old.mtime_nsec()));
+    assert_eq!((new.uid(), new.gid()), (old.uid(), old.gid()));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_preserve_xattrs() {
+    use std::ffi::CString;
+    use std::os::raw::{c_char, c_int, c_void};
+    use std::os::unix::ffi::OsStrExt;
+    extern "C" {
+        fn lsetxattr(path: *const c_char, name: *const c_char, value: *const c_void, size: usize,
+                     flags: c_int) -> c_int;
+        fn lgetxattr(path: *const c_char, name: *const c_char, value: *mut c_void, size: usize)
+                     -> isize;
+    }
+    let dir = test_dir("preserve-xattrs");
+    let link = dir.join("link");
+    let path = CString::new(link.as_os_str().as_bytes()).unwrap();
+    // Symlinks can’t have user attributes; trusted ones take privilege, so without it there’s
+    // nothing to test with.
+    let name = CString::new("trusted.symlink-crate").unwrap();
+    let get = || {
+        let mut value = [0u8; 16];
+        let len = unsafe {
+            lgetxattr(path.as_ptr(), name.as_ptr(), value.as_mut_ptr() as *mut _, value.len())
+        };
+        if len < 0 { None } else { Some(value[..len as usize].to_vec()) }
+    };
+    symlink_file("old", &link).unwrap();
+    if unsafe { lsetxattr(path.as_ptr(), name.as_ptr(), b"kept".as_ptr() as *const _, 4, 0) } != 0 {
+        fs::remove_dir_all(&dir).unwrap();
+        return;
+    }
+
+    let mut builder = SymlinkBuilder::new();
+    builder.replace_existing(true).preserve_xattrs(true).symlink_file("new", &link).unwrap();
+    assert_eq!(fs::read_link(&link).unwrap(), Path::new("new"));
+    assert_eq!(get(), Some(b"kept".to_vec()));
+    // And without it, the replacement is fresh.
+    builder.preserve_xattrs(false).symlink_file("newer", &link).unwrap();
+    assert_eq!(get(), None);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_target_style() {
+    use symlink::TargetStyle;
+    let dir = test_dir("target-style");
+    let sub = dir.join("sub");
+    fs::create_dir(&sub).unwrap();
+    let target = dir.join("target");
+    // The same target, spelled relative to the link and absolutely (with a detour).
+    let inputs = [PathBuf::from("../target"), sub.join("..").join("target")];
+    let mut builder = SymlinkBuilder::new();
+    for (i, input) in inputs.iter().enumerate() {
+        let link = sub.join(format!("given-{}", i));
+        builder.target_style(TargetStyle::AsGiven).symlink_file(input, &link).unwrap();
+        assert_eq!(fs::read_link(&link).unwrap(), *input);
+
+        let link = sub.join(format!("absolute-{}", i));
+        builder.target_style(TargetStyle::Absolute).symlink_file(input, &link).unwrap();
+        assert_eq!(fs::read_link(&link).unwrap(), target);
+
+        let link = sub.join(format!("relative-{}", i));
+        builder.target_style(TargetStyle::Relative).symlink_file(input, &link).unwrap();
+        assert_eq!(fs::read_link(&link).unwrap(), Path::new("..").join("target"));
+    }
+    // The link’s own directory, relatively, is just `.`.
+    builder.symlink_dir(&sub, sub.join("here")).unwrap();
+    assert_eq!(fs::read_link(sub.join("here")).unwrap(), Path::new("."));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_target_separators() {
+    use symlink::TargetSeparators;
+    let dir = test_dir("separators");
+    let target = r"a/b\c";
+    let mut builder = SymlinkBuilder::new();
+    let mut read_back = |separators, name| {
+        builder.target_separators(separators).symlink_file(target, dir.join(name)).unwrap();
+        fs::read_link(dir.join(name)).unwrap().into_os_string()
+    };
+    // Paths compare the same whichever separators they use, so look at the raw strings.
+    assert_eq!(read_back(TargetSeparators::AsGiven, "given"), target);
+    if cfg!(windows) {
+        assert_eq!(read_back(TargetSeparators::Native, "native"), r"a\b\c");
+        assert_eq!(read_back(TargetSeparators::ForwardSlash, "forward"), "a/b/c");
+    } else {
+        assert_eq!(read_back(TargetSeparators::Native, "native"), target);
+        assert_eq!(read_back(TargetSeparators::ForwardSlash, "forward"), target);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_lexically_normalize() {
+    let dir = test_dir("normalize");
+    fs::create_dir(dir.join("a")).unwrap();
+    fs::create_dir(dir.join("real")).unwrap();
+    symlink_dir("../real", dir.join("a").join("via")).unwrap();
+    let mut builder = SymlinkBuilder::new();
+    builder.lexically_normalize(true);
+
+    builder.symlink_file("target", dir.join("a/./b")).unwrap();
+    assert_eq!(fs::read_link(dir.join("a/b")).unwrap(), Path::new("target"));
+    // Lexically, a/via/../c is a/c, though the OS would go up from real instead.
+    builder.symlink_file("target", dir.join("a/via/../c")).unwrap();
+    assert!(dir.join("a/c").symlink_metadata().unwrap().file_type().is_symlink());
+    builder.symlink_file("target", dir.join("a/../d")).unwrap();
+    assert!(dir.join("d").symlink_metadata().unwrap().file_type().is_symlink());
+    let err = builder.symlink_file("target", "e/..").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    // Without it, the OS has its way, which on Unix means going up from real.
+    if cfg!(unix) {
+        SymlinkBuilder::new().symlink_file("target", dir.join("a/via/../f")).unwrap();
+        assert!(dir.join("f").symlink_metadata().unwrap().file_type().is_symlink());
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_case_sensitive_check() {
+    let dir = test_dir("case");
+    fs::write(dir.join("Config"), b"").unwrap();
+    let mut builder = SymlinkBuilder::new();
+    builder.case_sensitive_check(true);
+    let err = builder.symlink_file("target", dir.join("config")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    assert!(err.to_string().contains("Config"), "{}", err);
+    assert!(dir.join("config").symlink_metadata().map(|m| !m.file_type().is_symlink())
+            .unwrap_or(true));
+    builder.symlink_file("target", dir.join("other")).unwrap();
+    let err = builder.symlink_file("target", dir.join("Config")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_verify_after_create() {
+    use symlink::TargetStyle;
+    let dir = test_dir("verify");
+    let mut builder = SymlinkBuilder::new();
+    builder.verify_after_create(true);
+    // Dangling is fine; it’s only the stored target that’s checked.
+    builder.symlink_file("nonexistent", dir.join("dangling")).unwrap();
+    // The check is against the target as styled, not as given.
+    builder.target_style(TargetStyle::Absolute).symlink_file("target", dir.join("absolute"))
+        .unwrap();
+    assert_eq!(fs::read_link(dir.join("absolute")).unwrap(), dir.join("target"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_verify_reparse_point() {
+    let dir = test_dir("verify-reparse-point");
+    fs::create_dir(dir.join("target")).unwrap();
+    let mut builder = SymlinkBuilder::new();
+    builder.verify_reparse_point(true).verify_after_create(true);
+    // Nothing interferes here, so both kinds pass, and elsewhere there’s nothing to check.
+    builder.symlink_file("nonexistent", dir.join("file")).unwrap();
+    builder.symlink_dir("target", dir.join("dir")).unwrap();
+    assert_eq!(fs::read_link(dir.join("dir")).unwrap(), Path::new("target"));
+    remove_symlink_dir(dir.join("dir")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_skip_if_newer() {
+    use std::time::{Duration, SystemTime};
+    use symlink::PolicyOutcome;
+    let dir = test_dir("skip-if-newer");
+    let link = dir.join("link");
+    symlink_file("old", &link).unwrap();
+    let hour = Duration::from_secs(3600);
+    let mut builder = SymlinkBuilder::new();
+    // Made since the reference time, so it’s left exactly as it is.
+    builder.skip_if_newer(SystemTime::now() - hour).symlink_file("new", &link).unwrap();
+    assert_eq!(fs::read_link(&link).unwrap(), Path::new("old"));
+    assert_eq!(builder.symlink_file_outcome("new", &link).unwrap(), PolicyOutcome::Skipped);
+    // Older than the reference time, so it’s dealt with as usual, which here means failing.
+    let err = builder.skip_if_newer(SystemTime::now() + hour).symlink_file("new", &link)
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    // A missing link is just created.
+    builder.symlink_file("new", dir.join("fresh")).unwrap();
+    assert_eq!(builder.symlink_file_outcome("new", dir.join("fresher")).unwrap(),
+               PolicyOutcome::Created);
+    builder.replace_existing(true);
+    assert_eq!(builder.symlink_file_outcome("new", &link).unwrap(), PolicyOutcome::Replaced);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_skip_if_same_content() {
+    let dir = test_dir("same-content");
+    fs::write(dir.join("blob-a"), TEST_FILE_CONTENTS).unwrap();
+    fs::write(dir.join("blob-b"), TEST_FILE_CONTENTS).unwrap();
+    fs::write(dir.join("blob-c"), b"something else entirely").unwrap();
+    let link = dir.join("link");
+    symlink_file("blob-a", &link).unwrap();
+    let mut builder = SymlinkBuilder::new();
+    builder.replace_existing(true).skip_if_same_content(true).verify_after_create(true);
+    // The same bytes, so the link stays as it was, and that’s no verification failure.
+    builder.symlink_file("blob-b", &link).unwrap();
+    assert_eq!(fs::read_link(&link).unwrap(), Path::new("blob-a"));
+    builder.symlink_file("blob-c", &link).unwrap();
+    assert_eq!(fs::read_link(&link).unwrap(), Path::new("blob-c"));
+    // Missing targets are never the same.
+    builder.symlink_file("missing", &link).unwrap();
+    assert_eq!(fs::read_link(&link).unwrap(), Path::new("missing"));
+    // And without replace_existing there’s nothing to skip.
+    let err = SymlinkBuilder::new().skip_if_same_content(true).symlink_file("blob-a", &link)
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_classify_timeout() {
+    use std::time::Duration;
+    let dir = test_dir("classify-timeout");
+    let mut builder = SymlinkBuilder::new();
+    builder.classify_timeout(Duration::from_secs(30));
+    builder.symlink_auto(&dir, dir.join("link")).unwrap();
+    remove_symlink_dir(dir.join("link")).unwrap();
+    // Errors from the probe still come through as they are.
+    let result = builder.symlink_auto(dir.join("missing"), dir.join("link"));
+    if cfg!(windows) {
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::NotFound);
+    } else {
+        result.unwrap();
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_classify_heuristic() {
+    use symlink::ClassifyHeuristic;
+    let dir = test_dir("classify");
+    let mut builder = SymlinkBuilder::new();
+    builder.classify(ClassifyHeuristic::HeuristicOnly);
+    builder.symlink_auto("later/", dir.join("dir-link")).unwrap();
+    builder.symlink_auto("later.txt", dir.join("file-link")).unwrap();
+    builder.classify(ClassifyHeuristic::StatThenHeuristic);
+    builder.symlink_auto(&dir, dir.join("stat-link")).unwrap();
+    // On Windows, removing each link with the matching function only works if it was
+    // classified as expected.
+    remove_symlink_dir(dir.join("dir-link")).unwrap();
+    remove_symlink_file(dir.join("file-link")).unwrap();
+    remove_symlink_dir(dir.join("stat-link")).unwrap();
+
+    builder.classify(ClassifyHeuristic::StatOnly);
+    let result = builder.symlink_auto("later/", dir.join("stat-only-link"));
+    assert_eq!(result.is_err(), cfg!(windows));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symlink_in() {
+    use symlink::{symlink_file_in, symlink_dir_in, remove_symlink_in};
+    let dir = test_dir("in");
+    fs::create_dir(dir.join("sub")).unwrap();
+    symlink_dir(dir.join("sub"), dir.join("sneaky")).unwrap();
+    let handle = File::open(&dir).unwrap();
+
+    symlink_file_in(&handle, "target", "sub/link").unwrap();
+    assert_eq!(fs::read_link(dir.join("sub/link")).unwrap(), Path::new("target"));
+    symlink_dir_in(&handle, "sub", "dir-link").unwrap();
+    assert_eq!(fs::read_link(dir.join("dir-link")).unwrap(), Path::new("sub"));
+
+    for escape in &["../escaped", "/tmp/escaped", "sub/../../escaped", "sneaky/escaped"] {
+        let err = symlink_file_in(&handle, "target", escape).unwrap_err();
+        assert!(!dir.join("sub/escaped").exists() && !dir.join("../escaped").exists());
+        if *escape != "sneaky/escaped" {
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{:?}", escape);
+        }
+    }
+
+    remove_symlink_in(&handle, "sub/link").unwrap();
+    remove_symlink_in(&handle, "dir-link").unwrap();
+    assert!(dir.join("sub/link").symlink_metadata().is_err());
+    assert!(dir.join("dir-link").symlink_metadata().is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_symlink_lazy() {
+    use symlink::{symlink_file_lazy, symlink_dir_lazy};
+    let dir = test_dir("lazy");
+    let link = dir.join("link");
+    assert!(symlink_file_lazy(|| PathBuf::from("target"), &link).unwrap());
+    assert_eq!(fs::read_link(&link).unwrap(), Path::new("target"));
+    assert!(!symlink_file_lazy(|| panic!("target computed needlessly"), &link).unwrap());
+
+    assert!(symlink_dir_lazy(|| dir.clone(), dir.join("dir-link")).unwrap());
+    assert!(!symlink_dir_lazy(|| panic!("target computed needlessly"), dir.join("dir-link"))
+        .unwrap());
+
+    fs::write(dir.join("file"), b"").unwrap();
+    let err = symlink_file_lazy(|| PathBuf::from("target"), dir.join("file")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+    remove_symlink_dir(dir.join("dir-link")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_symlink_canonical() {
+    use symlink::{symlink_file_canonical, symlink_dir_canonical};
+    let dir = test_dir("canonical");
+    fs::create_dir(dir.join("sub")).unwrap();
+    fs::write(dir.join("sub/file"), b"").unwrap();
+    let real_dir = fs::canonicalize(&dir).unwrap();
+    assert_eq!(symlink_file_canonical("sub/file", dir.join("file-link")).unwrap(),
+               real_dir.join("sub").join("file"));
+    assert_eq!(symlink_dir_canonical("sub", dir.join("dir-link")).unwrap(), real_dir.join("sub"));
+
+    let err = symlink_file_canonical("nonexistent", dir.join("dangling")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert!(fs::symlink_metadata(dir.join("dangling")).unwrap().file_type().is_symlink());
+
+    remove_symlink_dir(dir.join("dir-link")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_symlink_named() {
+    use symlink::{symlink_file_named, symlink_dir_named};
+    let dir = test_dir("named");
+    symlink_file_named("target", &dir, "file-link").unwrap();
+    assert_eq!(fs::read_link(dir.join("file-link")).unwrap(), Path::new("target"));
+    symlink_dir_named(&dir, &dir, "dir-link").unwrap();
+    assert_eq!(fs::read_link(dir.join("dir-link")).unwrap(), dir);
+    for name in &["", ".", "..", "a/b", "trailing/", "/absolute"] {
+        let err = symlink_file_named("target", &dir, name).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{:?}", name);
+    }
+    remove_symlink_dir(dir.join("dir-link")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_symlink_rel_to() {
+    use symlink::{symlink_dir_rel_to, symlink_file_rel_to};
+    let dir = test_dir("rel-to");
+    fs::create_dir(dir.join("sub")).unwrap();
+    symlink_file_rel_to(&dir, "target", "sub/file-link").unwrap();
+    assert_eq!(fs::read_link(dir.join("sub/file-link")).unwrap(), Path::new("target"));
+    symlink_dir_rel_to(dir.join("sub"), ".", "../dir-link").unwrap();
+    remove_symlink_dir(dir.join("dir-link")).unwrap();
+    let err = symlink_file_rel_to(dir.join("missing"), "target", "link").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_symlink_backup() {
+    use symlink::{symlink_dir_backup, symlink_file_backup};
+    let dir = test_dir("backup");
+    let link = dir.join("dotfile");
+    fs::write(&link, b"mine").unwrap();
+    let backup = symlink_file_backup("target", &link, ".bak").unwrap();
+    assert_eq!(backup, Some(dir.join("dotfile.bak")));
+    assert_eq!(fs::read(dir.join("dotfile.bak")).unwrap(), b"mine");
+    assert_eq!(fs::read_link(&link).unwrap(), Path::new("target"));
+
+    // Now a symlink is in the way, which is left alone.
+    let err = symlink_file_backup("other", &link, ".bak").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    remove_symlink_file(&link).unwrap();
+
+    // An existing backup isn’t overwritten.
+    fs::write(&link, b"newer").unwrap();
+    let err = symlink_file_backup("target", &link, ".bak").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    assert_eq!(fs::read(&link).unwrap(), b"newer");
+    assert_eq!(fs::read(dir.join("dotfile.bak")).unwrap(), b"mine");
+
+    fs::create_dir(dir.join("config")).unwrap();
+    let backup = symlink_dir_backup("target", dir.join("config"), "~").unwrap();
+    assert_eq!(backup, Some(dir.join("config~")));
+    assert!(dir.join("config~").is_dir());
+    assert_eq!(symlink_dir_backup("target", dir.join("new"), "~").unwrap(), None);
+    remove_symlink_dir(dir.join("config")).unwrap();
+    remove_symlink_dir(dir.join("new")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_system_symlinks() {
+    use symlink::{SymlinkOps, SystemSymlinks};
+    let ops: &dyn SymlinkOps = &SystemSymlinks;
+    let dir = test_dir("system-ops");
+    let file_link = dir.join("file-link");
+    let dir_link = dir.join("dir-link");
+    ops.symlink_file(Path::new("target"), &file_link).unwrap();
+    ops.symlink_dir(&dir, &dir_link).unwrap();
+    assert!(ops.is_symlink(&file_link));
+    assert!(!ops.is_symlink(&dir));
+    assert_eq!(ops.read_symlink(&file_link).unwrap(), Path::new("target"));
+    ops.remove_symlink_file(&file_link).unwrap();
+    ops.remove_symlink_auto(&dir_link).unwrap();
+    assert!(!ops.is_symlink(&file_link));
+    assert!(!ops.is_symlink(&dir_link));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_remove_symlink_dir_and_target() {
+    use symlink::remove_symlink_dir_and_target;
+    let dir = test_dir("and-target");
+    let target = dir.join("target");
+    fs::create_dir_all(target.join("sub")).unwrap();
+    fs::write(target.join("sub/file"), TEST_FILE_CONTENTS).unwrap();
+    symlink_dir(&target, dir.join("link")).unwrap();
+    // A link inside the target mustn’t be deleted through.
+    symlink_dir(&dir, target.join("back")).unwrap();
+
+    let err = remove_symlink_dir_and_target(&target).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(target.exists());
+
+    symlink_file(dir.join("file"), dir.join("file-link")).unwrap();
+    fs::write(dir.join("file"), b"").unwrap();
+    let err = remove_symlink_dir_and_target(dir.join("file-link")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(dir.join("file").exists());
+
+    remove_symlink_dir_and_target(dir.join("link")).unwrap();
+    assert!(fs::symlink_metadata(dir.join("link")).is_err());
+    assert!(!target.exists());
+    assert!(dir.join("file").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_decision_log() {
+    use symlink::DecisionLog;
+    let dir = test_dir("decisions");
+    fs::create_dir(dir.join("target-dir")).unwrap();
+    fs::write(dir.join("target-file"), b"").unwrap();
+    let mut log = DecisionLog::new();
+    assert_eq!(log.symlink_auto("target-dir", dir.join("a")).unwrap(), SymlinkKind::Dir);
+    assert_eq!(log.symlink_auto("target-file", dir.join("b")).unwrap(), SymlinkKind::File);
+    assert!(log.symlink_auto("missing", dir.join("c")).is_err());
+    let mut saved = vec![];
+    log.write_to(&mut saved).unwrap();
+    assert_eq!(saved, b"dir\ttarget-dir\nfile\ttarget-file\n");
+
+    // Replayed, the targets needn’t exist.
+    let mut log = DecisionLog::read_from(&saved[..]).unwrap();
+    let other = test_dir("decisions-replay");
+    assert_eq!(log.symlink_auto("target-dir", other.join("a")).unwrap(), SymlinkKind::Dir);
+    remove_symlink_dir(other.join("a")).unwrap();
+    assert_eq!(log.get("target-file"), Some(SymlinkKind::File));
+
+    let err = DecisionLog::read_from(&b"dir\tok\nfolder\tbad\n"[..]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains("line 2"), "{}", err);
+    fs::remove_dir_all(&dir).unwrap();
+    fs::remove_dir_all(&other).unwrap();
+}
+
+#[test]
+fn test_targets_equivalent() {
+    use symlink::{targets_equivalent, TargetComparator};
+    let equivalent = |a: &str, b: &str| targets_equivalent(Path::new(a), Path::new(b));
+    assert!(equivalent("a/b", "a//b"));
+    assert!(equivalent("a/b", "a/./b/"));
+    assert!(!equivalent("a/b", "a/c/../b"));
+    assert_eq!(equivalent("a/b", "A/b"), cfg!(any(windows, target_os = "macos")));
+    assert!(!equivalent("./a", "a"));
+    assert_eq!(equivalent("a/b", r"a\b"), cfg!(windows));
+    assert_eq!(equivalent(r"\\?\C:\x\y", "C:/x/y"), cfg!(windows));
+    assert_eq!(equivalent(r"\??\C:\x", r"C:\x"), cfg!(windows));
+    assert_eq!(equivalent(r"\\?\UNC\server\share\x", r"\\server\share\x"), cfg!(windows));
+
+    let mut comparator = TargetComparator::new();
+    comparator.case_sensitive(false);
+    assert!(comparator.equivalent(Path::new("Docs/Ünï"), Path::new("docs//üNÏ/")));
+    assert!(!comparator.equivalent(Path::new("docs/a"), Path::new("docs/b")));
+    assert!(!comparator.equivalent(Path::new("docs/a"), Path::new("docs/a/b")));
+    assert_eq!(comparator.equivalent(Path::new(r"\\?\C:\X"), Path::new("c:/x")), cfg!(windows));
+    comparator.case_sensitive(true);
+    assert!(!comparator.equivalent(Path::new("Docs/a"), Path::new("docs/a")));
+    assert!(comparator.equivalent(Path::new("docs/./a"), Path::new("docs/a")));
+}
+
+#[test]
+fn test_ensure_symlink() {
+    use symlink::{ensure_symlink_file, ensure_symlink_dir, CreateOutcome};
+    let dir = test_dir("ensure");
+    let link = dir.join("link");
+    assert_eq!(ensure_symlink_file("target", &link).unwrap(), CreateOutcome::Created);
+    assert_eq!(ensure_symlink_file("target", &link).unwrap(), CreateOutcome::AlreadyCorrect);
+    assert_eq!(ensure_symlink_file("other", &link).unwrap(), CreateOutcome::Repointed);
+    assert_eq!(fs::read_link(&link).unwrap(), Path::new("other"));
+    // A repoint that’s refused leaves the old link as it was.
+    for target in &[&link, Path::new("link"), Path::new("")] {
+        let err = ensure_symlink_file(target, &link).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{}", target.display());
+        assert_eq!(fs::read_link(&link).unwrap(), Path::new("other"));
+    }
+
+    let dir_link = dir.join("dir-link");
+    assert_eq!(ensure_symlink_dir(&dir, &dir_link).unwrap(), CreateOutcome::Created);
+    assert_eq!(ensure_symlink_dir(&dir, &dir_link).unwrap(), CreateOutcome::AlreadyCorrect);
+
+    fs::write(dir.join("file"), b"").unwrap();
+    let err = ensure_symlink_file("target", dir.join("file")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+    remove_symlink_dir(&dir_link).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_ensure_symlink_detailed() {
+    use symlink::{ensure_symlink_dir_detailed, ensure_symlink_file_detailed, CreateOutcome,
+                  DetailedOutcome, ExistingEntry};
+    let dir = test_dir("ensure-detailed");
+    let link = dir.join("link");
+    let was = |target: &str| Some(ExistingEntry::Symlink { target: PathBuf::from(target) });
+    assert_eq!(ensure_symlink_file_detailed("old", &link).unwrap(),
+               DetailedOutcome { before: None, action: CreateOutcome::Created });
+    assert_eq!(ensure_symlink_file_detailed("old", &link).unwrap(),
+               DetailedOutcome { before: was("old"), action: CreateOutcome::AlreadyCorrect });
+    assert_eq!(ensure_symlink_file_detailed("new", &link).unwrap(),
+               DetailedOutcome { before: was("old"), action: CreateOutcome::Repointed });
+    assert_eq!(fs::read_link(&link).unwrap(), Path::new("new"));
+    let dir_link = dir.join("dir-link");
+    assert_eq!(ensure_symlink_dir_detailed(".", &dir_link).unwrap().action, CreateOutcome::Created);
+    fs::write(dir.join("file"), b"").unwrap();
+    let err = ensure_symlink_file_detailed("target", dir.join("file")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    remove_symlink_dir(&dir_link).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_try_symlink() {
+    use symlink::{try_symlink_dir, try_symlink_file, ExistingEntry, TrySymlinkResult};
+    let dir = test_dir("try");
+    let link = dir.join("link");
+    assert_eq!(try_symlink_file("target", &link).unwrap(), TrySymlinkResult::Created);
+    assert_eq!(try_symlink_file("target", &link).unwrap(), TrySymlinkResult::ExistsEquivalent);
+    assert_eq!(try_symlink_file("other", &link).unwrap(),
+               TrySymlinkResult::ExistsConflict(ExistingEntry::Symlink {
+                   target: PathBuf::from("target"),
+               }));
+    assert_eq!(fs::read_link(&link).unwrap(), Path::new("target"));
+
+    fs::write(dir.join("file"), b"").unwrap();
+    assert_eq!(try_symlink_file("target", dir.join("file")).unwrap(),
+               TrySymlinkResult::ExistsConflict(ExistingEntry::File));
+    fs::create_dir(dir.join("dir")).unwrap();
+    assert_eq!(try_symlink_dir("target", dir.join("dir")).unwrap(),
+               TrySymlinkResult::ExistsConflict(ExistingEntry::Dir));
+
+    let err = try_symlink_file("target", dir.join("missing/link")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(windows)]
+#[test]
+fn test_symlink_junction() {
+    use symlink::{symlink_junction, remove_mount_point};
+    let dir = test_dir("junction");
+    let target = dir.join("target");
+    fs::create_dir(&target).unwrap();
+    fs::write(target.join("file"), TEST_FILE_CONTENTS).unwrap();
+
+    symlink_junction(&target, dir.join("junction")).unwrap();
+    assert_eq!(fs::read(dir.join("junction").join("file")).unwrap(), TEST_FILE_CONTENTS);
+    remove_symlink_dir(dir.join("junction")).unwrap();
+
+    symlink_junction(&target, dir.join("junction")).unwrap();
+    remove_mount_point(dir.join("junction")).unwrap();
+    assert!(dir.join("junction").symlink_metadata().is_err());
+    assert_eq!(fs::read(target.join("file")).unwrap(), TEST_FILE_CONTENTS);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(windows)]
+#[test]
+fn test_symlink_auto_reparse_targets() {
+    use std::os::windows::fs::FileTypeExt;
+    use symlink::symlink_junction;
+    let is_dir_link = |path: PathBuf| {
+        fs::symlink_metadata(path).unwrap().file_type().is_symlink_dir()
+    };
+    let dir = test_dir("auto-reparse");
+    fs::create_dir(dir.join("real")).unwrap();
+    symlink_junction(dir.join("real"), dir.join("junction")).unwrap();
+    symlink_file(dir.join("real"), dir.join("file-link-to-dir")).unwrap();
+
+    symlink_auto(dir.join("junction"), dir.join("via-junction")).unwrap();
+    assert!(is_dir_link(dir.join("via-junction")));
+    // The file symlink isn’t followed to the directory it points at...
+    symlink_auto(dir.join("file-link-to-dir"), dir.join("via-file-link")).unwrap();
+    assert!(!is_dir_link(dir.join("via-file-link")));
+    // ...unless asked.
+    SymlinkBuilder::new().follow_target(true)
+        .symlink_auto(dir.join("file-link-to-dir"), dir.join("followed")).unwrap();
+    assert!(is_dir_link(dir.join("followed")));
+
+    remove_symlink_dir(dir.join("via-junction")).unwrap();
+    remove_symlink_file(dir.join("via-file-link")).unwrap();
+    remove_symlink_dir(dir.join("followed")).unwrap();
+    remove_symlink_file(dir.join("file-link-to-dir")).unwrap();
+    remove_symlink_dir(dir.join("junction")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(windows)]
+#[test]
+fn test_symlink_auto_detailed() {
+    use symlink::symlink_auto_detailed;
+    const IO_REPARSE_TAG_SYMLINK: u32 = 0xa000000c;
+    let dir = test_dir("detailed");
+    fs::create_dir(dir.join("target")).unwrap();
+    let details = symlink_auto_detailed(dir.join("target"), dir.join("link")).unwrap();
+    assert_eq!(details.kind, SymlinkKind::Dir);
+    assert_eq!(details.reparse_tag, IO_REPARSE_TAG_SYMLINK);
+    remove_symlink_dir(dir.join("link")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(windows)]
+#[test]
+fn test_read_symlink_names() {
+    use std::ffi::OsString;
+    use symlink::{symlink_junction, read_symlink_names};
+    let dir = test_dir("names");
+    let target = dir.join("target");
+    fs::create_dir(&target).unwrap();
+
+    symlink_file("relative\\file", dir.join("link")).unwrap();
+    let names = read_symlink_names(dir.join("link")).unwrap();
+    assert_eq!(names.substitute_name, OsString::from("relative\\file"));
+    assert!(names.relative);
+    assert!(!names.mount_point);
+
+    symlink_junction(&target, dir.join("junction")).unwrap();
+    let names = read_symlink_names(dir.join("junction")).unwrap();
+    assert!(names.mount_point);
+    assert!(!names.relative);
+    assert!(names.substitute_name.to_string_lossy().starts_with("\\??\\"));
+    assert!(names.substitute_name.to_string_lossy().ends_with("target"));
+
+    let err = read_symlink_names(&target).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    remove_symlink_file(dir.join("link")).unwrap();
+    remove_symlink_dir(dir.join("junction")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_link_type() {
+    use symlink::{link_type, LinkType};
+    let dir = test_dir("link-type");
+    let file = dir.join("file");
+    fs::write(&file, b"").unwrap();
+    assert_eq!(link_type(&file).unwrap(), LinkType::Regular);
+    fs::hard_link(&file, dir.join("hard")).unwrap();
+    assert_eq!(link_type(dir.join("hard")).unwrap(), LinkType::HardLinkedFile { nlink: 2 });
+    assert_eq!(link_type(&file).unwrap(), LinkType::HardLinkedFile { nlink: 2 });
+    assert_eq!(link_type(&dir).unwrap(), LinkType::Directory);
+
+    symlink_file(&file, dir.join("file-link")).unwrap();
+    assert_eq!(link_type(dir.join("file-link")).unwrap(), LinkType::Symlink(SymlinkKind::File));
+    symlink_dir(&dir, dir.join("dir-link")).unwrap();
+    assert_eq!(link_type(dir.join("dir-link")).unwrap(), LinkType::Symlink(SymlinkKind::Dir));
+    symlink_file("nonexistent", dir.join("dangling")).unwrap();
+    if cfg!(windows) {
+        assert_eq!(link_type(dir.join("dangling")).unwrap(), LinkType::Symlink(SymlinkKind::File));
+    } else {
+        assert_eq!(link_type(dir.join("dangling")).unwrap(), LinkType::DanglingUnknown);
+    }
+
+    let err = link_type(dir.join("nonexistent")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    remove_symlink_dir(dir.join("dir-link")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_guess_dangling_kind() {
+    use symlink::guess_dangling_kind;
+    let dir = test_dir("guess-dangling-kind");
+    fs::create_dir(dir.join("sub")).unwrap();
+    symlink_dir("sub", dir.join("real-dir")).unwrap();
+    symlink_file("nowhere", dir.join("no-hint")).unwrap();
+    assert_eq!(guess_dangling_kind(dir.join("real-dir")).unwrap(), SymlinkKind::Dir);
+    assert_eq!(guess_dangling_kind(dir.join("no-hint")).unwrap(), SymlinkKind::File);
+    for (name, target) in &[("slash", "gone/"), ("dot", "gone/."), ("dot-dot", "gone/..")] {
+        symlink_file(target, dir.join(name)).unwrap();
+        // On Windows the link’s own kind wins over what its target looks like.
+        let expected = if cfg!(windows) { SymlinkKind::File } else { SymlinkKind::Dir };
+        assert_eq!(guess_dangling_kind(dir.join(name)).unwrap(), expected, "{}", target);
+    }
+    let err = guess_dangling_kind(dir.join("sub")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    remove_symlink_dir(dir.join("real-dir")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_symlink_target_is_relative() {
+    use symlink::symlink_target_is_relative;
+    let dir = test_dir("target-is-relative");
+    fs::write(dir.join("target"), TEST_FILE_CONTENTS).unwrap();
+    symlink_file("target", dir.join("relative")).unwrap();
+    symlink_file("../elsewhere/target", dir.join("climbing")).unwrap();
+    symlink_file(dir.join("target"), dir.join("absolute")).unwrap();
+    assert!(symlink_target_is_relative(dir.join("relative")).unwrap());
+    assert!(symlink_target_is_relative(dir.join("climbing")).unwrap());
+    assert!(!symlink_target_is_relative(dir.join("absolute")).unwrap());
+    if cfg!(windows) {
+        // Rooted but on no drive: it lands on the link’s.
+        let rooted: PathBuf = dir.components().skip(1).collect();
+        symlink_file(&rooted, dir.join("rooted")).unwrap();
+        assert!(symlink_target_is_relative(dir.join("rooted")).unwrap());
+    }
+    let err = symlink_target_is_relative(dir.join("target")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    let err = symlink_target_is_relative(dir.join("missing")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_readlink_f() {
+    use symlink::readlink_f;
+    let dir = test_dir("readlink-f");
+    fs::create_dir_all(dir.join("real").join("sub")).unwrap();
+    fs::write(dir.join("real").join("sub").join("file"), TEST_FILE_CONTENTS).unwrap();
+    symlink_dir("real", dir.join("dir-link")).unwrap();
+    symlink_dir("dir-link/sub", dir.join("sub-link")).unwrap();
+    symlink_file("sub-link/file", dir.join("file-link")).unwrap();
+    let real = fs::canonicalize(&dir).unwrap();
+    let expected = readlink_f(&dir).unwrap().join("real").join("sub").join("file");
+    assert_eq!(readlink_f(dir.join("file-link")).unwrap(), expected);
+    assert_eq!(readlink_f(dir.join("dir-link").join("sub").join("file")).unwrap(), expected);
+    // `..` after a link is the parent of where the link leads, not of the link.
+    assert_eq!(readlink_f(dir.join("sub-link").join("..")).unwrap(),
+               readlink_f(dir.join("real")).unwrap());
+    if cfg!(windows) {
+        assert!(!expected.to_string_lossy().starts_with(r"\\?\"), "{}", expected.display());
+    } else {
+        assert_eq!(expected, real.join("real").join("sub").join("file"));
+    }
+    // Unlike resolve_symlink, nothing may be missing, the last component included.
+    symlink_file("nothing-here", dir.join("dangling")).unwrap();
+    for missing in &["dangling", "missing"] {
+        let err = readlink_f(dir.join(missing)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+    assert!(resolve_symlink(dir.join("dangling")).is_ok());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_conflict_policy() {
+    use symlink::{symlink, ConflictPolicy, LinkKind, PolicyOutcome};
+    let dir = test_dir("policy");
+    let backup = ConflictPolicy::Backup { suffix: ".bak".into() };
+    let policies = [ConflictPolicy::Fail, ConflictPolicy::ReplaceSymlinkOnly,
+                    ConflictPolicy::Overwrite, backup.clone(), ConflictPolicy::Skip];
+    for policy in &policies {
+        // What’s in the way, set up afresh for each policy.
+        for &state in &["nothing", "symlink", "file", "dir"] {
+            let link = dir.join(format!("{:?}-{}", policy, state).replace(['"', ' '], ""));
+            match state {
+                "symlink" => symlink_file("old", &link).unwrap(),
+                "file" => fs::write(&link, b"old").unwrap(),
+                "dir" => fs::create_dir(&link).unwrap(),
+                _ => {}
+            }
+            let result = symlink(LinkKind::File, "new", &link, policy);
+            let created = || fs::read_link(&link).ok() == Some(PathBuf::from("new"));
+            match (policy, state) {
+                (_, "nothing") => {
+                    assert_eq!(result.unwrap(), PolicyOutcome::Created);
+                    assert!(created());
+                }
+                (&ConflictPolicy::Fail, _) | (&ConflictPolicy::ReplaceSymlinkOnly, "file")
+                | (&ConflictPolicy::ReplaceSymlinkOnly, "dir")
+                | (&ConflictPolicy::Backup { .. }, "symlink") => {
+                    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::AlreadyExists,
+                               "{:?} on {}", policy, state);
+                    assert!(!created());
+                }
+                (&ConflictPolicy::ReplaceSymlinkOnly, _) | (&ConflictPolicy::Overwrite, _) => {
+                    assert_eq!(result.unwrap(), PolicyOutcome::Replaced);
+                    assert!(created());
+                }
+                (&ConflictPolicy::Backup { .. }, _) => {
+                    let mut moved = link.clone().into_os_string();
+                    moved.push(".bak");
+                    assert_eq!(result.unwrap(), PolicyOutcome::BackedUp(PathBuf::from(moved)));
+                    assert!(created());
+                }
+                (&ConflictPolicy::Skip, _) => {
+                    assert_eq!(result.unwrap(), PolicyOutcome::Skipped);
+                    assert!(!created());
+                }
+            }
+        }
+    }
+    // A directory with something in it is never overwritten.
+    fs::create_dir(dir.join("full")).unwrap();
+    fs::write(dir.join("full").join("file"), b"").unwrap();
+    assert!(symlink(LinkKind::Dir, "new", dir.join("full"), &ConflictPolicy::Overwrite).is_err());
+    assert!(dir.join("full").join("file").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_symlink_context() {
+    use symlink::{ConflictPolicy, PolicyOutcome, SymlinkContext, SymlinkOps};
+    let dir = test_dir("context");
+
+    // The default is just the free functions.
+    let ctx = SymlinkContext::new();
+    assert_eq!(ctx.symlink_file("target", dir.join("plain")).unwrap(), PolicyOutcome::Created);
+    let err = ctx.symlink_file("target", dir.join("plain")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+    // Options and policy both apply, and the policy wins over replace_existing.
+    let mut options = SymlinkBuilder::new();
+    options.create_parents(true).replace_existing(true);
+    let mut ctx = SymlinkContext::new();
+    ctx.options(options).conflict_policy(ConflictPolicy::Skip);
+    let nested = dir.join("a").join("b").join("link");
+    assert_eq!(ctx.symlink_dir("target", &nested).unwrap(), PolicyOutcome::Created);
+    assert_eq!(ctx.symlink_dir("other", &nested).unwrap(), PolicyOutcome::Skipped);
+    assert_eq!(fs::read_link(&nested).unwrap(), Path::new("target"));
+    ctx.conflict_policy(ConflictPolicy::Backup { suffix: ".bak".into() });
+    let in_the_way = dir.join("a").join("in-the-way");
+    fs::write(&in_the_way, b"").unwrap();
+    assert_eq!(ctx.symlink_file("target", &in_the_way).unwrap(),
+               PolicyOutcome::BackedUp(dir.join("a").join("in-the-way.bak")));
+
+    // And through SymlinkOps.
+    let ops: &dyn SymlinkOps = &ctx;
+    ops.symlink_file(Path::new("target"), &dir.join("ops")).unwrap();
+    assert!(ops.is_symlink(&dir.join("ops")));
+    ops.remove_symlink_file(&dir.join("ops")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_reflink_or_symlink_file() {
+    use symlink::{reflink_or_symlink_file, LinkStrategy};
+    let dir = test_dir("reflink");
+    fs::write(dir.join("source"), TEST_FILE_CONTENTS).unwrap();
+    let dst = dir.join("copy");
+    // Which we get depends on the filesystem the temporary directory is on.
+    match reflink_or_symlink_file("source", &dst).unwrap() {
+        LinkStrategy::Reflink => {
+            assert!(!fs::symlink_metadata(&dst).unwrap().file_type().is_symlink());
+            fs::write(&dst, b"changed").unwrap();
+            assert_eq!(fs::read(dir.join("source")).unwrap(), TEST_FILE_CONTENTS);
+        }
+        LinkStrategy::Symlink => {
+            assert_eq!(fs::read_link(&dst).unwrap(), Path::new("source"));
+            assert_eq!(fs::read(&dst).unwrap(), TEST_FILE_CONTENTS);
+        }
+    }
+    let err = reflink_or_symlink_file("source", &dst).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    // A clone needs something to clone, so there’s no falling back to a dangling link.
+    if cfg!(any(target_os = "linux", target_os = "android", target_os = "macos")) {
+        let err = reflink_or_symlink_file("missing", dir.join("dangling")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_symlink_auto_relative_to_link() {
+    use std::time::Duration;
+    let dir = test_dir("auto-relative");
+    // The tests run in the crate root, where Cargo.toml is a file; beside the link it’s a
+    // directory, and that’s what the link will lead to.
+    assert!(Path::new("Cargo.toml").is_file());
+    fs::create_dir(dir.join("Cargo.toml")).unwrap();
+    fs::create_dir(dir.join("only-here")).unwrap();
+    let mut timeout = SymlinkBuilder::new();
+    timeout.classify_timeout(Duration::from_secs(60));
+    for &timed in &[false, true] {
+        for target in &["Cargo.toml", "only-here"] {
+            let link = dir.join(format!("{}-{}", timed, target));
+            if timed {
+                timeout.symlink_auto(target, &link).unwrap();
+            } else {
+                symlink_auto(target, &link).unwrap();
+            }
+            // On Windows a file symlink to a directory can’t be listed through.
+            assert!(fs::metadata(&link).unwrap().is_dir(), "{}", link.display());
+            fs::read_dir(&link).unwrap();
+            remove_symlink_dir(&link).unwrap();
+        }
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_symlink_context_hooks() {
+    use std::sync::{Arc, Mutex};
+    use symlink::{ConflictPolicy, LinkKind, SymlinkContext};
+    let dir = test_dir("context-hooks");
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let mut ctx = SymlinkContext::new();
+    let created = seen.clone();
+    ctx.conflict_policy(ConflictPolicy::Skip).on_created(move |src, dst, kind| {
+        created.lock().unwrap().push(format!("created {:?} {} -> {}", kind, dst.display(),
+                                             src.display()));
+        Ok(())
+    });
+    let removed = seen.clone();
+    ctx.on_removed(move |path, kind| {
+        removed.lock().unwrap().push(format!("removed {:?} {}", kind, path.display()));
+        Ok(())
+    });
+
+    let link = dir.join("link");
+    ctx.symlink_file("target", &link).unwrap();
+    // Skipped, failed, not hooked.
+    ctx.symlink_file("other", &link).unwrap();
+    assert!(ctx.remove_symlink_dir(dir.join("missing")).is_err());
+    ctx.remove_symlink_file(&link).unwrap();
+    assert_eq!(*seen.lock().unwrap(),
+               [format!("created File {} -> target", link.display()),
+                format!("removed File {}", link.display())]);
+
+    // The hook’s error is the method’s, with the link left in place.
+    ctx.on_created(|_, _, kind| {
+        assert_eq!(kind, LinkKind::Dir);
+        Err(io::Error::other("index unavailable"))
+    });
+    let err = ctx.symlink_dir("target", &link).unwrap_err();
+    assert_eq!(err.to_string(), "index unavailable");
+    assert_eq!(fs::read_link(&link).unwrap(), Path::new("target"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_symlink_context_jail() {
+    use symlink::SymlinkContext;
+    let dir = test_dir("jail");
+    let root = dir.join("root");
+    fs::create_dir_all(root.join("sub")).unwrap();
+    fs::create_dir(dir.join("outside")).unwrap();
+    symlink_dir(dir.join("outside"), root.join("escape")).unwrap();
+    let mut ctx = SymlinkContext::new();
+    ctx.jail(&root);
+
+    ctx.symlink_file("target", root.join("sub").join("link")).unwrap();
+    ctx.symlink_file("../../anywhere", root.join("sub").join("far")).unwrap();
+    for bad in &[dir.join("link"), root.join("sub").join("..").join("..").join("link"),
+                 root.join("escape").join("link"), root.clone()] {
+        let err = ctx.symlink_file("target", bad).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{}", bad.display());
+        assert!(err.to_string().contains("outside the jail"), "{}", err);
+    }
+    assert!(!dir.join("outside").join("link").exists());
+    assert_eq!(ctx.remove_symlink_file(dir.join("link")).unwrap_err().kind(),
+               io::ErrorKind::InvalidInput);
+    // Lexically, this is root/escaped; but escape/.. is dir, as the OS sees it.
+    let err = ctx.symlink_file("target", root.join("escape").join("..").join("escaped"))
+        .unwrap_err();
+    assert!(err.to_string().contains("outside the jail"), "{}", err);
+    assert!(fs::symlink_metadata(dir.join("escaped")).is_err());
+
+    // Targets too, following symlinks on the way.
+    ctx.jail_targets(true);
+    ctx.symlink_file("target", root.join("inside")).unwrap();
+    ctx.symlink_file("sub/link", root.join("via-link")).unwrap();
+    // ../escape/../outside is lexically root/outside, and really dir/outside.
+    for &target in &["../../anywhere", "../escape/file", "/", "../escape/../outside"] {
+        let err = ctx.symlink_file(target, root.join("sub").join("checked")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{}", target);
+    }
+    // The link made earlier, pointing out of the jail, is refused as a way out.
+    let err = ctx.symlink_file("sub/far", root.join("via-far")).unwrap_err();
+    assert!(err.to_string().contains("by way of"), "{}", err);
+
+    // And a loop is refused rather than followed forever.
+    symlink_file("loop-b", root.join("loop-a")).unwrap();
+    symlink_file("loop-a", root.join("loop-b")).unwrap();
+    assert!(ctx.symlink_file("loop-a", root.join("looped")).is_err());
+
+    // No jail root, no operations.
+    let mut missing = SymlinkContext::new();
+    missing.jail(dir.join("missing"));
+    assert_eq!(missing.symlink_file("target", dir.join("missing").join("link")).unwrap_err()
+                   .kind(), io::ErrorKind::NotFound);
+    remove_symlink_dir(root.join("escape")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_symlink_context_jail_expand_tilde() {
+    use symlink::SymlinkContext;
+    // ~/link, taken as given, is beneath the working directory; expanded, it’s in the home
+    // directory, and that’s what is judged.
+    let cwd = std::env::current_dir().unwrap();
+    let home = std::env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" });
+    let home = match home.filter(|home| !home.is_empty()) {
+        Some(home) if !Path::new(&home).starts_with(&cwd) => PathBuf::from(home),
+        _ => return,
+    };
+    let mut options = SymlinkBuilder::new();
+    options.expand_tilde(true);
+    let mut ctx = SymlinkContext::new();
+    ctx.jail(&cwd).options(options);
+    let name = "symlink-crate-test-jail-expand-tilde";
+    let err = ctx.symlink_file("target", Path::new("~").join(name)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(err.to_string().contains("outside the jail"), "{}", err);
+    assert!(fs::symlink_metadata(home.join(name)).is_err());
+    assert!(fs::symlink_metadata(cwd.join("~")).is_err());
+}
+
+#[test]
+fn test_symlink_timestamped() {
+    use symlink::{symlink_timestamped, LinkKind};
+    let dir = test_dir("timestamped");
+    let first = symlink_timestamped("current.log", &dir, "log", LinkKind::File).unwrap();
+    let name = first.file_name().unwrap().to_str().unwrap().to_owned();
+    // log-YYYY-MM-DDTHHMMSSZ
+    assert_eq!(name.len(), "log-2026-10-14T153012Z".len(), "{}", name);
+    assert!(name.starts_with("log-") && name.ends_with('Z') && &name[14..15] == "T", "{}", name);
+    assert_eq!(fs::read_link(&first).unwrap(), Path::new("current.log"));
+    // In the same second, the rest get numbered.
+    let again: Vec<PathBuf> = (0..3).map(|_| {
+        symlink_timestamped("current.log", &dir, "log", LinkKind::File).unwrap()
+    }).collect();
+    for link in &again {
+        assert_ne!(link, &first);
+        let suffix = link.file_name().unwrap().to_str().unwrap().rsplit('-').next().unwrap();
+        assert!(suffix.ends_with('Z') || suffix.parse::<u32>().is_ok(), "{}", link.display());
+    }
+    let err = symlink_timestamped("x", &dir, "a/b", LinkKind::File).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_symlink_verbatim_check() {
+    use symlink::{symlink_dir_verbatim_check, symlink_file_verbatim_check};
+    let dir = test_dir("verbatim-check");
+    // Spellings a path comparison would call the same.
+    for (i, target) in ["target", "a//b", "./a/./b/", "../x"].iter().enumerate() {
+        let verbatim = symlink_file_verbatim_check(target, dir.join(format!("file-{}", i)))
+            .unwrap();
+        if cfg!(unix) {
+            assert!(verbatim, "{}", target);
+        }
+    }
+    assert!(symlink_dir_verbatim_check(".", dir.join("dir")).unwrap() || cfg!(windows));
+    let err = symlink_file_verbatim_check("target", dir.join("file-0")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    remove_symlink_dir(dir.join("dir")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_mirror_tree() {
+    use symlink::{mirror_tree, MirrorMode, MirrorPolicy};
+    let create = MirrorMode::Create;
+    let dir = test_dir("mirror");
+    let src = dir.join("src");
+    fs::create_dir_all(src.join("a/b")).unwrap();
+    fs::write(src.join("top"), TEST_FILE_CONTENTS).unwrap();
+    fs::write(src.join("a/middle"), TEST_FILE_CONTENTS).unwrap();
+    fs::write(src.join("a/b/bottom"), TEST_FILE_CONTENTS).unwrap();
+
+    let all = dir.join("all");
+    let report = mirror_tree(&src, &all, MirrorPolicy::LinkFiles, create).unwrap();
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    assert_eq!((report.links, report.dirs), (3, 3));
+    for path in &["a", "a/b"] {
+        assert!(fs::symlink_metadata(all.join(path)).unwrap().is_dir());
+    }
+    for path in &["top", "a/middle", "a/b/bottom"] {
+        assert_eq!(fs::read_link(all.join(path)).unwrap(), src.join(path));
+        assert_eq!(fs::read(all.join(path)).unwrap(), TEST_FILE_CONTENTS);
+    }
+
+    // Mirroring again finds the directories there and every link in the way.
+    let report = mirror_tree(&src, &all, MirrorPolicy::LinkFiles, create).unwrap();
+    assert_eq!((report.links, report.dirs, report.errors.len()), (0, 0, 3));
+    assert!(report.errors.iter().all(|(_, e)| e.kind() == io::ErrorKind::AlreadyExists));
+
+    let shallow = dir.join("shallow");
+    let report = mirror_tree(&src, &shallow, MirrorPolicy::LinkDirsBelow(1), create).unwrap();
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    assert_eq!((report.links, report.dirs), (3, 2));
+    assert!(fs::symlink_metadata(shallow.join("a")).unwrap().is_dir());
+    assert_eq!(fs::read_link(shallow.join("a/b")).unwrap(), src.join("a/b"));
+    assert_eq!(fs::read(shallow.join("a/b/bottom")).unwrap(), TEST_FILE_CONTENTS);
+
+    // A directory link left by a shallower mirror isn’t gone into, so the source is untouched.
+    let report = mirror_tree(&src, &shallow, MirrorPolicy::LinkFiles, create).unwrap();
+    assert_eq!(report.links, 0);
+    let in_the_way: Vec<_> = report.errors.iter().map(|(path, _)| path.clone()).collect();
+    assert!(in_the_way.contains(&shallow.join("a/b")), "{:?}", in_the_way);
+    assert_eq!(fs::read_dir(src.join("a/b")).unwrap().count(), 1);
+
+    let top = dir.join("top");
+    let report = mirror_tree(&src, &top, MirrorPolicy::LinkDirsBelow(0), create).unwrap();
+    assert_eq!((report.links, report.dirs, report.errors.len()), (2, 1, 0));
+    assert_eq!(fs::read_link(top.join("a")).unwrap(), src.join("a"));
+
+    let err = mirror_tree(dir.join("missing"), dir.join("nowhere"), MirrorPolicy::LinkFiles,
+                          create).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert!(!dir.join("nowhere").exists());
+
+    remove_symlink_dir(top.join("a")).unwrap();
+    remove_symlink_dir(shallow.join("a/b")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_mirror_tree_with_progress() {
+    use symlink::{mirror_tree_with_progress, MirrorMode, MirrorPolicy};
+    let dir = test_dir("mirror-progress");
+    let src = dir.join("src");
+    fs::create_dir_all(src.join("a")).unwrap();
+    fs::write(src.join("top"), TEST_FILE_CONTENTS).unwrap();
+    fs::write(src.join("a/inner"), TEST_FILE_CONTENTS).unwrap();
+    let dst = dir.join("dst");
+    let mut seen = vec![];
+    let report = mirror_tree_with_progress(&src, &dst, MirrorPolicy::LinkFiles, MirrorMode::Create,
+                                           |done, total, path| {
+        assert_eq!((done, total), (seen.len() + 1, None));
+        seen.push(path.to_owned());
+    }).unwrap();
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    // The directory is dealt with, and counted, before what’s in it.
+    let a = seen.iter().position(|path| *path == dst.join("a")).unwrap();
+    assert!(seen.iter().position(|path| *path == dst.join("a/inner")).unwrap() > a);
+    seen.sort();
+    assert_eq!(seen, [dst.join("a"), dst.join("a/inner"), dst.join("top")]);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_mirror_tree_sync() {
+    use symlink::{mirror_tree, MirrorAction, MirrorMode, MirrorPolicy};
+    let dir = test_dir("mirror-sync");
+    let (src, dst) = (dir.join("src"), dir.join("dst"));
+    fs::create_dir_all(src.join("a/b")).unwrap();
+    for path in &["keep", "gone", "a/b/deep"] {
+        fs::write(src.join(path), TEST_FILE_CONTENTS).unwrap();
+    }
+    let sync = |policy| mirror_tree(&src, &dst, policy, MirrorMode::Sync).unwrap();
+    let report = sync(MirrorPolicy::LinkDirsBelow(0));
+    assert_eq!((report.links, report.dirs, report.removed), (3, 1, 0));
+
+    // Again, with the farm made deeper, the source changed and some of the farm’s own entries.
+    fs::remove_file(src.join("gone")).unwrap();
+    fs::write(src.join("new"), TEST_FILE_CONTENTS).unwrap();
+    remove_symlink_file(dst.join("keep")).unwrap();
+    symlink_file(dir.join("elsewhere"), dst.join("keep")).unwrap();
+    symlink_file(dir.join("elsewhere"), dst.join("own-link")).unwrap();
+    fs::write(dst.join("own-file"), TEST_FILE_CONTENTS).unwrap();
+    let report = sync(MirrorPolicy::LinkFiles);
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    let mut actions = report.actions.clone();
+    actions.sort_by(|a, b| a.0.cmp(&b.0));
+    let expected = [
+        (dst.join("a"), MirrorAction::Removed),
+        (dst.join("a"), MirrorAction::CreatedDir),
+        (dst.join("a/b"), MirrorAction::CreatedDir),
+        (dst.join("a/b/deep"), MirrorAction::Created),
+        (dst.join("gone"), MirrorAction::Removed),
+        (dst.join("keep"), MirrorAction::Repointed),
+        (dst.join("new"), MirrorAction::Created),
+    ];
+    assert_eq!(actions, expected);
+    assert_eq!(fs::read_link(dst.join("keep")).unwrap(), src.join("keep"));
+    assert!(dst.join("own-file").is_file() && fs::read_link(dst.join("own-link")).is_ok());
+
+    // And once more, with nothing to do.
+    let report = sync(MirrorPolicy::LinkFiles);
+    assert!(report.actions.iter().all(|&(_, action)| action == MirrorAction::AlreadyCorrect));
+    assert_eq!(report.actions.len(), 3);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_collapse_target() {
+    use symlink::TargetStyle;
+    let dir = test_dir("collapse-target");
+    fs::write(dir.join("real"), TEST_FILE_CONTENTS).unwrap();
+    fs::create_dir(dir.join("sub")).unwrap();
+    symlink_file("../real", dir.join("sub/hop")).unwrap();
+    symlink_file("sub/hop", dir.join("first")).unwrap();
+    let mut builder = SymlinkBuilder::new();
+    builder.collapse_target(true);
+
+    builder.symlink_file("first", dir.join("relative")).unwrap();
+    assert_eq!(fs::read_link(dir.join("relative")).unwrap(), Path::new("real"));
+    assert_eq!(fs::read(dir.join("relative")).unwrap(), TEST_FILE_CONTENTS);
+    builder.symlink_file(dir.join("first"), dir.join("absolute")).unwrap();
+    assert_eq!(fs::read_link(dir.join("absolute")).unwrap(), dir.join("real"));
+    builder.target_style(TargetStyle::Relative);
+    builder.symlink_file(dir.join("first"), dir.join("sub/restyled")).unwrap();
+    assert_eq!(fs::read_link(dir.join("sub/restyled")).unwrap(), Path::new("../real"));
+
+    // A target that isn’t a link is left as it was written, and without the option so is one
+    // that is.
+    let mut builder = SymlinkBuilder::new();
+    builder.collapse_target(true);
+    builder.symlink_file("./real", dir.join("plain")).unwrap();
+    assert_eq!(fs::read_link(dir.join("plain")).unwrap(), Path::new("./real"));
+    SymlinkBuilder::new().symlink_file("first", dir.join("chained")).unwrap();
+    assert_eq!(fs::read_link(dir.join("chained")).unwrap(), Path::new("first"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_change_symlink_kind() {
+    use symlink::change_symlink_kind;
+    let dir = test_dir("change-kind");
+    fs::write(dir.join("file"), TEST_FILE_CONTENTS).unwrap();
+    fs::create_dir(dir.join("dir")).unwrap();
+    let link = dir.join("link");
+    symlink_file("file", &link).unwrap();
+
+    change_symlink_kind(&link, "dir", SymlinkKind::Dir).unwrap();
+    assert_eq!(fs::read_link(&link).unwrap(), Path::new("dir"));
+    assert!(link.is_dir());
+    change_symlink_kind(&link, "file", SymlinkKind::File).unwrap();
+    assert_eq!(fs::read(&link).unwrap(), TEST_FILE_CONTENTS);
+    // So it was removed as the directory symlink it was, leaving the directory alone.
+    assert!(fs::symlink_metadata(dir.join("dir")).unwrap().is_dir());
+
+    let err = change_symlink_kind(dir.join("file"), "dir", SymlinkKind::Dir).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(fs::read(dir.join("file")).unwrap(), TEST_FILE_CONTENTS);
+    let err = change_symlink_kind(dir.join("missing"), "dir", SymlinkKind::Dir).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    // A bad new target is refused before the old link goes anywhere.
+    let err = change_symlink_kind(&link, "", SymlinkKind::Dir).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(fs::read_link(&link).unwrap(), Path::new("file"));
+
+    remove_symlink_file(&link).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_require_target_exists() {
+    let dir = test_dir("require-target-exists");
+    fs::create_dir(dir.join("sub")).unwrap();
+    fs::write(dir.join("sub/target"), TEST_FILE_CONTENTS).unwrap();
+    let mut builder = SymlinkBuilder::new();
+    builder.require_target_exists(true);
+    // Relative to the link, not the working directory.
+    builder.symlink_file("target", dir.join("sub/link")).unwrap();
+    let err = builder.symlink_file("target", dir.join("link")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert!(err.to_string().contains("would dangle"), "{}", err);
+    assert!(fs::symlink_metadata(dir.join("link")).is_err());
+    // Followed through links, and a dangling one doesn’t count.
+    builder.symlink_file("sub/link", dir.join("chained")).unwrap();
+    symlink_file("nowhere", dir.join("dangling")).unwrap();
+    let err = builder.symlink_file("dangling", dir.join("via-dangling")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    // Without the option, dangling is fine.
+    SymlinkBuilder::new().symlink_file("target", dir.join("link")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_durable() {
+    let dir = test_dir("durable");
+    let link = dir.join("link");
+    let mut builder = SymlinkBuilder::new();
+    builder.durable(true).symlink_file("old", &link).unwrap();
+    builder.replace_existing(true).symlink_file("new", &link).unwrap();
+    assert_eq!(fs::read_link(&link).unwrap(), Path::new("new"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_find_symlinks() {
+    use symlink::{find_symlinks, find_symlinks_max_depth};
+    let dir = test_dir("find");
+    fs::create_dir_all(dir.join("a/b")).unwrap();
+    fs::write(dir.join("a/file"), TEST_FILE_CONTENTS).unwrap();
+    symlink_file("../file", dir.join("a/b/link")).unwrap();
+    symlink_file("nowhere", dir.join("dangling")).unwrap();
+    // Listed, but not gone into, or this would find a/b/link twice (and loop).
+    symlink_dir(".", dir.join("a/loop")).unwrap();
+    assert_eq!(find_symlinks(&dir).unwrap(),
+               [dir.join("a/b/link"), dir.join("a/loop"), dir.join("dangling")]);
+    assert_eq!(find_symlinks(dir.join("a/b")).unwrap(), [dir.join("a/b/link")]);
+    assert_eq!(find_symlinks(dir.join("missing")).unwrap_err().kind(), io::ErrorKind::NotFound);
+    assert_eq!(find_symlinks_max_depth(&dir, 2).unwrap(),
+               [dir.join("a/loop"), dir.join("dangling")]);
+    assert_eq!(find_symlinks_max_depth(&dir, 1).unwrap(), [dir.join("dangling")]);
+    assert!(find_symlinks_max_depth(&dir, 0).unwrap().is_empty());
+    remove_symlink_dir(dir.join("a/loop")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_symlink_context_restrict_to_device() {
+    use symlink::SymlinkContext;
+    let dir = test_dir("restrict-to-device");
+    let mut ctx = SymlinkContext::new();
+    ctx.restrict_to_device(&dir);
+    ctx.symlink_file("target", dir.join("link")).unwrap();
+    ctx.remove_symlink_file(dir.join("link")).unwrap();
+
+    // Another device, where there is one to hand: /dev/shm is a tmpfs of its own.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let shm = Path::new("/dev/shm");
+        let other = shm.metadata().ok().filter(|m| m.dev() != dir.metadata().unwrap().dev());
+        if other.is_some() {
+            let link = shm.join("symlink-crate-restrict-to-device");
+            let err = ctx.symlink_file("target", &link).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::CrossesDevices);
+            assert!(err.to_string().contains("on a different device"), "{}", err);
+            assert!(fs::symlink_metadata(&link).is_err());
+
+            // Nor is it fooled by a symlink into the other device and a .. after it.
+            let other = shm.join("symlink-crate-restrict-to-device-dir");
+            fs::create_dir_all(other.join("inner")).unwrap();
+            symlink_dir(other.join("inner"), dir.join("into-shm")).unwrap();
+            let err = ctx.symlink_file("target", dir.join("into-shm").join("..").join("link"))
+                .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::CrossesDevices);
+            assert!(fs::symlink_metadata(other.join("link")).is_err());
+            remove_symlink_dir(dir.join("into-shm")).unwrap();
+            fs::remove_dir_all(&other).unwrap();
+        }
+    }
+
+    // No such path, no operations.
+    ctx.restrict_to_device(dir.join("missing"));
+    let err = ctx.symlink_file("target", dir.join("link")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_symlink_context_cache() {
+    use symlink::{CacheStats, SymlinkContext};
+    let dir = test_dir("context-cache");
+    fs::create_dir(dir.join("target")).unwrap();
+    let mut ctx = SymlinkContext::new();
+    assert_eq!(ctx.cache_stats(), CacheStats { hits: 0, misses: 0, len: 0, capacity: 1024 });
+    ctx.classification_cache_capacity(1);
+    for name in &["a", "b", "c"] {
+        ctx.symlink_auto("target", dir.join(name)).unwrap();
+        assert!(dir.join(name).is_dir());
+    }
+    ctx.symlink_auto(dir.join("b"), dir.join("d")).unwrap();
+    let stats = ctx.cache_stats();
+    if cfg!(windows) {
+        // The same target three times (by the same path, relative to the links), and then another.
+        assert_eq!(stats, CacheStats { hits: 2, misses: 2, len: 1, capacity: 1 });
+    } else {
+        assert_eq!(stats, CacheStats { hits: 0, misses: 0, len: 0, capacity: 1 });
+    }
+    for name in &["a", "b", "c", "d"] {
+        remove_symlink_dir(dir.join(name)).unwrap();
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_symlink_context_dry_run() {
+    use symlink::{ConflictPolicy, PolicyOutcome, SymlinkContext};
+    let dir = test_dir("context-dry-run");
+    let (file, link) = (dir.join("file"), dir.join("link"));
+    File::create(&file).unwrap();
+    symlink_file("target", &link).unwrap();
+    let mut ctx = SymlinkContext::new();
+    ctx.dry_run(true).on_created(|_, _, _| panic!("hooked a dry run"));
+
+    assert_eq!(ctx.symlink_file("target", dir.join("new")).unwrap(), PolicyOutcome::Created);
+    assert_eq!(ctx.symlink_file("target", &link).unwrap_err().kind(),
+               io::ErrorKind::AlreadyExists);
+    ctx.conflict_policy(ConflictPolicy::ReplaceSymlinkOnly);
+    assert_eq!(ctx.symlink_file("other", &link).unwrap(), PolicyOutcome::Replaced);
+    assert_eq!(ctx.symlink_file("other", &file).unwrap_err().kind(),
+               io::ErrorKind::AlreadyExists);
+    ctx.conflict_policy(ConflictPolicy::Backup { suffix: ".bak".into() });
+    assert_eq!(ctx.symlink_file("other", &file).unwrap(),
+               PolicyOutcome::BackedUp(dir.join("file.bak")));
+    ctx.remove_symlink_file(&link).unwrap();
+    assert_eq!(ctx.remove_symlink_file(&file).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+    // Nothing was touched.
+    assert!(!dir.join("new").exists() && !dir.join("file.bak").exists() && file.is_file());
+    assert_eq!(fs::read_link(&link).unwrap(), Path::new("target"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_symlink_context_serialize_per_dir() {
+    use std::thread;
+    use symlink::{ConflictPolicy, SymlinkContext, LOCK_FILE_NAME};
+    let dir = test_dir("context-serialize");
+    let mut ctx = SymlinkContext::new();
+    ctx.conflict_policy(ConflictPolicy::Overwrite).serialize_per_dir(true).lock_files(true);
+    // Without the lock, two threads can both find nothing in the way, and one then fail.
+    let threads: Vec<_> = (0..8).map(|i| {
+        let (ctx, link) = (ctx.clone(), dir.join("link"));
+        thread::spawn(move || {
+            for j in 0..20 {
+                ctx.symlink_file(format!("target-{}-{}", i, j), &link).unwrap();
+            }
+        })
+    }).collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert!(dir.join(LOCK_FILE_NAME).is_file());
+    ctx.remove_symlink_file(dir.join("link")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_transaction() {
+    use symlink::{CreateOutcome, JournalEntry, Transaction};
+    let dir = test_dir("transaction");
+    let (a, b, c) = (dir.join("a"), dir.join("b"), dir.join("c"));
+    symlink_file("old-b", &b).unwrap();
+    symlink_file("old-c", &c).unwrap();
+
+    let mut tx = Transaction::new();
+    tx.symlink_file("new-a", &a).unwrap();
+    assert_eq!(tx.ensure_symlink_file("new-b", &b).unwrap(), CreateOutcome::Repointed);
+    assert_eq!(tx.ensure_symlink_file("new-b", &b).unwrap(), CreateOutcome::AlreadyCorrect);
+    tx.remove_symlink_file(&c).unwrap();
+    assert!(tx.remove_symlink_file(&c).is_err());
+    assert_eq!(tx.journal().len(), 3);
+    match tx.journal()[1] {
+        JournalEntry::Repointed { ref previous, .. } => assert_eq!(previous, Path::new("old-b")),
+        ref other => panic!("{:?}", other),
+    }
+    tx.rollback().unwrap();
+    assert!(tx.journal().is_empty());
+    assert!(fs::symlink_metadata(&a).is_err());
+    assert_eq!(fs::read_link(&b).unwrap(), Path::new("old-b"));
+    assert_eq!(fs::read_link(&c).unwrap(), Path::new("old-c"));
+
+    // A repoint that fails leaves the link to roll back to, or journals its loss.
+    let mut tx = Transaction::new();
+    assert_eq!(tx.ensure_symlink_file(&b, &b).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    tx.rollback().unwrap();
+    assert_eq!(fs::read_link(&b).unwrap(), Path::new("old-b"));
+
+    // A link changed by someone else since is left alone, and the rollback can be resumed.
+    let mut tx = Transaction::new();
+    tx.remove_symlink_file(&c).unwrap();
+    tx.symlink_file("mine", &a).unwrap();
+    remove_symlink_file(&a).unwrap();
+    symlink_file("theirs", &a).unwrap();
+    assert_eq!(tx.rollback().unwrap_err().kind(), io::ErrorKind::Other);
+    assert_eq!(tx.journal().len(), 2);
+    assert_eq!(fs::read_link(&a).unwrap(), Path::new("theirs"));
+    remove_symlink_file(&a).unwrap();
+    symlink_file("mine", &a).unwrap();
+    tx.rollback().unwrap();
+    assert!(fs::symlink_metadata(&a).is_err());
+    assert_eq!(fs::read_link(&c).unwrap(), Path::new("old-c"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_filesystem_supports_symlinks() {
+    use symlink::{filesystem_supports_symlinks, symlinks_supported_in};
+    let dir = test_dir("filesystem-supports");
+    // Wherever the tests run, the two ways of asking should agree, and a path that doesn’t
+    // exist yet is asked about by its directory.
+    let supported = filesystem_supports_symlinks(&dir).unwrap();
+    assert_eq!(filesystem_supports_symlinks(dir.join("not/there/yet")).unwrap(), supported);
+    if !supported {
+        assert!(!symlinks_supported_in(&dir).unwrap());
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_trailing_dots() {
+    let dir = test_dir("trailing-dots");
+    let names = |dir: &Path| {
+        let mut names: Vec<_> = fs::read_dir(dir).unwrap().map(|e| e.unwrap().file_name())
+            .collect();
+        names.sort();
+        names
+    };
+    let result = symlink_file("target", dir.join("link."));
+    let other = symlink_file("target. ", dir.join("other"));
+    if cfg!(windows) {
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(other.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert!(names(&dir).is_empty());
+    } else {
+        result.unwrap();
+        other.unwrap();
+        assert_eq!(names(&dir), ["link.", "other"]);
+        fs::remove_file(dir.join("link.")).unwrap();
+        fs::remove_file(dir.join("other")).unwrap();
+    }
+
+    // Kept as written, either way.
+    SymlinkBuilder::new().preserve_trailing_dots(true).symlink_file("target", dir.join("link."))
+        .unwrap();
+    assert_eq!(names(&dir), ["link."]);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(windows)]
+#[test]
+fn test_symlink_with_flags() {
+    use symlink::{symlink_with_flags, SymlinkFlags};
+    let flags = SymlinkFlags::DIRECTORY | SymlinkFlags::ALLOW_UNPRIVILEGED_CREATE;
+    assert_eq!(flags.bits(), 3);
+    assert!(flags.contains(SymlinkFlags::DIRECTORY) && !SymlinkFlags::NONE.contains(flags));
+    let dir = test_dir("with-flags");
+    fs::create_dir(dir.join("target")).unwrap();
+    match symlink_with_flags("target", dir.join("link"), flags) {
+        Ok(()) => {
+            assert!(dir.join("link").is_dir());
+            remove_symlink_dir(dir.join("link")).unwrap();
+        }
+        // Neither privileged nor in Developer Mode.
+        Err(e) => assert_eq!(e.kind(), io::ErrorKind::PermissionDenied),
+    }
+    let err = symlink_with_flags("target", dir.join("link"), SymlinkFlags::from_bits_retain(0x80))
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(windows)]
+#[test]
+fn test_symlink_privilege() {
+    use symlink::{symlink_privilege, SymlinkPrivilege};
+    let dir = test_dir("privilege");
+    // Whichever it is, it’s what creating a link finds.
+    let result = symlink_file("target", dir.join("link"));
+    match symlink_privilege() {
+        SymlinkPrivilege::NeitherUseJunctions => {
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+        }
+        SymlinkPrivilege::Privileged | SymlinkPrivilege::Unprivileged => result.unwrap(),
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_symlinks_supported() {
+    use symlink::{symlinks_supported, symlinks_supported_in};
+    let dir = test_dir("supported");
+    // This suite needs symlinks to pass at all, so here they’re supported.
+    assert!(symlinks_supported_in(&dir).unwrap());
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+    // Remembered, so this doesn’t look: it would fail if it did.
+    fs::remove_dir(&dir).unwrap();
+    assert!(symlinks_supported_in(&dir).unwrap());
+    let err = symlinks_supported_in(dir.join("missing")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert!(symlinks_supported().unwrap());
+}
+
+#[test]
+fn test_symlink_id() {
+    use symlink::symlink_id;
+    let dir = test_dir("symlink-id");
+    fs::write(dir.join("target"), TEST_FILE_CONTENTS).unwrap();
+    symlink_file("target", dir.join("a")).unwrap();
+    symlink_file("target", dir.join("b")).unwrap();
+    let id = symlink_id(dir.join("a")).unwrap();
+    assert_ne!(id, symlink_id(dir.join("b")).unwrap());
+    // The link’s own identity, which goes with it when it’s renamed.
+    fs::rename(dir.join("a"), dir.join("renamed")).unwrap();
+    assert_eq!(symlink_id(dir.join("renamed")).unwrap(), id);
+    let err = symlink_id(dir.join("target")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(symlink_id(dir.join("a")).unwrap_err().kind(), io::ErrorKind::NotFound);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_symlink_events() {
+    use symlink::{ConflictPolicy, EventOp, EventOutcome, LinkKind, SymlinkContext, SymlinkEvent,
+                  Transaction};
+    let dir = test_dir("events");
+    let link = dir.join("link");
+    let event = |op, kind, target: Option<&str>, outcome| {
+        SymlinkEvent { op, kind, link: link.clone(), target: target.map(PathBuf::from), outcome }
+    };
+
+    let mut ctx = SymlinkContext::new();
+    ctx.symlink_file("target", &link).unwrap();
+    assert!(ctx.events().is_empty());
+    ctx.remove_symlink_file(&link).unwrap();
+    ctx.record_events(true).conflict_policy(ConflictPolicy::Skip);
+    ctx.symlink_file("target", &link).unwrap();
+    ctx.symlink_dir("other", &link).unwrap();
+    ctx.remove_symlink_file(&link).unwrap();
+    let events = ctx.events();
+    assert_eq!(events[..3],
+               [event(EventOp::Create, LinkKind::File, Some("target"), EventOutcome::Created),
+                event(EventOp::Create, LinkKind::Dir, Some("other"), EventOutcome::Skipped),
+                event(EventOp::Remove, LinkKind::File, None, EventOutcome::Removed)]);
+    assert!(ctx.remove_symlink_file(&link).is_err());
+    match ctx.events()[3].outcome {
+        EventOutcome::Failed { kind, .. } => assert_eq!(kind, io::ErrorKind::NotFound),
+        ref other => panic!("{:?}", other),
+    }
+    assert!(ctx.record_events(true).events().is_empty());
+
+    // A transaction always records, undoings and all.
+    symlink_file("old", &link).unwrap();
+    let mut tx = Transaction::new();
+    tx.ensure_symlink_file("new", &link).unwrap();
+    assert!(tx.symlink_file("new", &link).is_err());
+    tx.rollback().unwrap();
+    let events = tx.events();
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[0],
+               event(EventOp::Ensure, LinkKind::File, Some("new"), EventOutcome::Repointed));
+    assert_eq!(events[1].op, EventOp::Create);
+    assert_eq!(events[2].op, EventOp::Undo);
+    assert_eq!(events[2].target, Some(PathBuf::from("old")));
+    assert_eq!(events[2].outcome, EventOutcome::Repointed);
+    assert_eq!(fs::read_link(&link).unwrap(), Path::new("old"));
+    fs::remove_dir_all(&dir).unwrap();
+}
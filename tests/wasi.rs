@@ -0,0 +1,51 @@
+// WASI has no temporary directory, only what the runtime preopens, so this needs running with one:
+//
+//     CARGO_TARGET_WASM32_WASIP1_RUNNER="wasmtime --dir=/tmp/symlink-wasi::sandbox" \
+//         cargo test --target wasm32-wasip1 --test wasi
+//
+// SYMLINK_WASI_DIR names the preopened directory as the guest sees it, defaulting to sandbox.
+#![cfg(target_os = "wasi")]
+
+use std::env;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+extern crate symlink_rs as symlink;
+use symlink::{remove_symlink_file, remove_symlink_in, symlink_dir_in, symlink_file,
+              symlink_file_in};
+
+fn preopen() -> PathBuf {
+    PathBuf::from(env::var("SYMLINK_WASI_DIR").unwrap_or_else(|_| "sandbox".into()))
+}
+
+#[test]
+fn test_symlink_by_path() {
+    let dir = preopen().join("by-path");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir(&dir).unwrap();
+    fs::write(dir.join("target"), b"contents").unwrap();
+    symlink_file("target", dir.join("link")).unwrap();
+    assert_eq!(fs::read_link(dir.join("link")).unwrap(), Path::new("target"));
+    assert_eq!(fs::read(dir.join("link")).unwrap(), b"contents");
+    remove_symlink_file(dir.join("link")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_symlink_in_preopen() {
+    let dir = preopen().join("in-preopen");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("sub")).unwrap();
+    let handle = File::open(&dir).unwrap();
+    symlink_file_in(&handle, "../target", "sub/link").unwrap();
+    assert_eq!(fs::read_link(dir.join("sub/link")).unwrap(), Path::new("../target"));
+    symlink_dir_in(&handle, "sub", "dir-link").unwrap();
+    for escape in &["../link", "/link"] {
+        let err = symlink_file_in(&handle, "target", escape).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+    remove_symlink_in(&handle, "sub/link").unwrap();
+    remove_symlink_in(&handle, "dir-link").unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}